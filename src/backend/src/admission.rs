@@ -0,0 +1,90 @@
+use std::{
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default)]
+struct GateState {
+    active: usize,
+    closed: bool,
+}
+
+impl GateState {
+    fn is_admitting(&self, limit: Option<usize>) -> bool {
+        !self.closed && limit.is_none_or(|limit| self.active < limit)
+    }
+}
+
+/// Controls when transactions may start, so that a quiescent checkpoint can wait for every
+/// running transaction to finish without new ones starting in the meantime, and so that the
+/// number of running transactions can be capped.
+#[derive(Debug, Default)]
+pub struct AdmissionGate {
+    state: Mutex<GateState>,
+    condvar: Condvar,
+    limit: Option<usize>,
+}
+
+impl AdmissionGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Admit at most `limit` transactions at a time.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Admit a new transaction, waiting while the gate is closed or at its limit.
+    pub fn enter(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.is_admitting(self.limit) {
+            state = self.condvar.wait(state).unwrap();
+        }
+        state.active += 1;
+    }
+
+    /// Admit a new transaction, waiting at most `timeout` while the gate is closed or at its
+    /// limit. Returns whether the transaction was admitted.
+    pub fn try_enter(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        while !state.is_admitting(self.limit) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            state = self.condvar.wait_timeout(state, remaining).unwrap().0;
+        }
+        state.active += 1;
+        true
+    }
+
+    /// Record that an admitted transaction has committed or rolled back.
+    pub fn leave(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.active -= 1;
+        self.condvar.notify_all();
+    }
+
+    /// Stop admitting transactions, then wait for every admitted transaction to finish. If the
+    /// gate is already closed, waits for it to be opened first.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        while state.closed {
+            state = self.condvar.wait(state).unwrap();
+        }
+        state.closed = true;
+        while state.active > 0 {
+            state = self.condvar.wait(state).unwrap();
+        }
+    }
+
+    /// Resume admitting transactions.
+    pub fn open(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = false;
+        self.condvar.notify_all();
+    }
+}
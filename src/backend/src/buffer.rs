@@ -2,7 +2,10 @@ use std::sync::{Arc, Mutex};
 
 use tracing::trace;
 
-use crate::{block_id::BlockId, file_manager::FileManager, log_manager::LogManager, page::Page};
+use crate::{
+    block_id::BlockId, buffer_manager::BufferError, file_manager::FileManager,
+    log_manager::LogManager, page::Page,
+};
 
 pub struct Buffer {
     file_manager: Arc<FileManager>,
@@ -12,6 +15,7 @@ pub struct Buffer {
     pub pin_count: u32,
     pub tx_num: i64,
     pub lsn: i64,
+    dirty: bool,
 }
 
 impl Buffer {
@@ -25,12 +29,19 @@ impl Buffer {
             // TODO: this will be changed back to -1 in the future
             tx_num: 0,
             lsn: -1,
+            dirty: false,
         }
     }
 
     pub fn set_modified(&mut self, tx_num: i64, lsn: i64) {
         self.tx_num = tx_num;
         self.lsn = lsn;
+        self.dirty = true;
+    }
+
+    /// Whether the page has been modified since it was last written to storage.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
     }
 
     pub fn pin(&mut self) {
@@ -52,41 +63,64 @@ impl Buffer {
     // TODO: error handling
     /// Assign this Buffer to a new Block
     ///
-    /// The buffer will be flushed and the pin count will reset to 0.
+    /// If the buffer holds modifications to its current block they are forced to storage first,
+    /// and the pin count will reset to 0.
     ///
     /// # Arguments
     ///
     /// * `blk` - The BlockId describing the block to load into the buffer.
     pub fn assign_to_block(&mut self, blk: BlockId) {
         trace!("Assign to block called");
-        self.flush();
+        self.force().expect("failed to write block");
         self.file_manager.get_block(&blk, &mut self.page).unwrap();
         self.blk = Some(blk);
         self.pin_count = 0;
     }
 
-    // TODO: error handling
-    pub fn flush(&mut self) {
+    /// Detach the buffer from its block without writing the page, e.g. when the block's file is
+    /// deleted.
+    pub fn discard(&mut self) {
+        self.blk = None;
+        self.dirty = false;
+    }
+
+    /// Write the page to storage if it has been modified since it was last written, e.g. before
+    /// the buffer is reused for another block. The log is flushed up to the page's last change
+    /// first, so that the change can be undone if the modifying transaction does not commit.
+    pub fn force(&mut self) -> Result<(), BufferError> {
+        if self.dirty {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the page to storage, flushing the log up to the page's last change first. Returns an
+    /// error if either write fails, leaving the page marked as modified.
+    pub fn flush(&mut self) -> Result<(), BufferError> {
         trace!("flush called");
         match &self.blk {
             Some(blk) => {
                 trace!("Checking to see if block needs to be written to storage");
                 if self.tx_num >= 0 {
                     trace!("Writing to storage");
+                    let failed = |e: String| BufferError::Flush(blk.clone(), e);
                     {
                         let mut lm = self.log_manager.lock().unwrap();
-                        lm.flush(self.lsn);
+                        lm.flush(self.lsn).map_err(|e| failed(e.to_string()))?;
                     }
 
-                    self.file_manager.write_block(&blk, &self.page).unwrap();
+                    self.file_manager
+                        .write_block(&blk, &self.page)
+                        .map_err(|e| failed(e.to_string()))?;
+                    self.dirty = false;
                     // TODO: this should be set in the future
                     //self.tx_num = -1;
                 }
             }
             None => {
                 trace!("No block to flush");
-                return;
             }
         }
+        Ok(())
     }
 }
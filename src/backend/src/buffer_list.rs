@@ -6,7 +6,9 @@ use std::{
 use tracing::trace;
 
 use crate::{
-    block_id::BlockId, buffer::Buffer, buffer_manager::BufferManager,
+    block_id::BlockId,
+    buffer::Buffer,
+    buffer_manager::{BufferError, BufferManager},
     eviction_policy::SimpleEvictionPolicy,
 };
 
@@ -36,16 +38,14 @@ impl BufferList {
         buf.clone()
     }
 
-    /// Pin the specified block.
-    pub fn pin(&mut self, blk: &BlockId) {
+    /// Pin the specified block, waiting for a buffer if every buffer is pinned. Returns an error,
+    /// leaving the list unchanged, if no buffer became available to hold the block in time.
+    pub fn pin(&mut self, blk: &BlockId) -> Result<(), BufferError> {
         trace!("pinning block {}", blk);
-        let buf = {
-            // TODO: error handling
-            let mut buf_mgr = self.buf_mgr.lock().unwrap();
-            buf_mgr.pin(blk)
-        };
+        let buf = BufferManager::pin_waiting(&self.buf_mgr, blk)?;
         self.buffers.insert(blk.clone(), buf);
         self.pins.push(blk.clone());
+        Ok(())
     }
 
     /// Unpin the specified block.
@@ -86,6 +86,24 @@ impl BufferList {
         self.pins.clear();
     }
 
+    /// Unpin every block of the specified file, however many times each is pinned.
+    pub fn unpin_file(&mut self, file_id: &str) {
+        let blks: Vec<BlockId> = self
+            .pins
+            .iter()
+            .filter(|b| b.file_id() == file_id)
+            .cloned()
+            .collect();
+        for blk in blks {
+            self.unpin(&blk);
+        }
+    }
+
+    /// Whether the specified block is pinned.
+    pub fn is_pinned(&self, blk: &BlockId) -> bool {
+        self.buffers.contains_key(blk)
+    }
+
     #[cfg(test)]
     pub fn pin_count(&self, blk: &BlockId) -> usize {
         self.pins.iter().filter(|b| *b == blk).count()
@@ -110,11 +128,11 @@ mod tests {
         let blk1 = BlockId::new("student", 1);
         let blk2 = BlockId::new("student", 2);
 
-        bl.pin(&blk0);
-        bl.pin(&blk1);
-        bl.pin(&blk0);
-        bl.pin(&blk2);
-        bl.pin(&blk0);
+        bl.pin(&blk0).unwrap();
+        bl.pin(&blk1).unwrap();
+        bl.pin(&blk0).unwrap();
+        bl.pin(&blk2).unwrap();
+        bl.pin(&blk0).unwrap();
 
         assert_eq!(3, bl.pin_count(&blk0));
 
@@ -132,7 +150,7 @@ mod tests {
         assert_eq!(1, bl.pin_count(&blk2));
 
         // assert block 0 is pinned normally after unpinning
-        bl.pin(&blk0);
+        bl.pin(&blk0).unwrap();
         assert_eq!(1, bl.pin_count(&blk0));
     }
 
@@ -145,10 +163,10 @@ mod tests {
 
         let blk1 = BlockId::new("student", 0);
         let blk2 = BlockId::new("student", 1);
-        bl.pin(&blk1);
-        bl.pin(&blk1);
-        bl.pin(&blk2);
-        bl.pin(&blk2);
+        bl.pin(&blk1).unwrap();
+        bl.pin(&blk1).unwrap();
+        bl.pin(&blk2).unwrap();
+        bl.pin(&blk2).unwrap();
         assert_eq!(2, bl.pin_count(&blk1));
         assert_eq!(2, bl.pin_count(&blk2));
         bl.unpin_all();
@@ -156,8 +174,8 @@ mod tests {
         assert_eq!(0, bl.pin_count(&blk2));
 
         // assert blocks are pinned normally after unpinning
-        bl.pin(&blk1);
-        bl.pin(&blk2);
+        bl.pin(&blk1).unwrap();
+        bl.pin(&blk2).unwrap();
         assert_eq!(1, bl.pin_count(&blk1));
         assert_eq!(1, bl.pin_count(&blk2));
     }
@@ -8,18 +8,56 @@ use crate::{
     log_manager::LogManager,
 };
 use std::{
-    fmt::write,
-    sync::{Arc, LockResult, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    fmt::{write, Display},
+    sync::{Arc, Condvar, LockResult, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::{Duration, Instant},
 };
 
 use std::collections::HashMap;
 
+/// How long a pin waits for a buffer to become available, by default.
+const MAX_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum BufferError {
+    /// Every buffer in the pool stayed pinned, so the block could not be loaded.
+    NoAvailableBuffers(BlockId),
+    /// The block, or the log records describing its changes, could not be written to storage.
+    Flush(BlockId, String),
+}
+
+impl Display for BufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoAvailableBuffers(blk) => {
+                write!(f, "no buffer is available to hold block {}", blk)
+            }
+            Self::Flush(blk, msg) => write!(f, "failed to write block {}: {}", blk, msg),
+        }
+    }
+}
+
+/// Counters describing how pins have been served by the buffer pool.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BufferStats {
+    /// Pins of blocks that were already held in a buffer.
+    pub hits: u64,
+    /// Pins of blocks that had to be read into a buffer.
+    pub misses: u64,
+    /// Blocks read into a buffer ahead of being pinned.
+    pub prefetched: u64,
+}
+
 pub struct BufferManager<E: EvictionPolicy = SimpleEvictionPolicy> {
     unused: Vec<usize>,
     blk_to_buf: HashMap<BlockId, usize>,
     buffers: Vec<Arc<RwLock<Buffer>>>,
     num_available: usize,
     eviction_policy: E,
+    stats: BufferStats,
+    /// Notified whenever a buffer is unpinned and becomes available.
+    available: Arc<Condvar>,
+    max_wait: Duration,
 }
 
 impl<E: EvictionPolicy> BufferManager<E> {
@@ -52,27 +90,55 @@ impl<E: EvictionPolicy> BufferManager<E> {
             blk_to_buf: HashMap::new(),
             num_available: size,
             eviction_policy,
+            stats: BufferStats::default(),
+            available: Arc::new(Condvar::new()),
+            max_wait: MAX_TIME,
         }
     }
 
+    /// Set how long `pin_waiting` waits for a buffer to become available before giving up.
+    pub fn set_max_wait(&mut self, max_wait: Duration) {
+        self.max_wait = max_wait;
+    }
+
     pub fn num_available(&self) -> usize {
         // TODO: error checking?
         self.num_available
     }
 
+    /// Get the hit, miss, and prefetch counters accumulated since the manager was created.
+    pub fn stats(&self) -> BufferStats {
+        self.stats
+    }
+
+    /// Get the number of buffers whose pages have been modified since they were last written to
+    /// storage. A checkpoint is worthwhile when many buffers are dirty.
+    pub fn dirty_count(&self) -> usize {
+        self.buffers
+            .iter()
+            .filter(|buf| buf.read().unwrap().is_dirty())
+            .count()
+    }
+
     /// Evict a block from a buffer to get a free buffer
     fn get_evicted_buffer(&mut self) -> Option<usize> {
         trace!("Evicting block from buffer");
         self.eviction_policy.evict()
     }
 
-    // TODO: Error Checking
-    pub fn pin(&mut self, blk: &BlockId) -> Arc<RwLock<Buffer>> {
+    /// Pin a block into a buffer, loading it into an unused or evicted buffer if necessary.
+    /// Returns an error if every buffer in the pool is pinned.
+    ///
+    /// # Arguments
+    ///
+    /// * `blk` - The block to pin.
+    pub fn pin(&mut self, blk: &BlockId) -> Result<Arc<RwLock<Buffer>>, BufferError> {
         let buf_index = match self.blk_to_buf.get(&blk) {
             Some(buf_index) => {
                 let _span = trace_span!("bufmgr-pin", bufidx = buf_index).entered();
 
                 trace!("pinning block");
+                self.stats.hits += 1;
 
                 // The block is loaded into an existing buffer
                 let arc = self.buffers[*buf_index].clone();
@@ -98,8 +164,8 @@ impl<E: EvictionPolicy> BufferManager<E> {
                     .unused
                     .pop()
                     .or_else(|| self.get_evicted_buffer())
-                    // TODO: condition variable to notify waiting threads on buffer availability
-                    .expect("No available buffers");
+                    .ok_or_else(|| BufferError::NoAvailableBuffers(blk.clone()))?;
+                self.stats.misses += 1;
 
                 let _span = trace_span!("bufmgr-pin", bufidx = buf_index).entered();
 
@@ -130,7 +196,74 @@ impl<E: EvictionPolicy> BufferManager<E> {
         // The page is no longer evictable, it has been pinned
         self.eviction_policy.remove(buf_index);
 
-        Arc::clone(&self.buffers[buf_index])
+        Ok(Arc::clone(&self.buffers[buf_index]))
+    }
+
+    /// Pin a block into a buffer like `pin`, but if every buffer in the pool is pinned, wait for
+    /// one to be unpinned. The manager's lock is released while waiting. Returns an error if no
+    /// buffer became available within the maximum wait.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer_mgr` - The shared buffer manager.
+    /// * `blk` - The block to pin.
+    pub fn pin_waiting(
+        buffer_mgr: &Mutex<Self>,
+        blk: &BlockId,
+    ) -> Result<Arc<RwLock<Buffer>>, BufferError> {
+        let mut mgr = buffer_mgr.lock().unwrap();
+        let deadline = Instant::now() + mgr.max_wait;
+        loop {
+            match mgr.pin(blk) {
+                Err(BufferError::NoAvailableBuffers(_)) if Instant::now() < deadline => {
+                    trace!("waiting for a buffer to become available");
+                    let available = mgr.available.clone();
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    mgr = available.wait_timeout(mgr, timeout).unwrap().0;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Read blocks into buffers ahead of them being pinned, so that a later `pin` is served from
+    /// the pool. Blocks that are already held in a buffer are skipped. Only unused or evictable
+    /// buffers are used, so prefetching stops early rather than waiting on pinned buffers. Returns
+    /// the number of blocks that were read.
+    ///
+    /// # Arguments
+    ///
+    /// * `blks` - The blocks to read, in the order they are expected to be pinned.
+    pub fn prefetch(&mut self, blks: &[BlockId]) -> usize {
+        // Acquire every buffer before making any of them evictable, so that blocks prefetched in
+        // this batch do not evict each other.
+        let mut loaded = vec![];
+        for blk in blks {
+            if self.blk_to_buf.contains_key(blk) {
+                continue;
+            }
+
+            let Some(buf_index) = self.unused.pop().or_else(|| self.get_evicted_buffer()) else {
+                break;
+            };
+
+            let mut buf = self.buffers[buf_index].write().unwrap();
+            if let Some(block_id) = &buf.blk {
+                self.blk_to_buf.remove(block_id);
+            }
+
+            trace!("prefetching block {} into buffer {}", blk, buf_index);
+            buf.assign_to_block(blk.clone());
+            self.blk_to_buf.insert(blk.clone(), buf_index);
+            loaded.push(buf_index);
+        }
+
+        for buf_index in loaded.iter() {
+            self.eviction_policy.add(*buf_index);
+        }
+
+        self.stats.prefetched += loaded.len() as u64;
+        loaded.len()
     }
 
     /// Unpin a buffer. The provided buffer will have a write lock taken for the duration of this
@@ -160,18 +293,76 @@ impl<E: EvictionPolicy> BufferManager<E> {
             }
             self.num_available += 1;
             trace!("Incremented available buffers to {}", self.num_available());
+            self.available.notify_all();
         }
     }
 
-    // TODO: error checking
-    pub fn flush_all(&mut self, tx_num: i64) {
+    /// Drop every block of a file from the buffer pool without writing it back, e.g. before the
+    /// file is deleted. None of the file's blocks may be pinned.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the file whose blocks are discarded.
+    pub fn discard_file(&mut self, file_id: &str) {
+        let blks: Vec<BlockId> = self
+            .blk_to_buf
+            .keys()
+            .filter(|blk| blk.file_id() == file_id)
+            .cloned()
+            .collect();
+        for blk in blks {
+            let buf_index = self.blk_to_buf.remove(&blk).unwrap();
+            let mut buf = self.buffers[buf_index].write().unwrap();
+            assert!(!buf.is_pinned(), "cannot discard pinned block {}", blk);
+            buf.discard();
+
+            self.eviction_policy.remove(buf_index);
+            self.unused.push(buf_index);
+        }
+    }
+
+    /// Write every dirty buffer to storage, whichever transaction modified it.
+    pub fn flush_dirty(&mut self) {
+        for buf in self.buffers.iter() {
+            // TODO: error handling
+            buf.write().unwrap().force().expect("failed to write block");
+        }
+    }
+
+    /// Write every dirty buffer holding a block of a file to storage, whichever transaction
+    /// modified it.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the file whose blocks are written.
+    pub fn flush_file(&mut self, file_id: &str) {
+        for (blk, buf_index) in self.blk_to_buf.iter() {
+            if blk.file_id() == file_id {
+                // TODO: error handling
+                self.buffers[*buf_index]
+                    .write()
+                    .unwrap()
+                    .force()
+                    .expect("failed to write block");
+            }
+        }
+    }
+
+    /// Write every buffer modified by a transaction to storage. Returns an error on the first
+    /// buffer that could not be written; the buffers not yet written stay modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_num` - The number of the transaction whose buffers are written.
+    pub fn flush_all(&mut self, tx_num: i64) -> Result<(), BufferError> {
         for buf in self.buffers.iter() {
             let arc = buf.clone();
             let mut b = arc.write().unwrap();
             if b.tx_num == tx_num {
-                b.flush();
+                b.flush()?;
             }
         }
+        Ok(())
     }
 }
 
@@ -179,8 +370,9 @@ impl<E: EvictionPolicy> BufferManager<E> {
 mod tests {
     use std::{
         fs,
-        sync::{Arc, Mutex},
+        sync::{mpsc, Arc, Mutex},
         thread,
+        time::Duration,
     };
 
     use tempfile::tempdir;
@@ -190,7 +382,7 @@ mod tests {
         log_manager::LogManager, page::Page,
     };
 
-    use super::BufferManager;
+    use super::{BufferError, BufferManager};
 
     #[test]
     fn test() {
@@ -211,16 +403,16 @@ mod tests {
 
         assert_eq!(bm.num_available(), 3);
 
-        let buf1 = bm.pin(&BlockId::new("test", 0));
+        let buf1 = bm.pin(&BlockId::new("test", 0)).unwrap();
         assert_eq!(bm.num_available(), 2);
 
-        let buf2 = bm.pin(&BlockId::new("test", 1));
+        let buf2 = bm.pin(&BlockId::new("test", 1)).unwrap();
         assert_eq!(bm.num_available(), 1);
 
-        let buf3 = bm.pin(&BlockId::new("test", 2));
+        let buf3 = bm.pin(&BlockId::new("test", 2)).unwrap();
         assert_eq!(bm.num_available(), 0);
 
-        let buf3_2 = bm.pin(&BlockId::new("test", 2));
+        let buf3_2 = bm.pin(&BlockId::new("test", 2)).unwrap();
         assert_eq!(bm.num_available(), 0);
 
         bm.unpin(&buf1);
@@ -269,10 +461,13 @@ mod tests {
             handles.push(thread::spawn(move || {
                 for i in 0..num_pages_per_thread {
                     let mut lock = bm.lock().unwrap();
-                    let buf = lock.pin(&BlockId::new("test", (t * num_pages_per_thread) + i));
+                    let buf = lock
+                        .pin(&BlockId::new("test", (t * num_pages_per_thread) + i))
+                        .unwrap();
                     {
                         let mut wb = buf.write().unwrap();
                         wb.page.write((t * num_pages_per_thread) + i, 0);
+                        wb.set_modified(1, -1);
                         lock.unpin_locked(&mut wb);
                     }
                 }
@@ -285,7 +480,7 @@ mod tests {
 
         let mut bm_lock = bm.lock().unwrap();
         for p in 0..num_threads * num_pages_per_thread {
-            let buf = bm_lock.pin(&BlockId::new("test", p));
+            let buf = bm_lock.pin(&BlockId::new("test", p)).unwrap();
             {
                 let mut wb = buf.write().unwrap();
                 let val: u64 = wb.page.read(0);
@@ -295,4 +490,126 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_pin_waits_for_unpin() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).expect("Failed to create root directory");
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).expect("Failed to create root directory");
+
+        let lm = LogManager::new(&log_dir);
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            1,
+            fm.clone(),
+            Arc::new(Mutex::new(lm)),
+            SimpleEvictionPolicy::new(),
+        )));
+        let blk0 = fm.append_block("test", &Page::new()).unwrap();
+        let blk1 = fm.append_block("test", &Page::new()).unwrap();
+
+        let buf0 = BufferManager::pin_waiting(&bm, &blk0).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn({
+            let bm = bm.clone();
+            let blk1 = blk1.clone();
+            move || {
+                let buf1 = BufferManager::pin_waiting(&bm, &blk1).unwrap();
+                sender.send(()).unwrap();
+                bm.lock().unwrap().unpin(&buf1);
+            }
+        });
+
+        // The second pin waits while the only buffer is pinned, without holding the manager's lock
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        assert_eq!(bm.lock().unwrap().num_available(), 0);
+
+        bm.lock().unwrap().unpin(&buf0);
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(()));
+        handle.join().unwrap();
+        assert_eq!(bm.lock().unwrap().num_available(), 1);
+
+        // A pin that waits longer than the maximum wait fails
+        let buf1 = BufferManager::pin_waiting(&bm, &blk1).unwrap();
+        bm.lock().unwrap().set_max_wait(Duration::from_millis(100));
+        assert_eq!(
+            BufferManager::pin_waiting(&bm, &blk0).err(),
+            Some(BufferError::NoAvailableBuffers(blk0.clone()))
+        );
+        bm.lock().unwrap().unpin(&buf1);
+    }
+
+    #[test]
+    fn test_dirty_count() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).expect("Failed to create root directory");
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).expect("Failed to create root directory");
+
+        let lm = LogManager::new(&log_dir);
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let mut bm = BufferManager::new(
+            3,
+            fm.clone(),
+            Arc::new(Mutex::new(lm)),
+            SimpleEvictionPolicy::new(),
+        );
+        for _ in 0..3 {
+            fm.append_block("test", &Page::new()).unwrap();
+        }
+
+        let bufs: Vec<_> = (0..3)
+            .map(|i| bm.pin(&BlockId::new("test", i)).unwrap())
+            .collect();
+        assert_eq!(bm.dirty_count(), 0);
+
+        for (i, buf) in bufs.iter().enumerate() {
+            let mut b = buf.write().unwrap();
+            b.page.write(i as u32, 0);
+            b.set_modified(1, -1);
+            assert!(b.is_dirty());
+            drop(b);
+            assert_eq!(bm.dirty_count(), i + 1);
+        }
+
+        bm.flush_all(1).unwrap();
+        assert_eq!(bm.dirty_count(), 0);
+        assert!(bufs.iter().all(|buf| !buf.read().unwrap().is_dirty()));
+    }
+
+    #[test]
+    fn test_evicted_dirty_buffer_is_written() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).expect("Failed to create root directory");
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).expect("Failed to create root directory");
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let mut bm = BufferManager::new(1, fm.clone(), lm.clone(), SimpleEvictionPolicy::new());
+        let blk_a = fm.append_block("test", &Page::new()).unwrap();
+        let blk_b = fm.append_block("test", &Page::new()).unwrap();
+
+        let lsn = lm.lock().unwrap().append(b"change to a").unwrap();
+        let buf = bm.pin(&blk_a).unwrap();
+        {
+            let mut b = buf.write().unwrap();
+            b.page.write(42u32, 0);
+            b.set_modified(1, lsn);
+            bm.unpin_locked(&mut b);
+        }
+
+        // Pinning another block evicts A, forcing its page to disk after the log record
+        let buf = bm.pin(&blk_b).unwrap();
+        assert_eq!(buf.read().unwrap().blk, Some(blk_b));
+        let mut page = Page::new();
+        fm.get_block(&blk_a, &mut page).unwrap();
+        assert_eq!(page.read::<u32>(0), 42);
+        let logged: Vec<Vec<u8>> = LogManager::new(&log_dir).snapshot().collect();
+        assert_eq!(logged, vec![b"change to a".to_vec()]);
+    }
 }
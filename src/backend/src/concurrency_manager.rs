@@ -1,11 +1,32 @@
 use std::{collections::HashMap, sync::Arc};
 
-use crate::{block_id::BlockId, lock_table::LockTable};
+use crate::{
+    block_id::BlockId,
+    lock_table::{LockAbortError, LockKey, LockTable},
+};
+
+/// The unit that a transaction's locks are taken on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockGranularity {
+    /// Lock each block that is read or written.
+    #[default]
+    Block,
+    /// Lock the whole file of any block that is read or written, with a single lock per file.
+    /// This takes far fewer locks for operations that touch a whole table, at the cost of
+    /// concurrency. Transactions locking blocks take intention locks on their files, so a table
+    /// lock still conflicts with the block locks of other transactions.
+    Table,
+}
 
 /// Manages locks for a single Transaction.
 pub struct ConcurrencyManager {
     lock_tbl: Arc<LockTable>,
-    locks: HashMap<BlockId, char>,
+    granularity: LockGranularity,
+    locks: HashMap<LockKey, char>,
+    // The intention locks held on files under block-level locking, by file
+    intentions: HashMap<String, char>,
+    slocks_acquired: u64,
+    xlocks_acquired: u64,
 }
 
 impl ConcurrencyManager {
@@ -17,45 +38,150 @@ impl ConcurrencyManager {
     pub fn new(lock_tbl: Arc<LockTable>) -> Self {
         Self {
             lock_tbl,
+            granularity: LockGranularity::Block,
             locks: HashMap::new(),
+            intentions: HashMap::new(),
+            slocks_acquired: 0,
+            xlocks_acquired: 0,
         }
     }
 
-    /// Take a shared lock on a block.
+    /// Set the unit that locks are taken on. This must be set before any lock is taken.
+    pub fn set_granularity(&mut self, granularity: LockGranularity) {
+        self.granularity = granularity;
+    }
+
+    /// Take a shared lock on a block. Returns an error if the lock could not be acquired before
+    /// the lock table's maximum wait elapsed.
     ///
     /// # Arguments
     ///
     /// * `blk` - The block to lock.
-    pub fn slock(&mut self, blk: &BlockId) {
-        if self.locks.get(blk).is_none() {
-            self.lock_tbl.slock(blk);
-            self.locks.insert(blk.clone(), 'S');
-        }
+    pub fn slock(&mut self, blk: &BlockId) -> Result<(), LockAbortError> {
+        self.slock_key(LockKey::Block(blk.clone()))
     }
 
-    /// Take an exclusive lock on a block.
+    /// Take an exclusive lock on a block. Returns an error if the lock could not be acquired
+    /// before the lock table's maximum wait elapsed.
     ///
     /// # Arguments
     ///
     /// * `blk` - The block to lock.
-    pub fn xlock(&mut self, blk: &BlockId) {
-        if !self.has_xlock(blk) {
-            // TODO: I don't really understand why an slock needs to be taken before the xlock
-            self.slock(blk);
-            self.lock_tbl.xlock(blk);
-            self.locks.insert(blk.clone(), 'X');
-        }
+    pub fn xlock(&mut self, blk: &BlockId) -> Result<(), LockAbortError> {
+        self.xlock_key(LockKey::Block(blk.clone()))
+    }
+
+    /// Take a shared lock on the end of a file, which prevents other transactions from appending
+    /// to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The file to lock.
+    pub fn slock_end_of_file(&mut self, file_id: &str) -> Result<(), LockAbortError> {
+        self.slock_key(LockKey::EndOfFile(file_id.to_string()))
+    }
+
+    /// Take an exclusive lock on the end of a file, which prevents other transactions from
+    /// reading its size or appending to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The file to lock.
+    pub fn xlock_end_of_file(&mut self, file_id: &str) -> Result<(), LockAbortError> {
+        self.xlock_key(LockKey::EndOfFile(file_id.to_string()))
     }
 
     /// Release all locks.
     pub fn release(&mut self) {
-        for (blk, _) in self.locks.iter() {
-            self.lock_tbl.unlock(blk);
+        for (key, _) in self.locks.iter() {
+            self.lock_tbl.unlock(key);
         }
         self.locks.clear();
+        for (file_id, lock) in self.intentions.iter() {
+            self.lock_tbl
+                .unlock_intention(&LockKey::File(file_id.clone()), *lock == 'X');
+        }
+        self.intentions.clear();
+    }
+
+    /// The number of shared locks acquired since the manager was created. Requests for a lock
+    /// that is already held are not counted.
+    pub fn slocks_acquired(&self) -> u64 {
+        self.slocks_acquired
+    }
+
+    /// The number of exclusive locks acquired since the manager was created. Requests for a lock
+    /// that is already held are not counted.
+    pub fn xlocks_acquired(&self) -> u64 {
+        self.xlocks_acquired
+    }
+
+    fn slock_key(&mut self, key: LockKey) -> Result<(), LockAbortError> {
+        let key = self.lock_target(key);
+        if !self.locks.contains_key(&key) {
+            self.intention_lock(&key, 'S')?;
+            self.lock_tbl.slock(&key)?;
+            self.locks.insert(key, 'S');
+            self.slocks_acquired += 1;
+        }
+        Ok(())
+    }
+
+    // A shared lock taken before an exclusive lock times out is kept until the locks are released.
+    fn xlock_key(&mut self, key: LockKey) -> Result<(), LockAbortError> {
+        let key = self.lock_target(key);
+        if !self.has_xlock(&key) {
+            self.intention_lock(&key, 'X')?;
+            // TODO: I don't really understand why an slock needs to be taken before the xlock
+            self.slock_key(key.clone())?;
+            self.lock_tbl.xlock(&key)?;
+            self.locks.insert(key, 'X');
+            self.xlocks_acquired += 1;
+        }
+        Ok(())
+    }
+
+    // The key whose lock covers `key` under this manager's granularity. Under table-level
+    // locking, the whole file's lock covers its blocks and its end.
+    fn lock_target(&self, key: LockKey) -> LockKey {
+        match (self.granularity, key) {
+            (LockGranularity::Table, LockKey::Block(blk)) => {
+                LockKey::File(blk.file_id().to_string())
+            }
+            (LockGranularity::Table, LockKey::EndOfFile(file_id)) => LockKey::File(file_id),
+            (_, key) => key,
+        }
+    }
+
+    // Take an intention lock of the given mode on the file of a block or file end, before the
+    // block or file end itself is locked. Whole files are only locked under table-level locking,
+    // and need no intention lock.
+    fn intention_lock(&mut self, key: &LockKey, mode: char) -> Result<(), LockAbortError> {
+        let file_id = match key {
+            LockKey::Block(blk) => blk.file_id(),
+            LockKey::EndOfFile(file_id) => file_id,
+            LockKey::File(_) => return Ok(()),
+        };
+        let held = self.intentions.get(file_id).copied();
+        let file = LockKey::File(file_id.to_string());
+        if held.is_none() {
+            self.lock_tbl.islock(&file)?;
+            self.intentions.insert(file_id.to_string(), 'S');
+        }
+        if mode == 'X' && held != Some('X') {
+            self.lock_tbl.ixlock(&file)?;
+            self.intentions.insert(file_id.to_string(), 'X');
+        }
+        Ok(())
+    }
+
+    fn has_xlock(&self, key: &LockKey) -> bool {
+        self.locks.get(key).is_some_and(|l| *l == 'X')
     }
 
-    fn has_xlock(&self, blk: &BlockId) -> bool {
-        self.locks.get(blk).is_some_and(|l| *l == 'X')
+    #[cfg(test)]
+    pub fn is_locked(&self, blk: &BlockId) -> bool {
+        self.locks
+            .contains_key(&self.lock_target(LockKey::Block(blk.clone())))
     }
 }
@@ -1,32 +1,158 @@
 use std::{
-    fs,
+    fmt::Display,
+    fs, io,
     path::Path,
     sync::{Arc, Mutex, RwLock},
+    thread,
+    time::Duration,
 };
 
+use tracing::warn;
+
 use crate::{
-    buffer_manager::BufferManager,
+    admission::AdmissionGate,
+    buffer_manager::{BufferError, BufferManager},
     eviction_policy::SimpleEvictionPolicy,
-    file_manager::{self, FileManager},
+    file_manager::{self, FileManager, DEFAULT_TEMP_DIRECTORY},
     lock_table::LockTable,
     log_manager::LogManager,
+    log_record::LogRecord,
     metadata::metadata_manager::MetadataManager,
-    transaction::Tx,
+    parser::parser::{parse, RootNode},
+    planning::{
+        basic_query_planner::BasicQueryPlanner, index_update_planner::IndexUpdatePlanner,
+        planner::Planner, query_planner::QueryPlanner, update_planner::RowCount,
+        validator::Validator,
+    },
+    row_stream::RowStream,
+    scan::scan::ScanError,
+    transaction::{Tx, TxError},
 };
 
 const DEFAULT_BUFFER_SIZE: usize = 1024;
 
+/// How long `SimpleDB::with_transaction` waits before its first retry. The wait doubles after
+/// each retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Errors returned by the `SimpleDB` API.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DbError {
+    /// The statement is not syntactically valid.
+    Parse(String),
+    /// The statement references tables or fields that do not exist, or uses them incorrectly.
+    Plan(String),
+    /// Reading or writing the files of the database failed.
+    Io(String),
+    /// Running transactions hold every buffer, so no block can be read until one finishes.
+    Concurrency(String),
+    /// No transaction could be started before the timeout expired.
+    AdmissionTimeout,
+    /// A transaction could not make a change.
+    Tx(TxError),
+    /// A scan could not read or write a record.
+    Scan(ScanError),
+}
+
+impl Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(msg) => write!(f, "parse error: {}", msg),
+            Self::Plan(msg) => write!(f, "plan error: {}", msg),
+            Self::AdmissionTimeout => write!(f, "timed out waiting to start a transaction"),
+            Self::Io(msg) => write!(f, "I/O error: {}", msg),
+            Self::Concurrency(msg) => write!(f, "concurrency error: {}", msg),
+            Self::Tx(e) => write!(f, "transaction error: {}", e),
+            Self::Scan(e) => write!(f, "scan error: {:?}", e),
+        }
+    }
+}
+
+impl DbError {
+    /// Whether the error is caused by other running transactions, so that trying again in a new
+    /// transaction may succeed once they finish.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Concurrency(_) | Self::AdmissionTimeout)
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<BufferError> for DbError {
+    fn from(e: BufferError) -> Self {
+        match e {
+            BufferError::NoAvailableBuffers(_) => Self::Concurrency(e.to_string()),
+            BufferError::Flush(..) => Self::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<TxError> for DbError {
+    fn from(e: TxError) -> Self {
+        match e {
+            TxError::Buffer(e) => e.into(),
+            TxError::LockAbort(e) => Self::Concurrency(e.to_string()),
+            e => Self::Tx(e),
+        }
+    }
+}
+
+impl From<ScanError> for DbError {
+    fn from(e: ScanError) -> Self {
+        match e {
+            ScanError::Tx(e) => e.into(),
+            e => Self::Scan(e),
+        }
+    }
+}
+
 pub struct SimpleDB {
     buffer_manager: Arc<Mutex<BufferManager<SimpleEvictionPolicy>>>,
     file_manager: Arc<FileManager>,
     lock_table: Arc<LockTable>,
     log_manager: Arc<Mutex<LogManager>>,
     metadata_manager: Arc<RwLock<MetadataManager>>,
+    admission: Arc<AdmissionGate>,
 }
 
 impl SimpleDB {
+    /// Create a database whose files are kept in `data_dir` and whose log is kept in `log_dir`.
+    /// The buffer pool holds `num_bufs` buffers, each the size of one page; the page size is
+    /// fixed, and given by `page_size`.
     pub fn new(data_dir: &Path, log_dir: &Path, num_bufs: usize) -> Self {
-        let file_manager = Arc::new(FileManager::new(data_dir));
+        Self::with_temp_directory(data_dir, log_dir, num_bufs, DEFAULT_TEMP_DIRECTORY)
+    }
+
+    /// Create a database that keeps the temporary tables of sorts and other materializing
+    /// operators in the named subdirectory of `data_dir`.
+    pub fn with_temp_directory(
+        data_dir: &Path,
+        log_dir: &Path,
+        num_bufs: usize,
+        temp_directory: &str,
+    ) -> Self {
+        Self::init(data_dir, log_dir, num_bufs, temp_directory, false)
+    }
+
+    /// Open a database that was created earlier, e.g. before a crash. The changes of transactions
+    /// that had not committed when the database was last shut down are undone, so only committed
+    /// changes survive.
+    pub fn open(data_dir: &Path, log_dir: &Path, num_bufs: usize) -> Self {
+        Self::init(data_dir, log_dir, num_bufs, DEFAULT_TEMP_DIRECTORY, true)
+    }
+
+    fn init(
+        data_dir: &Path,
+        log_dir: &Path,
+        num_bufs: usize,
+        temp_directory: &str,
+        recover: bool,
+    ) -> Self {
+        let file_manager = Arc::new(FileManager::with_temp_directory(data_dir, temp_directory));
         let log_manager = Arc::new(Mutex::new(LogManager::new(log_dir)));
         let buffer_manager = Arc::new(Mutex::new(BufferManager::new(
             num_bufs,
@@ -42,10 +168,24 @@ impl SimpleDB {
             buffer_manager.clone(),
             lock_table.clone(),
         )));
+        if recover {
+            tx.lock().unwrap().recover();
+        }
 
-        let metadata_manager = Arc::new(RwLock::new(MetadataManager::new(&tx)));
+        let metadata_manager = MetadataManager::new(&tx);
+        if recover {
+            // A crash during a DDL statement can leave the catalogs partially written
+            for problem in metadata_manager.verify(&tx) {
+                warn!("inconsistent metadata catalog: {}", problem);
+            }
+        }
+        let metadata_manager = Arc::new(RwLock::new(metadata_manager));
 
-        tx.lock().unwrap().commit();
+        // TODO: error handling
+        tx.lock()
+            .unwrap()
+            .commit()
+            .expect("failed to commit database initialization");
 
         Self {
             buffer_manager,
@@ -53,16 +193,105 @@ impl SimpleDB {
             log_manager,
             lock_table,
             metadata_manager,
+            admission: Arc::new(AdmissionGate::new()),
         }
     }
 
+    /// Allow at most `max` transactions to run at once, so that a burst of transactions cannot
+    /// exhaust the buffer pool. Further transactions wait until a running one commits, rolls
+    /// back, or is dropped.
+    pub fn with_max_transactions(mut self, max: usize) -> Self {
+        self.admission = Arc::new(AdmissionGate::new().with_limit(max));
+        self
+    }
+
+    /// Start a new transaction. Waits while a checkpoint is being written, or while the maximum
+    /// number of transactions are running.
     pub fn new_tx(&self) -> Tx {
+        self.admission.enter();
+        self.admitted_tx()
+    }
+
+    /// Start a new transaction, like `new_tx`, but return an error if it cannot start within
+    /// `timeout`.
+    pub fn try_new_tx(&self, timeout: Duration) -> Result<Tx, DbError> {
+        if !self.admission.try_enter(timeout) {
+            return Err(DbError::AdmissionTimeout);
+        }
+        Ok(self.admitted_tx())
+    }
+
+    fn admitted_tx(&self) -> Tx {
         Tx::new(
             self.file_manager(),
             self.log_manager(),
             self.buffer_manager(),
             self.lock_table(),
         )
+        .with_admission(self.admission.clone())
+    }
+
+    /// Write a quiescent checkpoint. New transactions are kept from starting while every running
+    /// transaction finishes, then all modified buffers are written to storage and a checkpoint
+    /// record is added to the log, so that recovery need not read any further back. Transactions
+    /// started with `new_tx` must commit, roll back, or be dropped for the checkpoint to complete.
+    pub fn checkpoint(&self) {
+        self.admission.close();
+        self.write_checkpoint();
+        self.admission.open();
+    }
+
+    /// Copy a consistent image of the database to `dest_dir`. A checkpoint is written as by
+    /// `checkpoint`, and no transaction may start until every file has been copied, so the backup
+    /// holds exactly the changes of the transactions committed before it was taken. The data
+    /// files are copied to the `data` subdirectory of `dest_dir` and the log to its `log`
+    /// subdirectory, and the backup is restored by opening those with `SimpleDB::open`.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest_dir` - The directory the backup is written to. It is created if it does not exist.
+    pub fn snapshot_backup(&self, dest_dir: &Path) -> Result<(), DbError> {
+        self.admission.close();
+        self.write_checkpoint();
+        let copied = self.copy_files(dest_dir).map_err(DbError::from);
+        self.admission.open();
+        copied
+    }
+
+    // Write every modified buffer to storage and log a checkpoint. No transaction may be running.
+    fn write_checkpoint(&self) {
+        self.buffer_manager.lock().unwrap().flush_dirty();
+
+        // TODO: error handling
+        let encoded = bincode::serialize(&LogRecord::Checkpoint).unwrap();
+        let mut log_manager = self.log_manager.lock().unwrap();
+        let lsn = log_manager
+            .append(&encoded)
+            .expect("failed to log checkpoint");
+        log_manager.flush(lsn).expect("failed to flush checkpoint");
+    }
+
+    fn copy_files(&self, dest_dir: &Path) -> io::Result<()> {
+        let data_dir = dest_dir.join("data");
+        fs::create_dir_all(&data_dir)?;
+        for entry in fs::read_dir(self.file_manager.root_directory())? {
+            // Temporary files live in a subdirectory and are not needed by a restored database
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                copy_file(&entry.path(), &data_dir.join(entry.file_name()))?;
+            }
+        }
+
+        let log_dir = dest_dir.join("log");
+        fs::create_dir_all(&log_dir)?;
+        let log_file = self.log_manager.lock().unwrap().file_path();
+        let log_name = log_file.file_name().expect("log file has no name");
+        copy_file(&log_file, &log_dir.join(log_name))
+    }
+
+    /// Get the size, in bytes, of the pages that blocks are read into and written from.
+    pub fn page_size(&self) -> usize {
+        self.file_manager.page_size()
     }
 
     pub fn buffer_manager(&self) -> Arc<Mutex<BufferManager>> {
@@ -84,4 +313,546 @@ impl SimpleDB {
     pub fn metadata_manager(&self) -> Arc<RwLock<MetadataManager>> {
         self.metadata_manager.clone()
     }
+
+    /// Execute an update statement: an insert, delete or modification of records, or a statement
+    /// that creates, alters or refreshes a table, view or index. Indexes on changed tables are
+    /// kept up to date. Returns the number of records changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - The statement to execute.
+    /// * `tx` - The transaction the statement runs in.
+    pub fn execute(&self, sql: &str, tx: &Arc<Mutex<Tx>>) -> Result<RowCount, DbError> {
+        let ast = parse(sql).map_err(DbError::Parse)?;
+        let mut planner = Planner::new(
+            Box::new(BasicQueryPlanner::new(self.metadata_manager())),
+            Box::new(IndexUpdatePlanner::new(self.metadata_manager())),
+        );
+        planner
+            .execute_statement(ast, tx.clone())
+            .map_err(DbError::Plan)
+    }
+
+    /// Run a query, returning a stream that reads its records from storage as it is iterated
+    /// rather than all at once. The transaction must not commit or roll back until the stream is
+    /// dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - The SELECT statement to run.
+    /// * `tx` - The transaction the query runs in.
+    pub fn query(&self, sql: &str, tx: &Arc<Mutex<Tx>>) -> Result<RowStream, DbError> {
+        // The planner expects the tables and fields of the query to exist
+        let ast = parse(sql).map_err(DbError::Parse)?;
+        Validator::new(self.metadata_manager())
+            .validate(&ast, tx)
+            .map_err(DbError::Plan)?;
+        let select = match ast {
+            RootNode::Select(select) => select,
+            _ => {
+                return Err(DbError::Plan(
+                    "only a SELECT statement can be queried".to_string(),
+                ))
+            }
+        };
+        let mut plan = BasicQueryPlanner::new(self.metadata_manager())
+            .create_plan(&select, tx.clone())
+            .map_err(DbError::Plan)?;
+
+        Ok(RowStream::new(plan.as_mut(), tx.clone()))
+    }
+
+    /// Check that a statement is valid without executing it.
+    ///
+    /// The statement is parsed and resolved against the metadata catalogs, catching unknown
+    /// tables, unknown fields, and type mismatches. No records are read from or written to the
+    /// tables referenced by the statement.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - The statement to validate.
+    /// * `tx` - The transaction used to read the metadata catalogs.
+    pub fn validate(&self, sql: &str, tx: &Arc<Mutex<Tx>>) -> Result<(), DbError> {
+        let ast = parse(sql).map_err(DbError::Parse)?;
+        Validator::new(self.metadata_manager())
+            .validate(&ast, tx)
+            .map_err(DbError::Plan)
+    }
+
+    /// Run `f` in a new transaction, committing the transaction if `f` succeeds. If `f` fails with
+    /// an error that other transactions caused, such as every buffer being held by them, the
+    /// transaction is aborted and `f` is run again in a new one, waiting longer before each retry.
+    /// Any other error, or a retryable error once `max_retries` retries have failed, aborts the
+    /// transaction and is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - The most times `f` is run again after its first attempt.
+    /// * `f` - The work done in the transaction. It may run more than once.
+    pub fn with_transaction<T, F>(&self, max_retries: u32, mut f: F) -> Result<T, DbError>
+    where
+        F: FnMut(&Arc<Mutex<Tx>>) -> Result<T, DbError>,
+    {
+        let mut retries = 0;
+        let mut backoff = RETRY_BACKOFF;
+        loop {
+            let tx = Arc::new(Mutex::new(self.new_tx()));
+            let result = f(&tx);
+            let mut tx = tx.lock().unwrap();
+            match result {
+                Ok(val) => {
+                    // A transaction that fails to commit is still active, and is aborted
+                    if let Err(e) = tx.commit() {
+                        abort(&mut tx, &e.to_string());
+                        return Err(e.into());
+                    }
+                    return Ok(val);
+                }
+                Err(e) => {
+                    abort(&mut tx, &e.to_string());
+                    if !e.is_retryable() || retries == max_retries {
+                        return Err(e);
+                    }
+                }
+            }
+
+            warn!("retrying transaction after error, attempt {}", retries + 1);
+            retries += 1;
+            thread::sleep(backoff);
+            backoff *= 2;
+        }
+    }
+}
+
+// Abort a transaction after an error. If the abort itself fails, the error that caused it is
+// still the one reported, and the transaction's changes are undone by recovery.
+fn abort(tx: &mut Tx, reason: &str) {
+    if let Err(e) = tx.abort(reason) {
+        warn!("failed to abort transaction {}: {}", tx.tx_num(), e);
+    }
+}
+
+// Copy a file, making sure the copy is on durable storage.
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    fs::File::open(to)?.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{
+        block_id::BlockId,
+        buffer_manager::BufferError,
+        layout::Layout,
+        log_manager::LogError,
+        log_record::LogRecord,
+        make_schema,
+        page::{Page, PAGE_SIZE},
+        scan::scan::{ScanError, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::{create_default_tables, test_db},
+        transaction::TxError,
+    };
+
+    use super::{DbError, SimpleDB};
+
+    #[test]
+    fn test_validate_valid_statements() {
+        let td = tempdir().unwrap();
+        let mut db = test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        for sql in [
+            "SELECT sname, dname FROM student, dept WHERE major_id = did",
+            "SELECT * FROM student WHERE sname = 'joe'",
+            "INSERT INTO student (sid, sname) VALUES (10, 'kim')",
+            "DELETE FROM student WHERE grad_year = 2020",
+            "UPDATE student SET grad_year = 2024 WHERE sid = 1",
+            "CREATE TABLE foo (a INT, b VARCHAR(5))",
+            "CREATE INDEX idx ON student (grad_year)",
+            "ALTER TABLE student ADD COLUMN gpa INT",
+            "ALTER TABLE student DROP COLUMN grad_year",
+            "CREATE MATERIALIZED VIEW cs AS SELECT sid FROM student WHERE major_id = 10",
+            "SELECT sname FROM student WHERE grad_year = '2021'",
+        ] {
+            assert_eq!(db.validate(sql, &tx), Ok(()), "{}", sql);
+        }
+
+        // Validation must not touch any of the records in the referenced tables.
+        let tx = tx.lock().unwrap();
+        for tbl in ["student", "dept"] {
+            assert!(!tx.is_locked(&BlockId::new(tbl, 0)));
+        }
+    }
+
+    #[test]
+    fn test_validate_invalid_statements() {
+        let td = tempdir().unwrap();
+        let mut db = test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(
+            db.validate("SELECT sname, gpa FROM student", &tx),
+            Err(DbError::Plan("field 'gpa' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("SELECT sname FROM student ORDER BY gpa", &tx),
+            Err(DbError::Plan("field 'gpa' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("SELECT sname, 3 FROM student", &tx),
+            Err(DbError::Plan(
+                "constant 3 can only be selected by a query without a FROM clause".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("SELECT 3, sname", &tx),
+            Err(DbError::Plan("field 'sname' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("SELECT 3, 3", &tx),
+            Err(DbError::Plan(
+                "field '3' is selected more than once".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("SELECT major_id FROM student HAVING major_id = 10", &tx),
+            Err(DbError::Plan(
+                "HAVING requires GROUP BY or an aggregate".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate(
+                "SELECT sname, count(sid) FROM student GROUP BY major_id",
+                &tx
+            ),
+            Err(DbError::Plan(
+                "field 'sname' must be in the GROUP BY clause or used in an aggregate".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("SELECT sum(sname) FROM student", &tx),
+            Err(DbError::Plan(
+                "cannot compute sum(sname): 'sname' is not an integer".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate(
+                "SELECT major_id FROM student GROUP BY major_id HAVING count(sid) > 2",
+                &tx
+            ),
+            Err(DbError::Plan(
+                "field 'count(sid)' does not exist".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate(
+                "SELECT major_id, count(sid) FROM student GROUP BY major_id HAVING count(sid) > 2 ORDER BY major_id",
+                &tx
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            db.validate("SELECT sname FROM students", &tx),
+            Err(DbError::Plan("table 'students' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("SELECT sname FROM student WHERE sid = 'joe'", &tx),
+            Err(DbError::Plan(
+                "cannot compare 'sid' with ''joe'': 'joe' is not an integer".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("SELECT sname FROM student WHERE sname = 10", &tx),
+            Err(DbError::Plan(
+                "cannot compare 'sname' with '10': mismatched types".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("INSERT INTO student (sid, sname) VALUES ('kim', 10)", &tx),
+            Err(DbError::Plan(
+                "cannot assign 'kim' to field 'sid': mismatched types".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("UPDATE student SET gpa = 4 WHERE sid = 1", &tx),
+            Err(DbError::Plan("field 'gpa' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("ALTER TABLE student DROP COLUMN gpa", &tx),
+            Err(DbError::Plan("field 'gpa' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("ALTER TABLE student ADD COLUMN sname VARCHAR(5)", &tx),
+            Err(DbError::Plan(
+                "field 'sname' already exists in table 'student'".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate(
+                "CREATE MATERIALIZED VIEW names AS SELECT sname, dname FROM student, dept",
+                &tx
+            ),
+            Err(DbError::Plan(
+                "materialized view 'names' must select from a single table".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("REFRESH VIEW student", &tx),
+            Err(DbError::Plan("view 'student' does not exist".to_string()))
+        );
+        assert_eq!(
+            db.validate("CREATE TABLE student (a INT)", &tx),
+            Err(DbError::Plan(
+                "a table or view named 'student' already exists".to_string()
+            ))
+        );
+        assert_eq!(
+            db.validate("CREATE TABLE twenty_characters_xx (a INT)", &tx),
+            Err(DbError::Plan(
+                "table name 'twenty_characters_xx' is 20 bytes long, exceeding the maximum of 16 bytes"
+                    .to_string()
+            ))
+        );
+        assert!(matches!(
+            db.validate("SELECT FROM student", &tx),
+            Err(DbError::Parse(_))
+        ));
+
+        let tx = tx.lock().unwrap();
+        assert!(!tx.is_locked(&BlockId::new("student", 0)));
+    }
+
+    #[test]
+    fn test_execute_errors() {
+        let td = tempdir().unwrap();
+        let mut db = test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(
+            db.execute("INSERT INTO dept (did, dname) VALUES (40, 'art')", &tx),
+            Ok(1)
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO dept VALUES", &tx),
+            Err(DbError::Parse(_))
+        ));
+        assert!(matches!(
+            db.execute("DELETE FROM depts WHERE did = 40", &tx),
+            Err(DbError::Plan(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT did FROM dept", &tx),
+            Err(DbError::Plan(_))
+        ));
+        tx.lock().unwrap().commit().unwrap();
+
+        // A backup cannot be written beneath a regular file
+        let file = td.path().join("not-a-directory");
+        fs::write(&file, "").unwrap();
+        assert!(matches!(
+            db.snapshot_backup(&file.join("backup")),
+            Err(DbError::Io(_))
+        ));
+
+        // Errors of lower layers convert to the matching category
+        let blk = BlockId::new("student", 0);
+        assert!(matches!(
+            DbError::from(ScanError::Tx(TxError::Buffer(
+                BufferError::NoAvailableBuffers(blk.clone())
+            ))),
+            DbError::Concurrency(_)
+        ));
+        assert_eq!(
+            DbError::from(ScanError::Tx(TxError::Log(LogError::RecordTooLarge(1)))),
+            DbError::Tx(TxError::Log(LogError::RecordTooLarge(1)))
+        );
+        assert_eq!(
+            DbError::from(ScanError::Closed),
+            DbError::Scan(ScanError::Closed)
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_waits_for_running_transactions() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let layout = Layout::from_schema(make_schema! { "A" => i32 });
+
+        let running: Vec<_> = (0..3)
+            .map(|i| {
+                let tx = Arc::new(Mutex::new(db.new_tx()));
+                let mut scan = TableScan::new(tx.clone(), layout.clone(), &format!("t{}", i));
+                scan.insert();
+                scan.set_int("A", i);
+                tx
+            })
+            .collect();
+        let running_nums: Vec<i64> = running
+            .iter()
+            .map(|tx| tx.lock().unwrap().tx_num())
+            .collect();
+
+        let checkpointed = AtomicBool::new(false);
+        let late_num = thread::scope(|s| {
+            s.spawn(|| {
+                db.checkpoint();
+                checkpointed.store(true, Ordering::SeqCst);
+            });
+
+            // Neither the checkpoint nor a new transaction can proceed while others are running
+            thread::sleep(Duration::from_millis(100));
+            let late = s.spawn(|| {
+                let mut tx = db.new_tx();
+                tx.commit().unwrap();
+                tx.tx_num()
+            });
+            thread::sleep(Duration::from_millis(100));
+            assert!(!checkpointed.load(Ordering::SeqCst));
+
+            for tx in &running {
+                tx.lock().unwrap().commit().unwrap();
+            }
+            late.join().unwrap()
+        });
+        assert!(checkpointed.load(Ordering::SeqCst));
+
+        // Read the log from the newest record back
+        let records: Vec<LogRecord> = db
+            .log_manager()
+            .lock()
+            .unwrap()
+            .snapshot()
+            .map(|r| bincode::deserialize(&r).unwrap())
+            .collect();
+        let position = |f: &dyn Fn(&LogRecord) -> bool| records.iter().position(f).unwrap();
+        let checkpoint = position(&|r| matches!(r, LogRecord::Checkpoint));
+        for tx_num in running_nums {
+            let commit =
+                position(&|r| matches!(r, LogRecord::Commit { tx_num: n } if *n == tx_num));
+            assert!(commit > checkpoint);
+        }
+        let late_commit =
+            position(&|r| matches!(r, LogRecord::Commit { tx_num: n } if *n == late_num));
+        assert!(late_commit < checkpoint);
+    }
+
+    #[test]
+    fn test_max_transactions() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td).with_max_transactions(2);
+
+        let mut tx1 = db.new_tx();
+        let tx2 = db.new_tx();
+        assert!(matches!(
+            db.try_new_tx(Duration::from_millis(50)),
+            Err(DbError::AdmissionTimeout)
+        ));
+
+        let started = AtomicBool::new(false);
+        thread::scope(|s| {
+            let waiting = s.spawn(|| {
+                let mut tx = db.new_tx();
+                started.store(true, Ordering::SeqCst);
+                tx.commit().unwrap();
+            });
+
+            thread::sleep(Duration::from_millis(100));
+            assert!(!started.load(Ordering::SeqCst));
+            tx1.commit().unwrap();
+            waiting.join().unwrap();
+            assert!(started.load(Ordering::SeqCst));
+        });
+
+        // Dropping a transaction also makes room for another
+        let _tx3 = db.try_new_tx(Duration::from_millis(50)).unwrap();
+        drop(tx2);
+        assert!(db.try_new_tx(Duration::from_millis(50)).is_ok());
+    }
+
+    #[test]
+    fn test_page_size() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        assert_eq!(db.page_size(), PAGE_SIZE);
+        assert_eq!(db.page_size(), db.file_manager().page_size());
+        assert_eq!(db.page_size(), db.new_tx().block_size());
+
+        // Each appended block takes up one page of the file
+        let fm = db.file_manager();
+        fm.append_block("sized", &Page::new()).unwrap();
+        let path = td.path().join("data").join("sized");
+        let len = fs::metadata(&path).unwrap().len();
+        fm.append_block("sized", &Page::new()).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().len() - len,
+            db.page_size() as u64
+        );
+    }
+
+    #[test]
+    fn test_with_transaction_retries_transient_errors() {
+        let td = tempdir().unwrap();
+        let (data_dir, log_dir) = (td.path().join("data"), td.path().join("log"));
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(&log_dir).unwrap();
+        let db = SimpleDB::new(&data_dir, &log_dir, 3);
+        db.buffer_manager()
+            .lock()
+            .unwrap()
+            .set_max_wait(Duration::from_millis(100));
+
+        // Another transaction holds every buffer until the first attempt fails
+        let mut blocker = db.new_tx();
+        let blks: Vec<BlockId> = (0..4).map(|_| blocker.append("retry").unwrap()).collect();
+        for blk in &blks[..3] {
+            blocker.pin(blk);
+        }
+        let mut blocker = Some(blocker);
+
+        let mut attempts = 0;
+        let result = db.with_transaction(3, |tx| {
+            attempts += 1;
+            let pinned = tx.lock().unwrap().try_pin(&blks[3]);
+            if let Some(mut blocker) = blocker.take() {
+                blocker.rollback().unwrap();
+            }
+            pinned?;
+            Ok(attempts)
+        });
+        assert_eq!(result, Ok(2));
+
+        // Errors that another attempt cannot fix are returned at once
+        let mut attempts = 0;
+        let result: Result<(), DbError> = db.with_transaction(3, |_| {
+            attempts += 1;
+            Err(DbError::Plan("no such table".to_string()))
+        });
+        assert_eq!(result, Err(DbError::Plan("no such table".to_string())));
+        assert_eq!(attempts, 1);
+
+        // Retryable errors are returned once every retry has failed
+        let mut attempts = 0;
+        let result: Result<(), DbError> = db.with_transaction(2, |_| {
+            attempts += 1;
+            Err(DbError::AdmissionTimeout)
+        });
+        assert_eq!(result, Err(DbError::AdmissionTimeout));
+        assert_eq!(attempts, 3);
+    }
 }
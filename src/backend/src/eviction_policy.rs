@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 pub trait EvictionPolicy {
     /// Adds a new frame to be available for eviction.
     fn add(&mut self, frame: usize);
@@ -39,3 +41,124 @@ impl EvictionPolicy for SimpleEvictionPolicy {
         self.frames.pop()
     }
 }
+
+/// Evicts the least recently unpinned frame, so that blocks that are used often stay in the pool.
+pub struct LruEvictionPolicy {
+    /// Frames available for eviction, from least to most recently added.
+    frames: VecDeque<usize>,
+}
+
+impl LruEvictionPolicy {
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+        }
+    }
+}
+
+impl EvictionPolicy for LruEvictionPolicy {
+    fn add(&mut self, frame: usize) {
+        // A frame that is added again has been used again, so it becomes the most recent
+        self.remove(frame);
+        self.frames.push_back(frame);
+    }
+
+    fn remove(&mut self, frame: usize) {
+        if let Some(i) = self.frames.iter().position(|f| *f == frame) {
+            self.frames.remove(i);
+        }
+    }
+
+    fn evict(&mut self) -> Option<usize> {
+        self.frames.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use tempfile::{tempdir, TempDir};
+
+    use crate::{
+        block_id::BlockId, buffer_manager::BufferManager, file_manager::FileManager,
+        log_manager::LogManager, page::Page,
+    };
+
+    use super::{EvictionPolicy, LruEvictionPolicy, SimpleEvictionPolicy};
+
+    // Create a buffer manager over a file of `num_blocks` blocks.
+    fn buffer_manager<E: EvictionPolicy>(
+        td: &TempDir,
+        size: usize,
+        num_blocks: u64,
+        eviction_policy: E,
+    ) -> BufferManager<E> {
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let fm = Arc::new(FileManager::new(&data_dir));
+        for _ in 0..num_blocks {
+            fm.append_block("test", &Page::new()).unwrap();
+        }
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        BufferManager::new(size, fm, lm, eviction_policy)
+    }
+
+    // Pin and immediately unpin each block in turn.
+    fn access<E: EvictionPolicy>(bm: &mut BufferManager<E>, blocks: &[u64]) {
+        for num in blocks {
+            let buf = bm.pin(&BlockId::new("test", *num)).unwrap();
+            bm.unpin(&buf);
+        }
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_unpinned() {
+        let td = tempdir().unwrap();
+        let mut bm = buffer_manager(&td, 2, 3, LruEvictionPolicy::new());
+
+        // Block 1 is unpinned before block 0, so it is the victim when block 2 is read
+        let buf0 = bm.pin(&BlockId::new("test", 0)).unwrap();
+        let buf1 = bm.pin(&BlockId::new("test", 1)).unwrap();
+        bm.unpin(&buf1);
+        bm.unpin(&buf0);
+        let buf2 = bm.pin(&BlockId::new("test", 2)).unwrap();
+        assert!(Arc::ptr_eq(&buf1, &buf2));
+        bm.unpin(&buf2);
+        assert_eq!(bm.stats().misses, 3);
+
+        access(&mut bm, &[0]);
+        assert_eq!(bm.stats().hits, 1);
+        access(&mut bm, &[1]);
+        assert_eq!(bm.stats().misses, 4);
+
+        // Block 2 was least recently used, so block 0 is still in the pool
+        access(&mut bm, &[0]);
+        assert_eq!(bm.stats().hits, 2);
+    }
+
+    #[test]
+    fn test_lru_hit_rate() {
+        // A hot block is read between reads of blocks that are each read only once
+        let pattern: Vec<u64> = (1..=20).flat_map(|num| [0, num]).collect();
+
+        let td = tempdir().unwrap();
+        let mut lru = buffer_manager(&td, 2, 21, LruEvictionPolicy::new());
+        access(&mut lru, &pattern);
+
+        let td = tempdir().unwrap();
+        let mut simple = buffer_manager(&td, 2, 21, SimpleEvictionPolicy::new());
+        access(&mut simple, &pattern);
+
+        // LRU keeps the hot block in the pool, while the simple policy evicts the most recently
+        // unpinned buffer, which holds the hot block after its first hit
+        assert_eq!(lru.stats().hits, 19);
+        assert_eq!(simple.stats().hits, 1);
+    }
+}
@@ -2,20 +2,32 @@ use byteorder::{ByteOrder, LittleEndian};
 use core::fmt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
-use std::io::{Error, Read, Seek, SeekFrom, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
 use std::mem::size_of;
 use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 
 use crate::block_id::BlockId;
 use crate::page::{Page, PAGE_SIZE};
 
-const HEADER_SIZE: u64 = 1024;
+/// The size, in bytes, of the header at the start of every file, before its first block.
+pub const HEADER_SIZE: u64 = 1024;
+
+/// The subdirectory of the data directory that temporary files are created in, by default.
+pub const DEFAULT_TEMP_DIRECTORY: &str = "temp";
 
 pub struct FileManager {
     files: RwLock<HashMap<String, Arc<Mutex<File>>>>,
     root_directory: PathBuf,
+    temp_directory: String,
+    next_temp_num: AtomicU64,
+    // Set by tests to make every block write fail, as it would on a full or failing disk
+    #[cfg(test)]
+    fail_writes: AtomicBool,
 }
 
 impl std::fmt::Debug for FileManager {
@@ -23,6 +35,7 @@ impl std::fmt::Debug for FileManager {
         f.debug_struct("FileManager")
             .field("files", &self.files)
             .field("root_directory", &self.root_directory)
+            .field("temp_directory", &self.temp_directory)
             .finish()
     }
 }
@@ -31,6 +44,18 @@ impl FileManager {
     pub type Page = Page;
 
     pub fn new(root_directory: &Path) -> Self {
+        Self::with_temp_directory(root_directory, DEFAULT_TEMP_DIRECTORY)
+    }
+
+    /// Create a FileManager that keeps temporary files in a subdirectory of the root directory.
+    /// Temporary files left behind by an earlier FileManager, e.g. one that crashed mid-query, are
+    /// removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_directory` - The directory holding the database files.
+    /// * `temp_directory` - The name of the subdirectory to create temporary files in.
+    pub fn with_temp_directory(root_directory: &Path, temp_directory: &str) -> Self {
         if !root_directory.exists() {
             panic!(
                 "Directory does not exist: {}",
@@ -38,12 +63,106 @@ impl FileManager {
             );
         }
 
+        // TODO: error handling
+        let temp_path = root_directory.join(temp_directory);
+        if temp_path.exists() {
+            fs::remove_dir_all(&temp_path).expect("failed to clear temp directory");
+        }
+        fs::create_dir_all(&temp_path).expect("failed to create temp directory");
+
         Self {
             files: RwLock::new(HashMap::new()),
             root_directory: root_directory.to_path_buf(),
+            temp_directory: temp_directory.to_string(),
+            next_temp_num: AtomicU64::new(0),
+            #[cfg(test)]
+            fail_writes: AtomicBool::new(false),
+        }
+    }
+
+    /// Get the path of the directory holding the database files.
+    pub fn root_directory(&self) -> &Path {
+        &self.root_directory
+    }
+
+    /// Get the path of the file with the given id.
+    pub fn file_path(&self, file_id: &str) -> PathBuf {
+        self.get_block_file(file_id)
+    }
+
+    /// Get the path of the directory that temporary files are created in.
+    pub fn temp_directory(&self) -> PathBuf {
+        self.root_directory.join(&self.temp_directory)
+    }
+
+    /// Allocate a file id for a new temporary file. The file is created when a block is first
+    /// appended to it.
+    pub fn new_temp_file_id(&self) -> String {
+        let num = self.next_temp_num.fetch_add(1, Ordering::SeqCst);
+        format!("{}/temp{}", self.temp_directory, num)
+    }
+
+    /// Whether a file id names a temporary file.
+    pub fn is_temp_file(&self, file_id: &str) -> bool {
+        file_id
+            .strip_prefix(&self.temp_directory)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// Delete a file, if it exists. Any block of the file still held in a buffer must be
+    /// discarded first, or a later flush of the buffer will fail.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the file to delete.
+    pub fn delete_file(&self, file_id: &str) -> Result<(), Error> {
+        self.files.write().unwrap().remove(file_id);
+        match fs::remove_file(self.get_block_file(file_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove every block of a file, leaving only its header. A file that does not exist is
+    /// created without any blocks. Any block of the file still held in a buffer must be discarded
+    /// first.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the file to truncate.
+    pub fn truncate(&self, file_id: &str) -> Result<(), Error> {
+        let file = self.get_or_create_file(file_id)?;
+        let file = file.lock().unwrap();
+        file.set_len(HEADER_SIZE)?;
+        file.sync_all()
+    }
+
+    /// Rename a file, replacing any file that already has the new name. Does nothing if the file
+    /// does not exist. Any block of either file still held in a buffer must be discarded first.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The id of the file to rename.
+    /// * `to` - The new id of the file.
+    pub fn rename_file(&self, from: &str, to: &str) -> Result<(), Error> {
+        let mut files = self.files.write().unwrap();
+        files.remove(from);
+        files.remove(to);
+        match fs::rename(self.get_block_file(from), self.get_block_file(to)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
         }
     }
 
+    /// Make every later block write fail, or succeed again, to test how failed writes are
+    /// handled.
+    #[cfg(test)]
+    pub fn set_fail_writes(&self, fail: bool) {
+        self.fail_writes.store(fail, Ordering::SeqCst);
+    }
+
+    /// Get the size, in bytes, of the blocks in every file. It is recorded in the header of each
+    /// file, and a file whose header records a different size cannot be read.
     pub fn page_size(&self) -> usize {
         PAGE_SIZE
     }
@@ -58,7 +177,7 @@ impl FileManager {
 
     pub fn get_block(&self, bid: &BlockId, page: &mut Self::Page) -> Result<(), Error> {
         let seek_position = Self::get_file_position(bid);
-        let file = self.get_or_create_file(&bid.file_id());
+        let file = self.get_or_create_file(&bid.file_id())?;
 
         //{
         //    let files = self.files.read().unwrap();
@@ -87,6 +206,11 @@ impl FileManager {
     /// * `blk` - The BlockId that identifies where the page should be written.
     /// * `page` - The page that will be written.
     pub fn write_block(&self, blk: &BlockId, page: &Self::Page) -> Result<(), Error> {
+        #[cfg(test)]
+        if self.fail_writes.load(Ordering::SeqCst) {
+            return Err(Error::other(format!("failed to write block {}", blk)));
+        }
+
         let seek_position = Self::get_file_position(blk);
         let file;
         {
@@ -112,7 +236,7 @@ impl FileManager {
     // TODO: proper error handling
     /// Append the provided page to the file identified by the file_id
     pub fn append_block(&self, file_id: &str, page: &Self::Page) -> Result<BlockId, Error> {
-        let file = self.get_or_create_file(file_id);
+        let file = self.get_or_create_file(file_id)?;
         let mut file = file.lock().unwrap();
         let block_start = file.seek(SeekFrom::End(0))?;
         let block_number = (block_start - HEADER_SIZE) / PAGE_SIZE as u64;
@@ -122,17 +246,15 @@ impl FileManager {
         Ok(BlockId::new(file_id, block_number))
     }
 
-    /// Get the number of blocks in a file.
+    /// Get the number of blocks in a file. A file that exists on disk, e.g. from before the
+    /// database was reopened, is opened so that its length is read from disk.
     pub fn length(&self, file_id: &str) -> Result<u64, Error> {
-        let file = {
-            let files = self.files.read().unwrap();
-            match files.get(file_id) {
-                None => {
-                    // The file is not currently managed by the filemanager
-                    return Ok(0);
-                }
-                Some(f) => f.clone(),
-            }
+        let managed = self.files.read().unwrap().get(file_id).cloned();
+        let file = match managed {
+            Some(f) => f,
+            // A file written before the database was last opened is not yet managed
+            None if self.get_block_file(file_id).exists() => self.get_or_create_file(file_id)?,
+            None => return Ok(0),
         };
 
         let file = file.lock().unwrap();
@@ -141,31 +263,50 @@ impl FileManager {
         Ok((file_size - 1) / PAGE_SIZE as u64)
     }
 
-    fn get_or_create_file(&self, file_id: &str) -> Arc<Mutex<File>> {
+    // Get a managed file, opening it if necessary. A new file is given a header recording the
+    // page size, and an existing file is rejected if its header records a different page size.
+    fn get_or_create_file(&self, file_id: &str) -> Result<Arc<Mutex<File>>, Error> {
         let mut files = self.files.write().unwrap();
-        files
-            .entry(file_id.to_string())
-            .or_insert_with_key(|f| {
-                let file_path = self.get_block_file(f);
-
-                // TODO: error handling
-                let mut file = OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open(file_path.clone())
-                    .expect(&format!(
-                        "Unable to open file path: {}",
-                        file_path.to_string_lossy()
-                    ));
-
-                // Add a header to the file for storing metadata
-                let buf = [0; HEADER_SIZE as usize];
-                file.write_all(&buf).unwrap();
-
-                Arc::new(Mutex::new(file))
-            })
-            .clone()
+        if let Some(file) = files.get(file_id) {
+            return Ok(file.clone());
+        }
+
+        let file_path = self.get_block_file(file_id);
+
+        // TODO: error handling
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(file_path.clone())
+            .expect(&format!(
+                "Unable to open file path: {}",
+                file_path.to_string_lossy()
+            ));
+
+        // Add a header to the file for storing metadata
+        let mut header = [0; HEADER_SIZE as usize];
+        if file.metadata()?.len() == 0 {
+            LittleEndian::write_u32(&mut header[..size_of::<u32>()], PAGE_SIZE as u32);
+            file.write_all(&header)?;
+        } else {
+            file.read_exact(&mut header)?;
+            let page_size = LittleEndian::read_u32(&header[..size_of::<u32>()]) as usize;
+            // Files written before the page size was recorded have an empty header
+            if page_size != 0 && page_size != PAGE_SIZE {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "file '{}' has {} byte pages, but the page size is {} bytes",
+                        file_id, page_size, PAGE_SIZE
+                    ),
+                ));
+            }
+        }
+
+        let file = Arc::new(Mutex::new(file));
+        files.insert(file_id.to_string(), file.clone());
+        Ok(file)
     }
 }
 
@@ -182,6 +323,106 @@ mod tests {
         (temp_dir, FileManager::new(&root_dir))
     }
 
+    #[test]
+    fn test_temp_files() {
+        let (temp_dir, fm) = setup();
+        let temp_path = temp_dir.path().join("data").join(DEFAULT_TEMP_DIRECTORY);
+        assert_eq!(fm.temp_directory(), temp_path);
+
+        let file_id = fm.new_temp_file_id();
+        assert_ne!(fm.new_temp_file_id(), file_id);
+        assert!(fm.is_temp_file(&file_id));
+        assert!(!fm.is_temp_file("student"));
+        assert!(!fm.is_temp_file("temporary"));
+
+        fm.append_block(&file_id, &Page::new()).unwrap();
+        assert_eq!(fm.length(&file_id).unwrap(), 1);
+        assert_eq!(fs::read_dir(&temp_path).unwrap().count(), 1);
+
+        fm.delete_file(&file_id).unwrap();
+        assert_eq!(fm.length(&file_id).unwrap(), 0);
+        assert_eq!(fs::read_dir(&temp_path).unwrap().count(), 0);
+
+        // Deleting a file that does not exist is not an error
+        fm.delete_file(&file_id).unwrap();
+
+        // Temp files left behind by an earlier FileManager are removed
+        fm.append_block(&fm.new_temp_file_id(), &Page::new())
+            .unwrap();
+        let fm = FileManager::new(&temp_dir.path().join("data"));
+        assert_eq!(fs::read_dir(fm.temp_directory()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_page_size_recorded_in_header() {
+        let (temp_dir, fm) = setup();
+        fm.append_block("sized", &Page::new()).unwrap();
+        fm.append_block("unsized", &Page::new()).unwrap();
+        fm.append_block("resized", &Page::new()).unwrap();
+
+        let data_dir = temp_dir.path().join("data");
+        let header = fs::read(data_dir.join("sized")).unwrap();
+        assert_eq!(LittleEndian::read_u32(&header) as usize, fm.page_size());
+
+        // Clear one file's header, as if written before the page size was recorded, and record a
+        // different page size in another's
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(data_dir.join("unsized"))
+            .unwrap();
+        file.write_all(&[0; size_of::<u32>()]).unwrap();
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(data_dir.join("resized"))
+            .unwrap();
+        let mut buf = [0; size_of::<u32>()];
+        LittleEndian::write_u32(&mut buf, (PAGE_SIZE / 2) as u32);
+        file.write_all(&buf).unwrap();
+
+        let fm = FileManager::new(&data_dir);
+        assert_eq!(fm.length("sized").unwrap(), 1);
+        assert_eq!(fm.length("unsized").unwrap(), 1);
+        assert_eq!(
+            fm.length("resized").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        let mut page = Page::new();
+        assert!(fm
+            .get_block(&BlockId::new("resized", 0), &mut page)
+            .is_err());
+    }
+
+    #[test]
+    fn test_truncate_and_rename() {
+        let (temp_dir, fm) = setup();
+        let mut page = Page::new();
+        page.write(42i32, 0);
+        fm.append_block("a", &page).unwrap();
+        fm.append_block("a", &page).unwrap();
+
+        fm.rename_file("a", "b").unwrap();
+        assert_eq!(fm.length("a").unwrap(), 0);
+        assert_eq!(fm.length("b").unwrap(), 2);
+
+        fm.truncate("b").unwrap();
+        assert_eq!(fm.length("b").unwrap(), 0);
+        let path = temp_dir.path().join("data").join("b");
+        assert_eq!(fs::metadata(&path).unwrap().len(), HEADER_SIZE);
+
+        // A truncated file can grow again
+        let blk = fm.append_block("b", &page).unwrap();
+        assert_eq!(blk.num(), 0);
+        let mut read = Page::new();
+        fm.get_block(&blk, &mut read).unwrap();
+        assert_eq!(read.read::<i32>(0), 42);
+
+        // Renaming a file that does not exist does nothing, and truncating one creates it empty
+        fm.rename_file("a", "c").unwrap();
+        assert_eq!(fm.length("c").unwrap(), 0);
+        fm.truncate("d").unwrap();
+        assert_eq!(fm.length("d").unwrap(), 0);
+    }
+
     #[test]
     fn test_write_primitive() {
         let mut page = Page::new();
@@ -4,7 +4,14 @@ use tracing::trace;
 
 use crate::{block_id::BlockId, layout::Layout, parser::constant::Value, transaction::Tx};
 
-use super::btree_page::{BTPage, LeafBlockNum};
+use super::{
+    btree_index::FillFactor,
+    btree_page::{BTPage, LeafBlockNum},
+};
+
+/// The deepest directory level a btree may grow to. Every split at least halves a page, so a tree
+/// this tall would index more entries than fit in a file; reaching it means the tree is corrupt.
+pub const MAX_DIRECTORY_LEVEL: i32 = 32;
 
 pub struct DirectoryEntry {
     data_val: Value,
@@ -54,51 +61,84 @@ impl BTreeDirectory {
     pub fn make_new_root(&mut self, entry: &DirectoryEntry) {
         let first_val = self.contents.get_data_val(0);
         let level = self.contents.get_flag();
+        assert!(
+            level < MAX_DIRECTORY_LEVEL,
+            "btree directory {} cannot grow past {} levels",
+            self.filename,
+            MAX_DIRECTORY_LEVEL
+        );
 
         // transfer all records to new block
         let newblk = self.contents.split(0, level);
         let oldroot = DirectoryEntry::new(&first_val, newblk.num());
-        self.insert_entry(&oldroot);
-        self.insert_entry(&entry);
+        self.insert_entry(&oldroot, FillFactor::Full);
+        self.insert_entry(&entry, FillFactor::Full);
         self.contents.set_flag(level + 1);
     }
 
-    pub fn insert(&mut self, entry: &DirectoryEntry) -> Option<DirectoryEntry> {
+    pub fn insert(
+        &mut self,
+        entry: &DirectoryEntry,
+        fill_factor: FillFactor,
+    ) -> Option<DirectoryEntry> {
         if self.contents.get_flag() == 0 {
             // We are at a directory page that points to leaf nodes (level 0)
-            return self.insert_entry(&entry);
+            return self.insert_entry(&entry, fill_factor);
         }
 
         let childblk = self.find_child_block(&entry.data_val);
         let new_entry = {
             let mut child = BTreeDirectory::new(self.tx.clone(), &childblk, self.layout.clone());
-            child.insert(&entry)
+            child.insert(&entry, fill_factor)
         };
 
         if let Some(new_entry) = new_entry {
-            self.insert_entry(&new_entry)
+            self.insert_entry(&new_entry, fill_factor)
         } else {
             None
         }
     }
 
-    fn insert_entry(&mut self, entry: &DirectoryEntry) -> Option<DirectoryEntry> {
+    fn insert_entry(
+        &mut self,
+        entry: &DirectoryEntry,
+        fill_factor: FillFactor,
+    ) -> Option<DirectoryEntry> {
         let newslot = (1 + self.contents.find_slot_before(&entry.data_val)) as u32;
         self.contents
             .insert_dir(newslot, &entry.data_val, entry.blk_num as i32);
+        debug_assert!(
+            self.is_ordered(),
+            "directory block {} is out of order",
+            self.contents.block()
+        );
 
-        if !self.contents.is_full() {
+        if !self.contents.is_full(fill_factor) {
             None
         } else {
-            // The page is full, split it pre-emptively
+            // The page is full, split it pre-emptively. An entry appended to the end of the page
+            // starts a new page on its own when appending.
             let level = self.contents.get_flag();
-            let split_pos = self.contents.get_num_records() / 2;
+            let num_records = self.contents.get_num_records();
+            let split_pos = if fill_factor == FillFactor::Append && newslot == num_records - 1 {
+                newslot
+            } else {
+                num_records / 2
+            };
             let split_val = self.contents.get_data_val(split_pos);
             let newblk = self.contents.split(split_pos, level);
             Some(DirectoryEntry::new(&split_val, newblk.num()))
         }
     }
 
+    /// Whether the entries of this directory page are in key order.
+    fn is_ordered(&self) -> bool {
+        let vals: Vec<Value> = (0..self.contents.get_num_records())
+            .map(|slot| self.contents.get_data_val(slot))
+            .collect();
+        vals.windows(2).all(|w| w[0] <= w[1])
+    }
+
     fn find_child_block(&self, key: &Value) -> BlockId {
         let mut slot = self.contents.find_slot_before(&key);
         if self.contents.get_data_val((slot + 1) as u32) == *key {
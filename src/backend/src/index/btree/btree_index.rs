@@ -1,4 +1,5 @@
 use std::{
+    collections::VecDeque,
     fs::File,
     io::{self, Write},
     sync::{Arc, Mutex},
@@ -11,6 +12,21 @@ use crate::{
 
 use super::{btree_directory::BTreeDirectory, btree_leaf::BTreeLeaf, btree_page::BTPage};
 
+/// How full a btree page may get before it is split, and where it is split.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillFactor {
+    /// Split a page in half once the next entry would not fit in it.
+    #[default]
+    Full,
+    /// Split a page in half once it holds this percentage of the entries that fit in it, leaving
+    /// room for later inserts of keys between the ones already in the page.
+    Percent(u8),
+    /// Split a page once the next entry would not fit in it. If the entry just inserted has the
+    /// largest key in the page, only that key moves to the new page, so that monotonically
+    /// increasing keys leave full pages behind them.
+    Append,
+}
+
 pub struct BTreeIndex {
     tx: Arc<Mutex<Tx>>,
     dir_layout: Layout,
@@ -18,6 +34,7 @@ pub struct BTreeIndex {
     leaf_tbl: String,
     leaf: Option<BTreeLeaf>,
     rootblk: BlockId,
+    fill_factor: FillFactor,
 }
 
 impl BTreeIndex {
@@ -27,8 +44,13 @@ impl BTreeIndex {
         // TODO: in the below block we lock the transaction to check the size and then separately
         // lock it to append a new (initial) leaf page. Possible race condition where the size
         // check can happen twice and then two pages are appended?
-        if tx.lock().unwrap().size(&leaf_tbl) == 0 {
-            let blk = tx.lock().unwrap().append(&leaf_tbl);
+        // TODO: error handling
+        if tx.lock().unwrap().size(&leaf_tbl).expect("failed to size leaf file") == 0 {
+            let blk = tx
+                .lock()
+                .unwrap()
+                .append(&leaf_tbl)
+                .expect("failed to append leaf block");
             let node = BTPage::new(tx.clone(), blk.clone(), leaf_layout.clone());
             let mut tx = tx.lock().unwrap();
             node.format(&blk, -1, &mut tx);
@@ -41,9 +63,13 @@ impl BTreeIndex {
         let dir_layout = Layout::from_schema(dir_schema);
         let dir_tbl = format!("{}-dir", index_name);
         let rootblk = BlockId::new(&dir_tbl, 0);
-        if tx.lock().unwrap().size(&dir_tbl) == 0 {
+        // TODO: error handling
+        if tx.lock().unwrap().size(&dir_tbl).expect("failed to size directory file") == 0 {
             // create and initialize a new root block
-            tx.lock().unwrap().append(&dir_tbl);
+            tx.lock()
+                .unwrap()
+                .append(&dir_tbl)
+                .expect("failed to append root block");
 
             // insert initial directory entry
             let fldtype = dir_layout
@@ -70,11 +96,265 @@ impl BTreeIndex {
             tx,
             rootblk,
             leaf: None,
+            fill_factor: FillFactor::default(),
+        }
+    }
+
+    /// Set how full pages get before they are split by inserts through this index. The fill
+    /// factor is not stored with the index, so it only affects inserts made through this handle.
+    pub fn with_fill_factor(mut self, fill_factor: FillFactor) -> Self {
+        self.fill_factor = fill_factor;
+        self
+    }
+
+    /// Get an iterator over every entry in the index, in key order.
+    pub fn entries(&self) -> BTreeEntries {
+        let mut leaves = VecDeque::new();
+        self.collect_leaves(&self.rootblk, &mut leaves);
+
+        BTreeEntries {
+            tx: self.tx.clone(),
+            leaf_layout: self.leaf_layout.clone(),
+            leaf_tbl: self.leaf_tbl.clone(),
+            leaves,
+            entries: VecDeque::new(),
+        }
+    }
+
+    // Collect the block numbers of the leaves below a directory block, from left to right.
+    fn collect_leaves(&self, blk: &BlockId, leaves: &mut VecDeque<u64>) {
+        let page = BTPage::new(self.tx.clone(), blk.clone(), self.dir_layout.clone());
+        let level = page.get_flag();
+        for slot in 0..page.get_num_records() {
+            let child = page.get_child_num(slot) as u64;
+            if level == 0 {
+                leaves.push_back(child);
+            } else {
+                self.collect_leaves(&BlockId::new(blk.file_id(), child), leaves);
+            }
+        }
+    }
+
+    // Find the smallest key in the index larger than the given key. The leaves holding larger keys
+    // are found by walking the directory, which is only done if the leaf holding the key has no
+    // larger one.
+    fn next_key_after(&self, key: &Value) -> Option<Value> {
+        let leaf_num = {
+            let mut root =
+                BTreeDirectory::new(self.tx.clone(), &self.rootblk, self.dir_layout.clone());
+            root.search(key) as u64
+        };
+        let larger_key = |blk_num: u64| {
+            // A leaf's overflow blocks only hold entries equal to its first key, so they can be
+            // skipped
+            let page = BTPage::new(
+                self.tx.clone(),
+                BlockId::new(&self.leaf_tbl, blk_num),
+                self.leaf_layout.clone(),
+            );
+            (0..page.get_num_records())
+                .map(|slot| page.get_data_val(slot))
+                .find(|val| val > key)
+        };
+        if let Some(val) = larger_key(leaf_num) {
+            return Some(val);
+        }
+
+        let mut leaves = VecDeque::new();
+        self.collect_leaves(&self.rootblk, &mut leaves);
+        leaves
+            .into_iter()
+            .skip_while(|num| *num != leaf_num)
+            .skip(1)
+            .find_map(larger_key)
+    }
+
+    /// Get the smallest key in the index, or `None` if the index is empty. The tree is descended
+    /// along its left edge, so only one block per level is read unless deletes have emptied the
+    /// leftmost leaves.
+    pub fn first_key(&self) -> Option<Value> {
+        self.edge_key(&self.rootblk, false)
+    }
+
+    /// Get the largest key in the index, or `None` if the index is empty. The tree is descended
+    /// along its right edge, so only one block per level is read unless deletes have emptied the
+    /// rightmost leaves.
+    pub fn last_key(&self) -> Option<Value> {
+        self.edge_key(&self.rootblk, true)
+    }
+
+    // Find the smallest or largest key below a directory block, trying its children from the
+    // left or right edge until one holds an entry.
+    fn edge_key(&self, blk: &BlockId, largest: bool) -> Option<Value> {
+        let page = BTPage::new(self.tx.clone(), blk.clone(), self.dir_layout.clone());
+        let level = page.get_flag();
+        let num_children = page.get_num_records();
+        let mut slots: Box<dyn Iterator<Item = u32>> = if largest {
+            Box::new((0..num_children).rev())
+        } else {
+            Box::new(0..num_children)
+        };
+        slots.find_map(|slot| {
+            let child = page.get_child_num(slot) as u64;
+            if level == 0 {
+                self.leaf_edge_key(child, largest)
+            } else {
+                self.edge_key(&BlockId::new(blk.file_id(), child), largest)
+            }
+        })
+    }
+
+    // Find the smallest or largest key in a leaf. Entries after the first in a leaf's overflow
+    // blocks all equal its first key, so the overflow blocks are only read if deletes have
+    // emptied the leaf itself.
+    fn leaf_edge_key(&self, blk_num: u64, largest: bool) -> Option<Value> {
+        let mut blk_num = blk_num;
+        loop {
+            let page = BTPage::new(
+                self.tx.clone(),
+                BlockId::new(&self.leaf_tbl, blk_num),
+                self.leaf_layout.clone(),
+            );
+            match page.get_num_records() {
+                0 if page.get_flag() >= 0 => blk_num = page.get_flag() as u64,
+                0 => return None,
+                n if largest => return Some(page.get_data_val(n - 1)),
+                _ => return Some(page.get_data_val(0)),
+            }
+        }
+    }
+
+    /// Remove every entry from the index by truncating its files. The index is set up again, empty,
+    /// when it is next opened.
+    pub fn clear(mut self) {
+        self.close();
+        let mut tx = self.tx.lock().unwrap();
+        // TODO: error handling
+        tx.truncate(&self.leaf_tbl)
+            .expect("failed to truncate leaf file");
+        tx.truncate(self.rootblk.file_id())
+            .expect("failed to truncate directory file");
+    }
+
+    /// Remove the index's files, e.g. when its table is dropped.
+    pub fn remove_files(mut self) {
+        self.close();
+        let mut tx = self.tx.lock().unwrap();
+        // TODO: error handling
+        tx.drop_file(&self.leaf_tbl)
+            .expect("failed to drop leaf file");
+        tx.drop_file(self.rootblk.file_id())
+            .expect("failed to drop directory file");
+    }
+
+    /// The number of levels in the tree, counting the directory levels and the leaf level.
+    pub fn height(&self) -> u32 {
+        let root = BTPage::new(
+            self.tx.clone(),
+            self.rootblk.clone(),
+            self.dir_layout.clone(),
+        );
+        // The root's level is 0 when it points directly at leaves
+        root.get_flag() as u32 + 2
+    }
+
+    /// The number of blocks read to find the first entry with a key: one block per level of the
+    /// tree.
+    pub fn search_cost(&self) -> u64 {
+        self.height() as u64
+    }
+
+    /// Walk the whole tree, checking that every page is in key order, that every directory entry
+    /// points at a child one level down, and that the keys of each child fall between its
+    /// directory entry and the next one. Returns a description of the first violation found.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let root = BTPage::new(
+            self.tx.clone(),
+            self.rootblk.clone(),
+            self.dir_layout.clone(),
+        );
+        let level = root.get_flag();
+        drop(root);
+        self.check_directory(&self.rootblk, level, None)
+    }
+
+    // Check a directory page at the given level whose keys must not exceed `upper`.
+    fn check_directory(
+        &self,
+        blk: &BlockId,
+        level: i32,
+        upper: Option<&Value>,
+    ) -> Result<(), String> {
+        let page = BTPage::new(self.tx.clone(), blk.clone(), self.dir_layout.clone());
+        if page.get_flag() != level {
+            return Err(format!(
+                "directory block {} is at level {}, expected {}",
+                blk,
+                page.get_flag(),
+                level
+            ));
+        }
+
+        let num_records = page.get_num_records();
+        if num_records == 0 {
+            return Err(format!("directory block {} has no entries", blk));
+        }
+
+        let entries: Vec<(Value, u64)> = (0..num_records)
+            .map(|slot| (page.get_data_val(slot), page.get_child_num(slot) as u64))
+            .collect();
+        drop(page);
+        Self::check_ordered(blk, entries.iter().map(|(val, _)| val), upper)?;
+
+        for (i, (lower, child)) in entries.iter().enumerate() {
+            let child_upper = entries.get(i + 1).map(|(val, _)| val).or(upper);
+            if level == 0 {
+                let leaf_blk = BlockId::new(&self.leaf_tbl, *child);
+                let leaf = BTPage::new(self.tx.clone(), leaf_blk.clone(), self.leaf_layout.clone());
+                let vals: Vec<Value> = (0..leaf.get_num_records())
+                    .map(|slot| leaf.get_data_val(slot))
+                    .collect();
+                drop(leaf);
+                Self::check_ordered(&leaf_blk, vals.iter(), child_upper)?;
+                if let Some(first) = vals.first()
+                    && first < lower
+                {
+                    return Err(format!(
+                        "leaf block {} starts below its directory entry {}",
+                        leaf_blk, lower
+                    ));
+                }
+            } else {
+                let child_blk = BlockId::new(blk.file_id(), *child);
+                self.check_directory(&child_blk, level - 1, child_upper)?;
+            }
         }
+
+        Ok(())
     }
 
-    pub fn search_cost(num_blocks: u64, rpb: u64) -> u64 {
-        1 + ((num_blocks as f64).log10() / (rpb as f64).log10()) as u64
+    // Check that the keys of a page are in order and do not exceed `upper`.
+    fn check_ordered<'a>(
+        blk: &BlockId,
+        vals: impl Iterator<Item = &'a Value>,
+        upper: Option<&Value>,
+    ) -> Result<(), String> {
+        let mut prev: Option<&Value> = None;
+        for val in vals {
+            if let Some(prev) = prev
+                && prev > val
+            {
+                return Err(format!("block {} is out of order at key {}", blk, val));
+            }
+            if let Some(upper) = upper
+                && val > upper
+            {
+                return Err(format!("block {} holds key {} above {}", blk, val, upper));
+            }
+            prev = Some(val);
+        }
+
+        Ok(())
     }
 
     pub fn generate_dot_file(&self, filename: &str) -> io::Result<()> {
@@ -207,13 +487,13 @@ impl Index for BTreeIndex {
         self.before_first(&key);
         // NOTE: we know that leaf is set at this point due to the above `before_first` call. Better way to design this?
         let leaf = self.leaf.as_mut().unwrap();
-        let entry = leaf.insert(&rid);
+        let entry = leaf.insert(&rid, self.fill_factor);
         self.leaf = None;
 
         if let Some(entry) = entry {
             let mut root =
                 BTreeDirectory::new(self.tx.clone(), &self.rootblk, self.dir_layout.clone());
-            let e2 = root.insert(&entry);
+            let e2 = root.insert(&entry, self.fill_factor);
             if let Some(e2) = e2 {
                 root.make_new_root(&e2);
             }
@@ -230,6 +510,90 @@ impl Index for BTreeIndex {
     fn close(&mut self) {
         self.leaf = None;
     }
+
+    fn skip_to_next_key(&mut self) -> Result<Option<Value>, String> {
+        let key = match &self.leaf {
+            Some(leaf) => leaf.search_key().clone(),
+            None => return Err("the index has no search key".to_string()),
+        };
+
+        match self.next_key_after(&key) {
+            Some(next_key) => {
+                self.before_first(&next_key);
+                self.next();
+                Ok(Some(next_key))
+            }
+            None => {
+                self.close();
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// An iterator over the entries of a `BTreeIndex` in key order, created by `BTreeIndex::entries`.
+///
+/// Leaves are read one at a time, so only the entries of the current leaf (and its overflow
+/// blocks) are held in memory.
+pub struct BTreeEntries {
+    tx: Arc<Mutex<Tx>>,
+    leaf_layout: Layout,
+    leaf_tbl: String,
+    leaves: VecDeque<u64>,
+    entries: VecDeque<(Value, RID)>,
+}
+
+impl BTreeEntries {
+    // Read the entries of a leaf. A leaf's overflow blocks only hold entries equal to the first
+    // key of the leaf, so they are read directly after the leaf's first entry.
+    fn read_leaf(&mut self, blk_num: u64) {
+        let page = BTPage::new(
+            self.tx.clone(),
+            BlockId::new(&self.leaf_tbl, blk_num),
+            self.leaf_layout.clone(),
+        );
+        let num_records = page.get_num_records();
+        if num_records == 0 {
+            return;
+        }
+
+        self.entries
+            .push_back((page.get_data_val(0), page.get_data_rid(0)));
+
+        let mut overflow = page.get_flag();
+        while overflow >= 0 {
+            let overflow_page = BTPage::new(
+                self.tx.clone(),
+                BlockId::new(&self.leaf_tbl, overflow as u64),
+                self.leaf_layout.clone(),
+            );
+            for slot in 0..overflow_page.get_num_records() {
+                self.entries.push_back((
+                    overflow_page.get_data_val(slot),
+                    overflow_page.get_data_rid(slot),
+                ));
+            }
+            overflow = overflow_page.get_flag();
+        }
+
+        for slot in 1..num_records {
+            self.entries
+                .push_back((page.get_data_val(slot), page.get_data_rid(slot)));
+        }
+    }
+}
+
+impl Iterator for BTreeEntries {
+    type Item = (Value, RID);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.entries.is_empty() {
+            let blk_num = self.leaves.pop_front()?;
+            self.read_leaf(blk_num);
+        }
+
+        self.entries.pop_front()
+    }
 }
 
 impl Drop for BTreeIndex {
@@ -245,7 +609,10 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::{
-        index::{btree::btree_index::BTreeIndex, index::Index},
+        index::{
+            btree::btree_index::{BTreeIndex, FillFactor},
+            index::Index,
+        },
         layout::Layout,
         make_schema,
         parser::constant::Value,
@@ -349,4 +716,290 @@ mod tests {
             assert!(!index.next());
         }
     }
+
+    #[test]
+    fn test_btree_index_skip_to_next_key() {
+        let dir = tempdir().unwrap();
+        let db = test_db(&dir);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        // Long keys keep the leaves small, so that few entries split them
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => varchar(400),
+            "block" => i32,
+            "id" => i32
+        });
+        let mut index = BTreeIndex::new(tx, "test-idx", leaf_layout);
+        assert!(index.skip_to_next_key().is_err());
+
+        // Some keys have more entries than fit in a leaf, so they overflow
+        let key = |n: i32| Value::Varchar(format!("key{:03}", n));
+        let repetitions = |n: i32| if n % 10 == 0 { 25 } else { 2 };
+        for n in (0..60).step_by(2) {
+            for i in 0..repetitions(n) {
+                index.insert(&key(n), RID::new(n as u64, i as i16));
+            }
+        }
+        assert!(index.height() > 2);
+
+        index.before_first(&key(0));
+        assert!(index.next());
+        let mut keys = vec![(key(0), 0)];
+        while let Some(k) = index.skip_to_next_key().unwrap() {
+            keys.push((k, index.get_rid().unwrap().block_num()));
+        }
+        assert_eq!(
+            keys,
+            (0..60)
+                .step_by(2)
+                .map(|n| (key(n), n as u64))
+                .collect::<Vec<_>>()
+        );
+        assert!(!index.next());
+
+        // Skipping part way through a key's entries lands on the first entry of the next key
+        index.before_first(&key(20));
+        for _ in 0..10 {
+            assert!(index.next());
+        }
+        assert_eq!(index.skip_to_next_key(), Ok(Some(key(22))));
+        let mut count = 1;
+        while index.next() {
+            count += 1;
+        }
+        assert_eq!(count, repetitions(22));
+
+        // A search key missing from the index skips to the next key present
+        index.before_first(&key(31));
+        assert_eq!(index.skip_to_next_key(), Ok(Some(key(32))));
+        index.before_first(&key(58));
+        assert_eq!(index.skip_to_next_key(), Ok(None));
+    }
+
+    #[test]
+    fn test_btree_index_mismatched_search_key() {
+        let dir = tempdir().unwrap();
+        let db = test_db(&dir);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => i32,
+            "block" => i32,
+            "id" => i32
+        });
+
+        let mut index = BTreeIndex::new(tx, "test-idx", leaf_layout);
+        for i in 0..400 {
+            index.insert(&Value::Int(i), RID::new(i as u64, 0));
+        }
+
+        // A varchar key orders after every int key, so it is searched for without panicking
+        // and matches nothing
+        index.before_first(&Value::Varchar("1".to_string()));
+        assert!(!index.next());
+
+        index.before_first(&Value::Int(7));
+        assert!(index.next());
+        assert_eq!(index.get_rid(), Some(RID::new(7, 0)));
+    }
+
+    #[test]
+    fn test_btree_index_entries_in_order() {
+        let dir = tempdir().unwrap();
+        let db = test_db(&dir);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => i32,
+            "block" => i32,
+            "id" => i32
+        });
+
+        let mut index = BTreeIndex::new(tx, "test-idx", leaf_layout);
+        assert_eq!(index.entries().count(), 0);
+
+        // Insert keys out of order, with enough duplicates of some keys to need overflow blocks
+        let mut expected = vec![];
+        for i in 0..400 {
+            let key = (i * 37) % 200;
+            let repetitions = if key % 50 == 0 { 100 } else { 1 };
+            for j in 0..repetitions {
+                let rid = RID::new(i as u64, j as i16);
+                index.insert(&Value::Int(key), rid.clone());
+                expected.push((Value::Int(key), rid));
+            }
+        }
+
+        let entries: Vec<(Value, RID)> = index.entries().collect();
+        assert_eq!(entries.len(), expected.len());
+        assert!(entries.windows(2).all(|w| w[0].0 <= w[1].0));
+
+        // Every inserted entry is visited exactly once
+        let key = |e: &(Value, RID)| (e.0.clone(), e.1.block_num(), e.1.slot());
+        let mut found: Vec<_> = entries.iter().map(key).collect();
+        let mut expected: Vec<_> = expected.iter().map(key).collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_btree_index_first_and_last_key() {
+        let dir = tempdir().unwrap();
+        let db = test_db(&dir);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => i32,
+            "block" => i32,
+            "id" => i32
+        });
+
+        let mut index = BTreeIndex::new(tx, "test-idx", leaf_layout);
+        assert_eq!(index.first_key(), None);
+        assert_eq!(index.last_key(), None);
+
+        // Insert keys out of order, enough to need several leaves
+        let num_recs = 1000;
+        for i in 0..num_recs {
+            let key = (i * 37) % num_recs;
+            index.insert(&Value::Int(key), RID::new(key as u64, 0));
+        }
+        assert_eq!(index.first_key(), Some(Value::Int(0)));
+        assert_eq!(index.last_key(), Some(Value::Int(num_recs - 1)));
+
+        // Leaves emptied by deletes at either edge are skipped
+        for key in (0..300).chain(700..num_recs) {
+            index.delete(&Value::Int(key), &RID::new(key as u64, 0));
+        }
+        assert_eq!(index.first_key(), Some(Value::Int(300)));
+        assert_eq!(index.last_key(), Some(Value::Int(699)));
+
+        // A new smallest key with enough duplicates to need overflow blocks
+        for i in 0..200 {
+            index.insert(&Value::Int(-1), RID::new(i, 1));
+        }
+        assert_eq!(index.first_key(), Some(Value::Int(-1)));
+        assert_eq!(index.last_key(), Some(Value::Int(699)));
+
+        for key in 300..700 {
+            index.delete(&Value::Int(key), &RID::new(key as u64, 0));
+        }
+        assert_eq!(index.first_key(), Some(Value::Int(-1)));
+        assert_eq!(index.last_key(), Some(Value::Int(-1)));
+    }
+
+    #[test]
+    fn test_btree_index_height() {
+        let dir = tempdir().unwrap();
+        let db = test_db(&dir);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        // Wide keys keep the fanout small, so the tree grows several levels
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => varchar(500),
+            "block" => i32,
+            "id" => i32
+        });
+        let mut index = BTreeIndex::new(tx, "test-idx", leaf_layout);
+        assert_eq!(index.height(), 2);
+        assert_eq!(index.check_invariants(), Ok(()));
+
+        let num_recs = 250;
+        let mut heights = vec![];
+        for i in 0..num_recs {
+            // Insert keys out of order
+            let key = (i * 97) % num_recs;
+            index.insert(
+                &Value::Varchar(format!("{:05}", key)),
+                RID::new(key as u64, 0),
+            );
+            if [10, 50, 250].contains(&(i + 1)) {
+                assert_eq!(index.check_invariants(), Ok(()));
+                heights.push(index.height());
+            }
+        }
+
+        // Pages split in half, so each holds at least 3 of its 7 entries and the tree grows by
+        // at most one level for every tripling of the keys
+        assert!(heights.windows(2).all(|w| w[0] <= w[1]), "{:?}", heights);
+        assert!(heights[2] > 2, "{:?}", heights);
+        for (n, height) in [10_f64, 50.0, 250.0].into_iter().zip(heights) {
+            assert!(
+                height <= 2 + n.log(3.0).ceil() as u32,
+                "{} keys, height {}",
+                n,
+                height
+            );
+        }
+        assert_eq!(index.search_cost(), index.height() as u64);
+
+        for key in 0..num_recs {
+            index.before_first(&Value::Varchar(format!("{:05}", key)));
+            assert!(index.next());
+            assert_eq!(index.get_rid(), Some(RID::new(key as u64, 0)));
+        }
+    }
+
+    #[test]
+    fn test_btree_index_fill_factor() {
+        let dir = tempdir().unwrap();
+        let db = test_db(&dir);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => varchar(100),
+            "block" => i32,
+            "id" => i32
+        });
+
+        // Insert increasing keys, returning the number of leaves the index ends up with
+        let num_recs = 120;
+        let num_leaves = |name: &str, fill_factor: FillFactor| {
+            let mut index = BTreeIndex::new(tx.clone(), name, leaf_layout.clone())
+                .with_fill_factor(fill_factor);
+            for key in 0..num_recs {
+                index.insert(
+                    &Value::Varchar(format!("{:05}", key)),
+                    RID::new(key as u64, 0),
+                );
+            }
+
+            assert_eq!(index.check_invariants(), Ok(()));
+            for key in 0..num_recs {
+                index.before_first(&Value::Varchar(format!("{:05}", key)));
+                assert!(index.next());
+                assert_eq!(index.get_rid(), Some(RID::new(key as u64, 0)));
+            }
+            let leaves = index.entries().leaves.len();
+            assert_eq!(
+                leaves as u64,
+                tx.lock().unwrap().size(&format!("{}-leaf", name)).unwrap()
+            );
+            leaves
+        };
+
+        let full = num_leaves("full-idx", FillFactor::Full);
+        let percent = num_leaves("percent-idx", FillFactor::Percent(70));
+        let append = num_leaves("append-idx", FillFactor::Append);
+
+        // Splitting in half leaves every page half empty, while appending fills each page before
+        // starting the next one
+        assert!(
+            percent > full,
+            "{} leaves at 70%, {} when full",
+            percent,
+            full
+        );
+        assert!(
+            append * 3 < full * 2,
+            "{} leaves appending, {} when full",
+            append,
+            full
+        );
+    }
 }
@@ -6,7 +6,7 @@ use crate::{
     block_id::BlockId, layout::Layout, parser::constant::Value, rid::RID, transaction::Tx,
 };
 
-use super::{btree_directory::DirectoryEntry, btree_page::BTPage};
+use super::{btree_directory::DirectoryEntry, btree_index::FillFactor, btree_page::BTPage};
 
 pub struct BTreeLeaf {
     tx: Arc<Mutex<Tx>>,
@@ -42,6 +42,11 @@ impl BTreeLeaf {
         }
     }
 
+    /// Get the key whose entries are being searched for.
+    pub fn search_key(&self) -> &Value {
+        &self.search_key
+    }
+
     pub fn get_data_rid(&self) -> RID {
         assert!(
             self.current_slot >= 0,
@@ -59,7 +64,7 @@ impl BTreeLeaf {
         }
     }
 
-    pub fn insert(&mut self, rid: &RID) -> Option<DirectoryEntry> {
+    pub fn insert(&mut self, rid: &RID, fill_factor: FillFactor) -> Option<DirectoryEntry> {
         // inserting to the "left"
         if self.contents.get_flag() >= 0
             && let first_val = self.contents.get_data_val(0)
@@ -85,7 +90,7 @@ impl BTreeLeaf {
         self.contents
             .insert_leaf(self.current_slot as u32, &self.search_key, rid);
 
-        if !self.contents.is_full() {
+        if !self.contents.is_full(fill_factor) {
             // Page is not full so no new directory page created.
             return None;
         }
@@ -103,6 +108,18 @@ impl BTreeLeaf {
 
             // No new directory page created.
             None
+        } else if fill_factor == FillFactor::Append
+            && self.current_slot as u32 == self.contents.get_num_records() - 1
+        {
+            // The new key is the largest in the page, so keys are likely being appended. Move only
+            // the entries of that key to a new page, leaving this one full.
+            let mut split_pos = self.current_slot as u32;
+            while self.contents.get_data_val(split_pos - 1) == last_key {
+                split_pos -= 1;
+            }
+
+            let newblk = self.contents.split(split_pos, -1);
+            Some(DirectoryEntry::new(&last_key, newblk.num()))
         } else {
             // Split in the middle
             let mut split_pos = self.contents.get_num_records() / 2;
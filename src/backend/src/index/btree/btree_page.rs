@@ -4,9 +4,15 @@ use std::{
 };
 
 use crate::{
-    block_id::BlockId, layout::Layout, parser::constant::Value, rid::RID, transaction::Tx,
+    block_id::BlockId,
+    layout::Layout,
+    parser::constant::Value,
+    rid::RID,
+    transaction::{Tx, TxError},
 };
 
+use super::btree_index::FillFactor;
+
 type Flag = u32;
 type RecordCount = u32;
 
@@ -21,12 +27,22 @@ pub struct BTPage {
 
 impl BTPage {
     pub fn new(tx: Arc<Mutex<Tx>>, current_blk: BlockId, layout: Layout) -> Self {
-        tx.lock().unwrap().pin(&current_blk);
-        Self {
+        // TODO: error handling
+        Self::try_new(tx, current_blk, layout).expect("failed to pin btree page")
+    }
+
+    /// Create a new `BTPage`, returning an error if its block could not be pinned.
+    pub fn try_new(
+        tx: Arc<Mutex<Tx>>,
+        current_blk: BlockId,
+        layout: Layout,
+    ) -> Result<Self, TxError> {
+        tx.lock().unwrap().try_pin(&current_blk)?;
+        Ok(Self {
             tx,
             current_blk,
             layout,
-        }
+        })
     }
 
     pub fn block(&self) -> BlockId {
@@ -42,8 +58,24 @@ impl BTPage {
         (slot as i32 - 1)
     }
 
-    pub fn is_full(&self) -> bool {
-        self.slot_pos(self.get_num_records() + 1) >= self.tx.lock().unwrap().block_size()
+    /// Whether the page should be split. A page is always full once the next record would not
+    /// fit, and with a fill factor below 100% it is full once it holds that share of the records
+    /// that fit in it. A page holding fewer than two records is never split early, so that both
+    /// halves of a split have at least one record.
+    pub fn is_full(&self, fill_factor: FillFactor) -> bool {
+        let block_size = self.tx.lock().unwrap().block_size();
+        let num_records = self.get_num_records();
+        if self.slot_pos(num_records + 1) >= block_size {
+            return true;
+        }
+
+        match fill_factor {
+            FillFactor::Full | FillFactor::Append => false,
+            FillFactor::Percent(percent) => {
+                let capacity = (block_size - self.slot_pos(0)) / self.layout.slot_size() as usize;
+                num_records >= 2 && num_records as usize * 100 >= capacity * percent as usize
+            }
+        }
     }
 
     /// Splits the page into two pages and divides records between them. Half of the records [0..split_pos]
@@ -64,15 +96,18 @@ impl BTPage {
     pub fn append_new(&self, flag: i32) -> BlockId {
         let mut txl = self.tx.lock().unwrap();
 
-        let blk = txl.append(self.current_blk.file_id());
+        // TODO: error handling
+        let blk = txl
+            .append(self.current_blk.file_id())
+            .expect("failed to append btree page");
         txl.pin(&blk);
         self.format(&blk, flag, &mut txl);
         blk
     }
 
     pub fn format(&self, blk: &BlockId, flag: i32, tx: &mut Tx) {
-        tx.set_int(&blk, 0, flag as i32, false);
-        tx.set_int(&blk, size_of::<Flag>(), 0, false);
+        tx.set_int(&blk, 0, flag as i32, false).unwrap();
+        tx.set_int(&blk, size_of::<Flag>(), 0, false).unwrap();
         let recsize = self.layout.slot_size();
     }
 
@@ -92,32 +127,43 @@ impl BTPage {
 
     /// Set the number of records currently stored in this page.
     fn set_num_records(&self, n: u32) {
+        // TODO: error handling
         self.tx
             .lock()
             .unwrap()
-            .set_int(&self.current_blk, size_of::<Flag>(), n as i32, true);
+            .set_int(&self.current_blk, size_of::<Flag>(), n as i32, true)
+            .unwrap();
     }
 
     /// Get the number of records currently stored in the page
     pub fn get_num_records(&self) -> u32 {
-        let num_records =
-            self.tx
-                .lock()
-                .unwrap()
-                .get_int(&self.current_blk, size_of::<Flag>() as usize) as u32;
+        // TODO: error handling
+        let num_records = self
+            .tx
+            .lock()
+            .unwrap()
+            .get_int(&self.current_blk, size_of::<Flag>() as usize)
+            .expect("failed to read btree record count") as u32;
 
         num_records
     }
 
     pub fn set_flag(&self, val: i32) {
+        // TODO: error handling
         self.tx
             .lock()
             .unwrap()
-            .set_int(&self.current_blk, 0, val as i32, true);
+            .set_int(&self.current_blk, 0, val as i32, true)
+            .unwrap();
     }
 
     pub fn get_flag(&self) -> i32 {
-        self.tx.lock().unwrap().get_int(&self.current_blk, 0)
+        // TODO: error handling
+        self.tx
+            .lock()
+            .unwrap()
+            .get_int(&self.current_blk, 0)
+            .expect("failed to read btree flag")
     }
 
     /// Delete the record at the provided slot by shifting all records [slot+1..] to the left by 1.
@@ -203,7 +249,10 @@ impl BTPage {
 
     /// Get the raw position of a field in a slot in the page.
     fn field_pos(&self, slot: u32, field: &str) -> usize {
-        let offset = self.layout.offset(field);
+        let offset = self
+            .layout
+            .offset(field)
+            .unwrap_or_else(|| panic!("field '{}' does not exist in the btree layout", field));
         self.slot_pos(slot) + offset as usize
     }
 
@@ -215,28 +264,42 @@ impl BTPage {
 
     fn get_int(&self, slot: u32, field: &str) -> i32 {
         let pos = self.field_pos(slot, field);
-        self.tx.lock().unwrap().get_int(&self.current_blk, pos)
+        // TODO: error handling
+        self.tx
+            .lock()
+            .unwrap()
+            .get_int(&self.current_blk, pos)
+            .expect("failed to read btree int field")
     }
 
     fn get_string(&self, slot: u32, field: &str) -> String {
         let pos = self.field_pos(slot, field);
-        self.tx.lock().unwrap().get_string(&self.current_blk, pos)
+        // TODO: error handling
+        self.tx
+            .lock()
+            .unwrap()
+            .get_string(&self.current_blk, pos)
+            .expect("failed to read btree string field")
     }
 
     fn set_int(&self, slot: u32, field: &str, val: i32) {
         let pos = self.field_pos(slot, field);
+        // TODO: error handling
         self.tx
             .lock()
             .unwrap()
-            .set_int(&self.current_blk, pos, val, true);
+            .set_int(&self.current_blk, pos, val, true)
+            .unwrap();
     }
 
     fn set_string(&self, slot: u32, field: &str, val: &str) {
         let pos = self.field_pos(slot, field);
+        // TODO: error handling
         self.tx
             .lock()
             .unwrap()
-            .set_string(&self.current_blk, pos, val, true);
+            .set_string(&self.current_blk, pos, val, true)
+            .unwrap();
     }
 
     fn set_val(&self, slot: u32, field: &str, val: &Value) {
@@ -248,7 +311,12 @@ impl BTPage {
     }
 
     pub fn close(&self) {
-        self.tx.lock().unwrap().unpin(&self.current_blk);
+        // The transaction releases every pin when it commits or rolls back, in which case a page
+        // dropped afterwards has nothing left to unpin
+        let mut tx = self.tx.lock().unwrap();
+        if tx.is_pinned(&self.current_blk) {
+            tx.unpin(&self.current_blk);
+        }
     }
 }
 
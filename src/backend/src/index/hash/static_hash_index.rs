@@ -10,6 +10,30 @@ use crate::{
     transaction::Tx,
 };
 
+/// Maps a search key to the hash that selects its bucket.
+///
+/// Any `Fn(&Value) -> u64` closure is a hash function, so alternative hashes (or a fixed hash in
+/// tests) can be supplied without a new type.
+pub trait HashFn {
+    fn hash(&self, key: &Value) -> u64;
+}
+
+impl<F: Fn(&Value) -> u64> HashFn for F {
+    fn hash(&self, key: &Value) -> u64 {
+        self(key)
+    }
+}
+
+/// The default hash function, which is stable across runs so that entries are found in the
+/// bucket they were inserted into after the database is reopened.
+pub struct StableHash;
+
+impl HashFn for StableHash {
+    fn hash(&self, key: &Value) -> u64 {
+        Hashable::hash(key)
+    }
+}
+
 // TODO: this is modeled on the API from the text (pg. 321), but it doesn't feel like it fits well
 // into Rust. e.g. having the search_key & table_scan be Option types that change after calling
 // `before_first` means that most of the method calls are invalid if called before `before_first`.
@@ -18,6 +42,7 @@ pub struct StaticHashIndex {
     tx: Arc<Mutex<Tx>>,
     index_name: String,
     num_buckets: u32,
+    hash_fn: Box<dyn HashFn>,
     layout: Layout,
     search_key: Option<Value>,
     table_scan: Option<TableScan>,
@@ -29,11 +54,19 @@ impl StaticHashIndex {
             tx,
             index_name: index_name.to_string(),
             num_buckets,
+            hash_fn: Box::new(StableHash),
             layout,
             search_key: None,
             table_scan: None,
         }
     }
+
+    /// Use the given hash function, in place of the stable default, to distribute keys among
+    /// buckets. Every index opened on the same buckets must use the same function.
+    pub fn with_hash_fn(mut self, hash_fn: impl HashFn + 'static) -> Self {
+        self.hash_fn = Box::new(hash_fn);
+        self
+    }
 }
 
 impl Drop for StaticHashIndex {
@@ -46,7 +79,7 @@ impl Index for StaticHashIndex {
     fn before_first(&mut self, search_key: &Value) {
         self.close();
 
-        let bucket = search_key.hash() % self.num_buckets as u64;
+        let bucket = self.hash_fn.hash(search_key) % self.num_buckets as u64;
         self.search_key = Some(search_key.clone());
         let tblname = format!("{}{}", self.index_name, bucket);
         self.table_scan = Some(TableScan::new(
@@ -56,6 +89,8 @@ impl Index for StaticHashIndex {
         ));
     }
 
+    /// Move to the next entry with the search key. A bucket can hold entries for other keys that
+    /// hash to it, so those are skipped rather than ending the search.
     fn next(&mut self) -> bool {
         let search_key = match &self.search_key {
             Some(k) => k,
@@ -64,8 +99,10 @@ impl Index for StaticHashIndex {
 
         if let Some(ts) = &mut self.table_scan {
             while ts.next() {
-                if let Ok(val) = &ts.get_val("dataval") {
-                    return val == search_key;
+                if let Ok(val) = &ts.get_val("dataval")
+                    && val == search_key
+                {
+                    return true;
                 }
             }
         }
@@ -118,6 +155,15 @@ impl Index for StaticHashIndex {
         // Setting to None will cause the `Drop` trait to be utilized to close scan
         self.table_scan = None;
     }
+
+    /// Keys are spread among buckets by their hash, so the next larger key cannot be found
+    /// without reading every bucket.
+    fn skip_to_next_key(&mut self) -> Result<Option<Value>, String> {
+        Err(format!(
+            "hash index '{}' does not keep its keys in order",
+            self.index_name
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -128,7 +174,7 @@ mod tests {
 
     use crate::{
         index::index::Index, layout::Layout, make_schema, parser::constant::Value, rid::RID,
-        schema::Schema, tests::test_utils::test_db,
+        scan::scan::Scannable, schema::Schema, table_scan::TableScan, tests::test_utils::test_db,
     };
 
     use super::StaticHashIndex;
@@ -189,5 +235,86 @@ mod tests {
 
         idx.before_first(&Value::Int(100));
         assert!(!idx.next());
+
+        idx.before_first(&Value::Int(13));
+        assert!(idx.skip_to_next_key().is_err());
+    }
+
+    #[test]
+    fn test_static_hash_colliding_keys() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let schema = make_schema! {
+            "block" => i32,
+            "id" => i32,
+            "dataval" => i32
+        };
+
+        // With a single bucket every key collides
+        let mut idx = StaticHashIndex::new(1, tx, "test_idx", Layout::from_schema(schema));
+        idx.insert(&Value::Int(1), RID::new(0, 1));
+        idx.insert(&Value::Int(2), RID::new(0, 2));
+        idx.insert(&Value::Int(1), RID::new(0, 3));
+
+        idx.before_first(&Value::Int(2));
+        assert!(idx.next());
+        assert_eq!(idx.get_rid(), Some(RID::new(0, 2)));
+        assert!(!idx.next());
+
+        idx.before_first(&Value::Int(1));
+        assert!(idx.next());
+        assert_eq!(idx.get_rid(), Some(RID::new(0, 1)));
+        assert!(idx.next());
+        assert_eq!(idx.get_rid(), Some(RID::new(0, 3)));
+        assert!(!idx.next());
+    }
+
+    #[test]
+    fn test_static_hash_custom_hash_fn() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let schema = make_schema! {
+            "block" => i32,
+            "id" => i32,
+            "dataval" => i32
+        };
+        let layout = Layout::from_schema(schema);
+
+        // Every key hashes to bucket 3
+        let mut idx = StaticHashIndex::new(10, tx.clone(), "test_idx", layout.clone())
+            .with_hash_fn(|_: &Value| 13);
+        for i in 0..20 {
+            idx.insert(&Value::Int(i % 4), RID::new(i as u64, i as i16));
+        }
+
+        for key in 0..4 {
+            idx.before_first(&Value::Int(key));
+            let mut rids = vec![];
+            while idx.next() {
+                rids.push(idx.get_rid().unwrap());
+            }
+            let expected: Vec<RID> = (key..20)
+                .step_by(4)
+                .map(|i| RID::new(i as u64, i as i16))
+                .collect();
+            assert_eq!(rids, expected);
+        }
+        idx.close();
+
+        let mut bucket = TableScan::new(tx.clone(), layout.clone(), "test_idx3");
+        let mut count = 0;
+        while bucket.next() {
+            count += 1;
+        }
+        assert_eq!(count, 20);
+
+        let mut other = TableScan::new(tx, layout, "test_idx0");
+        assert!(!other.next());
     }
 }
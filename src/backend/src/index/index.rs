@@ -22,4 +22,10 @@ pub trait Index {
     fn insert(&mut self, key: &Value, rid: RID);
     fn delete(&mut self, key: &Value, rid: &RID);
     fn close(&mut self);
+
+    /// Move past the remaining entries with the current search key to the next larger key in the
+    /// index, which becomes the search key, and position the index at its first entry. Returns
+    /// the new search key, or `None` if there is no larger key. Returns an error if the index does
+    /// not keep its keys in order, or if `before_first` has not been called.
+    fn skip_to_next_key(&mut self) -> Result<Option<Value>, String>;
 }
@@ -1,15 +1,56 @@
-use std::{collections::HashMap, mem};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
 
-use crate::schema::Schema;
+use crate::schema::{Encoding, Schema};
 
-// Space is allocated at the beginning for metadata, e.g. "inuse"
-static LAYOUT_START: u64 = mem::size_of::<u32>() as u64;
+// A slot begins with a single byte holding its state, e.g. whether it is in use
+static LAYOUT_START: u64 = mem::size_of::<u8>() as u64;
+
+/// The number of distinct values that the dictionary of a dictionary-encoded field holds in each
+/// page.
+pub const DICTIONARY_ENTRIES: usize = 16;
+
+/// The hidden field of a versioned record holding the number of the transaction that created it.
+pub const CREATED_TX_FIELD: &str = "$created_tx";
+
+/// The hidden field of a versioned record holding the number of the transaction that deleted it.
+pub const DELETED_TX_FIELD: &str = "$deleted_tx";
+
+/// How records are arranged in the pages of a table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PageFormat {
+    /// Every record occupies a fixed-size slot, with room for the longest value of each field.
+    #[default]
+    Fixed,
+    /// A slot directory at the head of the page points to variable-length record bodies, which
+    /// grow from the tail of the page. Strings take only as much space as they need.
+    Slotted,
+}
+
+/// What to do with a table whose records may be too large to fit in a page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LargeValuePolicy {
+    /// Refuse to create the table.
+    #[default]
+    Reject,
+    /// Shorten the longest varchar fields until records fit. Longer values are truncated to the
+    /// length of their field when they are written.
+    Truncate,
+    /// Store the values of the longest varchar fields outside of the record, in a chain of
+    /// overflow blocks, until records fit. Overflow values can be of any length.
+    Overflow,
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Layout {
     schema: Schema,
     offsets: HashMap<String, u64>,
     slot_size: u64,
+    format: PageFormat,
+    large_values: LargeValuePolicy,
+    overflow_fields: HashSet<String>,
 }
 
 impl Layout {
@@ -18,42 +59,218 @@ impl Layout {
             schema,
             offsets,
             slot_size,
+            format: PageFormat::Fixed,
+            large_values: LargeValuePolicy::Reject,
+            overflow_fields: HashSet::new(),
         }
     }
 
+    /// Store records in pages of the given format.
+    pub fn with_format(mut self, format: PageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn format(&self) -> PageFormat {
+        self.format
+    }
+
+    /// Handle records too large for a page according to the given policy. This only records the
+    /// policy; fields stored in overflow blocks are set with `with_overflow_fields`.
+    pub fn with_large_values(mut self, policy: LargeValuePolicy) -> Self {
+        self.large_values = policy;
+        self
+    }
+
+    pub fn large_values(&self) -> LargeValuePolicy {
+        self.large_values
+    }
+
+    /// Store the values of the given varchar fields in overflow blocks. The slot holds only a
+    /// pointer to the first overflow block, so the offsets of the layout must leave room for an
+    /// integer in place of each field.
+    pub fn with_overflow_fields(mut self, fields: HashSet<String>) -> Self {
+        self.overflow_fields = fields;
+        self
+    }
+
+    /// Whether the values of a field are stored in overflow blocks, rather than in the record.
+    pub fn is_overflow_field(&self, field_name: &str) -> bool {
+        self.overflow_fields.contains(field_name)
+    }
+
+    /// Whether the values of any field are stored in overflow blocks.
+    pub fn has_overflow_fields(&self) -> bool {
+        !self.overflow_fields.is_empty()
+    }
+
+    /// Add hidden fields to each slot recording the transactions that created and deleted the
+    /// record, so that transactions reading a snapshot can ignore records that are not part of
+    /// it. The hidden fields are not part of the schema. Only fixed-size slots can be versioned.
+    pub fn with_versions(mut self) -> Self {
+        for field_name in [CREATED_TX_FIELD, DELETED_TX_FIELD] {
+            if !self.offsets.contains_key(field_name) {
+                self.offsets.insert(field_name.to_string(), self.slot_size);
+                self.slot_size += mem::size_of::<i32>() as u64;
+            }
+        }
+        self
+    }
+
+    /// Whether records hold the transactions that created and deleted them.
+    pub fn is_versioned(&self) -> bool {
+        self.offsets.contains_key(CREATED_TX_FIELD)
+    }
+
     pub fn from_schema(schema: Schema) -> Self {
+        Self::from_schema_with_overflow(schema, HashSet::new())
+    }
+
+    /// Create a layout whose slots hold a pointer to overflow blocks, rather than the value, for
+    /// each of the given varchar fields. Slots also hold only a dictionary position for each
+    /// dictionary-encoded varchar field.
+    pub fn from_schema_with_overflow(schema: Schema, overflow_fields: HashSet<String>) -> Self {
         let mut offsets = HashMap::new();
         let mut pos = LAYOUT_START;
         for field_name in schema.fields().iter() {
             offsets.insert(field_name.clone(), pos);
 
-            let len = Self::byte_length(
-                schema.get_field_type(field_name).unwrap(),
-                schema.get_field_length(field_name).unwrap(),
-            );
+            let len = if overflow_fields.contains(field_name)
+                || Self::is_dictionary_encoded(&schema, field_name)
+            {
+                mem::size_of::<i32>() as u64
+            } else {
+                Self::byte_length(
+                    schema.get_field_type(field_name).unwrap(),
+                    schema.get_field_length(field_name).unwrap(),
+                )
+            };
             pos += len;
         }
 
-        Self::new(schema, offsets, pos)
+        Self::new(schema, offsets, pos).with_overflow_fields(overflow_fields)
     }
 
     pub fn schema(&self) -> &Schema {
         &self.schema
     }
 
-    pub fn offset(&self, field_name: &str) -> u64 {
-        // TODO: error handling
-        *self.offsets.get(field_name).expect(&format!(
-            "field '{}' does not exist in {:?}",
-            field_name,
-            self.schema()
-        ))
+    /// Get the offset of a field within a slot, or `None` if the layout has no such field.
+    pub fn offset(&self, field_name: &str) -> Option<u64> {
+        self.offsets.get(field_name).copied()
     }
 
     pub fn slot_size(&self) -> u64 {
         self.slot_size
     }
 
+    /// Get the number of bytes of each slot that hold no field values: the byte holding the
+    /// slot's state, and the hidden fields of a versioned record.
+    pub fn record_overhead(&self) -> u64 {
+        let versions = if self.is_versioned() {
+            2 * mem::size_of::<i32>() as u64
+        } else {
+            0
+        };
+        LAYOUT_START + versions
+    }
+
+    /// Get the number of fixed-size slots that fit in a page, in front of the dictionaries of its
+    /// dictionary-encoded fields. Records in slotted pages take only as much space as they need,
+    /// so more of them may fit.
+    ///
+    /// # Arguments
+    ///
+    /// * `page_size` - The size of the page, in bytes.
+    pub fn max_records(&self, page_size: usize) -> usize {
+        (page_size - self.dictionary_size()) / self.slot_size as usize
+    }
+
+    /// Whether the values of a field are stored in the dictionary at the end of each page, with
+    /// records holding the position of their value. Only varchar fields stored in fixed-size
+    /// slots, rather than overflow blocks, are dictionary encoded.
+    pub fn is_dictionary_field(&self, field_name: &str) -> bool {
+        self.format == PageFormat::Fixed
+            && !self.overflow_fields.contains(field_name)
+            && Self::is_dictionary_encoded(&self.schema, field_name)
+    }
+
+    /// Get the fields whose values are stored in a dictionary, in the order of the schema.
+    pub fn dictionary_fields(&self) -> Vec<String> {
+        self.schema
+            .fields()
+            .into_iter()
+            .filter(|f| self.is_dictionary_field(f))
+            .collect()
+    }
+
+    /// Get the number of bytes at the end of each page taken by the dictionaries of its
+    /// dictionary-encoded fields.
+    pub fn dictionary_size(&self) -> usize {
+        self.dictionary_fields()
+            .iter()
+            .map(|f| self.dictionary_length(f))
+            .sum()
+    }
+
+    /// Get the offset in a page of the dictionary of a field. A dictionary begins with the number
+    /// of values it holds, followed by room for `DICTIONARY_ENTRIES` values of the field's
+    /// length. The first dictionary-encoded field's dictionary ends the page.
+    ///
+    /// # Arguments
+    ///
+    /// * `field_name` - The name of the dictionary-encoded field.
+    /// * `page_size` - The size of the page, in bytes.
+    pub fn dictionary_offset(&self, field_name: &str, page_size: usize) -> usize {
+        let mut pos = page_size;
+        for f in self.dictionary_fields() {
+            pos -= self.dictionary_length(&f);
+            if f == field_name {
+                return pos;
+            }
+        }
+
+        panic!("field '{}' is not dictionary encoded", field_name)
+    }
+
+    /// Get the number of bytes taken by each value in the dictionary of a field: the length of
+    /// the string, followed by room for its longest value.
+    pub fn dictionary_entry_length(&self, field_name: &str) -> usize {
+        mem::size_of::<u32>()
+            + self.schema.get_field_length(field_name).unwrap_or_default() as usize
+    }
+
+    // Get the number of bytes taken by the dictionary of a field, including its count of values.
+    fn dictionary_length(&self, field_name: &str) -> usize {
+        mem::size_of::<i32>() + DICTIONARY_ENTRIES * self.dictionary_entry_length(field_name)
+    }
+
+    // Whether a schema asks for a varchar field to be dictionary encoded.
+    fn is_dictionary_encoded(schema: &Schema, field_name: &str) -> bool {
+        schema.get_field_type(field_name) == Some(1)
+            && schema.get_field_encoding(field_name) == Some(Encoding::Dictionary)
+    }
+
+    /// Get the offset in a page of the start of the specified slot.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot whose offset will be computed.
+    pub fn slot_offset(&self, slot: i16) -> usize {
+        self.slot_size as usize * slot as usize
+    }
+
+    /// Get the offset in a page of a field within the specified slot, or `None` if the layout
+    /// has no such field.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot containing the field.
+    /// * `field_name` - The name of the field.
+    pub fn field_offset(&self, slot: i16, field_name: &str) -> Option<usize> {
+        Some(self.slot_offset(slot) + self.offset(field_name)? as usize)
+    }
+
     // TODO: at the moment this is just assuming that strings are ASCII.
     pub fn byte_length(field_type: i32, field_length: u64) -> u64 {
         match field_type {
@@ -71,7 +288,7 @@ mod tests {
 
     use crate::{layout::LAYOUT_START, make_schema};
 
-    use super::Layout;
+    use super::*;
 
     #[test]
     fn test_layout() {
@@ -81,14 +298,38 @@ mod tests {
             "id" => i32
         });
 
-        assert_eq!(layout.offset("dataval"), LAYOUT_START);
+        assert_eq!(layout.offset("dataval"), Some(LAYOUT_START));
         assert_eq!(
             layout.offset("block"),
-            LAYOUT_START + TryInto::<u64>::try_into(size_of::<i32>()).unwrap()
+            Some(LAYOUT_START + TryInto::<u64>::try_into(size_of::<i32>()).unwrap())
         );
         assert_eq!(
             layout.offset("id"),
-            LAYOUT_START + TryInto::<u64>::try_into(size_of::<i32>()).unwrap() * 2
+            Some(LAYOUT_START + TryInto::<u64>::try_into(size_of::<i32>()).unwrap() * 2)
         );
+        assert_eq!(layout.offset("missing"), None);
+        assert_eq!(layout.field_offset(2, "missing"), None);
+        assert_eq!(layout.record_overhead(), LAYOUT_START);
+        assert_eq!(layout.max_records(4096), 4096 / 13);
+
+        let layout = layout.with_versions();
+        assert_eq!(layout.record_overhead(), LAYOUT_START + 8);
+        assert_eq!(layout.max_records(4096), 4096 / 21);
+    }
+
+    #[test]
+    fn test_layout_with_overflow() {
+        let schema = make_schema! {
+            "A" => i32,
+            "B" => varchar(10000),
+            "C" => i32
+        };
+        let layout = Layout::from_schema_with_overflow(schema, HashSet::from(["B".to_string()]));
+
+        assert!(layout.is_overflow_field("B"));
+        assert!(!layout.is_overflow_field("A"));
+        assert_eq!(layout.offset("B"), Some(LAYOUT_START + 4));
+        assert_eq!(layout.offset("C"), Some(LAYOUT_START + 8));
+        assert_eq!(layout.slot_size(), LAYOUT_START + 12);
     }
 }
@@ -7,6 +7,7 @@
 #[cfg(test)]
 mod tests;
 
+mod admission;
 mod block_id;
 mod buffer;
 mod buffer_list;
@@ -22,8 +23,10 @@ mod log_record;
 mod page;
 mod record_page;
 mod rid;
+mod row_stream;
 mod schema;
 mod table_scan;
+mod temp_table;
 mod transaction;
 
 mod index;
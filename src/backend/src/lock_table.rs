@@ -1,6 +1,8 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, Condvar, Mutex},
+    fmt::Display,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
 };
 
 use backtrace::Backtrace;
@@ -10,29 +12,79 @@ use crate::block_id::BlockId;
 
 static MAX_TIME_MS: u32 = 10000;
 
+/// What a lock is held on. The end of a file and a whole file have keys of their own, rather than
+/// reserved block numbers, so that no real block can ever share their lock.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum LockKey {
+    /// A single block.
+    Block(BlockId),
+    /// The end of a file, locked by transactions that read its size or append to it.
+    EndOfFile(String),
+    /// A whole file, locked in place of its blocks under table-level locking. Under block-level
+    /// locking, intention locks are taken on it before its blocks are locked.
+    File(String),
+}
+
+/// A lock could not be acquired before the maximum wait elapsed, most likely because the waiting
+/// transaction is deadlocked with the one holding it. The transaction should roll back, releasing
+/// its own locks, and can then be retried.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct LockAbortError(pub LockKey);
+
+impl Display for LockAbortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "timed out waiting for a lock on {:?}", self.0)
+    }
+}
+
 struct Lock {
-    count: Mutex<i16>,
+    state: Mutex<LockState>,
     condvar: Condvar,
 }
 
+// The locks held on a key. `count` is the number of shared locks, or -1 while an exclusive lock is
+// held. Intention locks, which announce shared or exclusive locks on the blocks of a file, are
+// counted separately.
+#[derive(Default)]
+struct LockState {
+    count: i16,
+    intention_shared: i16,
+    intention_exclusive: i16,
+}
+
 impl Lock {
-    fn new_shared() -> Self {
+    fn new(state: LockState) -> Self {
         Self {
-            count: Mutex::new(1),
+            state: Mutex::new(state),
             condvar: Condvar::new(),
         }
     }
 
+    fn new_shared() -> Self {
+        Self::new(LockState {
+            count: 1,
+            ..Default::default()
+        })
+    }
+
     fn new_exclusive() -> Self {
-        Self {
-            count: Mutex::new(-1),
-            condvar: Condvar::new(),
-        }
+        Self::new(LockState {
+            count: -1,
+            ..Default::default()
+        })
+    }
+
+    fn new_intention_shared() -> Self {
+        Self::new(LockState {
+            intention_shared: 1,
+            ..Default::default()
+        })
     }
 }
 
 pub struct LockTable {
-    locks: Arc<Mutex<HashMap<BlockId, Arc<Lock>>>>,
+    locks: Arc<Mutex<HashMap<LockKey, Arc<Lock>>>>,
+    max_wait: Duration,
 }
 
 //unsafe impl Send for LockTable {}
@@ -40,106 +92,212 @@ pub struct LockTable {
 
 impl LockTable {
     pub fn new() -> Self {
+        Self::with_max_wait(Duration::from_millis(MAX_TIME_MS as u64))
+    }
+
+    /// Create a lock table whose lock requests give up after waiting for `max_wait`.
+    pub fn with_max_wait(max_wait: Duration) -> Self {
         Self {
             locks: Arc::new(Mutex::new(HashMap::new())),
+            max_wait,
         }
     }
 
-    /// Request a shared lock on a block.
+    /// Request a shared lock on a key. Returns an error if an exclusive or intention exclusive
+    /// lock is held on the key for longer than the maximum wait.
     ///
     /// # Arguments
     ///
-    /// * `blk` - The BlockId that the shared lock will be held on.
-    pub fn slock(&self, blk: &BlockId) {
+    /// * `key` - The key that the shared lock will be held on.
+    pub fn slock(&self, key: &LockKey) -> Result<(), LockAbortError> {
         trace!("requesting an slock");
 
         let lock = {
             let mut locks = self.locks.lock().unwrap();
-            if let Some(lock) = locks.get(blk).cloned() {
+            if let Some(lock) = locks.get(key).cloned() {
                 lock
             } else {
                 trace!("adding new shared lock");
-                locks.insert(blk.clone(), Arc::new(Lock::new_shared()));
-                return;
+                locks.insert(key.clone(), Arc::new(Lock::new_shared()));
+                return Ok(());
             }
         };
 
         {
-            let mut count = lock.count.lock().unwrap();
-            while *count == -1 {
-                trace!("waiting for slock. xlock already exists");
-                count = lock.condvar.wait(count).unwrap();
-            }
+            // Wait while an xlock or an ixlock exists
+            let mut state = self.wait_while(key, &lock, |state| {
+                state.count == -1 || state.intention_exclusive > 0
+            })?;
 
             trace!("successfully retrieved slock");
-            *count += 1;
+            state.count += 1;
         }
+        Ok(())
     }
 
-    /// Request an exclusive lock on a block.
+    /// Request an exclusive lock on a key, which the caller must already hold a shared lock on.
+    /// Returns an error if other shared or intention locks are held on the key for longer than
+    /// the maximum wait.
     ///
     /// # Arguments
     ///
-    /// * `blk` - The BlockId that the exclusive lock will be held on.
-    pub fn xlock(&self, blk: &BlockId) {
+    /// * `key` - The key that the exclusive lock will be held on.
+    pub fn xlock(&self, key: &LockKey) -> Result<(), LockAbortError> {
         trace!("requesting an xlock");
 
         let lock = {
             let mut locks = self.locks.lock().unwrap();
-            if let Some(lock) = locks.get(blk).cloned() {
+            if let Some(lock) = locks.get(key).cloned() {
                 lock
             } else {
                 trace!("inserting new xlock");
-                locks.insert(blk.clone(), Arc::new(Lock::new_exclusive()));
-                return;
+                locks.insert(key.clone(), Arc::new(Lock::new_exclusive()));
+                return Ok(());
             }
         };
 
         {
-            let mut count = lock.count.lock().unwrap();
-            while *count > 1 {
-                trace!("waiting for xlock. Lock count > 1");
-                // TODO: wait_timeout
-                count = lock.condvar.wait(count).unwrap();
-            }
+            // Wait while other slocks or any intention locks exist
+            let mut state = self.wait_while(key, &lock, |state| {
+                state.count > 1 || state.intention_shared > 0 || state.intention_exclusive > 0
+            })?;
 
             trace!("successfully set xlock");
-            *count = -1;
+            state.count = -1;
         }
 
         // The lock will have been removed from the lock map while we were waiting for it. Move it
         // back.
         // TODO: Think more about this
         //self.locks.lock().unwrap().insert(blk.clone(), lock);
+        Ok(())
     }
 
-    /// Removes the lock held by the current thread.
+    /// Request an intention shared lock on a file, announcing that the caller will take shared
+    /// locks on its blocks. Returns an error if an exclusive lock is held on the file for longer
+    /// than the maximum wait.
     ///
     /// # Arguments
     ///
-    /// * `blk` - The BlockId that the lock will be removed for.
-    pub fn unlock(&self, blk: &BlockId) {
+    /// * `key` - The key of the file that the intention lock will be held on.
+    pub fn islock(&self, key: &LockKey) -> Result<(), LockAbortError> {
+        trace!("requesting an islock");
+
         let lock = {
-            let locks = self.locks.lock().unwrap();
-            if let Some(lock) = locks.get(blk).cloned() {
+            let mut locks = self.locks.lock().unwrap();
+            if let Some(lock) = locks.get(key).cloned() {
                 lock
             } else {
-                // TODO: return a Result instead?
-                panic!("attempting to unlock block that is not currently locked!");
+                trace!("adding new intention shared lock");
+                locks.insert(key.clone(), Arc::new(Lock::new_intention_shared()));
+                return Ok(());
             }
         };
 
-        let mut count = lock.count.lock().unwrap();
-        assert!(*count != 0, "Unexpected lock count value of 0");
-        if *count == -1 {
-            *count = 0;
-        } else if *count > 0 {
-            *count -= 1;
+        // Wait while an xlock exists
+        let mut state = self.wait_while(key, &lock, |state| state.count == -1)?;
+
+        trace!("successfully retrieved islock");
+        state.intention_shared += 1;
+        Ok(())
+    }
+
+    /// Request an intention exclusive lock on a file, announcing that the caller will take
+    /// exclusive locks on its blocks. The caller must already hold an intention shared lock on
+    /// the file, which is upgraded. Returns an error if shared or exclusive locks are held on the
+    /// file for longer than the maximum wait.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the file that the intention lock will be held on.
+    pub fn ixlock(&self, key: &LockKey) -> Result<(), LockAbortError> {
+        trace!("requesting an ixlock");
+
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .expect("an ixlock must be upgraded from an islock");
+
+        // Wait while slocks or an xlock exist
+        let mut state = self.wait_while(key, &lock, |state| state.count != 0)?;
+
+        trace!("successfully set ixlock");
+        state.intention_shared -= 1;
+        state.intention_exclusive += 1;
+        Ok(())
+    }
+
+    // Wait until the state of a lock no longer satisfies `condition`, returning the guarded state,
+    // or an error once the maximum wait has elapsed.
+    fn wait_while<'a>(
+        &self,
+        key: &LockKey,
+        lock: &'a Lock,
+        condition: impl Fn(&LockState) -> bool,
+    ) -> Result<MutexGuard<'a, LockState>, LockAbortError> {
+        let deadline = Instant::now() + self.max_wait;
+        let mut state = lock.state.lock().unwrap();
+        while condition(&state) {
+            let now = Instant::now();
+            if now >= deadline {
+                trace!("timed out waiting for lock on {:?}", key);
+                return Err(LockAbortError(key.clone()));
+            }
+            trace!("waiting for lock on {:?}", key);
+            state = lock.condvar.wait_timeout(state, deadline - now).unwrap().0;
         }
+        Ok(state)
+    }
 
-        // If no locks are held, or a single shared lock (can be upgraded to an xlock) is held, notify waiting threads
-        if *count == 0 || *count == 1 {
-            lock.condvar.notify_one();
+    /// Removes the shared or exclusive lock held by the current thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key that the lock will be removed for.
+    pub fn unlock(&self, key: &LockKey) {
+        let lock = self.get_held(key);
+        let mut state = lock.state.lock().unwrap();
+        assert!(state.count != 0, "Unexpected lock count value of 0");
+        if state.count == -1 {
+            state.count = 0;
+        } else if state.count > 0 {
+            state.count -= 1;
+        }
+
+        // Waiters for different kinds of lock may now be able to proceed, so wake them all
+        lock.condvar.notify_all();
+    }
+
+    /// Removes the intention lock held by the current thread on a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the file that the intention lock will be removed for.
+    /// * `exclusive` - Whether the lock is an intention exclusive lock.
+    pub fn unlock_intention(&self, key: &LockKey, exclusive: bool) {
+        let lock = self.get_held(key);
+        let mut state = lock.state.lock().unwrap();
+        let count = if exclusive {
+            &mut state.intention_exclusive
+        } else {
+            &mut state.intention_shared
+        };
+        assert!(*count > 0, "Unexpected intention lock count value of 0");
+        *count -= 1;
+
+        lock.condvar.notify_all();
+    }
+
+    fn get_held(&self, key: &LockKey) -> Arc<Lock> {
+        let locks = self.locks.lock().unwrap();
+        if let Some(lock) = locks.get(key).cloned() {
+            lock
+        } else {
+            // TODO: return a Result instead?
+            panic!("attempting to unlock block that is not currently locked!");
         }
     }
 }
@@ -149,14 +307,15 @@ mod tests {
     use std::{
         sync::{
             atomic::{AtomicUsize, Ordering},
-            Arc, Barrier,
+            mpsc, Arc, Barrier,
         },
         thread,
+        time::Duration,
     };
 
     use crate::block_id::BlockId;
 
-    use super::LockTable;
+    use super::{LockAbortError, LockKey, LockTable};
 
     #[test]
     fn test_threaded_locks() {
@@ -173,10 +332,14 @@ mod tests {
         let handle_x = thread::spawn(move || {
             start_barrier_x.wait();
             for _ in 0..500 {
-                lock_table_x.slock(&BlockId::new("test", 1));
-                lock_table_x.xlock(&BlockId::new("test", 1));
+                lock_table_x
+                    .slock(&LockKey::Block(BlockId::new("test", 1)))
+                    .unwrap();
+                lock_table_x
+                    .xlock(&LockKey::Block(BlockId::new("test", 1)))
+                    .unwrap();
                 xlock_counter_x.fetch_add(1, Ordering::SeqCst);
-                lock_table_x.unlock(&BlockId::new("test", 1));
+                lock_table_x.unlock(&LockKey::Block(BlockId::new("test", 1)));
             }
         });
 
@@ -187,11 +350,13 @@ mod tests {
             start_barrier_s.wait();
             for _ in 0..100 {
                 for _ in 0..5 {
-                    lock_table_s.slock(&BlockId::new("test", 1));
+                    lock_table_s
+                        .slock(&LockKey::Block(BlockId::new("test", 1)))
+                        .unwrap();
                     slock_counter_s.fetch_add(1, Ordering::SeqCst);
                 }
                 for _ in 0..5 {
-                    lock_table_s.unlock(&BlockId::new("test", 1));
+                    lock_table_s.unlock(&LockKey::Block(BlockId::new("test", 1)));
                 }
             }
         });
@@ -203,4 +368,75 @@ mod tests {
         assert_eq!(xlock_counter.load(Ordering::SeqCst), 500);
         assert_eq!(slock_counter.load(Ordering::SeqCst), 500);
     }
+
+    #[test]
+    fn test_end_of_file_lock_independent_of_last_block() {
+        let lock_table = Arc::new(LockTable::new());
+        let last_block = LockKey::Block(BlockId::new("test", u64::MAX));
+        lock_table.xlock(&last_block).unwrap();
+
+        // Locking the end of the file or the whole file would wait forever if either shared the
+        // lock on the highest block number
+        let lock_table_t = lock_table.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for key in [
+                LockKey::EndOfFile("test".to_string()),
+                LockKey::File("test".to_string()),
+            ] {
+                lock_table_t.slock(&key).unwrap();
+                lock_table_t.xlock(&key).unwrap();
+                lock_table_t.unlock(&key);
+            }
+            sender.send(()).unwrap();
+        });
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(()));
+
+        lock_table.unlock(&last_block);
+    }
+
+    #[test]
+    fn test_lock_wait_times_out() {
+        let lock_table = LockTable::with_max_wait(Duration::from_millis(100));
+        let key = LockKey::Block(BlockId::new("test", 1));
+        lock_table.slock(&key).unwrap();
+        lock_table.xlock(&key).unwrap();
+
+        // The wait gives up rather than blocking forever
+        assert_eq!(lock_table.slock(&key), Err(LockAbortError(key.clone())));
+
+        lock_table.unlock(&key);
+        lock_table.slock(&key).unwrap();
+        lock_table.slock(&key).unwrap();
+        assert_eq!(lock_table.xlock(&key), Err(LockAbortError(key.clone())));
+    }
+
+    #[test]
+    fn test_intention_locks() {
+        let lock_table = LockTable::with_max_wait(Duration::from_millis(100));
+        let key = LockKey::File("test".to_string());
+
+        // Intention locks are compatible with each other, and intention shared locks with shared
+        // locks
+        lock_table.islock(&key).unwrap();
+        lock_table.islock(&key).unwrap();
+        lock_table.ixlock(&key).unwrap();
+        lock_table.unlock_intention(&key, false);
+        assert_eq!(lock_table.slock(&key), Err(LockAbortError(key.clone())));
+        lock_table.unlock_intention(&key, true);
+        lock_table.slock(&key).unwrap();
+        lock_table.islock(&key).unwrap();
+
+        // Exclusive locks conflict with intention locks, and shared locks with intention
+        // exclusive locks
+        assert_eq!(lock_table.xlock(&key), Err(LockAbortError(key.clone())));
+        assert_eq!(lock_table.ixlock(&key), Err(LockAbortError(key.clone())));
+        lock_table.unlock_intention(&key, false);
+        lock_table.xlock(&key).unwrap();
+        assert_eq!(lock_table.islock(&key), Err(LockAbortError(key.clone())));
+
+        lock_table.unlock(&key);
+        lock_table.islock(&key).unwrap();
+        lock_table.ixlock(&key).unwrap();
+    }
 }
@@ -1,8 +1,9 @@
 use crate::block_id::BlockId;
 use crate::file_manager::FileManager;
 use crate::page::{Page, PAGE_SIZE};
+use std::fmt::Display;
 use std::mem::size_of;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 const LOG_NAME: &str = "log";
 
@@ -16,6 +17,39 @@ const FRONTIER_POS: usize = 0;
 // The initial value of the frontier
 const FRONTIER_START: usize = size_of::<Frontier>();
 
+// Set in a record's length to mark it as a fragment that continues a record begun in the previous
+// block. Records too large for a single page are split into fragments across consecutive blocks.
+const CONTINUATION_FLAG: RecordLength = 1 << (RecordLength::BITS - 1);
+
+// The largest record that can be written to the log
+const MAX_RECORD_LEN: usize = (CONTINUATION_FLAG - 1) as usize;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum LogError {
+    /// The record, with the given length in bytes, exceeds `MAX_RECORD_LEN`.
+    RecordTooLarge(usize),
+    /// The record could not be encoded into bytes.
+    Encode(String),
+    /// The log could not be written to storage.
+    Io(String),
+}
+
+impl Display for LogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RecordTooLarge(len) => {
+                write!(
+                    f,
+                    "log record of {} bytes exceeds the maximum of {} bytes",
+                    len, MAX_RECORD_LEN
+                )
+            }
+            Self::Encode(msg) => write!(f, "failed to encode log record: {}", msg),
+            Self::Io(msg) => write!(f, "failed to write log: {}", msg),
+        }
+    }
+}
+
 pub struct LogManager {
     file_manager: Arc<FileManager>,
     page: LogPage,
@@ -81,60 +115,108 @@ impl LogManager {
             .num();
     }
 
-    /// Append a record to the log and return the latest lsn.
+    /// Append a record to the log and return the latest lsn. Records that do not fit in a single
+    /// page are split across consecutive blocks. Returns an error if the record exceeds the
+    /// maximum record length.
     ///
     /// # Arguments
     ///
     /// * `record` - Bytes that will be written to the log
-    pub fn append(&mut self, record: &[u8]) -> i64 {
+    pub fn append(&mut self, record: &[u8]) -> Result<i64, LogError> {
+        if record.len() > MAX_RECORD_LEN {
+            return Err(LogError::RecordTooLarge(record.len()));
+        }
+
         // If the record will fit in the existing page, place it there and update the frontier
         // Otherwise, create a new block
-        let len = record.len() as u64;
-        assert!(
-            len < PAGE_SIZE as u64,
-            "record does not fit in a single page!"
-        );
+        if record.len() <= Self::page_capacity(FRONTIER_START) {
+            if record.len() > Self::page_capacity(self.page.get_frontier() as usize) {
+                self.flush_all()?;
+                self.append_block();
+            }
+            self.write_fragment(record, false);
+        } else {
+            // The record can't fit in any page, fill the remainder of the existing page and
+            // continue it in as many new blocks as needed
+            let mut remaining = record;
+            let mut continued = false;
+            loop {
+                let capacity = Self::page_capacity(self.page.get_frontier() as usize);
+                let (fragment, rest) = remaining.split_at(capacity.min(remaining.len()));
+                if !fragment.is_empty() {
+                    self.write_fragment(fragment, continued);
+                    continued = true;
+                }
+
+                remaining = rest;
+                if remaining.is_empty() {
+                    break;
+                }
+
+                self.flush_all()?;
+                self.append_block();
+            }
+        }
 
-        let mut frontier = self.page.get_frontier();
+        self.latest_lsn += 1;
+        Ok(self.latest_lsn)
+    }
 
-        if frontier as u64 + len + size_of::<RecordLength>() as u64 >= PAGE_SIZE as u64 {
-            // the record won't fit in the existing page, append a new block
-            self.flush_all();
-            self.append_block();
+    /// Get the lsn of the most recently appended record, or 0 if no record has been appended
+    /// since the log was opened.
+    pub fn latest_lsn(&self) -> i64 {
+        self.latest_lsn
+    }
+
+    // Get the number of record bytes that can be written into a page at the given frontier.
+    fn page_capacity(frontier: usize) -> usize {
+        (PAGE_SIZE - 1).saturating_sub(frontier + size_of::<RecordLength>())
+    }
 
-            // refresh the frontier, as it will now point to the start of the newly created block
-            frontier = self.page.get_frontier();
+    // Write a record, or a fragment of one, at the frontier of the existing page. The fragment
+    // must fit in the page.
+    fn write_fragment(&mut self, fragment: &[u8], continued: bool) {
+        let mut len = fragment.len() as RecordLength;
+        if continued {
+            len |= CONTINUATION_FLAG;
         }
 
-        frontier += self.page.write_bytes(record, frontier as usize) as u32;
-        frontier += self.page.write(len as RecordLength, frontier as usize) as u32;
-        self.page.set_frontier(frontier as RecordLength);
-        self.latest_lsn += 1;
-        self.latest_lsn
+        let mut frontier = self.page.get_frontier();
+        frontier += self.page.write_bytes(fragment, frontier as usize) as u32;
+        frontier += self.page.write(len, frontier as usize) as u32;
+        self.page.set_frontier(frontier);
     }
 
-    /// Flushes all log records to durable storage.
-    pub fn flush(&mut self, lsn: i64) {
-        if self.latest_lsn >= lsn {
-            return;
+    /// Flushes all log records to durable storage. Returns an error if the log could not be
+    /// written, in which case the records are still held in memory and a later flush retries.
+    pub fn flush(&mut self, lsn: i64) -> Result<(), LogError> {
+        if self.last_saved_lsn >= lsn {
+            return Ok(());
         }
 
-        self.flush_all();
+        self.flush_all()
     }
 
-    fn flush_all(&mut self) {
+    fn flush_all(&mut self) -> Result<(), LogError> {
         self.file_manager
             .write_block(&BlockId::new(&LOG_NAME, self.block_num), &self.page)
-            .unwrap();
+            .map_err(|e| LogError::Io(e.to_string()))?;
 
         self.last_saved_lsn = self.latest_lsn;
+        Ok(())
+    }
+
+    /// Get the path of the file the log is stored in.
+    pub fn file_path(&self) -> PathBuf {
+        self.file_manager.file_path(LOG_NAME)
     }
 
     /// Gets a snapshot of the log that can be iterated over.
     ///
     /// Creating a snapshot will cause the log to be flushed.
     pub fn snapshot(&mut self) -> LogManagerSnapshot {
-        self.flush_all();
+        // TODO: error handling
+        self.flush_all().expect("failed to flush log");
 
         // TODO: block_num should prob not be a usize?
         let block = BlockId::new(LOG_NAME, self.block_num);
@@ -163,9 +245,29 @@ impl Iterator for LogManagerSnapshot {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let (mut record, mut continued) = self.next_fragment()?;
+
+        // Fragments are read in reverse, so each continued fragment is prefixed with the fragment
+        // that precedes it in the previous block.
+        while continued {
+            let (mut fragment, c) = self.next_fragment()?;
+            fragment.append(&mut record);
+            record = fragment;
+            continued = c;
+        }
+
+        Some(record)
+    }
+}
+
+impl LogManagerSnapshot {
+    // Read the fragment before the current position, along with whether it continues a record
+    // from the previous block.
+    fn next_fragment(&mut self) -> Option<(Vec<u8>, bool)> {
         assert!(self.current_pos >= FRONTIER_START as u32);
 
-        if self.current_pos == FRONTIER_START as u32 {
+        // Blocks can be empty if the log was appended to just before a crash
+        while self.current_pos == FRONTIER_START as u32 {
             self.block = self.block.previous()?;
             self.page = LogPage::new();
             self.file_manager
@@ -176,13 +278,15 @@ impl Iterator for LogManagerSnapshot {
         }
 
         self.current_pos -= size_of::<RecordLength>() as u32;
-        let len = self.page.read::<RecordLength>(self.current_pos as usize) as usize;
+        let len = self.page.read::<RecordLength>(self.current_pos as usize);
+        let continued = len & CONTINUATION_FLAG != 0;
+        let len = (len & !CONTINUATION_FLAG) as usize;
         self.current_pos -= len as u32;
 
-        // Read the next record
+        // Read the next fragment
         let r = self.page.read_bytes(self.current_pos as usize, len);
 
-        Some(r.to_vec())
+        Some((r.to_vec(), continued))
     }
 }
 
@@ -205,7 +309,7 @@ mod tests {
 
             for i in 0..1000 {
                 let record = [(i % 256) as u8; 16];
-                lm.append(&record);
+                lm.append(&record).unwrap();
             }
 
             let snapshot = lm.snapshot();
@@ -218,7 +322,7 @@ mod tests {
 
             assert_eq!(i, -1);
 
-            lm.flush(1000);
+            lm.flush(1000).unwrap();
         }
 
         let mut lm = LogManager::new(&root_dir);
@@ -243,7 +347,7 @@ mod tests {
 
         for i in 0..1000 {
             let record = [(i % 256) as u8; 16];
-            lm.append(&record);
+            lm.append(&record).unwrap();
         }
 
         let snapshot1 = lm.snapshot();
@@ -258,7 +362,7 @@ mod tests {
 
         for i in 1000..1500 {
             let record = [(i % 256) as u8; 16];
-            lm.append(&record);
+            lm.append(&record).unwrap();
         }
 
         // Take a new snapshot after adding an additional 500 records
@@ -278,4 +382,65 @@ mod tests {
         }
         assert_eq!(i, -1);
     }
+
+    #[test]
+    fn test_append_spanning_records() {
+        let td = tempdir().unwrap();
+        let root_dir = td.path().join("data");
+        fs::create_dir_all(&root_dir).expect("Failed to create root directory");
+
+        let max_len = PAGE_SIZE - FRONTIER_START - size_of::<RecordLength>() - 1;
+        let records = [
+            vec![1; 16],
+            vec![2; max_len],
+            vec![3; max_len + 1],
+            vec![4; 16],
+            (0..3 * PAGE_SIZE)
+                .map(|i| (i % 251) as u8)
+                .collect::<Vec<u8>>(),
+            vec![5; 16],
+        ];
+
+        {
+            let mut lm = LogManager::new(&root_dir);
+            for (i, record) in records.iter().enumerate() {
+                assert_eq!(lm.append(record), Ok(i as i64 + 1));
+            }
+
+            // Only the records that do not fit in a single page are split
+            assert!(lm.block_num >= 5);
+
+            let read: Vec<Vec<u8>> = lm.snapshot().collect();
+            assert_eq!(read, records.iter().rev().cloned().collect::<Vec<_>>());
+        }
+
+        // The fragments are reassembled after the log is reopened
+        let mut lm = LogManager::new(&root_dir);
+        let read: Vec<Vec<u8>> = lm.snapshot().collect();
+        assert_eq!(read, records.iter().rev().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_flush_persists_records() {
+        let td = tempdir().unwrap();
+        let root_dir = td.path().join("data");
+        fs::create_dir_all(&root_dir).expect("Failed to create root directory");
+
+        {
+            let mut lm = LogManager::new(&root_dir);
+            let mut lsn = 0;
+            for i in 0..10 {
+                lsn = lm.append(&[i; 16]).unwrap();
+            }
+            lm.flush(lsn).unwrap();
+
+            // Not flushed, so lost when the log manager is dropped
+            lm.append(&[10; 16]).unwrap();
+        }
+
+        let mut lm = LogManager::new(&root_dir);
+        assert_eq!(lm.block_num, 0);
+        let read: Vec<Vec<u8>> = lm.snapshot().collect();
+        assert_eq!(read, (0..10).rev().map(|i| vec![i; 16]).collect::<Vec<_>>());
+    }
 }
@@ -14,41 +14,130 @@ pub enum LogRecord {
     Rollback {
         tx_num: i64,
     },
+    /// A transaction was rolled back because of an error, described by `reason`.
+    Abort {
+        tx_num: i64,
+        reason: String,
+    },
+    /// The changes logged in the `records` records before this one were undone by rolling the
+    /// transaction back to a savepoint. Records of other transactions are counted too.
+    RollbackTo {
+        tx_num: i64,
+        records: u64,
+    },
+    /// An integer at `offset` was changed from `old_val` to `new_val`.
     SetInt {
         tx_num: i64,
         block: BlockId,
         offset: u16,
-        val: i32,
+        old_val: i32,
+        new_val: i32,
     },
+    /// A string at `offset` was changed from `old_val` to `new_val`.
     SetString {
         tx_num: i64,
         block: BlockId,
         offset: u16,
-        val: String,
+        old_val: String,
+        new_val: String,
+    },
+    /// A run of bytes at `offset` was changed from `old_val` to `new_val`.
+    SetBytes {
+        tx_num: i64,
+        block: BlockId,
+        offset: u16,
+        old_val: Vec<u8>,
+        new_val: Vec<u8>,
+    },
+    /// Every block of a file was removed, after the file was moved aside to `backup`.
+    Truncate {
+        tx_num: i64,
+        file_id: String,
+        backup: String,
     },
 }
 
 impl LogRecord {
+    /// Write back the value that a change replaced.
     pub fn undo(&self, tx: &mut Tx) {
         match self {
             LogRecord::SetInt {
-                tx_num,
                 block,
                 offset,
-                val,
+                old_val,
+                ..
             } => {
                 tx.pin(block);
-                tx.set_int(block, *offset as usize, *val, false);
+                // Undo writes are not logged, so they cannot fail
+                tx.set_int(block, *offset as usize, *old_val, false)
+                    .unwrap();
                 tx.unpin(block);
             }
             LogRecord::SetString {
-                tx_num,
                 block,
                 offset,
-                val,
+                old_val,
+                ..
+            } => {
+                tx.pin(block);
+                tx.set_string(block, *offset as usize, old_val, false)
+                    .unwrap();
+                tx.unpin(block);
+            }
+            LogRecord::SetBytes {
+                block,
+                offset,
+                old_val,
+                ..
+            } => {
+                tx.pin(block);
+                tx.set_bytes(block, *offset as usize, old_val, false)
+                    .unwrap();
+                tx.unpin(block);
+            }
+            LogRecord::Truncate {
+                file_id, backup, ..
+            } => tx.restore_truncated(file_id, backup),
+            _ => return,
+        }
+    }
+
+    /// Write the value of a change again. A truncated file is already empty, so redoing its
+    /// truncation does nothing.
+    pub fn redo(&self, tx: &mut Tx) {
+        match self {
+            LogRecord::SetInt {
+                block,
+                offset,
+                new_val,
+                ..
+            } => {
+                tx.pin(block);
+                // Redo writes are not logged either
+                tx.set_int(block, *offset as usize, *new_val, false)
+                    .unwrap();
+                tx.unpin(block);
+            }
+            LogRecord::SetString {
+                block,
+                offset,
+                new_val,
+                ..
+            } => {
+                tx.pin(block);
+                tx.set_string(block, *offset as usize, new_val, false)
+                    .unwrap();
+                tx.unpin(block);
+            }
+            LogRecord::SetBytes {
+                block,
+                offset,
+                new_val,
+                ..
             } => {
                 tx.pin(block);
-                tx.set_string(block, *offset as usize, val, false);
+                tx.set_bytes(block, *offset as usize, new_val, false)
+                    .unwrap();
                 tx.unpin(block);
             }
             _ => return,
@@ -14,7 +14,7 @@ use crate::{
 
 use super::{
     stats_manager::{StatisticsInfo, StatisticsManager},
-    table_manager::{TableManager, MAX_NAME},
+    table_manager::{check_name_length, TableManager, MAX_NAME},
 };
 
 pub struct IndexInfo {
@@ -47,10 +47,18 @@ impl IndexInfo {
         BTreeIndex::new(self.tx.clone(), &self.name, self.layout.clone())
     }
 
+    /// The name of the index.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the indexed field.
+    pub fn field_name(&self) -> &str {
+        &self.field_name
+    }
+
     pub fn blocks_accessed(&self) -> u64 {
-        let recs_per_blk = self.tx.lock().unwrap().block_size() as u64 / self.layout.slot_size();
-        let num_blks = self.stat_info.records_output() / recs_per_blk;
-        BTreeIndex::search_cost(num_blks, recs_per_blk)
+        self.open().search_cost()
     }
 
     pub fn records_outputs(&self) -> u64 {
@@ -122,6 +130,10 @@ impl IndexManager {
         field_name: &str,
         tx: Arc<Mutex<Tx>>,
     ) -> Result<(), String> {
+        check_name_length("index", idx_name)?;
+        check_name_length("table", tbl_name)?;
+        check_name_length("field", field_name)?;
+
         // TODO: verify that index does not already exist
         let mut scan = TableScan::new(tx, self.layout.clone(), "idxcat");
         scan.insert();
@@ -167,4 +179,48 @@ impl IndexManager {
 
         result
     }
+
+    /// Remove every index on a table from the catalog. The indexes' files are not removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `tx` - The transaction used to update the metadata table.
+    pub fn drop_indexes(&self, tbl_name: &str, tx: &Arc<Mutex<Tx>>) {
+        let mut scan = TableScan::new(tx.clone(), self.layout.clone(), "idxcat");
+        while scan.next() {
+            if scan.get_string("tablename").unwrap() == tbl_name {
+                scan.delete();
+            }
+        }
+    }
+
+    /// Check that every index in the catalog is defined on a field of an existing table,
+    /// returning a description of each index that is not.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction used to read from the metadata tables.
+    pub fn verify(&self, tx: &Arc<Mutex<Tx>>) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut scan = TableScan::new(tx.clone(), self.layout.clone(), "idxcat");
+        while scan.next() {
+            let index_name = scan.get_string("indexname").unwrap();
+            let table_name = scan.get_string("tablename").unwrap();
+            let field_name = scan.get_string("fieldname").unwrap();
+            match self.tbl_mgr.get_table_layout(&table_name, tx) {
+                None => problems.push(format!(
+                    "index '{}' is on table '{}', which does not exist",
+                    index_name, table_name
+                )),
+                Some(layout) if !layout.schema().has_field(&field_name) => problems.push(format!(
+                    "index '{}' is on field '{}', which does not exist in table '{}'",
+                    index_name, field_name, table_name
+                )),
+                Some(_) => {}
+            }
+        }
+
+        problems
+    }
 }
@@ -3,7 +3,13 @@ use std::{
     sync::{Arc, Mutex, RwLock},
 };
 
-use crate::{layout::Layout, schema::Schema, transaction::Tx};
+use crate::{
+    layout::{LargeValuePolicy, Layout, PageFormat},
+    parser::constant::Value,
+    record_page::RecordPage,
+    schema::Schema,
+    transaction::Tx,
+};
 
 use super::{
     index_manager::{IndexInfo, IndexManager},
@@ -31,19 +37,142 @@ impl MetadataManager {
         }
     }
 
-    /// Create a new table in the metadata catalogs. Returns boolean indicating whether or not the
-    /// table was successfully created.
+    /// Create a new table in the metadata catalogs.
     ///
     /// # Arguments
     ///
     /// * `tbl_name` - The name of the table.
     /// * `schema` - The table's schema.
     /// * `tx` - The transaction that table creation will run inside of.
-    // TODO: why return `bool`? Mke this return a result
-    pub fn create_table(&self, tbl_name: &str, schema: &Schema, tx: &Arc<Mutex<Tx>>) -> bool {
+    pub fn create_table(
+        &self,
+        tbl_name: &str,
+        schema: &Schema,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
         self.tbl_mgr.create_table(tbl_name, schema, tx)
     }
 
+    /// Create a new table in the metadata catalogs whose records are stored in pages of the given
+    /// format.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `schema` - The table's schema.
+    /// * `format` - The format of the table's pages.
+    /// * `tx` - The transaction that table creation will run inside of.
+    pub fn create_table_with_format(
+        &self,
+        tbl_name: &str,
+        schema: &Schema,
+        format: PageFormat,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        self.tbl_mgr
+            .create_table_with_format(tbl_name, schema, format, tx)
+    }
+
+    /// Create a new table in the metadata catalogs, whose records are made to fit in a page
+    /// according to the given policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `schema` - The table's schema.
+    /// * `format` - The format of the table's pages.
+    /// * `large_values` - How to handle records that may be too large for a page.
+    /// * `tx` - The transaction that table creation will run inside of.
+    pub fn create_table_with_policy(
+        &self,
+        tbl_name: &str,
+        schema: &Schema,
+        format: PageFormat,
+        large_values: LargeValuePolicy,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        self.tbl_mgr
+            .create_table_with_policy(tbl_name, schema, format, large_values, tx)
+    }
+
+    /// Add a field to a table in the metadata catalogs, returning the table's new layout. Records
+    /// already stored in the table are not rewritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `field_name` - The name of the new field.
+    /// * `field_type` - The type of the new field.
+    /// * `length` - The length of the new field, for varchar fields.
+    /// * `tx` - The transaction that the change will run inside of.
+    pub fn add_field(
+        &self,
+        tbl_name: &str,
+        field_name: &str,
+        field_type: i32,
+        length: u64,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<Layout, String> {
+        self.tbl_mgr
+            .add_field(tbl_name, field_name, field_type, length, tx)
+    }
+
+    /// Remove a field from a table in the metadata catalogs, returning the table's new layout.
+    /// Returns an error if an index is defined on the field; the index must be dropped first.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `field_name` - The name of the field to remove.
+    /// * `tx` - The transaction that the change will run inside of.
+    pub fn drop_field(
+        &self,
+        tbl_name: &str,
+        field_name: &str,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<Layout, String> {
+        if let Some(ii) = self.get_index_info(tbl_name, tx.clone()).get(field_name) {
+            return Err(format!(
+                "cannot drop field '{}': index '{}' depends on it",
+                field_name,
+                ii.name()
+            ));
+        }
+
+        self.tbl_mgr.drop_field(tbl_name, field_name, tx)
+    }
+
+    /// Drop a table: remove it and its indexes from the metadata catalogs, and remove the files
+    /// holding its records and index entries. The files are restored if the transaction rolls
+    /// back. Returns an error if the table does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `tx` - The transaction that the change will run inside of.
+    pub fn drop_table(&self, tbl_name: &str, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let layout = self
+            .get_table_layout(tbl_name, tx)
+            .ok_or_else(|| format!("table '{}' does not exist", tbl_name))?;
+
+        for ii in self.get_index_info(tbl_name, tx.clone()).into_values() {
+            ii.open().remove_files();
+        }
+        self.idx_mgr.drop_indexes(tbl_name, tx);
+        self.tbl_mgr.drop_table(tbl_name, tx)?;
+        self.invalidate_stats(tbl_name);
+
+        let mut tx = tx.lock().unwrap();
+        tx.drop_file(tbl_name).map_err(|e| format!("{:?}", e))?;
+        // Values that did not fit in a page's dictionary are also stored in overflow blocks
+        if layout.has_overflow_fields() || !layout.dictionary_fields().is_empty() {
+            tx.drop_file(&RecordPage::overflow_file(tbl_name))
+                .map_err(|e| format!("{:?}", e))?;
+        }
+
+        Ok(())
+    }
+
     /// Get a table's layout from the metadata catalogs.
     ///
     /// # Arguments
@@ -70,6 +199,34 @@ impl MetadataManager {
         self.view_mgr.create_view(view_name, view_def, tx)
     }
 
+    /// Create a new materialized view in the metadata catalogs. The table holding the view's
+    /// records must be created separately, with the view's name.
+    ///
+    /// # Arguments
+    ///
+    /// * `view_name` - The name of the view.
+    /// * `view_def` - The SQL definition of the view.
+    /// * `tx` - The transaction that view creation will run inside of.
+    pub fn create_materialized_view(
+        &self,
+        view_name: &str,
+        view_def: &str,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        self.view_mgr
+            .create_materialized_view(view_name, view_def, tx)
+    }
+
+    /// Whether a view is materialized.
+    ///
+    /// # Arguments
+    ///
+    /// * `view_name` - The name of the view.
+    /// * `tx` - The transaction used to read the view from metadata tables.
+    pub fn is_materialized_view(&self, view_name: &str, tx: &Arc<Mutex<Tx>>) -> bool {
+        self.view_mgr.is_materialized(view_name, tx)
+    }
+
     /// Get a view definition.
     ///
     /// # Arguments
@@ -118,4 +275,154 @@ impl MetadataManager {
         let mut sm = self.stat_mgr.lock().unwrap();
         sm.get_stats(tbl_name, layout, tx)
     }
+
+    /// Widen the range of values recorded for a field of a table to include a value that was
+    /// written to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The table that was written to.
+    /// * `field_name` - The field that was written to.
+    /// * `val` - The value that was written.
+    pub fn record_value(&self, tbl_name: &str, field_name: &str, val: &Value) {
+        self.stat_mgr
+            .lock()
+            .unwrap()
+            .record_value(tbl_name, field_name, val);
+    }
+
+    /// Forget the statistics of a table after records were written without `record_value`.
+    pub fn invalidate_stats(&self, tbl_name: &str) {
+        self.stat_mgr.lock().unwrap().invalidate(tbl_name);
+    }
+
+    /// Check that the metadata catalogs are consistent with each other, returning a description
+    /// of each inconsistency found. Every table must have fields, every field must belong to a
+    /// table, and every index must be on a field of an existing table. Nothing is repaired.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction used to read metadata.
+    pub fn verify(&self, tx: &Arc<Mutex<Tx>>) -> Vec<String> {
+        let mut problems = self.tbl_mgr.verify(tx);
+        problems.extend(self.idx_mgr.verify(tx));
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::{
+        index::index::Index,
+        make_schema,
+        scan::scan::{Scan, Scannable, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::test_db,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_verify_reports_half_written_table() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        {
+            let tx = Arc::new(Mutex::new(db.new_tx()));
+            let mm = MetadataManager::new(&tx);
+            let schema = make_schema! { "a" => i32 };
+            mm.create_table("whole", &schema, &tx).unwrap();
+            mm.create_index("whole_a", "whole", "a", &tx).unwrap();
+            assert_eq!(mm.verify(&tx), Vec::<String>::new());
+
+            // Simulate a crash after the table catalog was written, but before the field catalog
+            let tcat_layout = mm.get_table_layout("tablecat", &tx).unwrap();
+            let mut scan = TableScan::new(tx.clone(), tcat_layout, "tablecat");
+            scan.insert();
+            scan.set_string("tblname", "torn");
+            scan.set_int("slotsize", 8);
+            scan.set_int("format", 0);
+            mm.create_index("torn_a", "torn", "a", &tx).unwrap();
+            mm.create_index("whole_b", "whole", "b", &tx).unwrap();
+            tx.lock().unwrap().commit().unwrap();
+        }
+
+        // Loading the metadata must not fail on the half-written table
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mm = MetadataManager::new(&tx);
+        assert_eq!(
+            mm.verify(&tx),
+            vec![
+                "table 'torn' has no fields in the catalog".to_string(),
+                "index 'torn_a' is on table 'torn', which does not exist".to_string(),
+                "index 'whole_b' is on field 'b', which does not exist in table 'whole'"
+                    .to_string(),
+            ]
+        );
+        assert!(mm.get_table_layout("torn", &tx).is_none());
+    }
+
+    #[test]
+    fn test_drop_table() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let data_dir = td.path().join("data");
+        let schema = make_schema! { "a" => i32, "b" => varchar(10) };
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mm = MetadataManager::new(&tx);
+        mm.create_table("doomed", &schema, &tx).unwrap();
+        mm.create_index("doomed_a", "doomed", "a", &tx).unwrap();
+        mm.create_table("kept", &schema, &tx).unwrap();
+        mm.create_index("kept_a", "kept", "a", &tx).unwrap();
+        {
+            let layout = mm.get_table_layout("doomed", &tx).unwrap();
+            let mut scan = TableScan::new(tx.clone(), layout, "doomed");
+            scan.insert();
+            scan.set_int("a", 1);
+            let mut index = mm.get_index_info("doomed", tx.clone())["a"].open();
+            index.insert(&Value::Int(1), scan.get_rid());
+            index.close();
+        }
+        tx.lock().unwrap().commit().unwrap();
+        assert!(data_dir.join("doomed").exists());
+        assert!(data_dir.join("doomed_a-leaf").exists());
+
+        // A rolled back drop leaves the table in place
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        mm.drop_table("doomed", &tx).unwrap();
+        assert!(mm.get_table_layout("doomed", &tx).is_none());
+        tx.lock().unwrap().rollback().unwrap();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = mm.get_table_layout("doomed", &tx).unwrap();
+        {
+            let mut scan = TableScan::new(tx.clone(), layout, "doomed");
+            assert!(scan.next());
+            assert_eq!(scan.get_int("a"), Ok(1));
+        }
+        mm.drop_table("doomed", &tx).unwrap();
+        tx.lock().unwrap().commit().unwrap();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert!(mm.get_table_layout("doomed", &tx).is_none());
+        assert!(mm.get_index_info("doomed", tx.clone()).is_empty());
+        assert_eq!(mm.get_index_info("kept", tx.clone()).len(), 1);
+        assert_eq!(mm.verify(&tx), Vec::<String>::new());
+        for file in ["doomed", "doomed_a-leaf", "doomed_a-dir"] {
+            assert!(!data_dir.join(file).exists(), "{} was not removed", file);
+        }
+        assert_eq!(
+            mm.drop_table("doomed", &tx),
+            Err("table 'doomed' does not exist".to_string())
+        );
+
+        // A table created with the same name starts out empty
+        mm.create_table("doomed", &schema, &tx).unwrap();
+        let layout = mm.get_table_layout("doomed", &tx).unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout, "doomed");
+        assert!(!scan.next());
+    }
 }
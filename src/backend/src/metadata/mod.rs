@@ -1,5 +1,5 @@
 pub mod index_manager;
 pub mod metadata_manager;
 pub mod stats_manager;
-mod table_manager;
+pub mod table_manager;
 mod view_manager;
@@ -1,10 +1,11 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     sync::{Arc, Mutex, RwLock},
 };
 
 use crate::{
     layout::Layout,
+    parser::constant::Value,
     scan::scan::{Scan, Scannable, UpdateScannable},
     table_scan::TableScan,
     transaction::Tx,
@@ -16,6 +17,8 @@ use super::table_manager::TableManager;
 pub struct StatisticsInfo {
     num_blocks: u64,
     num_records: u64,
+    field_ranges: HashMap<String, (i32, i32)>,
+    distinct_counts: HashMap<String, u64>,
 }
 
 impl StatisticsInfo {
@@ -23,9 +26,27 @@ impl StatisticsInfo {
         Self {
             num_blocks,
             num_records,
+            field_ranges: HashMap::new(),
+            distinct_counts: HashMap::new(),
         }
     }
 
+    /// Get the smallest and largest values of an integer field, if the table has any records.
+    pub fn field_range(&self, field_name: &str) -> Option<(i32, i32)> {
+        self.field_ranges.get(field_name).copied()
+    }
+
+    // Widen the range of an integer field to include a value, starting one if there is none.
+    fn include_value(&mut self, field_name: &str, val: i32) {
+        self.field_ranges
+            .entry(field_name.to_string())
+            .and_modify(|(min, max)| {
+                *min = val.min(*min);
+                *max = val.max(*max);
+            })
+            .or_insert((val, val));
+    }
+
     pub fn blocks_accessed(&self) -> u64 {
         self.num_blocks
     }
@@ -34,9 +55,14 @@ impl StatisticsInfo {
         self.num_records
     }
 
-    // TODO: lol
+    /// Get the number of distinct values of a field, as counted when the statistics were
+    /// calculated. Fields that were not counted, e.g. because the table was empty, are estimated
+    /// to hold a distinct value for every third record.
     pub fn distinct_values(&self, field_name: &str) -> u64 {
-        1 + (self.records_output() / 3) as u64
+        self.distinct_counts
+            .get(field_name)
+            .copied()
+            .unwrap_or(1 + self.records_output() / 3)
     }
 }
 
@@ -78,6 +104,32 @@ impl StatisticsManager {
         Some(stats.clone())
     }
 
+    /// Record that a value was written to a field of a table, so that the field's range includes
+    /// it. Ranges are otherwise only recalculated when statistics are refreshed, and planners
+    /// rely on them to skip tables that cannot match a predicate. A field without a range, e.g.
+    /// because the table was empty, keeps none.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The table that was written to.
+    /// * `field_name` - The field that was written to.
+    /// * `val` - The value that was written.
+    pub fn record_value(&mut self, tbl_name: &str, field_name: &str, val: &Value) {
+        if let Value::Int(val) = val
+            && let Some(stats) = self.tbl_stats.lock().unwrap().get_mut(tbl_name)
+            && let Some((min, max)) = stats.field_ranges.get_mut(field_name)
+        {
+            *min = (*val).min(*min);
+            *max = (*val).max(*max);
+        }
+    }
+
+    /// Forget the statistics of a table, so that they are recalculated when next requested.
+    /// Used after writes that do not record their values.
+    pub fn invalidate(&mut self, tbl_name: &str) {
+        self.tbl_stats.lock().unwrap().remove(tbl_name);
+    }
+
     fn refresh_stats(&mut self, tx: &Arc<Mutex<Tx>>) {
         let mut new_stats = HashMap::new();
         self.num_calls = 0;
@@ -90,10 +142,10 @@ impl StatisticsManager {
             let tblname = scan
                 .get_string("tblname")
                 .expect("table metadata not available");
-            let layout = self
-                .tbl_mgr
-                .get_table_layout(&tblname, tx)
-                .expect("could not get layout for table");
+            // A table without fields was left half-created, and is reported by verification
+            let Some(layout) = self.tbl_mgr.get_table_layout(&tblname, tx) else {
+                continue;
+            };
             let stats = self.calculate_stats(&tblname, &layout, tx);
             new_stats.insert(tblname, stats);
         }
@@ -107,15 +159,34 @@ impl StatisticsManager {
         layout: &Layout,
         tx: &Arc<Mutex<Tx>>,
     ) -> StatisticsInfo {
-        let mut num_records = 0;
-        let mut num_blocks = 0;
-
+        let mut stats = StatisticsInfo::new(0, 0);
+        let schema = layout.schema();
+        let int_fields: Vec<String> = schema
+            .fields()
+            .into_iter()
+            .filter(|f| schema.get_field_type(f) == Some(0))
+            .collect();
+
+        let mut distinct: HashMap<String, BTreeSet<Value>> = HashMap::new();
         let mut scan = TableScan::new(tx.clone(), layout.clone(), tbl_name);
         while scan.next() {
-            num_records += 1;
-            num_blocks = scan.get_rid().block_num() + 1;
+            stats.num_records += 1;
+            stats.num_blocks = scan.get_rid().block_num() + 1;
+            for field_name in &int_fields {
+                // TODO: error handling
+                let val = scan.get_int(field_name).expect("failed to read field");
+                stats.include_value(field_name, val);
+            }
+            for field_name in schema.fields() {
+                let val = scan.get_val(&field_name).expect("failed to read field");
+                distinct.entry(field_name).or_default().insert(val);
+            }
         }
+        stats.distinct_counts = distinct
+            .into_iter()
+            .map(|(field_name, vals)| (field_name, vals.len() as u64))
+            .collect();
 
-        StatisticsInfo::new(num_blocks, num_records)
+        stats
     }
 }
@@ -1,19 +1,41 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
 use crate::{
-    layout::Layout,
+    layout::{LargeValuePolicy, Layout, PageFormat},
+    record_page::RecordPage,
     scan::scan::{Scan, Scannable, UpdateScannable},
-    schema::Schema,
+    schema::{Encoding, Schema},
     table_scan::TableScan,
     transaction::Tx,
 };
 
-// The maximum length of the name of a table or a table field
+// The maximum length, in bytes, of the name of a table, view, index, or table field
 pub const MAX_NAME: u64 = 16;
 
+/// Verify that a name fits in the metadata catalogs. Returns an error, rather than letting the name
+/// be truncated, if it is longer than `MAX_NAME` bytes once encoded as UTF-8.
+///
+/// # Arguments
+///
+/// * `kind` - What is being named, e.g. "table", for use in the error message.
+/// * `name` - The name to check.
+pub fn check_name_length(kind: &str, name: &str) -> Result<(), String> {
+    if name.len() as u64 > MAX_NAME {
+        return Err(format!(
+            "{} name '{}' is {} bytes long, exceeding the maximum of {} bytes",
+            kind,
+            name,
+            name.len(),
+            MAX_NAME
+        ));
+    }
+
+    Ok(())
+}
+
 pub struct TableManager {
     tcat_layout: Layout,
     fcat_layout: Layout,
@@ -25,6 +47,8 @@ impl TableManager {
         let mut tcat_schema = Schema::new();
         tcat_schema.add_string_field("tblname", MAX_NAME);
         tcat_schema.add_int_field("slotsize");
+        tcat_schema.add_int_field("format");
+        tcat_schema.add_int_field("largevals");
 
         let mut fcat_schema = Schema::new();
         fcat_schema.add_string_field("tblname", MAX_NAME);
@@ -32,6 +56,8 @@ impl TableManager {
         fcat_schema.add_int_field("type");
         fcat_schema.add_int_field("length");
         fcat_schema.add_int_field("offset");
+        fcat_schema.add_int_field("overflow");
+        fcat_schema.add_int_field("encoding");
 
         (
             Layout::from_schema(tcat_schema),
@@ -51,21 +77,78 @@ impl TableManager {
             fcat_layout,
         };
 
-        sel.create_table("tablecat", &sel.tcat_layout.schema(), tx);
-        sel.create_table("fieldcat", &sel.fcat_layout.schema(), tx);
+        // The catalogs already exist if they were created by an earlier TableManager
+        let _ = sel.create_table("tablecat", &sel.tcat_layout.schema(), tx);
+        let _ = sel.create_table("fieldcat", &sel.fcat_layout.schema(), tx);
 
         sel
     }
 
-    /// Create a new table in the metadata catalog.
+    /// Create a new table in the metadata catalog. Returns an error if the table already exists,
+    /// or if the name of the table or one of its fields is longer than `MAX_NAME`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `schema` - The schema of the table.
+    /// * `tx` - The transaction to use when inserting into the metadata tables.
+    pub fn create_table(
+        &self,
+        tbl_name: &str,
+        schema: &Schema,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        self.create_table_with_format(tbl_name, schema, PageFormat::Fixed, tx)
+    }
+
+    /// Create a new table in the metadata catalog whose records are stored in pages of the given
+    /// format. Returns an error if the table already exists, if the name of the table or one of
+    /// its fields is longer than `MAX_NAME`, or if its records may not fit in a page.
     ///
     /// # Arguments
     ///
     /// * `tbl_name` - The name of the table.
     /// * `schema` - The schema of the table.
+    /// * `format` - The format of the table's pages.
     /// * `tx` - The transaction to use when inserting into the metadata tables.
-    pub fn create_table(&self, tbl_name: &str, schema: &Schema, tx: &Arc<Mutex<Tx>>) -> bool {
-        let new_tbl_layout = Layout::from_schema(schema.clone());
+    pub fn create_table_with_format(
+        &self,
+        tbl_name: &str,
+        schema: &Schema,
+        format: PageFormat,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        self.create_table_with_policy(tbl_name, schema, format, LargeValuePolicy::Reject, tx)
+    }
+
+    /// Create a new table in the metadata catalog whose records are stored in pages of the given
+    /// format, and whose records are made to fit in a page according to the given policy.
+    /// Returns an error if the table already exists, if the name of the table or one of its
+    /// fields is longer than `MAX_NAME`, or if the policy cannot make its records fit in a page.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `schema` - The schema of the table.
+    /// * `format` - The format of the table's pages.
+    /// * `large_values` - How to handle records that may be too large for a page.
+    /// * `tx` - The transaction to use when inserting into the metadata tables.
+    pub fn create_table_with_policy(
+        &self,
+        tbl_name: &str,
+        schema: &Schema,
+        format: PageFormat,
+        large_values: LargeValuePolicy,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        check_name_length("table", tbl_name)?;
+        for field in schema.fields() {
+            check_name_length("field", &field)?;
+        }
+
+        let page_size = tx.lock().unwrap().block_size();
+        let new_tbl_layout =
+            Self::fit_to_page(tbl_name, schema.clone(), format, large_values, page_size)?;
 
         {
             let mut scan = TableScan::new(tx.clone(), self.tcat_layout.clone(), "tablecat");
@@ -77,7 +160,7 @@ impl TableManager {
                     .expect("tblname does not exist in metadata catalog")
                     == tbl_name
                 {
-                    return false;
+                    return Err(format!("table '{}' already exists", tbl_name));
                 }
             }
 
@@ -85,6 +168,8 @@ impl TableManager {
             scan.insert();
             scan.set_string("tblname", tbl_name);
             scan.set_int("slotsize", new_tbl_layout.slot_size() as i32);
+            scan.set_int("format", Self::format_id(format));
+            scan.set_int("largevals", Self::policy_id(large_values));
         }
 
         // TODO: error checking
@@ -98,15 +183,248 @@ impl TableManager {
                     "type",
                     schema.get_field_type(&field).expect("unrecognized field"),
                 );
+                // Fields may have been shortened to fit in a page
                 scan.set_int(
                     "length",
-                    schema.get_field_length(&field).expect("unrecognized field") as i32,
+                    new_tbl_layout
+                        .schema()
+                        .get_field_length(&field)
+                        .expect("unrecognized field") as i32,
+                );
+                scan.set_int(
+                    "offset",
+                    new_tbl_layout.offset(&field).expect("unrecognized field") as i32,
                 );
-                scan.set_int("offset", new_tbl_layout.offset(&field) as i32);
+                scan.set_int("overflow", new_tbl_layout.is_overflow_field(&field) as i32);
+                scan.set_int(
+                    "encoding",
+                    Self::encoding_id(schema.get_field_encoding(&field).unwrap_or_default()),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add a field to a table already defined in the metadata catalogs, returning the table's new
+    /// layout. The field is placed after the existing fields, so records written with the old
+    /// layout must be rewritten by the caller. Returns an error if the table does not exist, if
+    /// it already has a field with the same name, if the name is longer than `MAX_NAME`, or if
+    /// the table's policy for large values cannot make its records fit in a page.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `field_name` - The name of the new field.
+    /// * `field_type` - The type of the new field.
+    /// * `length` - The length of the new field, for varchar fields.
+    /// * `tx` - The transaction to use when updating the metadata tables.
+    pub fn add_field(
+        &self,
+        tbl_name: &str,
+        field_name: &str,
+        field_type: i32,
+        length: u64,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<Layout, String> {
+        check_name_length("field", field_name)?;
+
+        let old_layout = self
+            .get_table_layout(tbl_name, tx)
+            .ok_or_else(|| format!("table '{}' does not exist", tbl_name))?;
+        if old_layout.schema().has_field(field_name) {
+            return Err(format!(
+                "field '{}' already exists in table '{}'",
+                field_name, tbl_name
+            ));
+        }
+
+        let mut schema = old_layout.schema().clone();
+        schema.add_field(field_name, field_type, length);
+        let page_size = tx.lock().unwrap().block_size();
+        let new_layout = Self::fit_to_page(
+            tbl_name,
+            schema,
+            old_layout.format(),
+            old_layout.large_values(),
+            page_size,
+        )?;
+
+        {
+            let mut scan = TableScan::new(tx.clone(), self.tcat_layout.clone(), "tablecat");
+            while scan.next() {
+                if scan
+                    .get_string("tblname")
+                    .expect("tblname does not exist in metadata catalog")
+                    == tbl_name
+                {
+                    scan.set_int("slotsize", new_layout.slot_size() as i32);
+                    break;
+                }
+            }
+        }
+
+        {
+            // Offsets are rewritten for every field, since the catalog may have been populated
+            // with a different field order than the one the new layout was computed from. Fitting
+            // the new layout to a page may also have shortened fields or moved them to overflow
+            // blocks.
+            let new_schema = new_layout.schema();
+            let mut scan = TableScan::new(tx.clone(), self.fcat_layout.clone(), "fieldcat");
+            while scan.next() {
+                if scan
+                    .get_string("tblname")
+                    .expect("tblname column does not exist in metadata catalog")
+                    == tbl_name
+                {
+                    let field = scan
+                        .get_string("fldname")
+                        .expect("fldname column does not exist in metadata catalog");
+                    scan.set_int(
+                        "length",
+                        new_schema.get_field_length(&field).unwrap_or_default() as i32,
+                    );
+                    scan.set_int(
+                        "offset",
+                        new_layout.offset(&field).expect("unrecognized field") as i32,
+                    );
+                    scan.set_int("overflow", new_layout.is_overflow_field(&field) as i32);
+                }
+            }
+
+            scan.insert();
+            scan.set_string("tblname", tbl_name);
+            scan.set_string("fldname", field_name);
+            scan.set_int("type", field_type);
+            scan.set_int(
+                "length",
+                new_schema.get_field_length(field_name).unwrap_or_default() as i32,
+            );
+            scan.set_int(
+                "offset",
+                new_layout.offset(field_name).expect("unrecognized field") as i32,
+            );
+            scan.set_int("overflow", new_layout.is_overflow_field(field_name) as i32);
+            scan.set_int("encoding", Self::encoding_id(Encoding::None));
+        }
+
+        Ok(new_layout)
+    }
+
+    /// Remove a field from a table already defined in the metadata catalogs, returning the
+    /// table's new layout. The slot size and the offsets of the remaining fields are unchanged,
+    /// so existing records stay readable; the dropped field's bytes are left in place. Returns an
+    /// error if the table does not exist, if it has no such field, or if it is the table's only
+    /// field.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `field_name` - The name of the field to remove.
+    /// * `tx` - The transaction to use when updating the metadata tables.
+    pub fn drop_field(
+        &self,
+        tbl_name: &str,
+        field_name: &str,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<Layout, String> {
+        let old_layout = self
+            .get_table_layout(tbl_name, tx)
+            .ok_or_else(|| format!("table '{}' does not exist", tbl_name))?;
+        if !old_layout.schema().has_field(field_name) {
+            return Err(format!(
+                "field '{}' does not exist in table '{}'",
+                field_name, tbl_name
+            ));
+        }
+        // The bodies of a slotted page are read field by field, so they cannot skip a field
+        // that is no longer in the layout
+        if old_layout.format() == PageFormat::Slotted {
+            return Err(format!(
+                "cannot drop '{}' from table '{}', whose records are stored in slotted pages",
+                field_name, tbl_name
+            ));
+        }
+        // Dictionaries are laid out from the end of the page, so removing one would move the
+        // others
+        if old_layout.is_dictionary_field(field_name) {
+            return Err(format!(
+                "cannot drop '{}' from table '{}', since its values are dictionary encoded",
+                field_name, tbl_name
+            ));
+        }
+        if old_layout.schema().fields().len() == 1 {
+            return Err(format!(
+                "cannot drop '{}', the only field of table '{}'",
+                field_name, tbl_name
+            ));
+        }
+
+        {
+            let mut scan = TableScan::new(tx.clone(), self.fcat_layout.clone(), "fieldcat");
+            while scan.next() {
+                if scan
+                    .get_string("tblname")
+                    .expect("tblname column does not exist in metadata catalog")
+                    == tbl_name
+                    && scan
+                        .get_string("fldname")
+                        .expect("fldname column does not exist in metadata catalog")
+                        == field_name
+                {
+                    scan.delete();
+                    break;
+                }
+            }
+        }
+
+        Ok(self
+            .get_table_layout(tbl_name, tx)
+            .expect("table has remaining fields"))
+    }
+
+    /// Remove a table from the metadata catalogs, deleting its rows from `tablecat` and
+    /// `fieldcat`. The table's records are not deleted. Returns an error if the table does not
+    /// exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `tbl_name` - The name of the table.
+    /// * `tx` - The transaction to use when updating the metadata tables.
+    pub fn drop_table(&self, tbl_name: &str, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let mut found = false;
+        {
+            let mut scan = TableScan::new(tx.clone(), self.tcat_layout.clone(), "tablecat");
+            while scan.next() {
+                if scan
+                    .get_string("tblname")
+                    .expect("tblname column does not exist in metadata catalog")
+                    == tbl_name
+                {
+                    scan.delete();
+                    found = true;
+                    break;
+                }
             }
         }
+        if !found {
+            return Err(format!("table '{}' does not exist", tbl_name));
+        }
 
-        true
+        {
+            let mut scan = TableScan::new(tx.clone(), self.fcat_layout.clone(), "fieldcat");
+            while scan.next() {
+                if scan
+                    .get_string("tblname")
+                    .expect("tblname column does not exist in metadata catalog")
+                    == tbl_name
+                {
+                    scan.delete();
+                }
+            }
+        }
+
+        Ok(())
     }
 
     /// Gets the layout of a table already defined in the metadata catalogs.
@@ -118,6 +436,8 @@ impl TableManager {
     pub fn get_table_layout(&self, tbl_name: &str, tx: &Arc<Mutex<Tx>>) -> Option<Layout> {
         let mut schema = Schema::new();
         let mut slot_size = None;
+        let mut format = PageFormat::Fixed;
+        let mut large_values = LargeValuePolicy::Reject;
         {
             let mut scan = TableScan::new(tx.clone(), self.tcat_layout.clone(), "tablecat");
             while scan.next() {
@@ -131,6 +451,14 @@ impl TableManager {
                             .expect("slotsize column does not exist in metadata catalog")
                             as u64,
                     );
+                    if scan.get_int("format") == Ok(Self::format_id(PageFormat::Slotted)) {
+                        format = PageFormat::Slotted;
+                    }
+                    large_values = match scan.get_int("largevals") {
+                        Ok(1) => LargeValuePolicy::Truncate,
+                        Ok(2) => LargeValuePolicy::Overflow,
+                        _ => LargeValuePolicy::Reject,
+                    };
                     break;
                 }
             }
@@ -139,6 +467,7 @@ impl TableManager {
         let slot_size = slot_size?;
 
         let mut offsets = HashMap::new();
+        let mut overflow_fields = HashSet::new();
         {
             let mut scan = TableScan::new(tx.clone(), self.fcat_layout.clone(), "fieldcat");
             while scan.next() {
@@ -162,8 +491,17 @@ impl TableManager {
                         .expect("offset column does not exist in metadata catalog")
                         as u64;
 
+                    if scan.get_int("overflow") == Ok(1) {
+                        overflow_fields.insert(field_name.clone());
+                    }
+                    let encoding = match scan.get_int("encoding") {
+                        Ok(1) => Encoding::Dictionary,
+                        Ok(2) => Encoding::Delta,
+                        _ => Encoding::None,
+                    };
                     offsets.insert(field_name.clone(), field_offset);
                     schema.add_field(&field_name, field_type, field_length);
+                    schema.set_field_encoding(&field_name, encoding);
                 }
             }
         }
@@ -171,7 +509,140 @@ impl TableManager {
         if schema.fields().is_empty() {
             None
         } else {
-            Some(Layout::new(schema, offsets, slot_size))
+            Some(
+                Layout::new(schema, offsets, slot_size)
+                    .with_format(format)
+                    .with_large_values(large_values)
+                    .with_overflow_fields(overflow_fields),
+            )
+        }
+    }
+
+    /// Check that the table and field catalogs agree, returning a description of each
+    /// inconsistency found. A crash part way through creating a table can leave a table without
+    /// fields, or fields without a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction to use when reading from the metadata tables.
+    pub fn verify(&self, tx: &Arc<Mutex<Tx>>) -> Vec<String> {
+        let mut tables = Vec::new();
+        {
+            let mut scan = TableScan::new(tx.clone(), self.tcat_layout.clone(), "tablecat");
+            while scan.next() {
+                tables.push(
+                    scan.get_string("tblname")
+                        .expect("tblname column does not exist in metadata catalog"),
+                );
+            }
+        }
+
+        let mut field_tables = Vec::new();
+        {
+            let mut scan = TableScan::new(tx.clone(), self.fcat_layout.clone(), "fieldcat");
+            while scan.next() {
+                let tbl_name = scan
+                    .get_string("tblname")
+                    .expect("tblname column does not exist in metadata catalog");
+                if !field_tables.contains(&tbl_name) {
+                    field_tables.push(tbl_name);
+                }
+            }
+        }
+
+        let mut problems = Vec::new();
+        for tbl_name in &tables {
+            if !field_tables.contains(tbl_name) {
+                problems.push(format!("table '{}' has no fields in the catalog", tbl_name));
+            }
+        }
+        for tbl_name in &field_tables {
+            if !tables.contains(tbl_name) {
+                problems.push(format!(
+                    "fields of table '{}' are in the catalog, but the table is not",
+                    tbl_name
+                ));
+            }
+        }
+
+        problems
+    }
+
+    // Lay out a table's records so that they fit in a page, shortening the longest varchar fields
+    // or moving them to overflow blocks as the policy allows.
+    fn fit_to_page(
+        tbl_name: &str,
+        mut schema: Schema,
+        format: PageFormat,
+        policy: LargeValuePolicy,
+        page_size: usize,
+    ) -> Result<Layout, String> {
+        // The bodies of slotted pages are read field by field, and have no room for pointers
+        if policy == LargeValuePolicy::Overflow && format == PageFormat::Slotted {
+            return Err(format!(
+                "table '{}' cannot store values in overflow blocks, since its records are stored in slotted pages",
+                tbl_name
+            ));
+        }
+
+        let mut overflow_fields = HashSet::new();
+        loop {
+            let layout = Layout::from_schema_with_overflow(schema.clone(), overflow_fields.clone())
+                .with_format(format)
+                .with_large_values(policy);
+            let size = RecordPage::max_record_size(&layout);
+            if size <= page_size {
+                return Ok(layout);
+            }
+
+            let longest = schema
+                .fields()
+                .into_iter()
+                .filter(|f| schema.get_field_type(f) == Some(1) && !overflow_fields.contains(f))
+                .filter(|f| schema.get_field_length(f) > Some(0))
+                .max_by_key(|f| schema.get_field_length(f));
+            match (policy, longest) {
+                (LargeValuePolicy::Truncate, Some(field)) => {
+                    let len = schema.get_field_length(&field).unwrap_or_default();
+                    let excess = (size - page_size) as u64;
+                    schema.set_field_length(&field, len.saturating_sub(excess));
+                }
+                (LargeValuePolicy::Overflow, Some(field)) => {
+                    overflow_fields.insert(field);
+                }
+                _ => {
+                    return Err(format!(
+                        "records of table '{}' take up to {} bytes, which does not fit in a page of {} bytes",
+                        tbl_name, size, page_size
+                    ))
+                }
+            }
+        }
+    }
+
+    // Get the value stored in the table catalog for a large value policy.
+    fn policy_id(policy: LargeValuePolicy) -> i32 {
+        match policy {
+            LargeValuePolicy::Reject => 0,
+            LargeValuePolicy::Truncate => 1,
+            LargeValuePolicy::Overflow => 2,
+        }
+    }
+
+    // Get the value stored in the field catalog for an encoding.
+    fn encoding_id(encoding: Encoding) -> i32 {
+        match encoding {
+            Encoding::None => 0,
+            Encoding::Dictionary => 1,
+            Encoding::Delta => 2,
+        }
+    }
+
+    // Get the value stored in the table catalog for a page format.
+    fn format_id(format: PageFormat) -> i32 {
+        match format {
+            PageFormat::Fixed => 0,
+            PageFormat::Slotted => 1,
         }
     }
 }
@@ -196,9 +667,15 @@ mod tests {
         let mut schema_1 = Schema::new();
         schema_1.add_int_field("test_int");
         schema_1.add_string_field("test_str", 16);
-        assert!(tbl_manager.create_table("test_table", &schema_1, tx));
-        assert!(!tbl_manager.create_table("test_table", &schema_1, tx));
-        tx.lock().unwrap().commit();
+        assert_eq!(
+            tbl_manager.create_table("test_table", &schema_1, tx),
+            Ok(())
+        );
+        assert_eq!(
+            tbl_manager.create_table("test_table", &schema_1, tx),
+            Err("table 'test_table' already exists".to_string())
+        );
+        tx.lock().unwrap().commit().unwrap();
 
         // Create second table in the catalog
         let tx = &Arc::new(Mutex::new(db.new_tx()));
@@ -208,9 +685,14 @@ mod tests {
         schema_2.add_int_field("test_int_2_2");
         schema_2.add_string_field("test_str_2", 16);
         schema_2.add_string_field("test_str_2_2", 16);
-        assert!(tbl_manager.create_table("test_table_2", &schema_2, tx));
-        assert!(!tbl_manager.create_table("test_table_2", &schema_2, tx));
-        tx.lock().unwrap().commit();
+        assert_eq!(
+            tbl_manager.create_table("test_table_2", &schema_2, tx),
+            Ok(())
+        );
+        assert!(tbl_manager
+            .create_table("test_table_2", &schema_2, tx)
+            .is_err());
+        tx.lock().unwrap().commit().unwrap();
 
         // Verify existence of both tables
         let tx = &Arc::new(Mutex::new(db.new_tx()));
@@ -229,4 +711,259 @@ mod tests {
         // Verify non-existence
         assert!(tbl_manager.get_table_layout("does_not_exist", tx).is_none());
     }
+
+    #[test]
+    fn test_add_field() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let tbl_manager = TableManager::new(tx);
+        let mut schema = Schema::new();
+        schema.add_int_field("a");
+        schema.add_string_field("b", 8);
+        tbl_manager.create_table("test_table", &schema, tx).unwrap();
+
+        schema.add_int_field("c");
+        let expected = Layout::from_schema(schema);
+        assert_eq!(
+            tbl_manager.add_field("test_table", "c", 0, 0, tx),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            tbl_manager.get_table_layout("test_table", tx),
+            Some(expected)
+        );
+
+        assert_eq!(
+            tbl_manager.add_field("test_table", "a", 0, 0, tx),
+            Err("field 'a' already exists in table 'test_table'".to_string())
+        );
+        assert_eq!(
+            tbl_manager.add_field("missing", "a", 0, 0, tx),
+            Err("table 'missing' does not exist".to_string())
+        );
+    }
+
+    #[test]
+    fn test_create_slotted_table() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let tbl_manager = TableManager::new(tx);
+        let mut schema = Schema::new();
+        schema.add_int_field("a");
+        schema.add_string_field("b", 200);
+        tbl_manager
+            .create_table_with_format("test_table", &schema, PageFormat::Slotted, tx)
+            .unwrap();
+
+        let layout = tbl_manager.get_table_layout("test_table", tx).unwrap();
+        assert_eq!(layout.format(), PageFormat::Slotted);
+        {
+            let mut scan = TableScan::new(tx.clone(), layout, "test_table");
+            for i in 0..100 {
+                scan.insert();
+                scan.set_int("a", i);
+                scan.set_string("b", &i.to_string());
+            }
+
+            // Short strings take far less than the 200 bytes reserved by fixed slots
+            assert_eq!(tx.lock().unwrap().size("test_table").unwrap(), 1);
+            scan.before_first();
+            for i in 0..100 {
+                assert!(scan.next());
+                assert_eq!(scan.get_int("a"), Ok(i));
+                assert_eq!(scan.get_string("b"), Ok(i.to_string()));
+            }
+            assert!(!scan.next());
+        }
+
+        // Added fields keep the table's format, but fields cannot be dropped
+        let layout = tbl_manager.add_field("test_table", "c", 0, 0, tx).unwrap();
+        assert_eq!(layout.format(), PageFormat::Slotted);
+        assert!(tbl_manager.drop_field("test_table", "a", tx).is_err());
+    }
+
+    #[test]
+    fn test_create_dictionary_encoded_table() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let tbl_manager = TableManager::new(tx);
+        let mut schema = Schema::new();
+        schema.add_int_field("a");
+        schema.add_string_field("b", 20);
+        schema.add_string_field("c", 20);
+        schema.set_field_encoding("b", Encoding::Dictionary);
+        tbl_manager.create_table("test_table", &schema, tx).unwrap();
+
+        let layout = tbl_manager.get_table_layout("test_table", tx).unwrap();
+        assert_eq!(
+            layout.schema().get_field_encoding("b"),
+            Some(Encoding::Dictionary)
+        );
+        assert!(layout.is_dictionary_field("b"));
+        assert!(!layout.is_dictionary_field("c"));
+        {
+            let mut scan = TableScan::new(tx.clone(), layout, "test_table");
+            for i in 0..300 {
+                scan.insert();
+                scan.set_int("a", i);
+                scan.set_string("b", ["x", "y", "z"][i as usize % 3]);
+            }
+
+            scan.before_first();
+            for i in 0..300 {
+                assert!(scan.next());
+                assert_eq!(scan.get_int("a"), Ok(i));
+                assert_eq!(
+                    scan.get_string("b"),
+                    Ok(["x", "y", "z"][i as usize % 3].into())
+                );
+            }
+            assert!(!scan.next());
+        }
+
+        // Removing a dictionary would move the others
+        assert!(tbl_manager.drop_field("test_table", "b", tx).is_err());
+        assert!(tbl_manager.drop_field("test_table", "c", tx).is_ok());
+    }
+
+    #[test]
+    fn test_large_value_policies() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let tbl_manager = TableManager::new(tx);
+        let mut schema = Schema::new();
+        schema.add_int_field("a");
+        schema.add_string_field("b", 10000);
+        schema.add_int_field("c");
+
+        assert!(tbl_manager
+            .create_table("rejected", &schema, tx)
+            .unwrap_err()
+            .contains("does not fit in a page"));
+        assert_eq!(tbl_manager.get_table_layout("rejected", tx), None);
+        assert!(tbl_manager
+            .create_table_with_policy(
+                "slotted",
+                &schema,
+                PageFormat::Slotted,
+                LargeValuePolicy::Overflow,
+                tx
+            )
+            .is_err());
+
+        // The field is shortened so that a slot fills the page, and longer values are cut short
+        tbl_manager
+            .create_table_with_policy(
+                "truncated",
+                &schema,
+                PageFormat::Fixed,
+                LargeValuePolicy::Truncate,
+                tx,
+            )
+            .unwrap();
+        let layout = tbl_manager.get_table_layout("truncated", tx).unwrap();
+        assert_eq!(layout.large_values(), LargeValuePolicy::Truncate);
+        assert_eq!(layout.slot_size() as usize, tx.lock().unwrap().block_size());
+        let len = layout.schema().get_field_length("b").unwrap() as usize;
+        {
+            let mut scan = TableScan::new(tx.clone(), layout, "truncated");
+            scan.insert();
+            scan.set_string("b", &"x".repeat(10000));
+            assert_eq!(scan.get_string("b"), Ok("x".repeat(len)));
+        }
+
+        // Values larger than a page are stored intact in overflow blocks
+        tbl_manager
+            .create_table_with_policy(
+                "overflowed",
+                &schema,
+                PageFormat::Fixed,
+                LargeValuePolicy::Overflow,
+                tx,
+            )
+            .unwrap();
+        let layout = tbl_manager.get_table_layout("overflowed", tx).unwrap();
+        assert!(layout.is_overflow_field("b"));
+        assert_eq!(layout.schema().get_field_length("b"), Some(10000));
+        let long_val: String = (0..10000)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        {
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "overflowed");
+            for i in 0..3 {
+                scan.insert();
+                scan.set_int("a", i);
+                scan.set_string("b", &long_val[..long_val.len() - i as usize]);
+                scan.set_int("c", -i);
+            }
+            scan.insert();
+            scan.set_int("a", 3);
+        }
+        tx.lock().unwrap().commit().unwrap();
+
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let mut scan = TableScan::new(tx.clone(), layout, "overflowed");
+        for i in 0..3 {
+            assert!(scan.next());
+            assert_eq!(scan.get_int("a"), Ok(i));
+            assert_eq!(
+                scan.get_string("b"),
+                Ok(long_val[..long_val.len() - i as usize].to_string())
+            );
+            assert_eq!(scan.get_int("c"), Ok(-i));
+        }
+        assert!(scan.next());
+        assert_eq!(scan.get_string("b"), Ok(String::new()));
+
+        scan.set_string("b", "short");
+        assert_eq!(scan.get_string("b"), Ok("short".to_string()));
+        assert!(!scan.next());
+    }
+
+    #[test]
+    fn test_create_table_name_length() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let tbl_manager = TableManager::new(tx);
+        let mut schema = Schema::new();
+        schema.add_int_field("a");
+
+        // Names of exactly MAX_NAME bytes are stored in full
+        let name = "sixteen_chars_ok";
+        assert_eq!(name.len() as u64, MAX_NAME);
+        assert_eq!(tbl_manager.create_table(name, &schema, tx), Ok(()));
+        assert_eq!(
+            tbl_manager.get_table_layout(name, tx),
+            Some(Layout::from_schema(schema.clone()))
+        );
+
+        assert_eq!(
+            tbl_manager.create_table("twenty_characters_xx", &schema, tx),
+            Err(
+                "table name 'twenty_characters_xx' is 20 bytes long, exceeding the maximum of 16 bytes"
+                    .to_string()
+            )
+        );
+        assert!(tbl_manager
+            .get_table_layout("twenty_characters_xx", tx)
+            .is_none());
+
+        // The limit applies to the UTF-8 encoding, not the number of characters
+        assert!(tbl_manager.create_table("ééééééééé", &schema, tx).is_err());
+
+        let mut long_field_schema = Schema::new();
+        long_field_schema.add_int_field("a_very_long_field_name");
+        assert_eq!(
+            tbl_manager.create_table("t", &long_field_schema, tx),
+            Err(
+                "field name 'a_very_long_field_name' is 22 bytes long, exceeding the maximum of 16 bytes"
+                    .to_string()
+            )
+        );
+    }
 }
@@ -8,7 +8,7 @@ use crate::{
     transaction::Tx,
 };
 
-use super::table_manager::TableManager;
+use super::table_manager::{check_name_length, TableManager};
 
 const MAX_VIEW_LENGTH: usize = 256;
 
@@ -26,7 +26,11 @@ impl ViewManager {
         let mut schema = Schema::new();
         schema.add_string_field("viewname", super::table_manager::MAX_NAME);
         schema.add_string_field("viewdef", MAX_VIEW_LENGTH as u64);
-        self.tbl_mgr.create_table("viewcat", &schema, tx);
+        schema.add_int_field("materialized");
+        // TODO: error handling
+        self.tbl_mgr
+            .create_table("viewcat", &schema, tx)
+            .expect("failed to create viewcat metadata table");
     }
 
     /// Create a new ViewManager. This method will create the backing metadata tables if they do
@@ -56,6 +60,43 @@ impl ViewManager {
         view_def: &str,
         tx: &Arc<Mutex<Tx>>,
     ) -> Result<(), String> {
+        self.insert_view(view_name, view_def, false, tx)
+    }
+
+    /// Create a new materialized view. Only the catalog entry is created; the table holding the
+    /// view's records is created and filled by the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `view_name` - The name of the view.
+    /// * `view_def` - The SQL definition of the view.
+    /// * `tx` - The transaction where the view creation will run.
+    pub fn create_materialized_view(
+        &self,
+        view_name: &str,
+        view_def: &str,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        self.insert_view(view_name, view_def, true, tx)
+    }
+
+    fn insert_view(
+        &self,
+        view_name: &str,
+        view_def: &str,
+        materialized: bool,
+        tx: &Arc<Mutex<Tx>>,
+    ) -> Result<(), String> {
+        check_name_length("view", view_name)?;
+        if view_def.len() > MAX_VIEW_LENGTH {
+            return Err(format!(
+                "definition of view '{}' is {} bytes long, exceeding the maximum of {} bytes",
+                view_name,
+                view_def.len(),
+                MAX_VIEW_LENGTH
+            ));
+        }
+
         let layout = self
             .tbl_mgr
             .get_table_layout("viewcat", tx)
@@ -69,6 +110,7 @@ impl ViewManager {
         scan.insert();
         scan.set_string("viewname", view_name);
         scan.set_string("viewdef", view_def);
+        scan.set_int("materialized", materialized as i32);
         Ok(())
     }
 
@@ -79,6 +121,22 @@ impl ViewManager {
     /// * `view_name` - The name of the view.
     /// * `tx` - The transaction to use when retrieving the view from the metadata tables.
     pub fn get_view_definition(&self, view_name: &str, tx: &Arc<Mutex<Tx>>) -> Option<String> {
+        self.find_view(view_name, tx).map(|(view_def, _)| view_def)
+    }
+
+    /// Whether a view is materialized. Returns false if no view has the given name.
+    ///
+    /// # Arguments
+    ///
+    /// * `view_name` - The name of the view.
+    /// * `tx` - The transaction to use when retrieving the view from the metadata tables.
+    pub fn is_materialized(&self, view_name: &str, tx: &Arc<Mutex<Tx>>) -> bool {
+        self.find_view(view_name, tx)
+            .is_some_and(|(_, materialized)| materialized)
+    }
+
+    /// Find a view's SQL definition, and whether it is materialized.
+    fn find_view(&self, view_name: &str, tx: &Arc<Mutex<Tx>>) -> Option<(String, bool)> {
         let layout = self.tbl_mgr.get_table_layout("viewcat", tx)?;
         let mut scan = TableScan::new(tx.clone(), layout, "viewcat");
         while scan.next() {
@@ -87,10 +145,13 @@ impl ViewManager {
                 .expect("viewname does not exist in the metadata catalog")
                 == view_name
             {
-                return Some(
-                    scan.get_string("viewdef")
-                        .expect("viewdef does not exist in the metadata catalog"),
-                );
+                let view_def = scan
+                    .get_string("viewdef")
+                    .expect("viewdef does not exist in the metadata catalog");
+                let materialized = scan
+                    .get_int("materialized")
+                    .expect("materialized does not exist in the metadata catalog");
+                return Some((view_def, materialized != 0));
             }
         }
 
@@ -129,7 +190,7 @@ mod tests {
                 .create_view("view_test_2", "SELECT * FROM test_table_3;", tx)
                 .is_err());
 
-            tx.lock().unwrap().commit();
+            tx.lock().unwrap().commit().unwrap();
         }
 
         // Verify that the view definitions can be read in a different transaction
@@ -143,4 +204,32 @@ mod tests {
         assert_eq!(view2, "SELECT * FROM test_table_2;");
         assert_eq!(view3, None);
     }
+
+    #[test]
+    fn test_create_materialized_view() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        let tx = &Arc::new(Mutex::new(db.new_tx()));
+        let _tbl_manager = TableManager::new(tx);
+        let view_manager = ViewManager::new(tx);
+        view_manager
+            .create_view("plain_view", "SELECT * FROM test_table_1;", tx)
+            .unwrap();
+        view_manager
+            .create_materialized_view("mat_view", "SELECT * FROM test_table_2;", tx)
+            .unwrap();
+
+        assert!(!view_manager.is_materialized("plain_view", tx));
+        assert!(view_manager.is_materialized("mat_view", tx));
+        assert!(!view_manager.is_materialized("no_view", tx));
+        assert!(view_manager
+            .create_view("long_view", &"x".repeat(MAX_VIEW_LENGTH + 1), tx)
+            .is_err());
+        assert_eq!(view_manager.get_view_definition("long_view", tx), None);
+        assert_eq!(
+            view_manager.get_view_definition("mat_view", tx).unwrap(),
+            "SELECT * FROM test_table_2;"
+        );
+    }
 }
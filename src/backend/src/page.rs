@@ -1,5 +1,5 @@
 use byteorder::{ByteOrder, LittleEndian};
-use std::mem::size_of;
+use std::{fmt::Display, mem::size_of};
 
 pub const PAGE_SIZE: usize = 4096;
 
@@ -9,7 +9,28 @@ pub struct Page {
     pub data: [u8; PAGE_SIZE],
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum PageError {
+    /// Writing the given number of bytes at the offset would run past the end of the page.
+    Overrun { offset: usize, len: usize },
+}
+
+impl Display for PageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overrun { offset, len } => write!(
+                f,
+                "writing {} bytes at offset {} overruns the {} byte page",
+                len, offset, PAGE_SIZE
+            ),
+        }
+    }
+}
+
 pub trait WriteTypeToPage {
+    /// The number of bytes that `write` writes to the page.
+    fn size(&self) -> usize;
+
     fn write(&self, page: &mut Page, offset: usize) -> usize;
 }
 
@@ -20,6 +41,10 @@ pub trait ReadTypeFromPage<'a> {
 macro_rules! impl_endian_io_traits {
     ($t:ty, $write_fn:ident, $read_fn:ident) => {
         impl WriteTypeToPage for $t {
+            fn size(&self) -> usize {
+                size_of::<Self>()
+            }
+
             fn write(&self, page: &mut Page, offset: usize) -> usize {
                 let size = size_of::<Self>();
                 let end = offset + size;
@@ -37,7 +62,22 @@ macro_rules! impl_endian_io_traits {
     };
 }
 
+/// Bytes are written as they are, without their length.
+impl WriteTypeToPage for &[u8] {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn write(&self, page: &mut Page, offset: usize) -> usize {
+        page.write_bytes(self, offset)
+    }
+}
+
 impl WriteTypeToPage for &str {
+    fn size(&self) -> usize {
+        size_of::<u32>() + self.len()
+    }
+
     fn write(&self, page: &mut Page, offset: usize) -> usize {
         assert!(self.is_ascii(), "strings must be ASCII");
 
@@ -96,6 +136,9 @@ impl Page {
 
     /// Write data to a page at the provided offset and return the number of bytes written.    
     ///
+    /// Panics if the data does not fit in the page, so this is only for data whose size the
+    /// caller controls. Values that come from users should be written with `try_write`.
+    ///
     /// # Arguments
     ///
     /// * `data` - Data to be written to the page.
@@ -104,6 +147,36 @@ impl Page {
         data.write(self, offset)
     }
 
+    /// Write data to a page at the provided offset and return the number of bytes written, or an
+    /// error, leaving the page unchanged, if the data would run past the end of the page.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Data to be written to the page.
+    /// * `offset` - The offset in the page where data will be written.
+    pub fn try_write<T: WriteTypeToPage>(
+        &mut self,
+        data: T,
+        offset: usize,
+    ) -> Result<usize, PageError> {
+        Page::check_fits(&data, offset)?;
+        Ok(data.write(self, offset))
+    }
+
+    /// Check that data written at the provided offset would fit in a page.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Data that will be written to the page.
+    /// * `offset` - The offset in the page where data will be written.
+    pub fn check_fits<T: WriteTypeToPage>(data: &T, offset: usize) -> Result<(), PageError> {
+        let len = data.size();
+        if offset.checked_add(len).is_none_or(|end| end > PAGE_SIZE) {
+            return Err(PageError::Overrun { offset, len });
+        }
+        Ok(())
+    }
+
     /// Write bytes to a page at the provided offset and return the number of bytes written.    
     ///
     /// # Arguments
@@ -123,3 +196,26 @@ impl Page {
         &self.data[offset..offset + length]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_write_overrun() {
+        let mut page = Page::new();
+        assert_eq!(page.try_write("abc", PAGE_SIZE - 7), Ok(7));
+        assert_eq!(page.read::<String>(PAGE_SIZE - 7), "abc");
+
+        assert_eq!(
+            page.try_write("abcd", PAGE_SIZE - 7),
+            Err(PageError::Overrun {
+                offset: PAGE_SIZE - 7,
+                len: 8
+            })
+        );
+        assert_eq!(page.read::<String>(PAGE_SIZE - 7), "abc");
+        assert!(page.try_write(1i32, PAGE_SIZE - 2).is_err());
+        assert!(page.try_write(1i32, usize::MAX).is_err());
+    }
+}
@@ -1,39 +1,228 @@
-use std::fmt::Display;
+use std::{cmp::Ordering, fmt::Display};
 
-// TODO: more research on ordering
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// A constant stored in, or compared against, a field.
+///
+/// Values have a total order, so they can be used as btree keys and sort keys without any
+/// comparison failing. Values of the same type compare by their contents. Values of different
+/// types compare by their field type code, so every `Int` orders before every `Varchar`.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Value {
     Int(i32),
     Varchar(String),
 }
 
+impl Value {
+    /// Get the schema field type code that this value can be stored in.
+    pub fn field_type(&self) -> i32 {
+        match self {
+            Self::Int(_) => 0,
+            Self::Varchar(_) => 1,
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a.cmp(b),
+            (Self::Varchar(a), Self::Varchar(b)) => a.cmp(b),
+            _ => self.field_type().cmp(&other.field_type()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Values are displayed as SQL literals that parse back to the same value. A varchar holding
+/// control characters is displayed as an escape literal (`E'...'`), so that printing it never
+/// breaks a line or moves the cursor.
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Int(i) => write!(f, "{}", i),
-            Self::Varchar(s) => write!(f, "'{}'", s),
+            Self::Varchar(s) if s.chars().any(char::is_control) => {
+                write!(f, "E'")?;
+                for ch in s.chars() {
+                    match ch {
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\'' => write!(f, "''")?,
+                        // Every control character is below U+00A0
+                        ch if ch.is_control() => write!(f, "\\x{:02x}", ch as u32)?,
+                        ch => write!(f, "{}", ch)?,
+                    }
+                }
+                write!(f, "'")
+            }
+            Self::Varchar(s) => write!(f, "'{}'", s.replace('\'', "''")),
         }
     }
 }
 
-pub trait FromDynamic {
-    fn as_val(&self) -> Value;
+impl From<i32> for Value {
+    fn from(val: i32) -> Self {
+        Self::Int(val)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(val: &str) -> Self {
+        Self::Varchar(val.to_string())
+    }
 }
 
-impl FromDynamic for i32 {
-    fn as_val(&self) -> Value {
-        Value::Int(*self)
+impl From<String> for Value {
+    fn from(val: String) -> Self {
+        Self::Varchar(val)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The value does not hold the type it was converted into.
+    TypeMismatch(Value),
+}
+
+impl TryFrom<Value> for i32 {
+    type Error = ValueError;
+
+    fn try_from(val: Value) -> Result<Self, Self::Error> {
+        match val {
+            Value::Int(i) => Ok(i),
+            _ => Err(ValueError::TypeMismatch(val)),
+        }
     }
 }
 
-impl FromDynamic for &str {
-    fn as_val(&self) -> Value {
-        Value::Varchar(self.to_string())
+impl TryFrom<Value> for String {
+    type Error = ValueError;
+
+    fn try_from(val: Value) -> Result<Self, Self::Error> {
+        match val {
+            Value::Varchar(s) => Ok(s),
+            _ => Err(ValueError::TypeMismatch(val)),
+        }
     }
 }
 
-impl FromDynamic for String {
-    fn as_val(&self) -> Value {
-        Value::Varchar(self.to_string())
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::parser::{lexer::Lexer, token::Token};
+
+    use super::{Value, ValueError};
+
+    fn mixed_values() -> Vec<Value> {
+        vec![
+            Value::Varchar("b".to_string()),
+            Value::Int(i32::MAX),
+            Value::Varchar("".to_string()),
+            Value::Int(-1),
+            Value::Varchar("a".to_string()),
+            Value::Int(0),
+            Value::Varchar("-1".to_string()),
+            Value::Int(i32::MIN),
+            Value::Varchar("0".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_total_order() {
+        let values = mixed_values();
+        for a in &values {
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            for b in &values {
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{} vs {}", a, b);
+                assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{} vs {}", a, b);
+                assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+                for c in &values {
+                    if a <= b && b <= c {
+                        assert!(a <= c, "{} <= {} <= {}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cross_type_order() {
+        let mut values = mixed_values();
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                Value::Int(i32::MIN),
+                Value::Int(-1),
+                Value::Int(0),
+                Value::Int(i32::MAX),
+                Value::Varchar("".to_string()),
+                Value::Varchar("-1".to_string()),
+                Value::Varchar("0".to_string()),
+                Value::Varchar("a".to_string()),
+                Value::Varchar("b".to_string()),
+            ]
+        );
+        assert!(Value::Int(i32::MAX) < Value::Varchar("".to_string()));
+    }
+
+    #[test]
+    fn test_from_native() {
+        assert_eq!(Value::from(5), Value::Int(5));
+        assert_eq!(Value::from("abc"), Value::Varchar("abc".to_string()));
+        assert_eq!(
+            Value::from("abc".to_string()),
+            Value::Varchar("abc".to_string())
+        );
+
+        let val: Value = (-3).into();
+        assert_eq!(val, Value::Int(-3));
+    }
+
+    #[test]
+    fn test_display_escapes_quotes() {
+        assert_eq!(Value::from("O'Brien").to_string(), "'O''Brien'");
+        assert_eq!(Value::from(-3).to_string(), "-3");
+    }
+
+    #[test]
+    fn test_display_escapes_control_characters() {
+        let val = Value::from("it's\ta\\b\r\nline\u{7}\u{9b}é");
+        assert_eq!(val.to_string(), r"E'it''s\ta\\b\r\nline\x07\x9bé'");
+
+        // The literal parses back to the same value
+        let literal = val.to_string();
+        let mut lexer = Lexer::new(&literal);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::VarcharConst(val.try_into().unwrap())))
+        );
+    }
+
+    #[test]
+    fn test_try_into_native() {
+        let n: i32 = Value::Int(7).try_into().unwrap();
+        assert_eq!(n, 7);
+
+        let s: String = Value::Varchar("xyz".to_string()).try_into().unwrap();
+        assert_eq!(s, "xyz");
+    }
+
+    #[test]
+    fn test_try_into_type_mismatch() {
+        assert_eq!(
+            i32::try_from(Value::Varchar("7".to_string())),
+            Err(ValueError::TypeMismatch(Value::Varchar("7".to_string())))
+        );
+        assert_eq!(
+            String::try_from(Value::Int(7)),
+            Err(ValueError::TypeMismatch(Value::Int(7)))
+        );
     }
 }
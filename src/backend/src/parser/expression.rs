@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use crate::{scan::scan::Scannable, schema::Schema};
 
-use super::{constant::Value, parser::FieldName};
+use super::{constant::Value, lexer::quote_identifier, parser::FieldName};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Expression {
@@ -27,12 +27,26 @@ impl Expression {
             Self::Constant(_) => true,
         }
     }
+
+    /// Get the schema field type code that this expression evaluates to.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema used to resolve field names.
+    pub fn field_type(&self, schema: &Schema) -> Result<i32, String> {
+        match self {
+            Self::Field(field_name) => schema
+                .get_field_type(field_name)
+                .ok_or_else(|| format!("field '{}' does not exist", field_name)),
+            Self::Constant(val) => Ok(val.field_type()),
+        }
+    }
 }
 
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Field(field_name) => write!(f, "{}", field_name),
+            Self::Field(field_name) => write!(f, "{}", quote_identifier(field_name)),
             Self::Constant(val) => write!(f, "{}", val),
         }
     }
@@ -9,6 +9,10 @@ use super::token::Token;
 #[derive(Debug, PartialEq, Eq)]
 pub enum LexerError {
     UnterminatedVarchar(String),
+    UnterminatedComment(String),
+    InvalidEscape(String),
+    UnterminatedIdentifier(String),
+    EmptyIdentifier(String),
 }
 
 pub type LexerResult = Result<Token, LexerError>;
@@ -70,6 +74,38 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Scan a `/* ... */` block comment, whose opening `/*` has already been consumed.
+    ///
+    /// Block comments do not nest: the first `*/` ends the comment, even if it contains another
+    /// `/*`.
+    fn scan_block_comment(&mut self) -> LexerResult {
+        loop {
+            match self.advance() {
+                Some('*') if self.peek() == Some('/') => {
+                    self.advance();
+                    return Ok(Token::Whitespace);
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexerError::UnterminatedComment(
+                        self.get_window(self.start, self.cur),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn scan_slash(&mut self) -> LexerResult {
+        if let Some(ch) = self.peek()
+            && ch == '*'
+        {
+            self.advance();
+            self.scan_block_comment()
+        } else {
+            Ok(Token::ForwardSlash)
+        }
+    }
+
     fn scan_number(&mut self) -> LexerResult {
         while let Some(ch) = self.peek()
             && ch.is_digit(10)
@@ -85,21 +121,52 @@ impl<'a> Lexer<'a> {
         }))
     }
 
-    fn scan_varchar(&mut self) -> LexerResult {
-        while let Some(ch) = self.peek()
-            && ch != '\''
-        {
-            self.advance();
+    /// Scan a varchar literal. A doubled quote (`''`) within the literal is an escaped `'`. In an
+    /// escape literal (`E'...'`) a backslash also starts an escape: `\n`, `\r`, `\t`, `\\`, `\'`,
+    /// or `\xNN` for the character with the hex code `NN`.
+    fn scan_varchar(&mut self, escapes: bool) -> LexerResult {
+        let mut val = String::new();
+
+        loop {
+            match self.advance() {
+                Some('\'') if self.peek() == Some('\'') => {
+                    self.advance();
+                    val.push('\'');
+                }
+                Some('\'') => break,
+                Some('\\') if escapes => val.push(self.scan_escape()?),
+                Some(ch) => val.push(ch),
+                None => {
+                    return Err(LexerError::UnterminatedVarchar(
+                        self.get_window(self.start, self.cur),
+                    ))
+                }
+            }
         }
 
-        // advance past the last `'` char
-        self.advance().ok_or(LexerError::UnterminatedVarchar(
-            self.get_window(self.start, self.cur),
-        ))?;
+        Ok(Token::VarcharConst(val))
+    }
+
+    /// Scan the rest of an escape in an escape literal, after its backslash.
+    fn scan_escape(&mut self) -> Result<char, LexerError> {
+        let escape_start = self.cur - 1;
+        let ch = match self.advance() {
+            Some('n') => Some('\n'),
+            Some('r') => Some('\r'),
+            Some('t') => Some('\t'),
+            Some('\\') => Some('\\'),
+            Some('\'') => Some('\''),
+            Some('x') => {
+                let digits: String = [self.advance(), self.advance()]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                u8::from_str_radix(&digits, 16).ok().map(char::from)
+            }
+            _ => None,
+        };
 
-        Ok(Token::VarcharConst(
-            self.get_window(self.start + 1, self.cur - 1),
-        ))
+        ch.ok_or_else(|| LexerError::InvalidEscape(self.get_window(escape_start, self.cur)))
     }
 
     fn scan_identifier(&mut self) -> LexerResult {
@@ -111,30 +178,59 @@ impl<'a> Lexer<'a> {
 
         let val = self.get_window(self.start, self.cur);
 
-        // TODO: less repetition?
-        let token = match val.to_lowercase().as_str() {
-            "and" => Token::And,
-            "as" => Token::As,
-            "create" => Token::Create,
-            "delete" => Token::Delete,
-            "from" => Token::From,
-            "index" => Token::Index,
-            "insert" => Token::Insert,
-            "int" => Token::Int,
-            "into" => Token::Into,
-            "on" => Token::On,
-            "select" => Token::Select,
-            "set" => Token::Set,
-            "table" => Token::Table,
-            "update" => Token::Update,
-            "values" => Token::Values,
-            "varchar" => Token::Varchar,
-            "view" => Token::View,
-            "where" => Token::Where,
-            _ => Token::Identifier(val),
+        Ok(keyword(&val).unwrap_or(Token::Identifier(val)))
+    }
+
+    /// Scan a double-quoted identifier, whose opening `"` has already been consumed. A quoted
+    /// identifier is never a keyword, so reserved words can be used as table and field names. A
+    /// doubled quote (`""`) within the identifier is an escaped `"`.
+    fn scan_quoted_identifier(&mut self) -> LexerResult {
+        let mut val = String::new();
+
+        loop {
+            match self.advance() {
+                Some('"') if self.peek() == Some('"') => {
+                    self.advance();
+                    val.push('"');
+                }
+                Some('"') => break,
+                Some(ch) => val.push(ch),
+                None => {
+                    return Err(LexerError::UnterminatedIdentifier(
+                        self.get_window(self.start, self.cur),
+                    ))
+                }
+            }
+        }
+
+        if val.is_empty() {
+            return Err(LexerError::EmptyIdentifier(
+                self.get_window(self.start, self.cur),
+            ));
+        }
+
+        Ok(Token::Identifier(val))
+    }
+
+    // Scan an operator beginning with '<': '<', '<=' or '<>'.
+    fn scan_less_than(&mut self) -> Token {
+        let token = match self.peek() {
+            Some('=') => Token::LessEqual,
+            Some('>') => Token::NotEqual,
+            _ => return Token::LessThan,
         };
+        self.advance();
+        token
+    }
 
-        Ok(token)
+    // Scan an operator beginning with '>': '>' or '>='.
+    fn scan_greater_than(&mut self) -> Token {
+        if self.peek() == Some('=') {
+            self.advance();
+            Token::GreaterEqual
+        } else {
+            Token::GreaterThan
+        }
     }
 
     fn scan_token(&mut self) -> LexerResult {
@@ -142,13 +238,25 @@ impl<'a> Lexer<'a> {
         match ch {
             Some(' ') | Some('\r') | Some('\t') | Some('\n') => Ok(Token::Whitespace),
             Some('=') => Ok(Token::Equal),
+            Some('<') => Ok(self.scan_less_than()),
+            Some('>') => Ok(self.scan_greater_than()),
+            Some('!') if self.peek() == Some('=') => {
+                self.advance();
+                Ok(Token::NotEqual)
+            }
             Some(',') => Ok(Token::Comma),
             Some(';') => Ok(Token::SemiColon),
             Some('-') => self.scan_dash(),
+            Some('/') => self.scan_slash(),
             Some('(') => Ok(Token::LeftParen),
             Some(')') => Ok(Token::RightParen),
             Some('*') => Ok(Token::Splat),
-            Some('\'') => self.scan_varchar(),
+            Some('\'') => self.scan_varchar(false),
+            Some('"') => self.scan_quoted_identifier(),
+            Some('E' | 'e') if self.peek() == Some('\'') => {
+                self.advance();
+                self.scan_varchar(true)
+            }
             Some(ch) if ch.is_digit(10) => self.scan_number(),
             Some(ch) if ch.is_alphabetic() => self.scan_identifier(),
             _ => Ok(Token::EOF),
@@ -156,6 +264,63 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// Get the keyword token for a word, ignoring case, or `None` if the word is not a keyword.
+fn keyword(word: &str) -> Option<Token> {
+    // TODO: less repetition?
+    let token = match word.to_lowercase().as_str() {
+        "add" => Token::Add,
+        "alter" => Token::Alter,
+        "and" => Token::And,
+        "as" => Token::As,
+        "asc" => Token::Asc,
+        "by" => Token::By,
+        "column" => Token::Column,
+        "create" => Token::Create,
+        "delete" => Token::Delete,
+        "desc" => Token::Desc,
+        "drop" => Token::Drop,
+        "exists" => Token::Exists,
+        "from" => Token::From,
+        "group" => Token::Group,
+        "having" => Token::Having,
+        "if" => Token::If,
+        "index" => Token::Index,
+        "insert" => Token::Insert,
+        "int" => Token::Int,
+        "into" => Token::Into,
+        "materialized" => Token::Materialized,
+        "not" => Token::Not,
+        "on" => Token::On,
+        "or" => Token::Or,
+        "order" => Token::Order,
+        "refresh" => Token::Refresh,
+        "select" => Token::Select,
+        "set" => Token::Set,
+        "table" => Token::Table,
+        "update" => Token::Update,
+        "values" => Token::Values,
+        "varchar" => Token::Varchar,
+        "view" => Token::View,
+        "where" => Token::Where,
+        _ => return None,
+    };
+
+    Some(token)
+}
+
+/// Format a table or field name so that it lexes back to the same identifier, quoting it if it
+/// is a keyword or is not made up of only letters, digits and underscores.
+pub fn quote_identifier(name: &str) -> String {
+    let is_plain = name.chars().next().is_some_and(|ch| ch.is_alphabetic())
+        && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_')
+        && keyword(name).is_none();
+    if is_plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
 impl<'a> Iterator for Lexer<'a> {
     type Item = LexerResult;
 
@@ -182,7 +347,7 @@ impl<'a> Iterator for Lexer<'a> {
 mod tests {
     use crate::parser::{lexer::LexerError, token::Token};
 
-    use super::Lexer;
+    use super::{quote_identifier, Lexer};
 
     macro_rules! lexer_tests {
         ($($name:ident: $input:expr => $expected:expr,)*) => {
@@ -219,6 +384,24 @@ mod tests {
         lexer_varchar_3: "123 'hello' 456" => vec![Ok(Token::IntegerConst(123)), Ok(Token::VarcharConst("hello".to_string())), Ok(Token::IntegerConst(456))],
         lexer_varchar_4: "'abc" => vec![Err(LexerError::UnterminatedVarchar("'abc".to_string()))],
         lexer_varchar_5: "''" => vec![Ok(Token::VarcharConst("".to_string()))],
+        lexer_varchar_escaped_1: "'O''Brien'" => [Ok(Token::VarcharConst("O'Brien".to_string()))],
+        lexer_varchar_escaped_2: "'''' , 'it''s ''quoted'''" => [
+            Ok(Token::VarcharConst("'".to_string())),
+            Ok(Token::Comma),
+            Ok(Token::VarcharConst("it's 'quoted'".to_string())),
+        ],
+        lexer_varchar_escaped_3: "'O''" => [Err(LexerError::UnterminatedVarchar("'O''".to_string()))],
+        lexer_varchar_escape_literal_1: r"E'a\nb\tc\\d\x07' 'a\nb'" => [
+            Ok(Token::VarcharConst("a\nb\tc\\d\x07".to_string())),
+            Ok(Token::VarcharConst("a\\nb".to_string())),
+        ],
+        lexer_varchar_escape_literal_2: r"e'it\'s' E'O''Brien' e" => [
+            Ok(Token::VarcharConst("it's".to_string())),
+            Ok(Token::VarcharConst("O'Brien".to_string())),
+            Ok(Token::Identifier("e".to_string())),
+        ],
+        lexer_varchar_escape_literal_3: r"E'\q" => [Err(LexerError::InvalidEscape(r"\q".to_string()))],
+        lexer_varchar_escape_literal_4: r"E'\x4'" => [Err(LexerError::InvalidEscape(r"\x4'".to_string()))],
 
         lexer_query_1: "SELECT a FROM x, z WHERE b = 3 AND c = 'hello';" => vec![
             Ok(Token::Select),
@@ -248,12 +431,114 @@ mod tests {
             Ok(Token::Identifier("SName".to_string())),
         ],
 
+        lexer_comparison_1: "a<3 AND b > 4" => [
+            Ok(Token::Identifier("a".to_string())),
+            Ok(Token::LessThan),
+            Ok(Token::IntegerConst(3)),
+            Ok(Token::And),
+            Ok(Token::Identifier("b".to_string())),
+            Ok(Token::GreaterThan),
+            Ok(Token::IntegerConst(4)),
+        ],
+
+        lexer_comparison_2: "a<=3 AND b >= 4 AND c<>5 AND d != 6 AND e <7" => [
+            Ok(Token::Identifier("a".to_string())),
+            Ok(Token::LessEqual),
+            Ok(Token::IntegerConst(3)),
+            Ok(Token::And),
+            Ok(Token::Identifier("b".to_string())),
+            Ok(Token::GreaterEqual),
+            Ok(Token::IntegerConst(4)),
+            Ok(Token::And),
+            Ok(Token::Identifier("c".to_string())),
+            Ok(Token::NotEqual),
+            Ok(Token::IntegerConst(5)),
+            Ok(Token::And),
+            Ok(Token::Identifier("d".to_string())),
+            Ok(Token::NotEqual),
+            Ok(Token::IntegerConst(6)),
+            Ok(Token::And),
+            Ok(Token::Identifier("e".to_string())),
+            Ok(Token::LessThan),
+            Ok(Token::IntegerConst(7)),
+        ],
+
+        lexer_order_by_1: "ORDER BY a DESC, b asc" => [
+            Ok(Token::Order),
+            Ok(Token::By),
+            Ok(Token::Identifier("a".to_string())),
+            Ok(Token::Desc),
+            Ok(Token::Comma),
+            Ok(Token::Identifier("b".to_string())),
+            Ok(Token::Asc),
+        ],
+
+        lexer_or_1: "a = 1 OR b = 2 or order" => [
+            Ok(Token::Identifier("a".to_string())),
+            Ok(Token::Equal),
+            Ok(Token::IntegerConst(1)),
+            Ok(Token::Or),
+            Ok(Token::Identifier("b".to_string())),
+            Ok(Token::Equal),
+            Ok(Token::IntegerConst(2)),
+            Ok(Token::Or),
+            Ok(Token::Order),
+        ],
+
         lexer_comment_1: "1234 -- a comment\n --another comment \n 5678 \n --another!\n\n 9" => vec![
             Ok(Token::IntegerConst(1234)),
             Ok(Token::IntegerConst(5678)),
             Ok(Token::IntegerConst(9)),
         ],
 
+        lexer_block_comment_1: "1234 /* a comment */ 5678/**/9" => [
+            Ok(Token::IntegerConst(1234)),
+            Ok(Token::IntegerConst(5678)),
+            Ok(Token::IntegerConst(9)),
+        ],
+
+        lexer_block_comment_2: "SELECT a /* first line\n * second line\n */ FROM x -- trailing\n/* a */ WHERE b = 'c' /**/" => [
+            Ok(Token::Select),
+            Ok(Token::Identifier("a".to_string())),
+            Ok(Token::From),
+            Ok(Token::Identifier("x".to_string())),
+            Ok(Token::Where),
+            Ok(Token::Identifier("b".to_string())),
+            Ok(Token::Equal),
+            Ok(Token::VarcharConst("c".to_string())),
+        ],
+
+        lexer_block_comment_3: "1 /* outer /* inner */ 2 */" => [
+            Ok(Token::IntegerConst(1)),
+            Ok(Token::IntegerConst(2)),
+            Ok(Token::Splat),
+            Ok(Token::ForwardSlash),
+        ],
+
+        lexer_block_comment_4: "1 /* not closed *" => [
+            Ok(Token::IntegerConst(1)),
+            Err(LexerError::UnterminatedComment("/* not closed *".to_string())),
+        ],
+
+        lexer_block_comment_5: "'/* not a comment */'" => [Ok(Token::VarcharConst("/* not a comment */".to_string()))],
+
+        lexer_quoted_identifier_1: "\"index\" \"SELECT\" \"year\" index" => [
+            Ok(Token::Identifier("index".to_string())),
+            Ok(Token::Identifier("SELECT".to_string())),
+            Ok(Token::Identifier("year".to_string())),
+            Ok(Token::Index),
+        ],
+        lexer_quoted_identifier_2: "\"two words\",\"say \"\"hi\"\"\"" => [
+            Ok(Token::Identifier("two words".to_string())),
+            Ok(Token::Comma),
+            Ok(Token::Identifier("say \"hi\"".to_string())),
+        ],
+        lexer_quoted_identifier_3: "a \"from" => [
+            Ok(Token::Identifier("a".to_string())),
+            Err(LexerError::UnterminatedIdentifier("\"from".to_string())),
+        ],
+        lexer_quoted_identifier_4: "\"\"" => [Err(LexerError::EmptyIdentifier("\"\"".to_string()))],
+
         lexer_create_table_1: "CREATE TABLE test ( id int, name varchar(10) )" => vec![
             Ok(Token::Create),
             Ok(Token::Table),
@@ -270,4 +555,16 @@ mod tests {
             Ok(Token::RightParen),
         ],
     }
+
+    #[test]
+    fn test_quote_identifier() {
+        for name in ["year", "Index", "two words", "say \"hi\"", "1st"] {
+            let quoted = quote_identifier(name);
+            let mut lex = Lexer::new(&quoted);
+            assert_eq!(lex.next(), Some(Ok(Token::Identifier(name.to_string()))));
+            assert_eq!(lex.next(), None);
+        }
+        assert_eq!(quote_identifier("year"), "year");
+        assert_eq!(quote_identifier("Index"), "\"Index\"");
+    }
 }
@@ -5,9 +5,9 @@ use std::slice::Iter;
 use super::{
     constant::Value,
     expression::Expression,
-    lexer::{Lexer, LexerError, LexerResult},
+    lexer::{quote_identifier, Lexer, LexerError, LexerResult},
     predicate::Predicate,
-    term::Term,
+    term::{Operator, Term},
     token::Token,
 };
 
@@ -32,22 +32,116 @@ impl InsertNode {
 pub enum SelectField {
     FieldName(String),
     Star,
+    /// A constant, which can only be selected by a query without a FROM clause.
+    Constant(Value),
+    Aggregate(AggregateField),
 }
 
 impl Display for SelectField {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SelectField::FieldName(name) => write!(f, "{}", name),
+            SelectField::FieldName(name) => write!(f, "{}", quote_identifier(name)),
             SelectField::Star => write!(f, "*"),
+            SelectField::Constant(val) => write!(f, "{}", val),
+            SelectField::Aggregate(agg) => write!(f, "{}", agg),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregateFn {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFn {
+    /// Get the aggregate function with the given name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AggregateFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+            Self::Min => "min",
+            Self::Max => "max",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// An aggregate function applied to a field over each group of records, e.g. `count(sid)`. The
+/// output field holding its value is named after how it is displayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateField(pub AggregateFn, pub FieldName);
+
+impl Display for AggregateField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({})", self.0, quote_identifier(&self.1))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// A field of an ORDER BY clause, and the direction records are ordered by it in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortField(pub FieldName, pub SortOrder);
+
+impl Display for SortField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.1 {
+            SortOrder::Ascending => write!(f, "{}", quote_identifier(&self.0)),
+            SortOrder::Descending => write!(f, "{} DESC", quote_identifier(&self.0)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SelectNode {
     pub fields: Vec<SelectField>,
     pub tables: Vec<TableName>,
     pub predicate: Option<Predicate>,
+    pub group_by: Vec<FieldName>,
+    /// A predicate on the groups of an aggregate query, which may reference aggregate outputs.
+    pub having: Option<Predicate>,
+    pub order_by: Vec<SortField>,
+}
+
+impl SelectNode {
+    /// Whether the query groups its records, with a GROUP BY clause or by selecting an aggregate.
+    /// A query that selects aggregates without a GROUP BY clause puts every record in one group.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty() || !self.aggregates().is_empty()
+    }
+
+    /// The aggregates selected by the query.
+    pub fn aggregates(&self) -> Vec<AggregateField> {
+        self.fields
+            .iter()
+            .filter_map(|f| match f {
+                SelectField::Aggregate(agg) => Some(agg.clone()),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl Display for SelectNode {
@@ -58,17 +152,54 @@ impl Display for SelectNode {
             .map(|f| format!("{}", f))
             .collect::<Vec<String>>()
             .join(", ");
-        let table_names = self.tables.join(", ");
+        let table_names = if self.tables.is_empty() {
+            "".to_string()
+        } else {
+            format!(" FROM {}", quote_identifiers(&self.tables))
+        };
         let pred = if let Some(pred) = &self.predicate {
             format!(" WHERE {}", pred)
         } else {
             "".to_string()
         };
+        let group_by = if self.group_by.is_empty() {
+            "".to_string()
+        } else {
+            format!(" GROUP BY {}", quote_identifiers(&self.group_by))
+        };
+        let having = if let Some(having) = &self.having {
+            format!(" HAVING {}", having)
+        } else {
+            "".to_string()
+        };
+        let order_by = if self.order_by.is_empty() {
+            "".to_string()
+        } else {
+            let sort_fields = self
+                .order_by
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!(" ORDER BY {}", sort_fields)
+        };
 
-        write!(f, "SELECT {} FROM {}{}", field_names, table_names, pred)
+        write!(
+            f,
+            "SELECT {}{}{}{}{}{}",
+            field_names, table_names, pred, group_by, having, order_by
+        )
     }
 }
 
+fn quote_identifiers(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| quote_identifier(name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct UpdateNode {
     pub id: String,
@@ -89,11 +220,29 @@ pub type FieldDefinitions = Vec<FieldDefinition>;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum CreateNode {
-    Table(TableName, FieldDefinitions),
+    /// A table, and whether the statement does nothing if the table already exists
+    /// (`IF NOT EXISTS`).
+    Table(TableName, FieldDefinitions, bool),
+    /// A table whose fields and records are those produced by a query.
+    TableAs(TableName, SelectNode),
     View(ViewName, SelectNode),
+    MaterializedView(ViewName, SelectNode),
     Index(IndexName, TableName, FieldName),
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct DropNode {
+    pub table: TableName,
+    /// Whether the statement does nothing if the table does not exist (`IF EXISTS`).
+    pub if_exists: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AlterNode {
+    AddColumn(TableName, FieldDefinition),
+    DropColumn(TableName, FieldName),
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum RootNode {
     Select(SelectNode),
@@ -101,6 +250,9 @@ pub enum RootNode {
     Delete(DeleteNode),
     Update(UpdateNode),
     Create(CreateNode),
+    Alter(AlterNode),
+    Drop(DropNode),
+    Refresh(ViewName),
 }
 
 pub struct Parser<'a> {
@@ -132,17 +284,38 @@ impl<'a> Parser<'a> {
         match next_token {
             Token::VarcharConst(val) => Ok(Value::Varchar(val)),
             Token::IntegerConst(val) => Ok(Value::Int(val)),
+            Token::Minus => self.parse_negated_constant(),
             _ => Err(format!("Expected constant, found {:?}", next_token)),
         }
     }
 
+    /// Parse the numeric constant following a leading `-`, which has already been consumed.
+    ///
+    /// Negation is handled here rather than in the lexer, which can't tell a negative number
+    /// apart from a subtraction.
+    fn parse_negated_constant(&mut self) -> Result<Value, String> {
+        let next_token = self.next_token()?;
+        match next_token {
+            Token::IntegerConst(val) => Ok(Value::Int(-val)),
+            _ => Err(format!(
+                "Expected numeric constant after '-', found {:?}",
+                next_token
+            )),
+        }
+    }
+
     fn parse_expression(&mut self) -> Result<Expression, String> {
         let next_token = self.next_token()?;
         match next_token {
+            // An aggregate refers to the output field holding its value
+            Token::Identifier(id) if self.next_token_is(Token::LeftParen) => {
+                Ok(Expression::Field(self.parse_aggregate(&id)?.to_string()))
+            }
             Token::Identifier(id) => Ok(Expression::Field(id)),
             // TODO: remove duplication between the `parse_constant` fn and the below code
             Token::VarcharConst(val) => Ok(Expression::Constant(Value::Varchar(val))),
             Token::IntegerConst(val) => Ok(Expression::Constant(Value::Int(val))),
+            Token::Minus => Ok(Expression::Constant(self.parse_negated_constant()?)),
             _ => Err(format!(
                 "Invalid token found in expression: {:?}",
                 next_token
@@ -152,21 +325,61 @@ impl<'a> Parser<'a> {
 
     fn parse_term(&mut self) -> Result<Term, String> {
         let lexpr = self.parse_expression()?;
-        self.expect_token(Token::Equal)?;
+        let op = match self.next_token()? {
+            Token::Equal => Operator::Equal,
+            Token::LessThan => Operator::LessThan,
+            Token::GreaterThan => Operator::GreaterThan,
+            Token::LessEqual => Operator::LessEqual,
+            Token::GreaterEqual => Operator::GreaterEqual,
+            Token::NotEqual => Operator::NotEqual,
+            t => return Err(format!("expected comparison operator, found {:?}", t)),
+        };
         let rexpr = self.parse_expression()?;
 
-        Ok(Term::new(lexpr, rexpr))
+        Ok(Term::with_operator(lexpr, op, rexpr))
     }
 
+    /// Parse one condition of a conjunction: a term, a negated condition, or a parenthesized
+    /// predicate.
+    fn parse_condition(&mut self) -> Result<Predicate, String> {
+        if self.next_token_is(Token::Not) {
+            self.expect_token(Token::Not)?;
+            return Ok(!self.parse_condition()?);
+        }
+        if self.next_token_is(Token::LeftParen) {
+            self.expect_token(Token::LeftParen)?;
+            let pred = self.parse_predicate()?;
+            self.expect_token(Token::RightParen)?;
+            return Ok(pred);
+        }
+
+        Ok(Predicate::Term(self.parse_term()?))
+    }
+
+    /// Parse a predicate: conjunctions joined by OR, since AND binds more tightly than OR. A
+    /// predicate without OR is a single conjunction.
     fn parse_predicate(&mut self) -> Result<Predicate, String> {
-        let mut terms = vec![self.parse_term()?];
+        let mut pred = self.parse_conjunction()?;
+
+        while self.next_token_is(Token::Or) {
+            self.expect_token(Token::Or)?;
+            pred = pred.or(self.parse_conjunction()?);
+        }
+
+        Ok(pred)
+    }
+
+    // Parenthesized conjunctions are flattened into the enclosing one, so that a predicate parses
+    // the same after being displayed.
+    fn parse_conjunction(&mut self) -> Result<Predicate, String> {
+        let mut pred = Predicate::new().and(self.parse_condition()?);
 
         while self.next_token_is(Token::And) {
             self.expect_token(Token::And)?;
-            terms.push(self.parse_term()?);
+            pred = pred.and(self.parse_condition()?);
         }
 
-        Ok(Predicate::from_terms(terms))
+        Ok(pred)
     }
 
     fn parse_type_def(&mut self) -> Result<FieldType, String> {
@@ -186,6 +399,18 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parse the parenthesized field of an aggregate call, whose function name has already been
+    /// consumed.
+    fn parse_aggregate(&mut self, name: &str) -> Result<AggregateField, String> {
+        let agg_fn = AggregateFn::from_name(name)
+            .ok_or_else(|| format!("unknown aggregate function '{}'", name))?;
+        self.expect_token(Token::LeftParen)?;
+        let field_name = self.parse_identifier()?;
+        self.expect_token(Token::RightParen)?;
+
+        Ok(AggregateField(agg_fn, field_name))
+    }
+
     fn parse_field_def(&mut self) -> Result<FieldDefinition, String> {
         Ok(FieldDefinition(
             self.parse_identifier()?,
@@ -257,11 +482,30 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_create_table(&mut self) -> Result<CreateNode, String> {
-        if let Token::Identifier(table_name) = self.next_token()? {
-            Ok(CreateNode::Table(table_name, self.parse_field_defs()?))
-        } else {
-            Err("failed to parse CREATE TABLE".to_string())
+        let if_not_exists = self.next_token_is(Token::If);
+        if if_not_exists {
+            self.expect_token(Token::If)?;
+            self.expect_token(Token::Not)?;
+            self.expect_token(Token::Exists)?;
+        }
+
+        let Token::Identifier(table_name) = self.next_token()? else {
+            return Err("failed to parse CREATE TABLE".to_string());
+        };
+        if self.next_token_is(Token::As) {
+            if if_not_exists {
+                return Err("CREATE TABLE AS cannot be used with IF NOT EXISTS".to_string());
+            }
+            self.expect_token(Token::As)?;
+            self.expect_token(Token::Select)?;
+            return Ok(CreateNode::TableAs(table_name, self.parse_select()?));
         }
+
+        Ok(CreateNode::Table(
+            table_name,
+            self.parse_field_defs()?,
+            if_not_exists,
+        ))
     }
 
     fn parse_create_view(&mut self) -> Result<CreateNode, String> {
@@ -275,6 +519,16 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_create_materialized_view(&mut self) -> Result<CreateNode, String> {
+        self.expect_token(Token::View)?;
+        match self.parse_create_view()? {
+            CreateNode::View(view_name, select) => {
+                Ok(CreateNode::MaterializedView(view_name, select))
+            }
+            _ => Err("failed to parse CREATE MATERIALIZED VIEW statement".to_string()),
+        }
+    }
+
     fn parse_create_index(&mut self) -> Result<CreateNode, String> {
         if let Token::Identifier(index_name) = self.next_token()?
             && self.expect_token(Token::On)?
@@ -294,18 +548,64 @@ impl<'a> Parser<'a> {
             Token::Index => self.parse_create_index(),
             Token::Table => self.parse_create_table(),
             Token::View => self.parse_create_view(),
-            _ => Err("Did not find expected INDEX, TABLE, or VIEW identifier".to_string()),
+            Token::Materialized => self.parse_create_materialized_view(),
+            _ => Err(
+                "Did not find expected INDEX, TABLE, VIEW, or MATERIALIZED VIEW identifier"
+                    .to_string(),
+            ),
+        }
+    }
+
+    fn parse_alter(&mut self) -> Result<AlterNode, String> {
+        if self.expect_token(Token::Table)?
+            && let Token::Identifier(table_name) = self.next_token()?
+        {
+            match self.next_token()? {
+                Token::Add => {
+                    self.expect_token(Token::Column)?;
+                    Ok(AlterNode::AddColumn(table_name, self.parse_field_def()?))
+                }
+                Token::Drop => {
+                    self.expect_token(Token::Column)?;
+                    Ok(AlterNode::DropColumn(table_name, self.parse_identifier()?))
+                }
+                tok => Err(format!("Expected ADD or DROP COLUMN, found {:?}", tok)),
+            }
+        } else {
+            Err("failed to parse ALTER TABLE statement".to_string())
+        }
+    }
+
+    fn parse_drop(&mut self) -> Result<DropNode, String> {
+        self.expect_token(Token::Table)?;
+        let if_exists = self.next_token_is(Token::If);
+        if if_exists {
+            self.expect_token(Token::If)?;
+            self.expect_token(Token::Exists)?;
         }
+
+        Ok(DropNode {
+            table: self.parse_identifier()?,
+            if_exists,
+        })
+    }
+
+    fn parse_refresh(&mut self) -> Result<ViewName, String> {
+        self.expect_token(Token::View)?;
+        self.parse_identifier()
     }
 
     fn expect_token(&mut self, tok: Token) -> Result<bool, String> {
-        let next_tok = self.next_token();
-        match next_tok {
-            Ok(tok) => Ok(true),
-            _ => Err(format!(
+        let next_tok = self
+            .next_token()
+            .map_err(|e| format!("expected token: {:?} but {}", tok, e))?;
+        if next_tok == tok {
+            Ok(true)
+        } else {
+            Err(format!(
                 "expected token: {:?} but found {:?}",
                 tok, next_tok
-            )),
+            ))
         }
     }
 
@@ -363,7 +663,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_delete(&mut self) -> Result<DeleteNode, String> {
-        if Token::From == self.next_token()?
+        if self.expect_token(Token::From)?
             && let Token::Identifier(table_name) = self.next_token()?
         {
             let where_clause = self.parse_optional_where_clause()?;
@@ -399,9 +699,21 @@ impl<'a> Parser<'a> {
                 items.push(SelectField::Star);
 
                 // eat the splat token
-                self.expect_token(Token::Splat);
+                self.expect_token(Token::Splat)?;
+            } else if matches!(
+                self.lexer.peek(),
+                Some(Ok(Token::IntegerConst(_)
+                    | Token::VarcharConst(_)
+                    | Token::Minus))
+            ) {
+                items.push(SelectField::Constant(self.parse_constant()?));
             } else {
-                items.push(SelectField::FieldName(self.parse_identifier()?));
+                let id = self.parse_identifier()?;
+                if self.next_token_is(Token::LeftParen) {
+                    items.push(SelectField::Aggregate(self.parse_aggregate(&id)?));
+                } else {
+                    items.push(SelectField::FieldName(id));
+                }
             }
 
             if !self.next_token_is(Token::Comma) {
@@ -414,16 +726,82 @@ impl<'a> Parser<'a> {
         Ok(items)
     }
 
+    fn parse_optional_group_by(&mut self) -> Result<Vec<FieldName>, String> {
+        if self.next_token_is(Token::Group) {
+            // eat the `GROUP` token
+            self.expect_token(Token::Group)?;
+            self.expect_token(Token::By)?;
+
+            self.parse_identifier_list()
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    fn parse_optional_having(&mut self) -> Result<Option<Predicate>, String> {
+        if self.next_token_is(Token::Having) {
+            // eat the `HAVING` token
+            self.expect_token(Token::Having)?;
+
+            Ok(Some(self.parse_predicate()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_sort_field(&mut self) -> Result<SortField, String> {
+        let field_name = self.parse_identifier()?;
+        let order = if self.next_token_is(Token::Desc) {
+            self.expect_token(Token::Desc)?;
+            SortOrder::Descending
+        } else {
+            if self.next_token_is(Token::Asc) {
+                self.expect_token(Token::Asc)?;
+            }
+            SortOrder::Ascending
+        };
+
+        Ok(SortField(field_name, order))
+    }
+
+    fn parse_optional_order_by(&mut self) -> Result<Vec<SortField>, String> {
+        if self.next_token_is(Token::Order) {
+            // eat the `ORDER` token
+            self.expect_token(Token::Order)?;
+            self.expect_token(Token::By)?;
+
+            let mut sort_fields = vec![self.parse_sort_field()?];
+            while self.next_token_is(Token::Comma) {
+                self.expect_token(Token::Comma)?;
+                sort_fields.push(self.parse_sort_field()?);
+            }
+            Ok(sort_fields)
+        } else {
+            Ok(vec![])
+        }
+    }
+
     fn parse_select(&mut self) -> Result<SelectNode, String> {
         let select_list = self.parse_select_list()?;
-        self.expect_token(Token::From)?;
-        let table_list = self.parse_table_list()?;
+        // A query without a FROM clause selects constants
+        let table_list = if self.next_token_is(Token::From) {
+            self.expect_token(Token::From)?;
+            self.parse_table_list()?
+        } else {
+            vec![]
+        };
         let where_clause = self.parse_optional_where_clause()?;
+        let group_by = self.parse_optional_group_by()?;
+        let having = self.parse_optional_having()?;
+        let order_by = self.parse_optional_order_by()?;
 
         Ok(SelectNode {
             fields: select_list,
             tables: table_list,
             predicate: where_clause,
+            group_by,
+            having,
+            order_by,
         })
     }
 
@@ -437,15 +815,53 @@ impl<'a> Parser<'a> {
                 Ok(Token::Delete) => self.parse_delete().map(RootNode::Delete),
                 Ok(Token::Insert) => self.parse_insert().map(RootNode::Insert),
                 Ok(Token::Select) => self.parse_select().map(RootNode::Select),
+                Ok(Token::Alter) => self.parse_alter().map(RootNode::Alter),
+                Ok(Token::Drop) => self.parse_drop().map(RootNode::Drop),
+                Ok(Token::Refresh) => self.parse_refresh().map(RootNode::Refresh),
                 Ok(_) | Err(_) => Err("Failed to parse root statement".to_string()),
             })
     }
+
+    /// Parse a script of statements separated by semicolons. Empty statements, including one
+    /// after a trailing semicolon, are ignored.
+    pub fn parse_script(&mut self) -> Result<Vec<RootNode>, String> {
+        let mut statements = Vec::new();
+
+        loop {
+            while self.next_token_is(Token::SemiColon) {
+                self.next_token()?;
+            }
+
+            if self.lexer.peek().is_none() {
+                return Ok(statements);
+            }
+
+            statements.push(self.parse()?);
+
+            match self.lexer.next() {
+                None => return Ok(statements),
+                Some(Ok(Token::SemiColon)) => {}
+                Some(tok) => {
+                    return Err(format!(
+                        "expected ';' after statement {}, found {:?}",
+                        statements.len(),
+                        tok
+                    ))
+                }
+            }
+        }
+    }
 }
 
 pub fn parse(text: &str) -> Result<RootNode, String> {
     Parser::new(Lexer::new(text)).parse()
 }
 
+/// Parse a script of semicolon-separated statements, e.g. the contents of a `.sql` file.
+pub fn parse_script(text: &str) -> Result<Vec<RootNode>, String> {
+    Parser::new(Lexer::new(text)).parse_script()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::parser::{lexer::Lexer, parser::*};
@@ -473,7 +889,75 @@ mod tests {
                 RootNode::Create(
                     CreateNode::Table("test".to_string(), vec![
                         FieldDefinition("id".to_string(), FieldType::Int),
-                        FieldDefinition("name".to_string(), FieldType::Varchar(10))]))),
+                        FieldDefinition("name".to_string(), FieldType::Varchar(10))], false))),
+
+        test_parser_create_table_if_not_exists: "CREATE TABLE IF NOT EXISTS test ( id int )" =>
+            Ok(
+                RootNode::Create(
+                    CreateNode::Table("test".to_string(), vec![
+                        FieldDefinition("id".to_string(), FieldType::Int)], true))),
+
+        test_parser_create_table_if_missing_not: "CREATE TABLE IF EXISTS test ( id int )" =>
+            Err("expected token: Not but found Exists".to_string()),
+
+        test_parser_create_table_quoted_1: "CREATE TABLE \"order\" ( \"index\" int, \"year\" varchar(4))" =>
+            Ok(
+                RootNode::Create(
+                    CreateNode::Table("order".to_string(), vec![
+                        FieldDefinition("index".to_string(), FieldType::Int),
+                        FieldDefinition("year".to_string(), FieldType::Varchar(4))], false))),
+
+        test_parser_create_table_as_1: "CREATE TABLE summary AS SELECT major_id, COUNT(sid) FROM student GROUP BY major_id" =>
+            Ok(
+                RootNode::Create(
+                    CreateNode::TableAs(
+                        "summary".to_string(),
+                        SelectNode{
+                            fields: vec![
+                                SelectField::FieldName("major_id".to_string()),
+                                SelectField::Aggregate(AggregateField(AggregateFn::Count, "sid".to_string())),
+                            ],
+                            tables: vec!["student".to_string()],
+                            predicate: None,
+                            group_by: vec!["major_id".to_string()],
+                            having: None,
+                            order_by: vec![],
+                        }
+                    )
+                )
+            ),
+
+        test_parser_create_table_as_if_not_exists: "CREATE TABLE IF NOT EXISTS summary AS SELECT sid FROM student" =>
+            Err("CREATE TABLE AS cannot be used with IF NOT EXISTS".to_string()),
+
+        test_parser_drop_table: "DROP TABLE test" =>
+            Ok(RootNode::Drop(DropNode{ table: "test".to_string(), if_exists: false })),
+
+        test_parser_drop_table_if_exists: "drop table if exists test" =>
+            Ok(RootNode::Drop(DropNode{ table: "test".to_string(), if_exists: true })),
+
+        test_parser_drop_table_missing_table: "DROP test" =>
+            Err("expected token: Table but found Identifier(\"test\")".to_string()),
+
+        test_parser_alter_table_add_column_1: "ALTER TABLE student ADD COLUMN gpa int" =>
+            Ok(
+                RootNode::Alter(
+                    AlterNode::AddColumn("student".to_string(),
+                        FieldDefinition("gpa".to_string(), FieldType::Int)))),
+
+        test_parser_alter_table_add_column_2: "alter table student add column nickname varchar(8)" =>
+            Ok(
+                RootNode::Alter(
+                    AlterNode::AddColumn("student".to_string(),
+                        FieldDefinition("nickname".to_string(), FieldType::Varchar(8))))),
+
+        test_parser_alter_table_drop_column_1: "ALTER TABLE student DROP COLUMN grad_year" =>
+            Ok(
+                RootNode::Alter(
+                    AlterNode::DropColumn("student".to_string(), "grad_year".to_string()))),
+
+        test_parser_alter_table_invalid_1: "ALTER TABLE student RENAME COLUMN grad_year" =>
+            Err("Expected ADD or DROP COLUMN, found Identifier(\"RENAME\")".to_string()),
 
         test_parser_create_index_1: "CREATE INDEX idx_test ON test_table ( test_field )" =>
             Ok(
@@ -491,11 +975,39 @@ mod tests {
                             fields: vec![SelectField::FieldName("f1".to_string()), SelectField::FieldName("f2".to_string())],
                             tables: vec!["test_table".to_string()],
                             predicate: None,
+                            group_by: vec![],
+                            having: None,
+                            order_by: vec![],
+                        }
+                    )
+                )
+            ),
+
+        test_parser_create_materialized_view_1: "CREATE MATERIALIZED VIEW view_test AS SELECT f1 FROM test_table WHERE f1 = 1" =>
+            Ok(
+                RootNode::Create(
+                    CreateNode::MaterializedView(
+                        "view_test".to_string(),
+                        SelectNode{
+                            fields: vec![SelectField::FieldName("f1".to_string())],
+                            tables: vec!["test_table".to_string()],
+                            predicate: Some(Predicate::from_term(
+                                Term::new(
+                                    Expression::Field("f1".to_string()),
+                                    Expression::Constant(Value::Int(1))
+                                )
+                            )),
+                            group_by: vec![],
+                            having: None,
+                            order_by: vec![],
                         }
                     )
                 )
             ),
 
+        test_parser_refresh_view_1: "REFRESH VIEW view_test" =>
+            Ok(RootNode::Refresh("view_test".to_string())),
+
 
         test_parser_update_1: "UPDATE test_table SET test_field = 10" =>
             Ok(
@@ -569,7 +1081,10 @@ mod tests {
                             Term::new(
                                 Expression::Field("a".to_string()),
                                 Expression::Field("c".to_string())
-                            )))}
+                            ))),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
                 )
             ),
 
@@ -591,8 +1106,367 @@ mod tests {
                             Term::new(
                                 Expression::Field("a".to_string()),
                                 Expression::Field("c".to_string())
-                            )))}
+                            ))),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_select_star: "select * from student" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::Star],
+                        tables: vec!["student".to_string()],
+                        predicate: None,
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_insert_negative: "INSERT INTO test_table ( a, b ) VALUES (-1, -20)" =>
+            Ok(
+                RootNode::Insert(
+                    InsertNode("test_table".to_string(),
+                        vec!["a".to_string(), "b".to_string()],
+                        vec![Value::Int(-1), Value::Int(-20)])
+                )
+            ),
+
+        test_parser_select_negative: "SELECT a FROM t1 WHERE a = -5 AND -7 = b" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("a".to_string())],
+                        tables: vec!["t1".to_string()],
+                        predicate: Some(Predicate::from_terms(vec![
+                            Term::new(
+                                Expression::Field("a".to_string()),
+                                Expression::Constant(Value::Int(-5))
+                            ),
+                            Term::new(
+                                Expression::Constant(Value::Int(-7)),
+                                Expression::Field("b".to_string())
+                            )
+                        ])),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_select_comparisons: "SELECT a FROM t1 WHERE a > 5 AND 7 < b AND c = 1" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("a".to_string())],
+                        tables: vec!["t1".to_string()],
+                        predicate: Some(Predicate::from_terms(vec![
+                            Term::gt(
+                                Expression::Field("a".to_string()),
+                                Expression::Constant(Value::Int(5))
+                            ),
+                            Term::lt(
+                                Expression::Constant(Value::Int(7)),
+                                Expression::Field("b".to_string())
+                            ),
+                            Term::eq(
+                                Expression::Field("c".to_string()),
+                                Expression::Constant(Value::Int(1))
+                            )
+                        ])),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_select_not: "SELECT a FROM t1 WHERE NOT (a = 1 AND b > 2) AND NOT c = 3" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("a".to_string())],
+                        tables: vec!["t1".to_string()],
+                        predicate: Some(Predicate::And(vec![
+                            !Predicate::from_terms(vec![
+                                Term::eq(
+                                    Expression::Field("a".to_string()),
+                                    Expression::Constant(Value::Int(1))
+                                ),
+                                Term::gt(
+                                    Expression::Field("b".to_string()),
+                                    Expression::Constant(Value::Int(2))
+                                )
+                            ]),
+                            !Predicate::Term(Term::eq(
+                                Expression::Field("c".to_string()),
+                                Expression::Constant(Value::Int(3))
+                            ))
+                        ])),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_select_missing_operator: "SELECT a FROM t1 WHERE a 5" =>
+            Err("expected comparison operator, found IntegerConst(5)".to_string()),
+
+        test_parser_update_negative: "UPDATE test_table SET test_field = -10" =>
+            Ok(
+                RootNode::Update(
+                    UpdateNode{
+                        id: "test_table".to_string(),
+                        field: "test_field".to_string(),
+                        expr: Expression::Constant(Value::Int(-10)),
+                        where_clause: None})
+            ),
+
+        test_parser_select_order_by: "SELECT a, b FROM t1 WHERE a = 1 ORDER BY b, a" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![
+                            SelectField::FieldName("a".to_string()),
+                            SelectField::FieldName("b".to_string())
+                        ],
+                        tables: vec!["t1".to_string()],
+                        predicate: Some(Predicate::from_term(
+                            Term::new(
+                                Expression::Field("a".to_string()),
+                                Expression::Constant(Value::Int(1))
+                            ))),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![
+                            SortField("b".to_string(), SortOrder::Ascending),
+                            SortField("a".to_string(), SortOrder::Ascending)
+                        ]}
+                )
+            ),
+
+        test_parser_select_order_by_without_where: "SELECT a FROM t1 ORDER BY a" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("a".to_string())],
+                        tables: vec!["t1".to_string()],
+                        predicate: None,
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![SortField("a".to_string(), SortOrder::Ascending)]}
+                )
+            ),
+
+        test_parser_select_order_by_direction: "SELECT a FROM t1 ORDER BY a DESC, b ASC, c" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("a".to_string())],
+                        tables: vec!["t1".to_string()],
+                        predicate: None,
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![
+                            SortField("a".to_string(), SortOrder::Descending),
+                            SortField("b".to_string(), SortOrder::Ascending),
+                            SortField("c".to_string(), SortOrder::Ascending)
+                        ]}
+                )
+            ),
+
+        test_parser_select_having: "SELECT major_id FROM student WHERE grad_year = 2020 HAVING major_id = 20 ORDER BY major_id" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("major_id".to_string())],
+                        tables: vec!["student".to_string()],
+                        predicate: Some(Predicate::from_term(
+                            Term::new(
+                                Expression::Field("grad_year".to_string()),
+                                Expression::Constant(Value::Int(2020))
+                            ))),
+                        group_by: vec![],
+                        having: Some(Predicate::from_term(
+                            Term::new(
+                                Expression::Field("major_id".to_string()),
+                                Expression::Constant(Value::Int(20))
+                            ))),
+                        order_by: vec![SortField("major_id".to_string(), SortOrder::Ascending)]}
                 )
             ),
+
+        test_parser_select_group_by: "SELECT major_id, COUNT(sid), max(grad_year) FROM student GROUP BY major_id HAVING count(sid) > 2" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![
+                            SelectField::FieldName("major_id".to_string()),
+                            SelectField::Aggregate(AggregateField(AggregateFn::Count, "sid".to_string())),
+                            SelectField::Aggregate(AggregateField(AggregateFn::Max, "grad_year".to_string()))
+                        ],
+                        tables: vec!["student".to_string()],
+                        predicate: None,
+                        group_by: vec!["major_id".to_string()],
+                        having: Some(Predicate::from_term(
+                            Term::gt(
+                                Expression::Field("count(sid)".to_string()),
+                                Expression::Constant(Value::Int(2))
+                            ))),
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_select_unknown_aggregate: "SELECT total(sid) FROM student" =>
+            Err("unknown aggregate function 'total'".to_string()),
+
+        test_parser_select_without_from: "SELECT 3, 'hello', -1" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![
+                            SelectField::Constant(Value::Int(3)),
+                            SelectField::Constant(Value::Varchar("hello".to_string())),
+                            SelectField::Constant(Value::Int(-1))
+                        ],
+                        tables: vec![],
+                        predicate: None,
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_select_inclusive_comparisons: "SELECT a FROM t1 WHERE a >= 'm' AND b <= 2 AND c <> 3 AND d != 4" =>
+            Ok(
+                RootNode::Select(
+                    SelectNode{
+                        fields: vec![SelectField::FieldName("a".to_string())],
+                        tables: vec!["t1".to_string()],
+                        predicate: Some(Predicate::from_terms(vec![
+                            Term::ge(
+                                Expression::Field("a".to_string()),
+                                Expression::Constant(Value::Varchar("m".to_string()))
+                            ),
+                            Term::le(
+                                Expression::Field("b".to_string()),
+                                Expression::Constant(Value::Int(2))
+                            ),
+                            Term::ne(
+                                Expression::Field("c".to_string()),
+                                Expression::Constant(Value::Int(3))
+                            ),
+                            Term::ne(
+                                Expression::Field("d".to_string()),
+                                Expression::Constant(Value::Int(4))
+                            )
+                        ])),
+                        group_by: vec![],
+                        having: None,
+                        order_by: vec![]}
+                )
+            ),
+
+        test_parser_insert_missing_into: "INSERT test_table (a) VALUES (1)" =>
+            Err("expected token: Into but found Identifier(\"test_table\")".to_string()),
+
+        test_parser_insert_missing_left_paren: "INSERT INTO test_table a, b) VALUES (1, 2)" =>
+            Err("expected token: LeftParen but found Identifier(\"a\")".to_string()),
+
+        test_parser_insert_missing_right_paren: "INSERT INTO test_table (a, b VALUES (1, 2)" =>
+            Err("expected token: RightParen but found Values".to_string()),
+
+        test_parser_delete_missing_from: "DELETE test_table WHERE a = 1" =>
+            Err("expected token: From but found Identifier(\"test_table\")".to_string()),
+
+        test_parser_order_missing_by: "SELECT a FROM test_table ORDER" =>
+            Err("expected token: By but reached unexpected end of input".to_string()),
+
+        test_parser_create_index_missing_paren: "CREATE INDEX idx ON test_table a)" =>
+            Err("expected token: LeftParen but found Identifier(\"a\")".to_string()),
+
+        test_parser_update_missing_set: "UPDATE test_table a = 1" =>
+            Err("expected token: Set but found Identifier(\"a\")".to_string()),
+
+        test_parser_minus_without_number: "INSERT INTO test_table ( a ) VALUES (- 'x')" =>
+            Err("Expected numeric constant after '-', found VarcharConst(\"x\")".to_string()),
+    }
+
+    #[test]
+    fn test_display_select_without_from() {
+        let query = "SELECT 3, 'hello', -1 WHERE 1 = 1";
+        match Parser::new(Lexer::new(query)).parse().unwrap() {
+            RootNode::Select(node) => assert_eq!(node.to_string(), query),
+            _ => panic!("expected a select statement"),
+        }
+    }
+
+    #[test]
+    fn test_display_select_with_or() {
+        let query = "SELECT a FROM t WHERE a = 1 OR b = 2 AND c = 3";
+        let node = match parse(query).unwrap() {
+            RootNode::Select(node) => node,
+            _ => panic!("expected a select statement"),
+        };
+        let displayed = node.to_string();
+        assert_eq!(
+            displayed,
+            "SELECT a FROM t WHERE a = 1 OR (b = 2 AND c = 3)"
+        );
+        assert_eq!(parse(&displayed), Ok(RootNode::Select(node)));
+    }
+
+    #[test]
+    fn test_display_select_with_quoted_identifiers() {
+        let query = "SELECT \"index\", \"two words\" FROM \"order\" WHERE \"select\" = year ORDER BY \"index\" DESC, year";
+        let node = match parse(query).unwrap() {
+            RootNode::Select(node) => node,
+            _ => panic!("expected a select statement"),
+        };
+        assert_eq!(node.fields[0], SelectField::FieldName("index".to_string()));
+        assert_eq!(node.to_string(), query);
+    }
+
+    #[test]
+    fn test_display_select_with_group_by() {
+        let query =
+            "SELECT major_id, count(sid) FROM student GROUP BY major_id HAVING count(sid) > 2";
+        let node = match parse(query).unwrap() {
+            RootNode::Select(node) => node,
+            _ => panic!("expected a select statement"),
+        };
+        let displayed = node.to_string();
+        assert_eq!(
+            displayed,
+            "SELECT major_id, count(sid) FROM student GROUP BY major_id HAVING \"count(sid)\" > 2"
+        );
+        assert_eq!(parse(&displayed), Ok(RootNode::Select(node)));
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = "
+            CREATE TABLE t1 (a int, b varchar(10));
+            INSERT INTO t1 (a, b) VALUES (1, 'one');;
+            SELECT a, b FROM t1 WHERE a = 1;
+        ";
+
+        let statements = parse_script(script).unwrap();
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[0], RootNode::Create(CreateNode::Table(..))));
+        assert!(matches!(statements[1], RootNode::Insert(_)));
+        assert!(matches!(statements[2], RootNode::Select(_)));
+
+        // The final semicolon is optional
+        let statements = parse_script("DELETE FROM t1; DELETE FROM t2").unwrap();
+        assert_eq!(statements.len(), 2);
+
+        assert_eq!(parse_script("  ;; -- nothing here\n"), Ok(vec![]));
+        assert_eq!(
+            parse_script("DELETE FROM t1 DELETE FROM t2"),
+            Err("expected ';' after statement 1, found Ok(Delete)".to_string())
+        );
     }
 }
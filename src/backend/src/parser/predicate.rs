@@ -1,55 +1,199 @@
-use std::fmt::Display;
+use std::{fmt::Display, mem, ops::Not};
 
-use crate::{planning::plan::Plan, scan::scan::Scannable};
+use crate::{planning::plan::Plan, scan::scan::Scannable, schema::Schema};
 
 use super::term::Term;
 
+/// A boolean condition over the fields of a record.
+///
+/// Predicates form a tree: leaves are `Term`s, and interior nodes combine their children as a
+/// conjunction or a disjunction, or negate their only child. The parser emits a conjunction for
+/// a WHERE clause, or a disjunction of conjunctions if the clause uses OR.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Predicate {
-    terms: Vec<Term>,
+pub enum Predicate {
+    Term(Term),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>),
 }
 
 impl Predicate {
+    /// Create an empty predicate, which is satisfied by every record.
     pub fn new() -> Self {
-        Self { terms: vec![] }
+        Self::And(vec![])
     }
 
     pub fn from_terms(terms: Vec<Term>) -> Self {
-        Self { terms }
+        Self::And(terms.into_iter().map(Self::Term).collect())
     }
 
     pub fn from_term(term: Term) -> Self {
-        Self { terms: vec![term] }
+        Self::from_terms(vec![term])
+    }
+
+    /// Combine two predicates into one that is satisfied only when both are satisfied.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The predicate to conjoin with this one.
+    pub fn and(self, other: Predicate) -> Self {
+        let mut preds = self.into_conjuncts();
+        preds.append(&mut other.into_conjuncts());
+        Self::And(preds)
+    }
+
+    /// Combine two predicates into one that is satisfied when either is satisfied.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The predicate to disjoin with this one.
+    pub fn or(self, other: Predicate) -> Self {
+        let mut preds = self.into_disjuncts();
+        preds.append(&mut other.into_disjuncts());
+        Self::Or(preds)
     }
 
     pub fn conjoin_with(&mut self, other: &mut Predicate) {
-        self.terms.append(&mut other.terms);
+        let lhs = mem::replace(self, Self::new());
+        let rhs = mem::replace(other, Self::new());
+        *self = lhs.and(rhs);
     }
 
     pub fn is_satisfied(&self, scan: &dyn Scannable) -> bool {
-        for t in &self.terms {
-            if !t.is_satisfied(scan) {
-                return false;
+        match self {
+            Self::Term(t) => t.is_satisfied(scan),
+            Self::And(preds) => preds.iter().all(|p| p.is_satisfied(scan)),
+            Self::Or(preds) => preds.iter().any(|p| p.is_satisfied(scan)),
+            Self::Not(pred) => !pred.is_satisfied(scan),
+        }
+    }
+
+    /// Verify that every term in the predicate can be evaluated against a schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema used to resolve field names.
+    pub fn type_check(&self, schema: &Schema) -> Result<(), String> {
+        match self {
+            Self::Term(t) => t.type_check(schema),
+            Self::Not(pred) => pred.type_check(schema),
+            Self::And(preds) | Self::Or(preds) => {
+                for p in preds {
+                    p.type_check(schema)?;
+                }
+                Ok(())
             }
         }
+    }
 
-        true
+    /// Convert varchar constants compared with integer fields into integer constants, returning
+    /// an error if one is not an integer. Terms on fields that are not in the schema are left
+    /// unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema used to resolve field names.
+    pub fn coerce(&mut self, schema: &Schema) -> Result<(), String> {
+        match self {
+            Self::Term(t) => t.coerce(schema),
+            Self::Not(pred) => pred.coerce(schema),
+            Self::And(preds) | Self::Or(preds) => {
+                for p in preds {
+                    p.coerce(schema)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether no record can satisfy the predicate, given the range of values of each integer
+    /// field. A predicate that cannot be shown to be unsatisfiable may still match no records.
+    ///
+    /// # Arguments
+    ///
+    /// * `field_range` - Gets the smallest and largest values of a field, if known.
+    pub fn is_unsatisfiable(&self, field_range: &dyn Fn(&str) -> Option<(i32, i32)>) -> bool {
+        match self {
+            Self::Term(t) => t.is_unsatisfiable(field_range),
+            Self::And(preds) => preds.iter().any(|p| p.is_unsatisfiable(field_range)),
+            Self::Or(preds) => {
+                !preds.is_empty() && preds.iter().all(|p| p.is_unsatisfiable(field_range))
+            }
+            // Ranges only show that no record can satisfy a predicate, not that every record
+            // does, so they say nothing about its negation
+            Self::Not(_) => false,
+        }
+    }
+
+    /// Split off a conjunct that equates a field with a constant, such as one that an index on the
+    /// field can look up. Returns that term along with the predicate formed by the remaining
+    /// conjuncts, which is `None` if no conjuncts remain, or `None` if no conjunct equates the
+    /// field with a constant.
+    ///
+    /// # Arguments
+    ///
+    /// * `field_name` - The name of the field.
+    pub fn split(&self, field_name: &str) -> Option<(Term, Option<Predicate>)> {
+        let mut conjuncts = self.clone().into_conjuncts();
+        let pos = conjuncts.iter().position(
+            |p| matches!(p, Self::Term(t) if t.equates_with_constant(field_name).is_some()),
+        )?;
+        let Self::Term(term) = conjuncts.remove(pos) else {
+            unreachable!("conjunct is a term");
+        };
+        let residual = (!conjuncts.is_empty()).then_some(Self::And(conjuncts));
+        Some((term, residual))
     }
 
     pub fn reduction_factor(&self, plan: &dyn Plan) -> u64 {
         1
     }
+
+    // Get the predicates that must all hold for this predicate to hold.
+    fn into_conjuncts(self) -> Vec<Predicate> {
+        match self {
+            Self::And(preds) => preds,
+            pred => vec![pred],
+        }
+    }
+
+    // Get the predicates of which at least one must hold for this predicate to hold.
+    fn into_disjuncts(self) -> Vec<Predicate> {
+        match self {
+            Self::Or(preds) => preds,
+            pred => vec![pred],
+        }
+    }
+}
+
+impl Not for Predicate {
+    type Output = Self;
+
+    /// Negate the predicate, so that it is satisfied only by records that do not satisfy it.
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
 }
 
 impl Display for Predicate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let terms = self
-            .terms
+        let (preds, separator) = match self {
+            Self::Term(t) => return write!(f, "{}", t),
+            Self::Not(pred) => return write!(f, "NOT ({})", pred),
+            Self::And(preds) => (preds, " AND "),
+            Self::Or(preds) => (preds, " OR "),
+        };
+
+        let preds = preds
             .iter()
-            .map(|t| format!("{}", t))
-            .intersperse(" AND ".to_string());
-        for term in terms {
-            write!(f, "{}", term)?;
+            .map(|p| match p {
+                // Nested compound predicates are parenthesized to preserve their grouping.
+                Self::And(inner) | Self::Or(inner) if inner.len() > 1 => format!("({})", p),
+                _ => format!("{}", p),
+            })
+            .intersperse(separator.to_string());
+        for pred in preds {
+            write!(f, "{}", pred)?;
         }
         Ok(())
     }
@@ -62,14 +206,48 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::{
+        db::SimpleDB,
         metadata::metadata_manager::MetadataManager,
-        parser::{constant::Value, expression::Expression},
+        parser::{
+            constant::Value,
+            expression::Expression,
+            parser::{parse, RootNode},
+        },
         table_scan::TableScan,
         tests::test_utils::{create_default_tables, default_test_db},
     };
 
     use super::*;
 
+    fn field(name: &str) -> Expression {
+        Expression::Field(name.to_string())
+    }
+
+    fn int(val: i32) -> Expression {
+        Expression::Constant(Value::Int(val))
+    }
+
+    // Get the ids of all students that satisfy the predicate.
+    fn matching_students(db: &SimpleDB, pred: &Predicate) -> Vec<i32> {
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout, "student");
+        let mut sids = vec![];
+        while scan.next() {
+            if pred.is_satisfied(&scan) {
+                sids.push(scan.get_int("sid").unwrap());
+            }
+        }
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+        sids
+    }
+
     #[test]
     fn test_predicate() {
         let td = tempdir().unwrap();
@@ -98,4 +276,141 @@ mod tests {
         scan.next();
         assert!(pred1.is_satisfied(&*scan));
     }
+
+    #[test]
+    fn test_combinators_match_parsed_predicate() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let parsed = match parse("SELECT sid FROM student WHERE major_id = 10 AND grad_year = 2021")
+        {
+            Ok(RootNode::Select(node)) => node.predicate.unwrap(),
+            _ => panic!("failed to parse select statement"),
+        };
+        let built = Predicate::from_term(Term::eq(field("major_id"), int(10))).and(
+            Predicate::from_term(Term::eq(field("grad_year"), int(2021))),
+        );
+
+        assert_eq!(built, parsed);
+        assert_eq!(matching_students(&db, &built), vec![1, 9]);
+        assert_eq!(
+            matching_students(&db, &built),
+            matching_students(&db, &parsed)
+        );
+    }
+
+    #[test]
+    fn test_compound_predicate() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let pred = Predicate::from_term(Term::gt(field("grad_year"), int(2020))).and(
+            Predicate::from_term(Term::eq(field("major_id"), int(10)))
+                .or(Predicate::from_term(Term::lt(field("sid"), int(3)))),
+        );
+
+        assert_eq!(
+            format!("{}", pred),
+            "grad_year > 2020 AND (major_id = 10 OR sid < 3)"
+        );
+        assert_eq!(matching_students(&db, &pred), vec![1, 3, 9]);
+
+        let mut conjoined = Predicate::from_term(Term::gt(field("sid"), int(7)));
+        conjoined.conjoin_with(&mut Predicate::from_term(Term::lt(field("sid"), int(9))));
+        assert_eq!(
+            conjoined,
+            Predicate::from_terms(vec![
+                Term::gt(field("sid"), int(7)),
+                Term::lt(field("sid"), int(9)),
+            ])
+        );
+        assert_eq!(matching_students(&db, &conjoined), vec![8]);
+    }
+
+    #[test]
+    fn test_parsed_disjunction() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let parse_predicate = |sql: &str| match parse(sql) {
+            Ok(RootNode::Select(node)) => node.predicate.unwrap(),
+            _ => panic!("failed to parse select statement"),
+        };
+
+        let parsed =
+            parse_predicate("SELECT sid FROM student WHERE major_id = 10 OR major_id = 20");
+        let built = Predicate::from_term(Term::eq(field("major_id"), int(10)))
+            .or(Predicate::from_term(Term::eq(field("major_id"), int(20))));
+        assert_eq!(parsed, built);
+        assert_eq!(format!("{}", parsed), "major_id = 10 OR major_id = 20");
+        assert_eq!(matching_students(&db, &parsed), vec![1, 2, 3, 4, 6, 8, 9]);
+
+        // AND binds more tightly than OR, and parentheses override it
+        let parsed = parse_predicate(
+            "SELECT sid FROM student WHERE sid < 3 OR major_id = 10 AND grad_year = 2022",
+        );
+        assert_eq!(
+            format!("{}", parsed),
+            "sid < 3 OR (major_id = 10 AND grad_year = 2022)"
+        );
+        assert_eq!(matching_students(&db, &parsed), vec![1, 2, 3]);
+        let parsed = parse_predicate(
+            "SELECT sid FROM student WHERE (sid < 3 OR major_id = 10) AND grad_year = 2022",
+        );
+        assert_eq!(
+            format!("{}", parsed),
+            "(sid < 3 OR major_id = 10) AND grad_year = 2022"
+        );
+        assert_eq!(matching_students(&db, &parsed), vec![3]);
+    }
+
+    #[test]
+    fn test_negated_predicate() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let parsed = match parse("SELECT sid FROM student WHERE NOT (major_id = 10)") {
+            Ok(RootNode::Select(node)) => node.predicate.unwrap(),
+            _ => panic!("failed to parse select statement"),
+        };
+        let positive = Predicate::from_term(Term::eq(field("major_id"), int(10)));
+        assert_eq!(format!("{}", parsed), "NOT (major_id = 10)");
+        assert_eq!(matching_students(&db, &positive), vec![1, 3, 9]);
+        assert_eq!(matching_students(&db, &parsed), vec![2, 4, 5, 6, 7, 8]);
+        assert_eq!(matching_students(&db, &!!positive), vec![1, 3, 9]);
+    }
+
+    #[test]
+    fn test_split() {
+        let pred = Predicate::from_terms(vec![
+            Term::gt(field("grad_year"), int(2020)),
+            Term::eq(int(4), field("sid")),
+            Term::eq(field("major_id"), int(20)),
+        ]);
+
+        let (term, residual) = pred.split("sid").unwrap();
+        assert_eq!(term, Term::eq(int(4), field("sid")));
+        assert_eq!(term.equates_with_constant("sid"), Some(Value::Int(4)));
+        assert_eq!(
+            residual,
+            Some(Predicate::from_terms(vec![
+                Term::gt(field("grad_year"), int(2020)),
+                Term::eq(field("major_id"), int(20)),
+            ]))
+        );
+
+        // Only equalities with a constant at the top level of the predicate can be split off
+        assert_eq!(pred.split("grad_year"), None);
+        assert_eq!(pred.split("sname"), None);
+        assert_eq!((!pred.clone()).split("sid"), None);
+        let pred = Predicate::from_term(Term::eq(field("sid"), int(4)));
+        assert_eq!(
+            pred.split("sid"),
+            Some((Term::eq(field("sid"), int(4)), None))
+        );
+    }
 }
@@ -2,35 +2,211 @@ use std::fmt::Display;
 
 use crate::{scan::scan::Scannable, schema::Schema};
 
-use super::expression::Expression;
+use super::{constant::Value, expression::Expression};
+
+/// The comparison applied between the two sides of a `Term`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    NotEqual,
+}
+
+impl Operator {
+    /// The operator that gives the same result with its operands swapped, e.g. `>` for `<`.
+    fn flipped(self) -> Self {
+        match self {
+            Self::Equal | Self::NotEqual => self,
+            Self::LessThan => Self::GreaterThan,
+            Self::GreaterThan => Self::LessThan,
+            Self::LessEqual => Self::GreaterEqual,
+            Self::GreaterEqual => Self::LessEqual,
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Equal => write!(f, "="),
+            Self::LessThan => write!(f, "<"),
+            Self::GreaterThan => write!(f, ">"),
+            Self::LessEqual => write!(f, "<="),
+            Self::GreaterEqual => write!(f, ">="),
+            Self::NotEqual => write!(f, "<>"),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Term {
     lhs: Expression,
+    op: Operator,
     rhs: Expression,
 }
 
 impl Term {
+    /// Create a term that is satisfied when `lhs` equals `rhs`.
     pub fn new(lhs: Expression, rhs: Expression) -> Self {
-        Self { lhs, rhs }
+        Self::with_operator(lhs, Operator::Equal, rhs)
+    }
+
+    pub fn with_operator(lhs: Expression, op: Operator, rhs: Expression) -> Self {
+        Self { lhs, op, rhs }
+    }
+
+    /// Create a term that is satisfied when `lhs` equals `rhs`.
+    pub fn eq(lhs: Expression, rhs: Expression) -> Self {
+        Self::with_operator(lhs, Operator::Equal, rhs)
+    }
+
+    /// Create a term that is satisfied when `lhs` is less than `rhs`.
+    pub fn lt(lhs: Expression, rhs: Expression) -> Self {
+        Self::with_operator(lhs, Operator::LessThan, rhs)
+    }
+
+    /// Create a term that is satisfied when `lhs` is greater than `rhs`.
+    pub fn gt(lhs: Expression, rhs: Expression) -> Self {
+        Self::with_operator(lhs, Operator::GreaterThan, rhs)
+    }
+
+    /// Create a term that is satisfied when `lhs` is less than or equal to `rhs`.
+    pub fn le(lhs: Expression, rhs: Expression) -> Self {
+        Self::with_operator(lhs, Operator::LessEqual, rhs)
+    }
+
+    /// Create a term that is satisfied when `lhs` is greater than or equal to `rhs`.
+    pub fn ge(lhs: Expression, rhs: Expression) -> Self {
+        Self::with_operator(lhs, Operator::GreaterEqual, rhs)
+    }
+
+    /// Create a term that is satisfied when `lhs` does not equal `rhs`.
+    pub fn ne(lhs: Expression, rhs: Expression) -> Self {
+        Self::with_operator(lhs, Operator::NotEqual, rhs)
     }
 
     pub fn is_satisfied(&self, scan: &dyn Scannable) -> bool {
         let lhs_val = self.lhs.evaluate(scan);
         let rhs_val = self.rhs.evaluate(scan);
-        lhs_val == rhs_val
+        match self.op {
+            Operator::Equal => lhs_val == rhs_val,
+            Operator::LessThan => lhs_val < rhs_val,
+            Operator::GreaterThan => lhs_val > rhs_val,
+            Operator::LessEqual => lhs_val <= rhs_val,
+            Operator::GreaterEqual => lhs_val >= rhs_val,
+            Operator::NotEqual => lhs_val != rhs_val,
+        }
     }
 
     pub fn applies_to(&self, schema: &Schema) -> bool {
         self.lhs.applies_to(schema) && self.rhs.applies_to(schema)
     }
 
+    /// Verify that both sides of the term exist in a schema and have comparable types.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema used to resolve field names.
+    pub fn type_check(&self, schema: &Schema) -> Result<(), String> {
+        let lhs_type = self.lhs.field_type(schema)?;
+        let rhs_type = self.rhs.field_type(schema)?;
+        if lhs_type != rhs_type {
+            return Err(format!(
+                "cannot compare '{}' with '{}': mismatched types",
+                self.lhs, self.rhs
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Convert a varchar constant compared with an integer field into an integer constant, so
+    /// that e.g. `grad_year = '2020'` compares integers. Returns an error if the constant is not
+    /// an integer. Fields that are not in the schema are left for a later call to resolve.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema used to resolve field names.
+    pub fn coerce(&mut self, schema: &Schema) -> Result<(), String> {
+        if self.lhs.field_type(schema) == Ok(0) {
+            Self::coerce_to_int(&self.lhs, &mut self.rhs)?;
+        }
+        if self.rhs.field_type(schema) == Ok(0) {
+            Self::coerce_to_int(&self.rhs, &mut self.lhs)?;
+        }
+
+        Ok(())
+    }
+
+    // Convert a varchar constant compared with an integer field into an integer constant.
+    fn coerce_to_int(field: &Expression, constant: &mut Expression) -> Result<(), String> {
+        let Expression::Constant(Value::Varchar(s)) = &*constant else {
+            return Ok(());
+        };
+        let val = s.parse::<i32>().map_err(|_| {
+            format!(
+                "cannot compare '{}' with '{}': '{}' is not an integer",
+                field, constant, s
+            )
+        })?;
+        *constant = Expression::Constant(Value::Int(val));
+
+        Ok(())
+    }
+
+    /// Whether no record can satisfy the term, given the range of values of each integer field.
+    /// Only terms comparing an integer field with a constant are considered.
+    ///
+    /// # Arguments
+    ///
+    /// * `field_range` - Gets the smallest and largest values of a field, if known.
+    pub fn is_unsatisfiable(&self, field_range: &dyn Fn(&str) -> Option<(i32, i32)>) -> bool {
+        // Normalize the term to `field op val`
+        let (field_name, op, val) = match (&self.lhs, &self.rhs) {
+            (Expression::Field(f), Expression::Constant(Value::Int(v))) => (f, self.op, *v),
+            (Expression::Constant(Value::Int(v)), Expression::Field(f)) => {
+                (f, self.op.flipped(), *v)
+            }
+            _ => return false,
+        };
+
+        let Some((min, max)) = field_range(field_name) else {
+            return false;
+        };
+        match op {
+            Operator::Equal => val < min || val > max,
+            Operator::LessThan => min >= val,
+            Operator::GreaterThan => max <= val,
+            Operator::LessEqual => min > val,
+            Operator::GreaterEqual => max < val,
+            Operator::NotEqual => min == val && max == val,
+        }
+    }
+
     // TODO
     //pub fn reduction_factor(&self, plan: Plan) -> i32 {
     //}
 
-    // TODO
-    //pub fn equates_with_constant(&self, field_name: &str) -> Optional<Constant> {}
+    /// Get the constant that the term equates a field with, if the term has the form
+    /// `field = constant` or `constant = field`.
+    ///
+    /// # Arguments
+    ///
+    /// * `field_name` - The name of the field.
+    pub fn equates_with_constant(&self, field_name: &str) -> Option<Value> {
+        match (&self.lhs, self.op, &self.rhs) {
+            (Expression::Field(f), Operator::Equal, Expression::Constant(v))
+            | (Expression::Constant(v), Operator::Equal, Expression::Field(f))
+                if f == field_name =>
+            {
+                Some(v.clone())
+            }
+            _ => None,
+        }
+    }
 
     // TODO
     //pub fn equates_with_field(&self, field_name: &str) -> Optional<String> {}
@@ -38,6 +214,6 @@ impl Term {
 
 impl Display for Term {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} = {}", self.lhs, self.rhs)
+        write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
     }
 }
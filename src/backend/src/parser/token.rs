@@ -18,18 +18,39 @@ pub enum Token {
     Splat,
     ForwardSlash,
     Equal,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    NotEqual,
 
     // Keywords
+    Add,
+    Alter,
     And,
     As,
+    Asc,
+    By,
+    Column,
     Create,
     Delete,
+    Desc,
+    Drop,
+    Exists,
     From,
+    Group,
+    Having,
+    If,
     Index,
     Insert,
     Int,
     Into,
+    Materialized,
+    Not,
     On,
+    Or,
+    Order,
+    Refresh,
     Select,
     Set,
     Table,
@@ -0,0 +1,300 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    block_id::BlockId,
+    index::index::Index,
+    metadata::metadata_manager::MetadataManager,
+    parser::parser::{FieldDefinition, FieldType},
+    record_page::RecordPage,
+    scan::{
+        row::{map_to_row, row_to_map},
+        scan::{Scannable, UpdateScannable},
+    },
+    table_scan::TableScan,
+    transaction::Tx,
+};
+
+use super::update_planner::RowCount;
+
+/// Add a column to a table, returning the number of records rewritten.
+///
+/// Records are stored in fixed-size slots, so adding a field changes the slot size of the table.
+/// Every record is read with the old layout, the table's blocks are reformatted, and the records
+/// are written back with the new layout. The new field holds its default value (0 or an empty
+/// string) in every existing record. Records move to new slots, so the table's indexes are
+/// updated to point at them.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager holding the table's catalog entries.
+/// * `tblname` - The name of the table.
+/// * `field_def` - The definition of the new field.
+/// * `tx` - The transaction that the change will run inside of.
+pub fn add_column(
+    metadata_mgr: &MetadataManager,
+    tblname: &str,
+    field_def: &FieldDefinition,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    let old_layout = metadata_mgr
+        .get_table_layout(tblname, tx)
+        .ok_or_else(|| format!("table '{}' does not exist", tblname))?;
+
+    let mut records = vec![];
+    {
+        let mut scan = TableScan::new(tx.clone(), old_layout.clone(), tblname);
+        while scan.next() {
+            let row = row_to_map(&scan, old_layout.schema()).map_err(|e| format!("{:?}", e))?;
+            records.push((scan.get_rid(), row));
+        }
+        scan.close();
+    }
+
+    let (field_type, length) = match field_def.1 {
+        FieldType::Int => (0, 0),
+        FieldType::Varchar(len) => (1, len as u64),
+    };
+    let new_layout = metadata_mgr.add_field(tblname, &field_def.0, field_type, length, tx)?;
+
+    let num_blocks = tx
+        .lock()
+        .unwrap()
+        .size(tblname)
+        .map_err(|e| format!("{:?}", e))?;
+    for blk_num in 0..num_blocks {
+        let blk = BlockId::new(tblname, blk_num);
+        let mut page = RecordPage::try_new(tx.clone(), blk.clone(), new_layout.clone())
+            .map_err(|e| format!("{:?}", e))?;
+        let reformatted = page.reformat().map_err(|e| format!("{:?}", e));
+        tx.lock().unwrap().unpin(&blk);
+        reformatted?;
+    }
+
+    let mut new_rids = vec![];
+    {
+        let mut scan = TableScan::new(tx.clone(), new_layout, tblname);
+        for (_, row) in &records {
+            scan.insert();
+            map_to_row(&mut scan, row).map_err(|e| format!("{:?}", e))?;
+            new_rids.push(scan.get_rid());
+        }
+        scan.close();
+    }
+
+    // Remove every old entry before adding the new ones, since a record's new RID may be the old
+    // RID of another record
+    for (field_name, ii) in metadata_mgr.get_index_info(tblname, tx.clone()) {
+        let mut idx = ii.open();
+        for (rid, row) in &records {
+            idx.delete(&row[&field_name], rid);
+        }
+        for ((_, row), rid) in records.iter().zip(new_rids.iter()) {
+            idx.insert(&row[&field_name], rid.clone());
+        }
+        idx.close();
+    }
+
+    Ok(records.len() as RowCount)
+}
+
+/// Drop a column from a table. Records are not rewritten: the column's bytes stay in each slot,
+/// but the column is no longer visible to scans. Returns an error if an index depends on the
+/// column.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager holding the table's catalog entries.
+/// * `tblname` - The name of the table.
+/// * `field_name` - The name of the column to drop.
+/// * `tx` - The transaction that the change will run inside of.
+pub fn drop_column(
+    metadata_mgr: &MetadataManager,
+    tblname: &str,
+    field_name: &str,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    metadata_mgr.drop_field(tblname, field_name, tx)?;
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        db::DbError,
+        parser::{
+            constant::Value,
+            parser::{parse, AlterNode, RootNode},
+        },
+        planning::{index_select_plan::IndexSelectPlan, plan::Plan, table_plan::TablePlan},
+        scan::scan::Scannable,
+        tests::test_utils::{create_default_tables, default_test_db},
+    };
+
+    use super::{add_column, drop_column};
+
+    #[test]
+    fn test_add_column() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let Ok(RootNode::Alter(AlterNode::AddColumn(tblname, field_def))) =
+            parse("ALTER TABLE student ADD COLUMN gpa int")
+        else {
+            panic!("failed to parse ALTER TABLE statement");
+        };
+        let rows = add_column(&mm.read().unwrap(), &tblname, &field_def, &tx).unwrap();
+        assert_eq!(rows, 9);
+        assert_eq!(
+            add_column(&mm.read().unwrap(), &tblname, &field_def, &tx),
+            Err("field 'gpa' already exists in table 'student'".to_string())
+        );
+        tx.lock().unwrap().commit().unwrap();
+
+        // Existing records keep their values and read the new column as its default
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mut plan = TablePlan::new(tx.clone(), "student", &mut mm.write().unwrap());
+        assert!(plan.schema().has_field("gpa"));
+        let mut scan = plan.open();
+        let mut records = vec![];
+        while scan.next() {
+            records.push((
+                scan.get_int("sid").unwrap(),
+                scan.get_string("sname").unwrap(),
+                scan.get_int("gpa").unwrap(),
+            ));
+        }
+        scan.close();
+        records.sort();
+        assert_eq!(records.len(), 9);
+        assert_eq!(records[3], (4, "sue".to_string(), 0));
+        assert!(records.iter().all(|r| r.2 == 0));
+
+        // The index on sid points at the rewritten records
+        let ii = mm
+            .read()
+            .unwrap()
+            .get_index_info("student", tx.clone())
+            .remove("sid")
+            .unwrap();
+        let table_plan = TablePlan::new(tx.clone(), "student", &mut mm.write().unwrap());
+        let mut plan = IndexSelectPlan::new(Box::new(table_plan), ii, Value::Int(4));
+        let mut scan = plan.open();
+        scan.before_first();
+        assert!(scan.next());
+        assert_eq!(scan.get_string("sname").unwrap(), "sue");
+        assert_eq!(scan.get_int("gpa").unwrap(), 0);
+        assert!(!scan.next());
+        scan.close();
+    }
+
+    #[test]
+    fn test_add_column_rollback() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let Ok(RootNode::Alter(AlterNode::AddColumn(tblname, field_def))) =
+            parse("ALTER TABLE dept ADD COLUMN building varchar(10)")
+        else {
+            panic!("failed to parse ALTER TABLE statement");
+        };
+        assert_eq!(
+            add_column(&mm.read().unwrap(), &tblname, &field_def, &tx),
+            Ok(3)
+        );
+        tx.lock().unwrap().rollback().unwrap();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mut plan = TablePlan::new(tx.clone(), "dept", &mut mm.write().unwrap());
+        assert!(!plan.schema().has_field("building"));
+        let mut scan = plan.open();
+        let mut records = vec![];
+        while scan.next() {
+            records.push((
+                scan.get_int("did").unwrap(),
+                scan.get_string("dname").unwrap(),
+            ));
+        }
+        scan.close();
+        assert_eq!(
+            records,
+            vec![
+                (10, "compsci".to_string()),
+                (20, "math".to_string()),
+                (30, "drama".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drop_column() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(
+            drop_column(&mm.read().unwrap(), "student", "grad_year", &tx),
+            Ok(0)
+        );
+        assert_eq!(
+            drop_column(&mm.read().unwrap(), "student", "grad_year", &tx),
+            Err("field 'grad_year' does not exist in table 'student'".to_string())
+        );
+        assert_eq!(
+            drop_column(&mm.read().unwrap(), "student", "sid", &tx),
+            Err("cannot drop field 'sid': index 'student-idx' depends on it".to_string())
+        );
+        tx.lock().unwrap().commit().unwrap();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = mm.read().unwrap().get_table_layout("student", &tx).unwrap();
+        assert_eq!(layout.schema().fields(), vec!["sid", "sname", "major_id"]);
+        assert_eq!(
+            db.validate("SELECT grad_year FROM student", &tx),
+            Err(DbError::Plan(
+                "field 'grad_year' does not exist".to_string()
+            ))
+        );
+
+        // The remaining fields are read from their original offsets
+        let mut plan = TablePlan::new(tx.clone(), "student", &mut mm.write().unwrap());
+        let mut scan = plan.open();
+        let mut records = vec![];
+        while scan.next() {
+            records.push((
+                scan.get_int("sid").unwrap(),
+                scan.get_string("sname").unwrap(),
+                scan.get_int("major_id").unwrap(),
+            ));
+        }
+        scan.close();
+        records.sort();
+        assert_eq!(records.len(), 9);
+        assert_eq!(records[3], (4, "sue".to_string(), 20));
+
+        // Adding the column back rewrites the records, so it reads as its default
+        let Ok(RootNode::Alter(AlterNode::AddColumn(tblname, field_def))) =
+            parse("ALTER TABLE student ADD COLUMN grad_year int")
+        else {
+            panic!("failed to parse ALTER TABLE statement");
+        };
+        add_column(&mm.read().unwrap(), &tblname, &field_def, &tx).unwrap();
+        let mut plan = TablePlan::new(tx.clone(), "student", &mut mm.write().unwrap());
+        let mut scan = plan.open();
+        while scan.next() {
+            assert_eq!(scan.get_int("grad_year").unwrap(), 0);
+        }
+        scan.close();
+    }
+}
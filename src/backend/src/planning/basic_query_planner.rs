@@ -3,19 +3,34 @@ use std::sync::{Arc, Mutex, RwLock};
 use crate::{
     metadata::metadata_manager::MetadataManager,
     parser::{
+        constant::Value,
         lexer::Lexer,
-        parser::{Parser, RootNode, SelectField, SelectNode},
+        parser::{
+            AggregateField, AggregateFn, Parser, RootNode, SelectField, SelectNode, SortField,
+            SortOrder,
+        },
     },
     schema::Schema,
     transaction::Tx,
 };
 
 use super::{
-    plan::Plan, product_plan::ProductPlan, project_plan::ProjectPlan, query_planner::QueryPlanner,
-    select_plan::SelectPlan, table_plan::TablePlan,
+    constant_plan::ConstantPlan,
+    empty_plan::EmptyPlan,
+    group_by_plan::{check_grouped_fields, GroupByPlan},
+    index_min_max_plan::IndexMinMaxPlan,
+    index_ordered_plan::IndexOrderedPlan,
+    index_select_plan::IndexSelectPlan,
+    plan::Plan,
+    product_plan::ProductPlan,
+    project_plan::ProjectPlan,
+    query_planner::QueryPlanner,
+    select_plan::SelectPlan,
+    sort_plan::SortPlan,
+    table_plan::TablePlan,
 };
 
-struct BasicQueryPlanner {
+pub struct BasicQueryPlanner {
     metadata_mgr: Arc<RwLock<MetadataManager>>,
 }
 
@@ -29,22 +44,52 @@ impl QueryPlanner for BasicQueryPlanner {
     //fn expand_select_star(&self, )
 
     fn create_plan(&self, data: &SelectNode, tx: Arc<Mutex<Tx>>) -> Result<Box<dyn Plan>, String> {
-        let mut plans = vec![];
+        if data.having.is_some() && !data.is_aggregate() {
+            return Err("HAVING requires GROUP BY or an aggregate".to_string());
+        }
+        if data.is_aggregate() {
+            check_grouped_fields(data)?;
+        }
+
+        // Constants in the predicate are coerced to the types of the fields they are compared
+        // with as each table's schema becomes known
+        let mut predicate = data.predicate.clone();
+        let mut plans: Vec<Box<dyn Plan>> = vec![];
+        let mut ordered = false;
+        let mut aggregated = false;
+
+        // A query without a FROM clause selects a single record of constants
+        if data.tables.is_empty() {
+            let plan = ConstantPlan::new(self.constant_fields(&data.fields)?);
+            if let Some(pred) = &mut predicate {
+                pred.coerce(plan.schema())?;
+            }
+            plans.push(Box::new(plan));
+        }
         for tblname in &data.tables {
-            let view_def = self
-                .metadata_mgr
-                .read()
-                .unwrap()
-                .get_view_def(&tblname, &tx);
+            let (view_def, materialized) = {
+                let mm = self.metadata_mgr.read().unwrap();
+                (
+                    mm.get_view_def(tblname, &tx),
+                    mm.is_materialized_view(tblname, &tx),
+                )
+            };
 
-            // Check whether the table name matches a view definition
+            // Check whether the table name matches a view definition. Materialized views are read
+            // from the table holding their records.
             let plan = match view_def {
-                Some(def) => {
+                Some(def) if !materialized => {
                     let lexer = Lexer::new(&def);
                     let mut parser = Parser::new(lexer);
                     let ast = parser.parse()?;
                     match ast {
-                        RootNode::Select(node) => self.create_plan(&node, tx.clone())?,
+                        RootNode::Select(node) => {
+                            let plan = self.create_plan(&node, tx.clone())?;
+                            if let Some(pred) = &mut predicate {
+                                pred.coerce(plan.schema())?;
+                            }
+                            plan
+                        }
                         _ => {
                             return Err(format!(
                             "did not find SELECT query statement in view defined with name '{}'",
@@ -52,9 +97,100 @@ impl QueryPlanner for BasicQueryPlanner {
                         }
                     }
                 }
-                None => {
+                _ => {
                     let mut locked_mgr = self.metadata_mgr.write().unwrap();
-                    Box::new(TablePlan::new(tx.clone(), tblname, &mut locked_mgr))
+                    let table_plan = TablePlan::new(tx.clone(), tblname, &mut locked_mgr);
+                    if let Some(pred) = &mut predicate {
+                        pred.coerce(table_plan.schema())?;
+                    }
+
+                    // A table whose statistics rule out every record need not be read at all, and
+                    // the query's empty result need not be sorted
+                    if predicate.as_ref().is_some_and(|p| table_plan.excludes(p)) {
+                        ordered = true;
+                        plans.push(Box::new(EmptyPlan::new(table_plan.schema().clone())));
+                        continue;
+                    }
+                    // An equality on an indexed field is looked up in the index, leaving the rest of
+                    // the predicate to be applied to the records found. The index does not know
+                    // which versions of a record are visible, so versioned tables are scanned.
+                    // Each record found may be on a different block, read out of order, so an
+                    // index expected to find more records than the table has blocks is no
+                    // cheaper than scanning the table.
+                    let mut indexes = locked_mgr.get_index_info(tblname, tx.clone());
+                    let lookup = predicate
+                        .as_ref()
+                        .filter(|_| !table_plan.layout().is_versioned())
+                        .and_then(|pred| {
+                            table_plan
+                                .schema()
+                                .fields()
+                                .into_iter()
+                                .find_map(|field_name| {
+                                    let (term, residual) = pred.split(&field_name)?;
+                                    indexes.get(&field_name).filter(|ii| {
+                                        ii.records_outputs() <= table_plan.blocks_accessed()
+                                    })?;
+                                    Some((field_name, term, residual))
+                                })
+                        });
+                    if let Some((field_name, term, residual)) = lookup {
+                        let ii = indexes.remove(&field_name).expect("index was found above");
+                        let val = term
+                            .equates_with_constant(ii.field_name())
+                            .expect("split term equates the indexed field with a constant");
+                        predicate = residual;
+                        plans.push(Box::new(IndexSelectPlan::new(
+                            Box::new(table_plan),
+                            ii,
+                            val,
+                        )));
+                        continue;
+                    }
+                    // A lone MIN or MAX of an indexed field over a whole table is the first or
+                    // last key of the index, so the table need not be read. As above, versioned
+                    // tables are scanned.
+                    let min_max = match (data.tables.len(), data.aggregates().as_slice()) {
+                        (1, [agg @ AggregateField(AggregateFn::Min | AggregateFn::Max, field)])
+                            if predicate.is_none()
+                                && data.group_by.is_empty()
+                                && !table_plan.layout().is_versioned() =>
+                        {
+                            indexes.remove(field).map(|ii| (agg.clone(), ii))
+                        }
+                        _ => None,
+                    };
+                    if let Some((agg, ii)) = min_max {
+                        aggregated = true;
+                        plans.push(Box::new(IndexMinMaxPlan::new(
+                            Box::new(table_plan),
+                            ii,
+                            agg,
+                        )?));
+                        continue;
+                    }
+                    let versioned = table_plan.layout().is_versioned();
+                    let plan: Box<dyn Plan> = Box::new(table_plan);
+
+                    // A lone table with an index on the only ORDER BY field can be read in
+                    // index order instead of being sorted, if that field is in ascending order.
+                    // The groups of an aggregate query are sorted after they are formed. As
+                    // above, versioned tables are scanned.
+                    let index_info = match (data.tables.len(), data.order_by.as_slice()) {
+                        (1, [SortField(field_name, SortOrder::Ascending)])
+                            if !data.is_aggregate() && !versioned =>
+                        {
+                            indexes.remove(field_name)
+                        }
+                        _ => None,
+                    };
+                    match index_info {
+                        Some(ii) => {
+                            ordered = true;
+                            Box::new(IndexOrderedPlan::new(plan, ii))
+                        }
+                        None => plan,
+                    }
                 }
             };
 
@@ -70,30 +206,99 @@ impl QueryPlanner for BasicQueryPlanner {
             Box::new(ProductPlan::new(acc, next))
         });
 
-        if let Some(pred) = &data.predicate {
-            plan = Box::new(SelectPlan::new(plan, pred.clone()));
+        if let Some(pred) = predicate {
+            plan = SelectPlan::select(plan, pred);
         }
 
-        let fields = self.prepare_select_fields(plan.schema(), &data.fields);
+        // The selected records are grouped before HAVING filters the groups
+        if data.is_aggregate() && !aggregated {
+            plan = Box::new(GroupByPlan::new(
+                tx.clone(),
+                plan,
+                data.group_by.clone(),
+                data.aggregates(),
+            )?);
+        }
+        if let Some(having) = &data.having {
+            let mut having = having.clone();
+            having.coerce(plan.schema())?;
+            plan = SelectPlan::select(plan, having);
+        }
+
+        if !ordered && !data.order_by.is_empty() {
+            plan = Box::new(SortPlan::new(tx.clone(), plan, data.order_by.clone()));
+        }
+
+        let fields = self.prepare_select_fields(plan.schema(), &data.fields)?;
         Ok(Box::new(ProjectPlan::new(plan, fields)))
     }
 }
 
 impl BasicQueryPlanner {
-    /// Expands '*' into all fields in an underlying plan
+    /// Expands '*' into all fields in an underlying plan, in the order of its schema. Returns an
+    /// error if a selected field does not exist in the plan, or if a field is selected more than
+    /// once, e.g. both by name and by '*'.
     fn prepare_select_fields(
         &self,
         plan_schema: &Schema,
         select_fields: &Vec<SelectField>,
-    ) -> Vec<String> {
-        //let plan_schema = self.plan.schema();
-        select_fields
-            .iter()
-            .flat_map(|f| match f {
-                SelectField::Star => plan_schema.fields(),
-                SelectField::FieldName(name) => vec![name.to_string()],
-            })
-            .collect()
+    ) -> Result<Vec<String>, String> {
+        let mut fields = vec![];
+        for f in select_fields {
+            match f {
+                SelectField::Star => fields.extend(plan_schema.fields()),
+                SelectField::FieldName(name) => fields.push(name.to_string()),
+                SelectField::Aggregate(agg) => fields.push(agg.to_string()),
+                SelectField::Constant(val) if plan_schema.has_field(&val.to_string()) => {
+                    fields.push(val.to_string())
+                }
+                SelectField::Constant(val) => {
+                    return Err(format!(
+                        "constant {} can only be selected by a query without a FROM clause",
+                        val
+                    ))
+                }
+            }
+        }
+
+        for (i, name) in fields.iter().enumerate() {
+            if !plan_schema.has_field(name) {
+                return Err(format!("field '{}' does not exist", name));
+            }
+            if fields[..i].contains(name) {
+                return Err(format!("field '{}' is selected more than once", name));
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Names each constant selected by a query without a FROM clause after its value.
+    fn constant_fields(
+        &self,
+        select_fields: &[SelectField],
+    ) -> Result<Vec<(String, Value)>, String> {
+        let mut fields: Vec<(String, Value)> = vec![];
+        for f in select_fields {
+            match f {
+                SelectField::Constant(val) => {
+                    let name = val.to_string();
+                    if fields.iter().any(|(n, _)| *n == name) {
+                        return Err(format!("constant {} is selected more than once", name));
+                    }
+                    fields.push((name, val.clone()));
+                }
+                SelectField::FieldName(name) => {
+                    return Err(format!("field '{}' does not exist", name))
+                }
+                SelectField::Star => return Err("SELECT * requires a FROM clause".to_string()),
+                SelectField::Aggregate(agg) => {
+                    return Err(format!("{} requires a FROM clause", agg))
+                }
+            }
+        }
+
+        Ok(fields)
     }
 }
 
@@ -104,13 +309,23 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::{
+        db::SimpleDB,
+        index::index::Index,
+        make_schema,
         parser::{
+            constant::Value,
             lexer::Lexer,
             parser::{Parser, RootNode, SelectNode},
         },
-        planning::query_planner::QueryPlanner,
-        scan::scan::Scannable,
-        tests::test_utils::{create_default_tables, default_test_db, test_db},
+        planning::{
+            materialized_view::create_materialized_view, plan::Plan, query_planner::QueryPlanner,
+        },
+        scan::scan::{ScanError, Scannable, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::{
+            assert_rescannable, create_default_tables, default_test_db, test_db, BlockCounter,
+        },
+        transaction::Tx,
     };
 
     use super::BasicQueryPlanner;
@@ -140,4 +355,682 @@ mod tests {
             panic!("failed to parse select statement");
         }
     }
+
+    fn plan_select(db: &SimpleDB, query: &str, tx: Arc<Mutex<Tx>>) -> Box<dyn Plan> {
+        let planner = BasicQueryPlanner::new(db.metadata_manager());
+        match Parser::new(Lexer::new(query)).parse().unwrap() {
+            RootNode::Select(sel) => planner.create_plan(&sel, tx).unwrap(),
+            _ => panic!("failed to parse select statement"),
+        }
+    }
+
+    #[test]
+    fn test_order_by_indexed_field_uses_index() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(&db, "SELECT sid FROM student ORDER BY sid", tx.clone());
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], IndexOrdered(sid, Table(student)))"
+        );
+
+        let mut scan = plan.open();
+        let mut sids = vec![];
+        while scan.next() {
+            sids.push(scan.get_int("sid").unwrap());
+        }
+        assert_eq!(sids, (1..=9).collect::<Vec<_>>());
+
+        // The predicate is applied on top of the index order
+        let mut plan = plan_select(
+            &db,
+            "SELECT sid FROM student WHERE grad_year = 2020 ORDER BY sid",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Select(grad_year = 2020, IndexOrdered(sid, Table(student))))"
+        );
+
+        let mut scan = plan.open();
+        let mut sids = vec![];
+        while scan.next() {
+            sids.push(scan.get_int("sid").unwrap());
+        }
+        assert_eq!(sids, vec![2, 5, 6]);
+    }
+
+    #[test]
+    fn test_order_by_falls_back_to_sort() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(
+            &db,
+            "SELECT sname FROM student ORDER BY grad_year, sname",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project([sname], Sort([grad_year, sname], Table(student)))"
+        );
+
+        let mut scan = plan.open();
+        let mut names = vec![];
+        while scan.next() {
+            names.push(scan.get_string("sname").unwrap());
+        }
+        assert_eq!(
+            names,
+            vec!["pat", "amy", "bob", "kim", "art", "joe", "lee", "max", "sue"]
+        );
+
+        // An index is only used when its field is the sole sort field
+        let plan = plan_select(&db, "SELECT sid FROM student ORDER BY sid, sname", tx);
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Sort([sid, sname], Table(student)))"
+        );
+    }
+
+    #[test]
+    fn test_order_by_descending() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(
+            &db,
+            "SELECT sname, grad_year FROM student ORDER BY grad_year DESC, sname ASC",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project([sname, grad_year], Sort([grad_year DESC, sname], Table(student)))"
+        );
+
+        let mut scan = plan.open();
+        let mut rows = vec![];
+        while scan.next() {
+            rows.push((
+                scan.get_int("grad_year").unwrap(),
+                scan.get_string("sname").unwrap(),
+            ));
+        }
+        let expected = [
+            (2022, "max"),
+            (2022, "sue"),
+            (2021, "art"),
+            (2021, "joe"),
+            (2021, "lee"),
+            (2020, "amy"),
+            (2020, "bob"),
+            (2020, "kim"),
+            (2019, "pat"),
+        ]
+        .map(|(year, name)| (year, name.to_string()));
+        assert_eq!(rows, expected);
+
+        // The index on sid only produces an ascending order
+        let mut plan = plan_select(&db, "SELECT sid FROM student ORDER BY sid DESC", tx);
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Sort([sid DESC], Table(student)))"
+        );
+        let mut scan = plan.open();
+        let mut sids = vec![];
+        while scan.next() {
+            sids.push(scan.get_int("sid").unwrap());
+        }
+        assert_eq!(sids, (1..=9).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(
+            &db,
+            "SELECT major_id, count(sid), max(grad_year) FROM student GROUP BY major_id",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project([major_id, count(sid), max(grad_year)], GroupBy([major_id], [count(sid), max(grad_year)], Sort([major_id], Table(student))))"
+        );
+
+        let mut scan = plan.open();
+        let rows = assert_rescannable(&mut scan, &["major_id", "count(sid)", "max(grad_year)"]);
+        let expected = [(10, 3, 2022), (20, 4, 2022), (30, 2, 2021)]
+            .map(|(major, count, max)| vec![Value::Int(major), Value::Int(count), Value::Int(max)]);
+        assert_eq!(rows, expected);
+
+        // HAVING filters the groups, which can then be ordered by their group fields
+        let mut plan = plan_select(
+            &db,
+            "SELECT major_id, count(sid) FROM student WHERE grad_year > 2019 GROUP BY major_id HAVING count(sid) > 2 ORDER BY major_id DESC",
+            tx.clone(),
+        );
+        let mut scan = plan.open();
+        let rows = assert_rescannable(&mut scan, &["major_id", "count(sid)"]);
+        assert_eq!(
+            rows,
+            [(20, 3), (10, 3)].map(|(major, count)| vec![Value::Int(major), Value::Int(count)])
+        );
+
+        // Without a GROUP BY clause every record is in a single group
+        let mut plan = plan_select(
+            &db,
+            "SELECT count(sid), min(sname), avg(grad_year) FROM student",
+            tx,
+        );
+        let mut scan = plan.open();
+        let rows = assert_rescannable(&mut scan, &["count(sid)", "min(sname)", "avg(grad_year)"]);
+        assert_eq!(
+            rows,
+            [vec![Value::Int(9), Value::from("amy"), Value::Int(2020)]]
+        );
+    }
+
+    #[test]
+    fn test_min_max_of_indexed_field_uses_index() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        for (query, field, expected) in [
+            ("SELECT min(sid) FROM student", "min(sid)", 1),
+            ("SELECT max(sid) FROM student", "max(sid)", 9),
+        ] {
+            let mut plan = plan_select(&db, query, tx.clone());
+            assert_eq!(
+                plan.to_string(),
+                format!("Project([{0}], IndexMinMax({0}, Table(student)))", field)
+            );
+
+            // Only the index is read, one block per level, rather than every student
+            let counter = BlockCounter::new(&db, &tx);
+            let mut scan = plan.open();
+            let rows = assert_rescannable(&mut scan, &[field]);
+            assert_eq!(rows, [vec![Value::Int(expected)]]);
+            assert_eq!(counter.blocks_pinned(), 2);
+        }
+
+        // Other aggregates, and aggregates over some of the records, are computed by grouping
+        for query in [
+            "SELECT min(sid), max(sid) FROM student",
+            "SELECT min(grad_year) FROM student",
+            "SELECT max(sid) FROM student WHERE grad_year = 2020",
+            "SELECT major_id, max(sid) FROM student GROUP BY major_id",
+        ] {
+            let plan = plan_select(&db, query, tx.clone());
+            assert!(plan.to_string().contains("GroupBy("), "{}", plan);
+        }
+    }
+
+    #[test]
+    fn test_materialized_view_reads_stored_records() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mm = db.metadata_manager();
+        mm.read()
+            .unwrap()
+            .create_view("v", "SELECT sid FROM student WHERE major_id = 10", &tx)
+            .unwrap();
+        let select = match Parser::new(Lexer::new("SELECT sid FROM student WHERE major_id = 10"))
+            .parse()
+            .unwrap()
+        {
+            RootNode::Select(sel) => sel,
+            _ => panic!("failed to parse select statement"),
+        };
+        create_materialized_view(&mut mm.write().unwrap(), "mv", &select, &tx).unwrap();
+
+        let plan = plan_select(&db, "SELECT sid FROM v", tx.clone());
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Project([sid], Select(major_id = 10, Table(student))))"
+        );
+
+        let mut plan = plan_select(&db, "SELECT sid FROM mv", tx.clone());
+        assert_eq!(plan.to_string(), "Project([sid], Table(mv))");
+
+        let mut scan = plan.open();
+        let mut sids = vec![];
+        while scan.next() {
+            sids.push(scan.get_int("sid").unwrap());
+        }
+        sids.sort();
+        assert_eq!(sids, vec![1, 3, 9]);
+    }
+
+    #[test]
+    fn test_select_profile() {
+        let temp_dir = tempdir().unwrap();
+        let db = default_test_db(&temp_dir);
+
+        let schema = make_schema! {
+            "id" => i32,
+            "padding" => varchar(100)
+        };
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mm = db.metadata_manager();
+        mm.read().unwrap().create_table("big", &schema, &tx).unwrap();
+        let layout = mm.read().unwrap().get_table_layout("big", &tx).unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout, "big");
+        for i in 0..100 {
+            scan.insert();
+            scan.set_int("id", i);
+            scan.set_string("padding", "x");
+        }
+        scan.close();
+        assert!(tx.lock().unwrap().profile().log_bytes > 0);
+        tx.lock().unwrap().commit().unwrap();
+
+        let num_blocks = db.file_manager().length("big").unwrap();
+        assert!(num_blocks > 1);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mut plan = plan_select(&db, "SELECT id FROM big WHERE id = 50", tx.clone());
+        let mut scan = plan.open();
+        let mut ids = vec![];
+        while scan.next() {
+            ids.push(scan.get_int("id").unwrap());
+        }
+        scan.close();
+        assert_eq!(ids, vec![50]);
+
+        let profile = tx.lock().unwrap().profile();
+        assert!(profile.blocks_pinned >= num_blocks);
+        assert!(profile.slocks >= num_blocks);
+        assert_eq!(profile.log_bytes, 0);
+    }
+
+    #[test]
+    fn test_indexed_equality_uses_index() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let students = |query: &str| {
+            let mut scan = plan_select(&db, query, tx.clone()).open();
+            let mut sids = vec![];
+            while scan.next() {
+                sids.push(scan.get_int("sid").unwrap());
+            }
+            sids
+        };
+
+        let plan = plan_select(
+            &db,
+            "SELECT sid FROM student WHERE sid = 4 AND grad_year = 2022",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Select(grad_year = 2022, IndexSelect(sid = 4, Table(student))))"
+        );
+        assert_eq!(
+            students("SELECT sid FROM student WHERE sid = 4 AND grad_year = 2022"),
+            vec![4]
+        );
+        assert_eq!(
+            students("SELECT sid FROM student WHERE grad_year = 2021 AND sid = 4"),
+            Vec::<i32>::new()
+        );
+
+        let plan = plan_select(&db, "SELECT sid FROM student WHERE 7 = sid", tx.clone());
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], IndexSelect(sid = 7, Table(student)))"
+        );
+        assert_eq!(students("SELECT sid FROM student WHERE 7 = sid"), vec![7]);
+    }
+
+    #[test]
+    fn test_index_select_reads_fewer_blocks() {
+        let temp_dir = tempdir().unwrap();
+        let db = default_test_db(&temp_dir);
+
+        let schema = make_schema! {
+            "id" => i32,
+            "grp" => i32,
+            "padding" => varchar(100)
+        };
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mm = db.metadata_manager();
+        mm.read().unwrap().create_table("big", &schema, &tx).unwrap();
+        mm.read()
+            .unwrap()
+            .create_index("big-idx", "big", "id", &tx)
+            .unwrap();
+        let layout = mm.read().unwrap().get_table_layout("big", &tx).unwrap();
+        let mut index = mm
+            .read()
+            .unwrap()
+            .get_index_info("big", tx.clone())
+            .remove("id")
+            .unwrap()
+            .open();
+        let mut scan = TableScan::new(tx.clone(), layout, "big");
+        for i in 0..300 {
+            scan.insert();
+            scan.set_int("id", i);
+            scan.set_int("grp", i % 2);
+            scan.set_string("padding", "x");
+            index.insert(&Value::Int(i), scan.get_rid());
+        }
+        scan.close();
+        index.close();
+        tx.lock().unwrap().commit().unwrap();
+
+        // Count the blocks pinned by a query, which returns the ids it finds
+        let blocks_pinned = |query: &str| {
+            let tx = Arc::new(Mutex::new(db.new_tx()));
+            let mut scan = plan_select(&db, query, tx.clone()).open();
+            let mut ids = vec![];
+            while scan.next() {
+                ids.push(scan.get_int("id").unwrap());
+            }
+            scan.close();
+            let pinned = tx.lock().unwrap().profile().blocks_pinned;
+            tx.lock().unwrap().commit().unwrap();
+            (ids, pinned)
+        };
+
+        let (ids, indexed) = blocks_pinned("SELECT id FROM big WHERE id = 51 AND grp = 1");
+        assert_eq!(ids, vec![51]);
+        // A range on the indexed field is not looked up in the index
+        let (ids, scanned) = blocks_pinned("SELECT id FROM big WHERE id > 50 AND id < 52");
+        assert_eq!(ids, vec![51]);
+        assert!(scanned >= db.file_manager().length("big").unwrap());
+        assert!(indexed < scanned, "{} >= {}", indexed, scanned);
+    }
+
+    #[test]
+    fn test_index_skipped_on_low_cardinality_field() {
+        let temp_dir = tempdir().unwrap();
+        let db = default_test_db(&temp_dir);
+
+        let schema = make_schema! {
+            "id" => i32,
+            "grp" => i32,
+            "padding" => varchar(100)
+        };
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mm = db.metadata_manager();
+        mm.read()
+            .unwrap()
+            .create_table("big", &schema, &tx)
+            .unwrap();
+        for field_name in ["id", "grp"] {
+            mm.read()
+                .unwrap()
+                .create_index(&format!("big-{}", field_name), "big", field_name, &tx)
+                .unwrap();
+        }
+        let layout = mm.read().unwrap().get_table_layout("big", &tx).unwrap();
+        let mut indexes = mm.read().unwrap().get_index_info("big", tx.clone());
+        let mut id_index = indexes.remove("id").unwrap().open();
+        let mut grp_index = indexes.remove("grp").unwrap().open();
+        let mut scan = TableScan::new(tx.clone(), layout, "big");
+        for i in 0..300 {
+            scan.insert();
+            scan.set_int("id", i);
+            scan.set_int("grp", i % 3);
+            scan.set_string("padding", "x");
+            id_index.insert(&Value::Int(i), scan.get_rid());
+            grp_index.insert(&Value::Int(i % 3), scan.get_rid());
+        }
+        scan.close();
+        id_index.close();
+        grp_index.close();
+        mm.read().unwrap().invalidate_stats("big");
+        tx.lock().unwrap().commit().unwrap();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let plan = |query: &str| plan_select(&db, query, tx.clone()).to_string();
+        // A third of the records match, spread over every block of the table
+        assert_eq!(
+            plan("SELECT id FROM big WHERE grp = 1"),
+            "Project([id], Select(grp = 1, Table(big)))"
+        );
+        assert_eq!(
+            plan("SELECT id FROM big WHERE id = 51"),
+            "Project([id], IndexSelect(id = 51, Table(big)))"
+        );
+        // The selective index is used even when the other field is constrained too
+        assert_eq!(
+            plan("SELECT id FROM big WHERE grp = 0 AND id = 51"),
+            "Project([id], Select(grp = 0, IndexSelect(id = 51, Table(big))))"
+        );
+    }
+
+    #[test]
+    fn test_range_outside_statistics_is_pruned() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(&db, "SELECT sid FROM student WHERE sid > 1000", tx.clone());
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Select(sid > 1000, Empty))"
+        );
+        assert_eq!(plan.blocks_accessed(), 0);
+
+        let pinned = tx.lock().unwrap().profile().blocks_pinned;
+        let mut scan = plan.open();
+        assert!(!scan.next());
+        scan.close();
+        assert_eq!(tx.lock().unwrap().profile().blocks_pinned, pinned);
+
+        // Ranges that overlap the statistics are still read
+        for query in [
+            "SELECT sid FROM student WHERE sid > 8",
+            "SELECT sid FROM student WHERE 2 > sid",
+            "SELECT sid FROM student WHERE sid = 9",
+            "SELECT sid FROM student WHERE sid >= 9",
+            "SELECT sid FROM student WHERE 1 >= sid",
+            "SELECT sid FROM student WHERE sid <> 1",
+        ] {
+            let mut scan = plan_select(&db, query, tx.clone()).open();
+            assert!(scan.next(), "{}", query);
+        }
+        for query in [
+            "SELECT sid FROM student WHERE sid < 1",
+            "SELECT sid FROM student WHERE sid <= 0",
+            "SELECT sid FROM student WHERE sid >= 10",
+            "SELECT sid FROM student WHERE 10 <= sid",
+        ] {
+            let plan = plan_select(&db, query, tx.clone());
+            assert_eq!(plan.blocks_accessed(), 0, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_varchar_constant_coerced_to_int() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(
+            &db,
+            "SELECT sid FROM student WHERE grad_year = '2021'",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.to_string(),
+            "Project([sid], Select(grad_year = 2021, Table(student)))"
+        );
+        let mut scan = plan.open();
+        let mut sids = vec![];
+        while scan.next() {
+            sids.push(scan.get_int("sid").unwrap());
+        }
+        assert_eq!(sids, [1, 7, 9]);
+
+        let planner = BasicQueryPlanner::new(db.metadata_manager());
+        let query = "SELECT sid FROM student WHERE grad_year = '20x1'";
+        let RootNode::Select(sel) = Parser::new(Lexer::new(query)).parse().unwrap() else {
+            panic!("failed to parse select statement");
+        };
+        assert_eq!(
+            planner.create_plan(&sel, tx).err(),
+            Some("cannot compare 'grad_year' with ''20x1'': '20x1' is not an integer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_select_star() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(
+            &db,
+            "SELECT * FROM student WHERE grad_year = 2020",
+            tx.clone(),
+        );
+        assert_eq!(
+            plan.schema().fields(),
+            vec!["sid", "sname", "grad_year", "major_id"]
+        );
+        let mut scan = plan.open();
+        let rows = assert_rescannable(&mut scan, &["sid", "sname", "grad_year", "major_id"]);
+        let expected = [(2, "amy", 20), (5, "bob", 30), (6, "kim", 20)].map(|(sid, sname, major)| {
+            vec![
+                Value::Int(sid),
+                Value::from(sname),
+                Value::Int(2020),
+                Value::Int(major),
+            ]
+        });
+        assert_eq!(rows, expected);
+
+        // Every field of every table in the FROM clause is selected
+        let plan = plan_select(&db, "SELECT * FROM dept, course", tx.clone());
+        assert_eq!(
+            plan.schema().fields(),
+            vec!["did", "dname", "cid", "title", "dept_id"]
+        );
+
+        let planner = BasicQueryPlanner::new(db.metadata_manager());
+        for (query, err) in [
+            (
+                "SELECT *, sid FROM student",
+                "field 'sid' is selected more than once",
+            ),
+            ("SELECT *, gpa FROM student", "field 'gpa' does not exist"),
+        ] {
+            let RootNode::Select(sel) = Parser::new(Lexer::new(query)).parse().unwrap() else {
+                panic!("failed to parse select statement");
+            };
+            assert_eq!(
+                planner.create_plan(&sel, tx.clone()).err(),
+                Some(err.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_without_from() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let mut plan = plan_select(&db, "SELECT 3, 'hello', -1", tx.clone());
+        assert_eq!(
+            plan.to_string(),
+            "Project([3, 'hello', -1], Constant(3, 'hello', -1))"
+        );
+        assert_eq!(plan.records_output(), 1);
+        let mut scan = plan.open();
+        assert_eq!(
+            assert_rescannable(&mut scan, &["3", "'hello'", "-1"]),
+            vec![vec![
+                Value::Int(3),
+                Value::Varchar("hello".to_string()),
+                Value::Int(-1)
+            ]]
+        );
+        assert_eq!(scan.get_int("3"), Err(ScanError::NoCurrentRecord));
+
+        let mut plan = plan_select(&db, "SELECT 3 WHERE 1 = 2", tx.clone());
+        assert!(!plan.open().next());
+
+        let planner = BasicQueryPlanner::new(db.metadata_manager());
+        for (query, err) in [
+            ("SELECT 3, 3", "constant 3 is selected more than once"),
+            ("SELECT 3, sid", "field 'sid' does not exist"),
+            (
+                "SELECT sid, 3 FROM student",
+                "constant 3 can only be selected by a query without a FROM clause",
+            ),
+        ] {
+            let RootNode::Select(sel) = Parser::new(Lexer::new(query)).parse().unwrap() else {
+                panic!("failed to parse select statement");
+            };
+            assert_eq!(
+                planner.create_plan(&sel, tx.clone()).err(),
+                Some(err.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let temp_dir = tempdir().unwrap();
+        let mut db = default_test_db(&temp_dir);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        for (query, expected) in [
+            (
+                "SELECT sid FROM student WHERE grad_year > 2020",
+                vec![1, 3, 4, 7, 9],
+            ),
+            (
+                "SELECT sid FROM student WHERE grad_year >= 2021",
+                vec![1, 3, 4, 7, 9],
+            ),
+            ("SELECT sid FROM student WHERE grad_year <= 2019", vec![8]),
+            ("SELECT sid FROM student WHERE sname >= 'm'", vec![3, 4, 8]),
+            ("SELECT sid FROM student WHERE sname < 'b'", vec![2, 7]),
+            (
+                "SELECT sid FROM student WHERE major_id <> 20",
+                vec![1, 3, 5, 7, 9],
+            ),
+            (
+                "SELECT sid FROM student WHERE major_id != 20 AND sid <= 3",
+                vec![1, 3],
+            ),
+        ] {
+            let mut scan = plan_select(&db, query, tx.clone()).open();
+            let records = assert_rescannable(&mut scan, &["sid"]);
+            let expected: Vec<Vec<Value>> = expected
+                .into_iter()
+                .map(|sid| vec![Value::Int(sid)])
+                .collect();
+            assert_eq!(records, expected, "{}", query);
+        }
+    }
 }
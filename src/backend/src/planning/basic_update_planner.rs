@@ -3,7 +3,8 @@ use std::sync::{Arc, Mutex, RwLock};
 use crate::{
     metadata::metadata_manager::MetadataManager,
     parser::parser::{
-        CreateNode, DeleteNode, FieldDefinitions, InsertNode, SelectNode, UpdateNode,
+        AlterNode, CreateNode, DeleteNode, DropNode, FieldDefinitions, InsertNode, SelectNode,
+        TableName, UpdateNode, ViewName,
     },
     planning::table_plan::TablePlan,
     scan::scan::{Scannable, UpdateScannable},
@@ -12,12 +13,17 @@ use crate::{
 };
 
 use super::{
+    alter_table::{add_column, drop_column},
+    create_table_as::create_table_as,
+    drop_table::drop_table,
+    materialized_view::{create_materialized_view, refresh_view},
     plan::Plan,
     select_plan::SelectPlan,
+    truncate_table::truncate_table,
     update_planner::{RowCount, UpdatePlanner},
 };
 
-struct BasicUpdatePlanner {
+pub struct BasicUpdatePlanner {
     metadata_mgr: Arc<RwLock<MetadataManager>>,
 }
 
@@ -28,8 +34,18 @@ impl UpdatePlanner for BasicUpdatePlanner {
         tx: Arc<Mutex<Tx>>,
     ) -> Result<RowCount, String> {
         match create {
-            CreateNode::Table(name, fields) => self.create_table(name, fields, &tx),
+            CreateNode::Table(name, fields, if_not_exists) => {
+                self.create_table(name, fields, *if_not_exists, &tx)
+            }
+            CreateNode::TableAs(name, _) => Err(format!(
+                "table '{}' must be created from the plan of its query",
+                name
+            )),
             CreateNode::View(name, select) => self.create_view(name, select, &tx),
+            CreateNode::MaterializedView(name, select) => {
+                let mut mm = self.metadata_mgr.write().unwrap();
+                create_materialized_view(&mut mm, name, select, &tx)
+            }
             CreateNode::Index(name, tblname, fieldname) => {
                 self.create_index(name, tblname, fieldname)
             }
@@ -41,13 +57,26 @@ impl UpdatePlanner for BasicUpdatePlanner {
         delete: &DeleteNode,
         tx: Arc<Mutex<Tx>>,
     ) -> Result<RowCount, String> {
+        // Without a predicate every record is deleted, so the table can be truncated instead
+        if delete.1.is_none() {
+            let mm = self.metadata_mgr.read().unwrap();
+            if mm
+                .get_table_layout(&delete.0, &tx)
+                .is_some_and(|layout| !layout.is_versioned())
+            {
+                return truncate_table(&mm, &delete.0, &tx);
+            }
+        }
+
         let mut plan: Box<dyn Plan> = {
             let mut lmm = self.metadata_mgr.write().unwrap();
             Box::new(TablePlan::new(tx, &delete.0, &mut lmm))
         };
 
         if let Some(pred) = &delete.1 {
-            plan = Box::new(SelectPlan::new(plan, pred.clone()));
+            let mut pred = pred.clone();
+            pred.coerce(plan.schema())?;
+            plan = Box::new(SelectPlan::new(plan, pred));
         }
 
         let mut scan = plan.open();
@@ -81,8 +110,10 @@ impl UpdatePlanner for BasicUpdatePlanner {
         let mut scan = plan.open();
         scan.insert();
         let field_values = insert.1.iter().zip(insert.2.iter());
+        let mm = self.metadata_mgr.read().unwrap();
         for (field_name, val) in field_values {
             scan.set_val(field_name, val);
+            mm.record_value(&insert.0, field_name, val);
         }
 
         Ok(1)
@@ -99,7 +130,9 @@ impl UpdatePlanner for BasicUpdatePlanner {
 
             if let Some(pred) = &update.where_clause {
                 // Wrap the table plan in a select plan so records can be filtered by the predicate
-                Box::new(SelectPlan::new(table_plan, pred.clone()))
+                let mut pred = pred.clone();
+                pred.coerce(table_plan.schema())?;
+                Box::new(SelectPlan::new(table_plan, pred))
             } else {
                 table_plan
             }
@@ -110,10 +143,55 @@ impl UpdatePlanner for BasicUpdatePlanner {
         while scan.next() {
             let val = update.expr.evaluate(&scan);
             scan.set_val(&update.field, &val);
+            self.metadata_mgr
+                .read()
+                .unwrap()
+                .record_value(&update.id, &update.field, &val);
             count += 1;
         }
         Ok(count)
     }
+
+    fn execute_create_table_as(
+        &mut self,
+        tblname: &TableName,
+        plan: Box<dyn Plan>,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        let mm = self.metadata_mgr.read().unwrap();
+        create_table_as(&mm, tblname, plan, &tx)
+    }
+
+    fn execute_alter(
+        &mut self,
+        alter: &AlterNode,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        match alter {
+            AlterNode::AddColumn(tblname, field_def) => {
+                let mm = self.metadata_mgr.write().unwrap();
+                add_column(&mm, tblname, field_def, &tx)
+            }
+            AlterNode::DropColumn(tblname, field_name) => {
+                let mm = self.metadata_mgr.write().unwrap();
+                drop_column(&mm, tblname, field_name, &tx)
+            }
+        }
+    }
+
+    fn execute_drop(&mut self, drop: &DropNode, tx: Arc<Mutex<Tx>>) -> Result<RowCount, String> {
+        let mm = self.metadata_mgr.write().unwrap();
+        drop_table(&mm, drop, &tx)
+    }
+
+    fn execute_refresh(
+        &mut self,
+        view_name: &ViewName,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        let mut mm = self.metadata_mgr.write().unwrap();
+        refresh_view(&mut mm, view_name, &tx)
+    }
 }
 
 impl BasicUpdatePlanner {
@@ -138,7 +216,7 @@ impl BasicUpdatePlanner {
     ) -> Result<RowCount, String> {
         let lock = self.metadata_mgr.write().unwrap();
         let view_source = format!("{}", select);
-        lock.create_view(name, &view_source, tx);
+        lock.create_view(name, &view_source, tx)?;
 
         Ok(0)
     }
@@ -147,11 +225,15 @@ impl BasicUpdatePlanner {
         &self,
         name: &str,
         fields: &FieldDefinitions,
+        if_not_exists: bool,
         tx: &Arc<Mutex<Tx>>,
     ) -> Result<RowCount, String> {
         let schema = Schema::from_field_defs(fields);
         let mm = self.metadata_mgr.write().unwrap();
-        mm.create_table(name, &schema, tx);
+        if if_not_exists && mm.get_table_layout(name, tx).is_some() {
+            return Ok(0);
+        }
+        mm.create_table(name, &schema, tx)?;
         Ok(0)
     }
 }
@@ -170,6 +252,7 @@ mod tests {
             parser::{parse, CreateNode, DeleteNode, FieldDefinition, FieldType, Parser, RootNode},
         },
         planning::update_planner::UpdatePlanner,
+        scan::scan::Scannable,
         table_scan::TableScan,
         tests::test_utils::{create_default_tables, test_db},
     };
@@ -195,11 +278,17 @@ mod tests {
             // Assert that 1 row was inserted
             assert_eq!(count, 1);
 
-            let mut scan = TableScan::new(
-                tx.clone(),
-                mm.read().unwrap().get_table_layout("student", &tx).unwrap(),
-                "student",
-            );
+            // The ranges in the table's statistics include the inserted values
+            let layout = mm.read().unwrap().get_table_layout("student", &tx).unwrap();
+            let stats = mm
+                .write()
+                .unwrap()
+                .get_stat_info("student", &layout, &tx)
+                .unwrap();
+            assert_eq!(stats.field_range("sid"), Some((1, 10)));
+            assert_eq!(stats.field_range("grad_year"), Some((2019, 2025)));
+
+            let mut scan = TableScan::new(tx.clone(), layout, "student");
 
             assert_table_scan_results![
                 scan,
@@ -219,6 +308,42 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_plan_insert_escaped_varchar() {
+        let testdir = tempdir().unwrap();
+        let mut db = test_db(&testdir);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+        let mut planner = BasicUpdatePlanner::new(mm.clone());
+
+        if let Ok(RootNode::Insert(insert)) = parse(
+            "INSERT INTO student (sid, sname, grad_year, major_id) VALUES (10, 'O''Brien', 2025, 30)",
+        ) {
+            let tx = Arc::new(Mutex::new(db.new_tx()));
+            let count = planner
+                .execute_insert(&insert, tx.clone())
+                .expect("failed to execute insert statement");
+            assert_eq!(count, 1);
+
+            let mut scan = TableScan::new(
+                tx.clone(),
+                mm.read().unwrap().get_table_layout("student", &tx).unwrap(),
+                "student",
+            );
+
+            // The escaped quote is stored as a single apostrophe
+            let mut sname = None;
+            while scan.next() {
+                if scan.get_int("sid") == Ok(10) {
+                    sname = scan.get_string("sname").ok();
+                }
+            }
+            assert_eq!(sname, Some("O'Brien".to_string()));
+        } else {
+            panic!("failed to parse insert statement");
+        }
+    }
+
     #[test]
     fn test_plan_delete_all_statement() {
         let testdir = tempdir().unwrap();
@@ -346,7 +471,7 @@ mod tests {
                 planner.execute_create(&create, tx.clone());
             }
 
-            tx.lock().unwrap().commit();
+            tx.lock().unwrap().commit().unwrap();
         }
 
         let tx = Arc::new(Mutex::new(db.new_tx()));
@@ -366,6 +491,91 @@ mod tests {
         assert_eq!(layout, expected_layout);
     }
 
+    #[test]
+    fn test_plan_create_table_if_not_exists() {
+        let testdir = tempdir().unwrap();
+        let db = test_db(&testdir);
+        let mm = db.metadata_manager();
+        let mut planner = BasicUpdatePlanner::new(mm.clone());
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let Ok(RootNode::Create(create)) = parse("CREATE TABLE IF NOT EXISTS test ( f1 int )")
+        else {
+            panic!("Failed to parse CREATE TABLE statement");
+        };
+        assert_eq!(planner.execute_create(&create, tx.clone()), Ok(0));
+        assert_eq!(planner.execute_create(&create, tx.clone()), Ok(0));
+
+        // The existing table is left as it was
+        let Ok(RootNode::Create(create)) =
+            parse("CREATE TABLE IF NOT EXISTS test ( f2 varchar(10) )")
+        else {
+            panic!("Failed to parse CREATE TABLE statement");
+        };
+        assert_eq!(planner.execute_create(&create, tx.clone()), Ok(0));
+        let layout = mm.read().unwrap().get_table_layout("test", &tx).unwrap();
+        assert_eq!(layout, Layout::from_schema(make_schema! { "f1" => i32 }));
+
+        let Ok(RootNode::Create(create)) = parse("CREATE TABLE test ( f1 int )") else {
+            panic!("Failed to parse CREATE TABLE statement");
+        };
+        assert_eq!(
+            planner.execute_create(&create, tx.clone()),
+            Err("table 'test' already exists".to_string())
+        );
+    }
+
+    #[test]
+    fn test_plan_drop_table_if_exists() {
+        let testdir = tempdir().unwrap();
+        let db = test_db(&testdir);
+        let mm = db.metadata_manager();
+        let mut planner = BasicUpdatePlanner::new(mm.clone());
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let Ok(RootNode::Create(create)) = parse("CREATE TABLE test ( f1 int )") else {
+            panic!("Failed to parse CREATE TABLE statement");
+        };
+        planner.execute_create(&create, tx.clone()).unwrap();
+        mm.read()
+            .unwrap()
+            .create_index("test_f1", "test", "f1", &tx)
+            .unwrap();
+        let Ok(RootNode::Insert(insert)) = parse("INSERT INTO test (f1) VALUES (1)") else {
+            panic!("Failed to parse INSERT statement");
+        };
+        assert_eq!(planner.execute_insert(&insert, tx.clone()), Ok(1));
+
+        let Ok(RootNode::Drop(drop)) = parse("DROP TABLE IF EXISTS test") else {
+            panic!("Failed to parse DROP TABLE statement");
+        };
+        assert_eq!(planner.execute_drop(&drop, tx.clone()), Ok(0));
+        assert!(mm.read().unwrap().get_table_layout("test", &tx).is_none());
+        assert_eq!(planner.execute_drop(&drop, tx.clone()), Ok(0));
+
+        // The dropped table's records and indexes are gone, so a table created with the same name
+        // starts out empty and unindexed
+        planner.execute_create(&create, tx.clone()).unwrap();
+        assert!(mm
+            .read()
+            .unwrap()
+            .get_index_info("test", tx.clone())
+            .is_empty());
+        let layout = mm.read().unwrap().get_table_layout("test", &tx).unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout, "test");
+        assert!(!scan.next());
+        scan.close();
+        assert_eq!(planner.execute_drop(&drop, tx.clone()), Ok(0));
+
+        let Ok(RootNode::Drop(drop)) = parse("DROP TABLE test") else {
+            panic!("Failed to parse DROP TABLE statement");
+        };
+        assert_eq!(
+            planner.execute_drop(&drop, tx.clone()),
+            Err("table 'test' does not exist".to_string())
+        );
+    }
+
     #[test]
     fn test_plan_create_view() {
         let testdir = tempdir().unwrap();
@@ -387,7 +597,7 @@ mod tests {
                 panic!("Failed to parse CREATE VIEW statement");
             }
 
-            tx.lock().unwrap().commit();
+            tx.lock().unwrap().commit().unwrap();
         }
 
         let tx = Arc::new(Mutex::new(db.new_tx()));
@@ -399,6 +609,43 @@ mod tests {
         assert_eq!(view_def, "SELECT sid FROM student");
     }
 
+    #[test]
+    fn test_plan_create_view_with_predicate() {
+        let testdir = tempdir().unwrap();
+        let db = test_db(&testdir);
+        let mm = db.metadata_manager();
+        let mut planner = BasicUpdatePlanner::new(mm.clone());
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let sql = "CREATE VIEW v AS SELECT sid FROM student WHERE major_id = 10 AND grad_year > -1";
+        let Ok(RootNode::Create(CreateNode::View(_, select))) = parse(sql) else {
+            panic!("Failed to parse CREATE VIEW statement");
+        };
+        planner
+            .execute_create(
+                &CreateNode::View("v".to_string(), select.clone()),
+                tx.clone(),
+            )
+            .unwrap();
+
+        // The stored definition parses back to the same query, predicate included
+        let view_def = mm.read().unwrap().get_view_def("v", &tx).unwrap();
+        assert_eq!(
+            view_def,
+            "SELECT sid FROM student WHERE major_id = 10 AND grad_year > -1"
+        );
+        let Ok(RootNode::Select(reparsed)) = parse(&view_def) else {
+            panic!("Failed to parse view definition");
+        };
+        assert_eq!(reparsed, select);
+        assert!(reparsed.predicate.is_some());
+
+        // Creating a view with an existing name fails
+        assert!(planner
+            .execute_create(&CreateNode::View("v".to_string(), select), tx.clone())
+            .is_err());
+    }
+
     #[test]
     fn test_plan_create_index() {}
 }
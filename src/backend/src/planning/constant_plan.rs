@@ -0,0 +1,69 @@
+use std::fmt::Display;
+
+use crate::{
+    parser::constant::Value,
+    scan::{constant_scan::ConstantScan, scan::Scan},
+    schema::Schema,
+};
+
+use super::plan::Plan;
+
+/// A plan that produces a single record of constant values, used for queries without a FROM
+/// clause.
+pub struct ConstantPlan {
+    schema: Schema,
+    vals: Vec<Value>,
+}
+
+impl ConstantPlan {
+    /// Create a plan whose only record holds the given fields, each named and typed after its
+    /// value.
+    ///
+    /// # Arguments
+    ///
+    /// * `fields` - The name and value of each field, in order.
+    pub fn new(fields: Vec<(String, Value)>) -> Self {
+        let mut schema = Schema::new();
+        let mut vals = Vec::with_capacity(fields.len());
+        for (name, val) in fields {
+            match &val {
+                Value::Int(_) => {
+                    schema.add_int_field(&name);
+                }
+                Value::Varchar(s) => schema.add_string_field(&name, s.len() as u64),
+            }
+            vals.push(val);
+        }
+
+        Self { schema, vals }
+    }
+}
+
+impl Plan for ConstantPlan {
+    fn open(&mut self) -> Scan {
+        Scan::Constant(ConstantScan::new(self.schema.clone(), self.vals.clone()))
+    }
+
+    fn blocks_accessed(&self) -> u64 {
+        0
+    }
+
+    fn records_output(&self) -> u64 {
+        1
+    }
+
+    fn distinct_values(&self, _field_name: &str) -> u64 {
+        1
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl Display for ConstantPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let vals: Vec<String> = self.vals.iter().map(|v| v.to_string()).collect();
+        write!(f, "Constant({})", vals.join(", "))
+    }
+}
@@ -0,0 +1,27 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{metadata::metadata_manager::MetadataManager, transaction::Tx};
+
+use super::{materialized_view::store_records, plan::Plan, update_planner::RowCount};
+
+/// Create a table holding the records of a query, returning the number of records inserted.
+///
+/// The table's fields are the fields output by the query's plan, so aggregates are stored in
+/// fields named after them (e.g. `count(sid)`). Unlike a materialized view, the table keeps no
+/// record of the query that created it.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager to record the table in.
+/// * `tblname` - The name of the table.
+/// * `plan` - The plan of the query producing the table's records.
+/// * `tx` - The transaction that the table creation will run inside of.
+pub fn create_table_as(
+    metadata_mgr: &MetadataManager,
+    tblname: &str,
+    plan: Box<dyn Plan>,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    metadata_mgr.create_table(tblname, plan.schema(), tx)?;
+    store_records(metadata_mgr, plan, tblname, tx)
+}
@@ -0,0 +1,29 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    metadata::metadata_manager::MetadataManager, parser::parser::DropNode, transaction::Tx,
+};
+
+use super::update_planner::RowCount;
+
+/// Drop a table, removing it from the metadata catalogs along with its records and indexes.
+/// Dropping a table that does not exist is an error, unless the statement has an `IF EXISTS`
+/// clause, in which case nothing is done.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager holding the table's catalog entries.
+/// * `drop` - The parsed DROP TABLE statement.
+/// * `tx` - The transaction that the change will run inside of.
+pub fn drop_table(
+    metadata_mgr: &MetadataManager,
+    drop: &DropNode,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    if drop.if_exists && metadata_mgr.get_table_layout(&drop.table, tx).is_none() {
+        return Ok(0);
+    }
+
+    metadata_mgr.drop_table(&drop.table, tx)?;
+    Ok(0)
+}
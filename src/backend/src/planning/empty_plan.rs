@@ -0,0 +1,48 @@
+use std::fmt::Display;
+
+use crate::{
+    scan::{empty_scan::EmptyScan, scan::Scan},
+    schema::Schema,
+};
+
+use super::plan::Plan;
+
+/// A plan that produces no records, used in place of a table that the statistics show cannot
+/// contain any record satisfying a query's predicate.
+pub struct EmptyPlan {
+    schema: Schema,
+}
+
+impl EmptyPlan {
+    pub fn new(schema: Schema) -> Self {
+        Self { schema }
+    }
+}
+
+impl Plan for EmptyPlan {
+    fn open(&mut self) -> Scan {
+        Scan::Empty(EmptyScan::new(self.schema.clone()))
+    }
+
+    fn blocks_accessed(&self) -> u64 {
+        0
+    }
+
+    fn records_output(&self) -> u64 {
+        0
+    }
+
+    fn distinct_values(&self, field_name: &str) -> u64 {
+        0
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl Display for EmptyPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Empty")
+    }
+}
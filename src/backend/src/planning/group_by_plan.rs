@@ -0,0 +1,191 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    parser::parser::{AggregateField, AggregateFn, SelectField, SelectNode, SortField, SortOrder},
+    scan::{group_by_scan::GroupByScan, scan::Scan},
+    schema::Schema,
+    transaction::Tx,
+};
+
+use super::{plan::Plan, sort_plan::SortPlan};
+
+/// A plan that groups the records of an underlying plan by a list of group fields, and computes
+/// aggregates over each group. The records are sorted on the group fields to bring each group
+/// together.
+pub struct GroupByPlan {
+    plan: Box<dyn Plan>,
+    schema: Schema,
+    group_fields: Vec<String>,
+    aggregates: Vec<AggregateField>,
+}
+
+impl GroupByPlan {
+    /// Create a plan grouping the records of an underlying plan. Returns an error if a group field
+    /// or an aggregated field is not in the underlying plan, or a sum or average is taken of a
+    /// field that is not an integer.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction that owns the temporary tables used to sort the records.
+    /// * `plan` - The plan whose records are grouped.
+    /// * `group_fields` - The fields records are grouped by. Without any, every record is in a
+    ///   single group.
+    /// * `aggregates` - The aggregates computed over each group.
+    pub fn new(
+        tx: Arc<Mutex<Tx>>,
+        plan: Box<dyn Plan>,
+        group_fields: Vec<String>,
+        aggregates: Vec<AggregateField>,
+    ) -> Result<Self, String> {
+        let schema = group_by_schema(plan.schema(), &group_fields, &aggregates)?;
+        let plan: Box<dyn Plan> = if group_fields.is_empty() {
+            plan
+        } else {
+            let sort_fields = group_fields
+                .iter()
+                .map(|f| SortField(f.to_string(), SortOrder::Ascending))
+                .collect();
+            Box::new(SortPlan::new(tx, plan, sort_fields))
+        };
+
+        Ok(Self {
+            plan,
+            schema,
+            group_fields,
+            aggregates,
+        })
+    }
+}
+
+/// Get the schema of the records produced by grouping records with the given schema: the group
+/// fields, followed by a field for each aggregate named after it. Counts, sums and averages are
+/// integers, and minimums and maximums have the type of the field they are taken of.
+///
+/// # Arguments
+///
+/// * `schema` - The schema of the records being grouped.
+/// * `group_fields` - The fields records are grouped by.
+/// * `aggregates` - The aggregates computed over each group.
+pub fn group_by_schema(
+    schema: &Schema,
+    group_fields: &[String],
+    aggregates: &[AggregateField],
+) -> Result<Schema, String> {
+    let mut output = Schema::new();
+    for field_name in group_fields {
+        if !schema.has_field(field_name) {
+            return Err(format!("field '{}' does not exist", field_name));
+        }
+        output.add_from(field_name, schema);
+    }
+
+    for agg in aggregates {
+        let AggregateField(agg_fn, field_name) = agg;
+        let typ = schema
+            .get_field_type(field_name)
+            .ok_or_else(|| format!("field '{}' does not exist", field_name))?;
+        let name = agg.to_string();
+        if output.has_field(&name) {
+            return Err(format!("aggregate {} is selected more than once", name));
+        }
+        match agg_fn {
+            AggregateFn::Count => {
+                output.add_int_field(&name);
+            }
+            AggregateFn::Sum | AggregateFn::Avg if typ != 0 => {
+                return Err(format!(
+                    "cannot compute {}: '{}' is not an integer",
+                    name, field_name
+                ));
+            }
+            AggregateFn::Sum | AggregateFn::Avg => {
+                output.add_int_field(&name);
+            }
+            AggregateFn::Min | AggregateFn::Max => {
+                output.add_field(&name, typ, schema.get_field_length(field_name).unwrap());
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Check that every field selected by an aggregate query is one of its group fields, since other
+/// fields do not have a single value in a group.
+///
+/// # Arguments
+///
+/// * `select` - The query, which must be an aggregate query.
+pub fn check_grouped_fields(select: &SelectNode) -> Result<(), String> {
+    for field in &select.fields {
+        match field {
+            SelectField::Star => {
+                return Err("SELECT * cannot be used in an aggregate query".to_string());
+            }
+            SelectField::FieldName(name) if !select.group_by.contains(name) => {
+                return Err(format!(
+                    "field '{}' must be in the GROUP BY clause or used in an aggregate",
+                    name
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+impl Plan for GroupByPlan {
+    fn open(&mut self) -> Scan {
+        let scan = Box::new(self.plan.open());
+        Scan::GroupBy(GroupByScan::new(
+            scan,
+            self.schema.clone(),
+            self.group_fields.clone(),
+            self.aggregates.clone(),
+        ))
+    }
+
+    // Only blocks of the underlying plan are counted; the temporary tables sorting the records
+    // are not.
+    fn blocks_accessed(&self) -> u64 {
+        self.plan.blocks_accessed()
+    }
+
+    // There is at most one group for each combination of values of the group fields.
+    fn records_output(&self) -> u64 {
+        self.group_fields
+            .iter()
+            .map(|f| self.plan.distinct_values(f))
+            .product::<u64>()
+            .min(self.plan.records_output())
+    }
+
+    fn distinct_values(&self, field_name: &str) -> u64 {
+        if self.group_fields.iter().any(|f| f == field_name) {
+            self.plan.distinct_values(field_name)
+        } else {
+            self.records_output()
+        }
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl Display for GroupByPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let aggregates: Vec<String> = self.aggregates.iter().map(|a| a.to_string()).collect();
+        write!(
+            f,
+            "GroupBy([{}], [{}], {})",
+            self.group_fields.join(", "),
+            aggregates.join(", "),
+            self.plan
+        )
+    }
+}
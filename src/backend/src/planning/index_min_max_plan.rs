@@ -0,0 +1,85 @@
+use std::fmt::Display;
+
+use crate::{
+    metadata::index_manager::IndexInfo,
+    parser::parser::{AggregateField, AggregateFn},
+    planning::plan::Plan,
+    scan::{constant_scan::ConstantScan, empty_scan::EmptyScan, scan::Scan},
+    schema::Schema,
+};
+
+use super::group_by_plan::group_by_schema;
+
+/// A plan that computes the minimum or maximum of an indexed field over every record of a table
+/// from the first or last key of the index, without reading the table.
+pub struct IndexMinMaxPlan {
+    plan: Box<dyn Plan>,
+    index_info: IndexInfo,
+    aggregate: AggregateField,
+    schema: Schema,
+}
+
+impl IndexMinMaxPlan {
+    /// Create a plan computing a MIN or MAX from an index. Returns an error if the aggregated
+    /// field is not in the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - The plan of the table the index is on. It is only used for its schema and
+    ///   statistics, and is never opened.
+    /// * `index_info` - The index on the aggregated field.
+    /// * `aggregate` - The aggregate, which must be a MIN or MAX of the indexed field.
+    pub fn new(
+        plan: Box<dyn Plan>,
+        index_info: IndexInfo,
+        aggregate: AggregateField,
+    ) -> Result<Self, String> {
+        assert!(matches!(aggregate.0, AggregateFn::Min | AggregateFn::Max));
+        assert_eq!(aggregate.1, index_info.field_name());
+        let schema = group_by_schema(plan.schema(), &[], std::slice::from_ref(&aggregate))?;
+
+        Ok(Self {
+            plan,
+            index_info,
+            aggregate,
+            schema,
+        })
+    }
+}
+
+impl Plan for IndexMinMaxPlan {
+    // Like an aggregate over a table without a GROUP BY clause, an empty index produces no record.
+    fn open(&mut self) -> Scan {
+        let idx = self.index_info.open();
+        let key = match self.aggregate.0 {
+            AggregateFn::Min => idx.first_key(),
+            _ => idx.last_key(),
+        };
+        match key {
+            Some(val) => Scan::Constant(ConstantScan::new(self.schema.clone(), vec![val])),
+            None => Scan::Empty(EmptyScan::new(self.schema.clone())),
+        }
+    }
+
+    fn blocks_accessed(&self) -> u64 {
+        self.index_info.blocks_accessed()
+    }
+
+    fn records_output(&self) -> u64 {
+        self.plan.records_output().min(1)
+    }
+
+    fn distinct_values(&self, _field_name: &str) -> u64 {
+        self.records_output()
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+}
+
+impl Display for IndexMinMaxPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IndexMinMax({}, {})", self.aggregate, self.plan)
+    }
+}
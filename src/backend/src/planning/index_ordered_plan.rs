@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+use crate::{
+    metadata::index_manager::IndexInfo,
+    planning::plan::Plan,
+    scan::{index_ordered_scan::IndexOrderedScan, scan::Scan},
+    schema::Schema,
+};
+
+/// A plan that reads every record of a table in the order of an index on one of its fields.
+pub struct IndexOrderedPlan {
+    plan: Box<dyn Plan>,
+    index_info: IndexInfo,
+}
+
+impl IndexOrderedPlan {
+    pub fn new(plan: Box<dyn Plan>, index_info: IndexInfo) -> Self {
+        Self { plan, index_info }
+    }
+}
+
+impl Plan for IndexOrderedPlan {
+    fn open(&mut self) -> Scan {
+        if let Scan::Table(scan) = self.plan.open() {
+            let idx = self.index_info.open();
+            Scan::IndexOrdered(IndexOrderedScan::new(Box::new(scan), idx))
+        } else {
+            panic!("An index ordered plan can only wrap a TableScan");
+        }
+    }
+
+    // Records are fetched in index order rather than block order, so each one may need a
+    // separate block access.
+    fn blocks_accessed(&self) -> u64 {
+        self.plan.records_output()
+    }
+
+    fn records_output(&self) -> u64 {
+        self.plan.records_output()
+    }
+
+    fn distinct_values(&self, field_name: &str) -> u64 {
+        self.plan.distinct_values(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.plan.schema()
+    }
+}
+
+impl Display for IndexOrderedPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "IndexOrdered({}, {})",
+            self.index_info.field_name(),
+            self.plan
+        )
+    }
+}
@@ -34,8 +34,9 @@ impl Plan for IndexSelectPlan {
         }
     }
 
+    /// Searching the index, then reading the block of each record found.
     fn blocks_accessed(&self) -> u64 {
-        self.index_info.blocks_accessed()
+        self.index_info.blocks_accessed() + self.index_info.records_outputs()
     }
 
     fn records_output(&self) -> u64 {
@@ -53,6 +54,12 @@ impl Plan for IndexSelectPlan {
 
 impl Display for IndexSelectPlan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        write!(
+            f,
+            "IndexSelect({} = {}, {})",
+            self.index_info.field_name(),
+            self.val,
+            self.plan
+        )
     }
 }
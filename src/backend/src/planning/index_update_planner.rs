@@ -2,11 +2,17 @@ use crate::index::index::Index;
 use crate::insert;
 use crate::metadata::metadata_manager::MetadataManager;
 use crate::parser::parser::{
-    CreateNode, DeleteNode, FieldDefinitions, InsertNode, SelectNode, UpdateNode,
+    AlterNode, CreateNode, DeleteNode, DropNode, FieldDefinitions, InsertNode, SelectNode,
+    TableName, UpdateNode, ViewName,
 };
+use crate::planning::alter_table::{add_column, drop_column};
+use crate::planning::create_table_as::create_table_as;
+use crate::planning::drop_table::drop_table;
+use crate::planning::materialized_view::{create_materialized_view, refresh_view};
 use crate::planning::plan::Plan;
 use crate::planning::select_plan::SelectPlan;
 use crate::planning::table_plan::TablePlan;
+use crate::planning::truncate_table::truncate_table;
 use crate::planning::update_planner::{RowCount, UpdatePlanner};
 use crate::scan::scan::Scan::Select;
 use crate::scan::scan::{Scannable, UpdateScannable};
@@ -14,7 +20,7 @@ use crate::schema::Schema;
 use crate::transaction::Tx;
 use std::sync::{Arc, Mutex, RwLock};
 
-struct IndexUpdatePlanner {
+pub struct IndexUpdatePlanner {
     metadata_mgr: Arc<RwLock<MetadataManager>>,
 }
 
@@ -55,15 +61,16 @@ impl IndexUpdatePlanner {
         &self,
         name: &str,
         fields: &FieldDefinitions,
+        if_not_exists: bool,
         tx: &Arc<Mutex<Tx>>,
     ) -> Result<RowCount, String> {
         let schema = Schema::from_field_defs(fields);
         let mm = self.metadata_mgr.write().unwrap();
-        if !mm.create_table(name, &schema, tx) {
-            Err("Failed to create table".to_string())
-        } else {
-            Ok(0)
+        if if_not_exists && mm.get_table_layout(name, tx).is_some() {
+            return Ok(0);
         }
+        mm.create_table(name, &schema, tx)?;
+        Ok(0)
     }
 }
 
@@ -99,6 +106,10 @@ impl UpdatePlanner for IndexUpdatePlanner {
 
         for (name, val) in insert.fields() {
             table_scan.set_val(name, val);
+            self.metadata_mgr
+                .read()
+                .unwrap()
+                .record_value(table_name, name, val);
 
             if let Some(ii) = column_indexes.get(name) {
                 let mut index = ii.open();
@@ -123,13 +134,20 @@ impl UpdatePlanner for IndexUpdatePlanner {
             .get_table_layout(table_name, &tx)
             .ok_or(format!("table '{}' does not exist", table_name))?;
 
+        // Without a predicate every record is deleted, so the table can be truncated instead
+        if delete.1.is_none() && !layout.is_versioned() {
+            return truncate_table(&self.metadata_mgr.read().unwrap(), table_name, &tx);
+        }
+
         let mut plan: Box<dyn Plan> = {
             let mut lmm = self.metadata_mgr.write().unwrap();
             Box::new(TablePlan::new(tx.clone(), table_name, &mut lmm))
         };
 
         if let Some(pred) = &delete.1 {
-            plan = Box::new(SelectPlan::new(plan, pred.clone()));
+            let mut pred = pred.clone();
+            pred.coerce(plan.schema())?;
+            plan = Box::new(SelectPlan::new(plan, pred));
         }
 
         let mut scan = plan.open();
@@ -181,7 +199,9 @@ impl UpdatePlanner for IndexUpdatePlanner {
         };
 
         if let Some(pred) = &modify.where_clause {
-            plan = Box::new(SelectPlan::new(plan, pred.clone()));
+            let mut pred = pred.clone();
+            pred.coerce(plan.schema())?;
+            plan = Box::new(SelectPlan::new(plan, pred));
         }
 
         let ii = self
@@ -203,6 +223,10 @@ impl UpdatePlanner for IndexUpdatePlanner {
                 .map_err(|err| "field not found in scan".to_string())?;
 
             scan.set_val(field_name, &newval);
+            self.metadata_mgr
+                .read()
+                .unwrap()
+                .record_value(table_name, field_name, &newval);
 
             // If an index exists on this column, it must be updated
             if let Some(idx) = &mut idx {
@@ -223,24 +247,80 @@ impl UpdatePlanner for IndexUpdatePlanner {
         tx: Arc<Mutex<Tx>>,
     ) -> Result<RowCount, String> {
         match create {
-            CreateNode::Table(name, fields) => self.create_table(name, fields, &tx),
+            CreateNode::Table(name, fields, if_not_exists) => {
+                self.create_table(name, fields, *if_not_exists, &tx)
+            }
+            CreateNode::TableAs(name, _) => Err(format!(
+                "table '{}' must be created from the plan of its query",
+                name
+            )),
             CreateNode::View(name, select) => self.create_view(name, select, &tx),
+            CreateNode::MaterializedView(name, select) => {
+                let mut mm = self.metadata_mgr.write().unwrap();
+                create_materialized_view(&mut mm, name, select, &tx)
+            }
             CreateNode::Index(name, tblname, fieldname) => {
                 self.create_index(name, tblname, fieldname, &tx)
             }
         }
     }
+
+    fn execute_create_table_as(
+        &mut self,
+        tblname: &TableName,
+        plan: Box<dyn Plan>,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        let mm = self.metadata_mgr.read().unwrap();
+        create_table_as(&mm, tblname, plan, &tx)
+    }
+
+    fn execute_alter(
+        &mut self,
+        alter: &AlterNode,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        match alter {
+            AlterNode::AddColumn(tblname, field_def) => {
+                let mm = self.metadata_mgr.write().unwrap();
+                add_column(&mm, tblname, field_def, &tx)
+            }
+            AlterNode::DropColumn(tblname, field_name) => {
+                let mm = self.metadata_mgr.write().unwrap();
+                drop_column(&mm, tblname, field_name, &tx)
+            }
+        }
+    }
+
+    fn execute_drop(&mut self, drop: &DropNode, tx: Arc<Mutex<Tx>>) -> Result<RowCount, String> {
+        let mm = self.metadata_mgr.write().unwrap();
+        drop_table(&mm, drop, &tx)
+    }
+
+    fn execute_refresh(
+        &mut self,
+        view_name: &ViewName,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        let mut mm = self.metadata_mgr.write().unwrap();
+        refresh_view(&mut mm, view_name, &tx)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, Mutex};
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
 
     use tempfile::tempdir;
 
     use crate::layout::Layout;
     use crate::{
-        assert_table_scan_results, make_schema,
+        assert_table_scan_results,
+        file_manager::HEADER_SIZE,
+        make_schema,
         parser::parser::{parse, RootNode},
         planning::update_planner::UpdatePlanner,
         table_scan::TableScan,
@@ -309,6 +389,10 @@ mod tests {
 
             assert_eq!(count, 9);
 
+            // The table was truncated rather than deleted from record by record
+            let path = testdir.path().join("data").join("student");
+            assert_eq!(fs::metadata(path).unwrap().len(), HEADER_SIZE);
+
             let mut scan = TableScan::new(
                 tx.clone(),
                 mm.read().unwrap().get_table_layout("student", &tx).unwrap(),
@@ -417,7 +501,7 @@ mod tests {
                 planner.execute_create(&create, tx.clone());
             }
 
-            tx.lock().unwrap().commit();
+            tx.lock().unwrap().commit().unwrap();
         }
 
         let tx = Arc::new(Mutex::new(db.new_tx()));
@@ -458,7 +542,7 @@ mod tests {
                 panic!("Failed to parse CREATE VIEW statement");
             }
 
-            tx.lock().unwrap().commit();
+            tx.lock().unwrap().commit().unwrap();
         }
 
         let tx = Arc::new(Mutex::new(db.new_tx()));
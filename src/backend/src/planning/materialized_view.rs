@@ -0,0 +1,290 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    metadata::metadata_manager::MetadataManager,
+    parser::parser::{parse, RootNode, SelectField, SelectNode},
+    scan::{
+        row::{map_to_row, row_to_map},
+        scan::{Scannable, UpdateScannable},
+    },
+    table_scan::TableScan,
+    transaction::Tx,
+};
+
+use super::{
+    plan::Plan, project_plan::ProjectPlan, select_plan::SelectPlan, table_plan::TablePlan,
+    update_planner::RowCount,
+};
+
+/// Create a materialized view, returning the number of records stored in it.
+///
+/// A materialized view stores its records in a table with the view's name, which queries read
+/// instead of planning the view's definition. The records are only recomputed when the view is
+/// refreshed. Materialized views must select from a single table and cannot be ordered.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager to record the view and its table in.
+/// * `view_name` - The name of the view.
+/// * `select` - The query defining the view.
+/// * `tx` - The transaction that the view creation will run inside of.
+pub fn create_materialized_view(
+    metadata_mgr: &mut MetadataManager,
+    view_name: &str,
+    select: &SelectNode,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    let plan = view_plan(metadata_mgr, view_name, select, tx)?;
+    metadata_mgr.create_materialized_view(view_name, &format!("{}", select), tx)?;
+    metadata_mgr.create_table(view_name, plan.schema(), tx)?;
+
+    store_records(metadata_mgr, plan, view_name, tx)
+}
+
+/// Recompute the records of a materialized view from its definition, returning the number of
+/// records stored in it.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager holding the view's catalog entries.
+/// * `view_name` - The name of the view.
+/// * `tx` - The transaction that the refresh will run inside of.
+pub fn refresh_view(
+    metadata_mgr: &mut MetadataManager,
+    view_name: &str,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    let view_def = metadata_mgr
+        .get_view_def(view_name, tx)
+        .ok_or_else(|| format!("view '{}' does not exist", view_name))?;
+    if !metadata_mgr.is_materialized_view(view_name, tx) {
+        return Err(format!("view '{}' is not materialized", view_name));
+    }
+
+    let select = match parse(&view_def)? {
+        RootNode::Select(node) => node,
+        _ => {
+            return Err(format!(
+                "did not find SELECT query statement in view defined with name '{}'",
+                view_name
+            ))
+        }
+    };
+    let plan = view_plan(metadata_mgr, view_name, &select, tx)?;
+
+    let layout = metadata_mgr
+        .get_table_layout(view_name, tx)
+        .ok_or_else(|| format!("table for view '{}' does not exist", view_name))?;
+    let mut scan = TableScan::new(tx.clone(), layout, view_name);
+    while scan.next() {
+        scan.delete();
+    }
+    scan.close();
+
+    store_records(metadata_mgr, plan, view_name, tx)
+}
+
+/// Plan the query defining a materialized view.
+fn view_plan(
+    metadata_mgr: &mut MetadataManager,
+    view_name: &str,
+    select: &SelectNode,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<Box<dyn Plan>, String> {
+    let [tblname] = select.tables.as_slice() else {
+        return Err(format!(
+            "materialized view '{}' must select from a single table",
+            view_name
+        ));
+    };
+    if !select.order_by.is_empty() {
+        return Err(format!(
+            "materialized view '{}' cannot have an ORDER BY clause",
+            view_name
+        ));
+    }
+    if select.is_aggregate() {
+        return Err(format!(
+            "materialized view '{}' cannot group its records",
+            view_name
+        ));
+    }
+    if metadata_mgr.get_view_def(tblname, tx).is_some() {
+        return Err(format!("'{}' is a view, not a table", tblname));
+    }
+    if metadata_mgr.get_table_layout(tblname, tx).is_none() {
+        return Err(format!("table '{}' does not exist", tblname));
+    }
+
+    let mut plan: Box<dyn Plan> = Box::new(TablePlan::new(tx.clone(), tblname, metadata_mgr));
+    if let Some(pred) = &select.predicate {
+        plan = Box::new(SelectPlan::new(plan, pred.clone()));
+    }
+
+    let mut fields = vec![];
+    for field in &select.fields {
+        match field {
+            SelectField::Star => fields.extend(plan.schema().fields()),
+            SelectField::FieldName(name) if plan.schema().has_field(name) => {
+                fields.push(name.to_string())
+            }
+            SelectField::FieldName(name) => {
+                return Err(format!("field '{}' does not exist", name));
+            }
+            SelectField::Constant(val) => {
+                return Err(format!(
+                    "materialized view '{}' cannot select constant {}",
+                    view_name, val
+                ));
+            }
+            SelectField::Aggregate(agg) => {
+                return Err(format!(
+                    "materialized view '{}' cannot select aggregate {}",
+                    view_name, agg
+                ));
+            }
+        }
+    }
+
+    Ok(Box::new(ProjectPlan::new(plan, fields)))
+}
+
+/// Insert the records of a plan into a table, returning the number of records inserted.
+pub(super) fn store_records(
+    metadata_mgr: &MetadataManager,
+    mut plan: Box<dyn Plan>,
+    tblname: &str,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    let layout = metadata_mgr
+        .get_table_layout(tblname, tx)
+        .ok_or_else(|| format!("table '{}' does not exist", tblname))?;
+
+    let mut src = plan.open();
+    let mut dest = TableScan::new(tx.clone(), layout, tblname);
+    let mut count = 0;
+    while src.next() {
+        let row = row_to_map(&src, plan.schema()).map_err(|e| format!("{:?}", e))?;
+        dest.insert();
+        map_to_row(&mut dest, &row).map_err(|e| format!("{:?}", e))?;
+        count += 1;
+    }
+    src.close();
+    dest.close();
+    metadata_mgr.invalidate_stats(tblname);
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        parser::{
+            constant::Value,
+            parser::{parse, CreateNode, RootNode},
+        },
+        planning::{plan::Plan, table_plan::TablePlan},
+        scan::scan::{Scannable, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::{create_default_tables, default_test_db},
+        transaction::Tx,
+    };
+
+    use super::{create_materialized_view, refresh_view};
+
+    #[test]
+    fn test_refresh_materialized_view() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let Ok(RootNode::Create(CreateNode::MaterializedView(view_name, select))) = parse(
+            "CREATE MATERIALIZED VIEW compsci_students AS SELECT sid, sname FROM student WHERE major_id = 10",
+        ) else {
+            panic!("failed to parse CREATE MATERIALIZED VIEW statement");
+        };
+        let rows =
+            create_materialized_view(&mut mm.write().unwrap(), &view_name, &select, &tx).unwrap();
+        assert_eq!(rows, 3);
+
+        let read_view = |tx: &Arc<Mutex<Tx>>| {
+            let mut plan = TablePlan::new(tx.clone(), "compsci_students", &mut mm.write().unwrap());
+            let mut scan = plan.open();
+            let mut records = vec![];
+            while scan.next() {
+                records.push((
+                    scan.get_int("sid").unwrap(),
+                    scan.get_string("sname").unwrap(),
+                ));
+            }
+            scan.close();
+            records.sort();
+            records
+        };
+
+        // Records inserted into the base table only show up once the view is refreshed
+        let layout = mm.read().unwrap().get_table_layout("student", &tx).unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout, "student");
+        scan.insert();
+        scan.set_int("sid", 10);
+        scan.set_string("sname", "ann");
+        scan.set_int("grad_year", 2023);
+        scan.set_int("major_id", 10);
+        scan.close();
+        assert_eq!(read_view(&tx).len(), 3);
+
+        assert_eq!(
+            refresh_view(&mut mm.write().unwrap(), "compsci_students", &tx),
+            Ok(4)
+        );
+        assert_eq!(
+            read_view(&tx),
+            vec![
+                (1, "joe".to_string()),
+                (3, "max".to_string()),
+                (9, "lee".to_string()),
+                (10, "ann".to_string()),
+            ]
+        );
+        tx.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_materialized_view_errors() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let Ok(RootNode::Create(CreateNode::MaterializedView(view_name, select))) =
+            parse("CREATE MATERIALIZED VIEW enrolled AS SELECT sid, dname FROM student, dept")
+        else {
+            panic!("failed to parse CREATE MATERIALIZED VIEW statement");
+        };
+        assert_eq!(
+            create_materialized_view(&mut mm.write().unwrap(), &view_name, &select, &tx),
+            Err("materialized view 'enrolled' must select from a single table".to_string())
+        );
+
+        mm.read()
+            .unwrap()
+            .create_view("plain", "SELECT sid FROM student", &tx)
+            .unwrap();
+        assert_eq!(
+            refresh_view(&mut mm.write().unwrap(), "plain", &tx),
+            Err("view 'plain' is not materialized".to_string())
+        );
+        assert_eq!(
+            refresh_view(&mut mm.write().unwrap(), "missing", &tx),
+            Err("view 'missing' does not exist".to_string())
+        );
+        tx.lock().unwrap().commit().unwrap();
+    }
+}
@@ -1,13 +1,26 @@
+pub mod alter_table;
 pub mod basic_query_planner;
 pub mod basic_update_planner;
+pub mod constant_plan;
+pub mod create_table_as;
+pub mod drop_table;
+pub mod empty_plan;
+pub mod group_by_plan;
 pub mod index_join_plan;
+pub mod index_min_max_plan;
+pub mod index_ordered_plan;
 pub mod index_select_plan;
+pub mod index_update_planner;
+pub mod materialized_view;
 pub mod plan;
 pub mod planner;
 pub mod product_plan;
 pub mod project_plan;
+pub mod query_cache;
 pub mod query_planner;
 pub mod select_plan;
+pub mod sort_plan;
 pub mod table_plan;
+pub mod truncate_table;
 pub mod update_planner;
-mod index_update_planner;
+pub mod validator;
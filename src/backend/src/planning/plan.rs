@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 use crate::{scan::scan::Scan, schema::Schema};
 
+use super::select_plan::SelectPlan;
+
 pub trait Plan: Display {
     //fn open(&mut self) -> Box<dyn Scan>;
     fn open(&mut self) -> Scan;
@@ -9,4 +11,9 @@ pub trait Plan: Display {
     fn records_output(&self) -> u64;
     fn distinct_values(&self, field_name: &str) -> u64;
     fn schema(&self) -> &Schema;
+
+    /// Get this plan as a select plan, so that another predicate can be fused into it.
+    fn as_select_mut(&mut self) -> Option<&mut SelectPlan> {
+        None
+    }
 }
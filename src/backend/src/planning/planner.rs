@@ -1,12 +1,16 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
 
 use crate::{
-    parser::parser::{parse, CreateNode, RootNode},
+    parser::parser::{parse, AlterNode, CreateNode, DropNode, RootNode},
     transaction::Tx,
 };
 
 use super::{
     plan::Plan,
+    query_cache::QueryCache,
     query_planner::QueryPlanner,
     update_planner::{RowCount, UpdatePlanner},
 };
@@ -14,6 +18,8 @@ use super::{
 pub struct Planner {
     query_planner: Box<dyn QueryPlanner>,
     update_planner: Box<dyn UpdatePlanner>,
+    query_cache: Option<Mutex<QueryCache>>,
+    parse_count: AtomicU64,
 }
 
 impl Planner {
@@ -24,29 +30,285 @@ impl Planner {
         Self {
             query_planner,
             update_planner,
+            query_cache: None,
+            parse_count: AtomicU64::new(0),
         }
     }
 
+    /// Cache up to `capacity` parsed queries, so that running a query again skips parsing it.
+    /// Cached queries that read from a table or view are dropped when a statement creates or
+    /// alters it.
+    pub fn with_query_cache(mut self, capacity: usize) -> Self {
+        self.query_cache = Some(Mutex::new(QueryCache::new(capacity)));
+        self
+    }
+
+    /// Get the number of statements that have been parsed, excluding queries found in the cache.
+    pub fn parse_count(&self) -> u64 {
+        self.parse_count.load(Ordering::SeqCst)
+    }
+
     pub fn create_query_plan(
         &self,
         cmd: &str,
         tx: Arc<Mutex<Tx>>,
     ) -> Result<Box<dyn Plan>, String> {
-        let ast = parse(cmd)?;
+        let key = QueryCache::normalize(cmd);
+        if let Some(cache) = &self.query_cache {
+            let cached = cache.lock().unwrap().get(&key).cloned();
+            if let Some(select_node) = cached {
+                return self.query_planner.create_plan(&select_node, tx);
+            }
+        }
+
+        let ast = self.parse(cmd)?;
         match ast {
-            RootNode::Select(select_node) => self.query_planner.create_plan(&select_node, tx),
+            RootNode::Select(select_node) => {
+                if let Some(cache) = &self.query_cache {
+                    cache.lock().unwrap().insert(key, select_node.clone());
+                }
+                self.query_planner.create_plan(&select_node, tx)
+            }
             _ => Err("provided query does not support plan creation".to_string()),
         }
     }
 
     pub fn execute_update(&mut self, cmd: &str, tx: Arc<Mutex<Tx>>) -> Result<RowCount, String> {
-        let ast = parse(cmd)?;
+        let ast = self.parse(cmd)?;
+        self.execute_statement(ast, tx)
+    }
+
+    /// Execute an update statement that has already been parsed.
+    pub fn execute_statement(
+        &mut self,
+        ast: RootNode,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String> {
+        let changed_table = match &ast {
+            RootNode::Create(CreateNode::Table(name, _, _))
+            | RootNode::Create(CreateNode::TableAs(name, _))
+            | RootNode::Create(CreateNode::View(name, _))
+            | RootNode::Create(CreateNode::MaterializedView(name, _))
+            | RootNode::Create(CreateNode::Index(_, name, _))
+            | RootNode::Alter(AlterNode::AddColumn(name, _))
+            | RootNode::Alter(AlterNode::DropColumn(name, _))
+            | RootNode::Drop(DropNode { table: name, .. }) => Some(name.clone()),
+            _ => None,
+        };
+        if let (Some(cache), Some(name)) = (&self.query_cache, &changed_table) {
+            cache.lock().unwrap().invalidate(name);
+        }
+
         match ast {
+            // The update planner cannot plan queries, so the table's query is planned here
+            RootNode::Create(CreateNode::TableAs(name, select_node)) => {
+                let plan = self.query_planner.create_plan(&select_node, tx.clone())?;
+                self.update_planner.execute_create_table_as(&name, plan, tx)
+            }
             RootNode::Create(create_node) => self.update_planner.execute_create(&create_node, tx),
             RootNode::Insert(insert_node) => self.update_planner.execute_insert(&insert_node, tx),
             RootNode::Delete(delete_node) => self.update_planner.execute_delete(&delete_node, tx),
             RootNode::Update(update_node) => self.update_planner.execute_modify(&update_node, tx),
+            RootNode::Alter(alter_node) => self.update_planner.execute_alter(&alter_node, tx),
+            RootNode::Drop(drop_node) => self.update_planner.execute_drop(&drop_node, tx),
+            RootNode::Refresh(view_name) => self.update_planner.execute_refresh(&view_name, tx),
             _ => Err("provided query does not support plan-less execution".to_string()),
         }
     }
+
+    fn parse(&self, cmd: &str) -> Result<RootNode, String> {
+        self.parse_count.fetch_add(1, Ordering::SeqCst);
+        parse(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        planning::{
+            basic_query_planner::BasicQueryPlanner, basic_update_planner::BasicUpdatePlanner,
+        },
+        scan::scan::Scannable,
+        tests::test_utils::{create_default_tables, default_test_db},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_query_cache() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let mut planner = Planner::new(
+            Box::new(BasicQueryPlanner::new(db.metadata_manager())),
+            Box::new(BasicUpdatePlanner::new(db.metadata_manager())),
+        )
+        .with_query_cache(10);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let run = |planner: &Planner, query: &str| {
+            let mut scan = planner.create_query_plan(query, tx.clone()).unwrap().open();
+            let mut sids = vec![];
+            while scan.next() {
+                sids.push(scan.get_int("sid").unwrap());
+            }
+            sids
+        };
+
+        let query = "SELECT sid FROM student WHERE grad_year = 2020";
+        assert_eq!(run(&planner, query), vec![2, 5, 6]);
+        assert_eq!(
+            run(
+                &planner,
+                "SELECT sid\n  FROM student\n  WHERE grad_year = 2020"
+            ),
+            vec![2, 5, 6]
+        );
+        assert_eq!(planner.parse_count(), 1);
+
+        // Changing another table leaves the query cached, but changing the student table does not
+        planner
+            .execute_update("ALTER TABLE dept ADD COLUMN budget int", tx.clone())
+            .unwrap();
+        assert_eq!(run(&planner, query), vec![2, 5, 6]);
+        assert_eq!(planner.parse_count(), 2);
+
+        planner
+            .execute_update("ALTER TABLE student ADD COLUMN gpa int", tx.clone())
+            .unwrap();
+        assert_eq!(run(&planner, query), vec![2, 5, 6]);
+        assert_eq!(planner.parse_count(), 4);
+    }
+
+    #[test]
+    fn test_render_control_characters() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let mut planner = Planner::new(
+            Box::new(BasicQueryPlanner::new(db.metadata_manager())),
+            Box::new(BasicUpdatePlanner::new(db.metadata_manager())),
+        );
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        planner
+            .execute_update(
+                "INSERT INTO dept (did, dname) VALUES (40, 'line one\nline two')",
+                tx.clone(),
+            )
+            .unwrap();
+
+        // Render the result set one record per line
+        let mut scan = planner
+            .create_query_plan("SELECT did, dname FROM dept WHERE did = 40", tx.clone())
+            .unwrap()
+            .open();
+        let mut rendered = String::new();
+        while scan.next() {
+            let did = scan.get_val("did").unwrap();
+            let dname = scan.get_val("dname").unwrap();
+            rendered.push_str(&format!("{} | {}\n", did, dname));
+        }
+
+        assert_eq!(rendered, "40 | E'line one\\nline two'\n");
+    }
+
+    #[test]
+    fn test_create_table_as_select() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let mut planner = Planner::new(
+            Box::new(BasicQueryPlanner::new(mm.clone())),
+            Box::new(BasicUpdatePlanner::new(mm.clone())),
+        );
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let rows = planner
+            .execute_update(
+                "CREATE TABLE compsci AS SELECT sid, sname FROM student WHERE major_id = 10",
+                tx.clone(),
+            )
+            .unwrap();
+        assert_eq!(rows, 3);
+
+        // The table's fields have the names, types, and lengths of the fields it was created from
+        let schema = mm
+            .read()
+            .unwrap()
+            .get_table_layout("compsci", &tx)
+            .unwrap()
+            .schema()
+            .clone();
+        let student_schema = mm
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap()
+            .schema()
+            .clone();
+        assert_eq!(schema.fields(), vec!["sid", "sname"]);
+        for field_name in schema.fields() {
+            assert_eq!(
+                schema.get_field_type(&field_name),
+                student_schema.get_field_type(&field_name)
+            );
+            assert_eq!(
+                schema.get_field_length(&field_name),
+                student_schema.get_field_length(&field_name)
+            );
+        }
+
+        let mut scan = planner
+            .create_query_plan("SELECT sid, sname FROM compsci", tx.clone())
+            .unwrap()
+            .open();
+        let mut records = vec![];
+        while scan.next() {
+            records.push((
+                scan.get_int("sid").unwrap(),
+                scan.get_string("sname").unwrap(),
+            ));
+        }
+        scan.close();
+        records.sort();
+        assert_eq!(
+            records,
+            vec![
+                (1, "joe".to_string()),
+                (3, "max".to_string()),
+                (9, "lee".to_string()),
+            ]
+        );
+
+        // Aggregates are stored in fields named after them
+        let rows = planner
+            .execute_update(
+                "CREATE TABLE summary AS SELECT major_id, COUNT(sid) FROM student GROUP BY major_id",
+                tx.clone(),
+            )
+            .unwrap();
+        assert_eq!(rows, 3);
+
+        let mut scan = planner
+            .create_query_plan("SELECT * FROM summary", tx.clone())
+            .unwrap()
+            .open();
+        let mut counts = vec![];
+        while scan.next() {
+            counts.push((
+                scan.get_int("major_id").unwrap(),
+                scan.get_int("count(sid)").unwrap(),
+            ));
+        }
+        scan.close();
+        counts.sort();
+        assert_eq!(counts, vec![(10, 3), (20, 4), (30, 2)]);
+        tx.lock().unwrap().commit().unwrap();
+    }
 }
@@ -11,6 +11,7 @@ pub struct ProductPlan {
     p1: Box<dyn Plan>,
     p2: Box<dyn Plan>,
     schema: Schema,
+    chunk_size: Option<usize>,
 }
 
 impl ProductPlan {
@@ -19,19 +20,55 @@ impl ProductPlan {
         schema.add_all(p1.schema());
         schema.add_all(p2.schema());
 
-        Self { p1, p2, schema }
+        Self {
+            p1,
+            p2,
+            schema,
+            chunk_size: None,
+        }
+    }
+
+    /// Hold `chunk_size` records of the first plan in memory at once, reading the second plan
+    /// once per chunk rather than once per record. By default the second plan is read once per
+    /// record.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "a chunk must hold at least one record");
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Hold as many records of the first plan in memory at once as its blocks hold in
+    /// `num_buffers` buffers, going by its statistics, reading the second plan once per chunk.
+    pub fn with_buffer_budget(self, num_buffers: u64) -> Self {
+        let records_per_block = self
+            .p1
+            .records_output()
+            .div_ceil(self.p1.blocks_accessed().max(1));
+        let chunk_size = (num_buffers * records_per_block).max(1);
+        self.with_chunk_size(chunk_size as usize)
+    }
+
+    // The number of times the second plan is read
+    fn passes(&self) -> u64 {
+        match self.chunk_size {
+            Some(chunk_size) => self.p1.records_output().div_ceil(chunk_size as u64),
+            None => self.p1.records_output(),
+        }
     }
 }
 
 impl Plan for ProductPlan {
     fn open(&mut self) -> Scan {
-        let mut s1 = Box::new(self.p1.open());
-        let mut s2 = Box::new(self.p2.open());
-        Scan::Product(ProductScan::new(s1, s2))
+        let s1 = Box::new(self.p1.open());
+        let s2 = Box::new(self.p2.open());
+        Scan::Product(match self.chunk_size {
+            Some(chunk_size) => ProductScan::with_chunk_size(s1, s2, chunk_size),
+            None => ProductScan::new(s1, s2),
+        })
     }
 
     fn blocks_accessed(&self) -> u64 {
-        self.p1.blocks_accessed() + (self.p1.records_output() * self.p2.blocks_accessed())
+        self.p1.blocks_accessed() + (self.passes() * self.p2.blocks_accessed())
     }
 
     fn records_output(&self) -> u64 {
@@ -53,6 +90,13 @@ impl Plan for ProductPlan {
 
 impl Display for ProductPlan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        match self.chunk_size {
+            Some(chunk_size) => write!(
+                f,
+                "ChunkedProduct({}, {}, {})",
+                chunk_size, self.p1, self.p2
+            ),
+            None => write!(f, "Product({}, {})", self.p1, self.p2),
+        }
     }
 }
@@ -50,6 +50,11 @@ impl Plan for ProjectPlan {
 
 impl Display for ProjectPlan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        write!(
+            f,
+            "Project([{}], {})",
+            self.schema.fields().join(", "),
+            self.plan
+        )
     }
 }
@@ -0,0 +1,148 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::parser::parser::SelectNode;
+
+/// A cache of parsed queries, so that a query that is run repeatedly is only parsed once.
+///
+/// Queries are keyed by their SQL text with runs of whitespace collapsed, except inside string
+/// literals, so that queries differing only in layout share an entry. Once the cache is full, the
+/// query that was cached first is evicted.
+pub struct QueryCache {
+    capacity: usize,
+    queries: HashMap<String, SelectNode>,
+    order: VecDeque<String>,
+}
+
+impl QueryCache {
+    /// Create an empty cache holding up to `capacity` queries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            queries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Get the key under which a query is cached: its SQL text with leading and trailing
+    /// whitespace removed, and every other run of whitespace outside of a string literal replaced
+    /// by a single space.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - The text of the query.
+    pub fn normalize(sql: &str) -> String {
+        let mut key = String::with_capacity(sql.len());
+        let mut in_literal = false;
+        let mut pending_space = false;
+        for c in sql.trim().chars() {
+            if !in_literal && c.is_whitespace() {
+                pending_space = true;
+                continue;
+            }
+
+            if pending_space {
+                key.push(' ');
+                pending_space = false;
+            }
+            // A doubled quote inside a literal toggles twice, leaving the literal open
+            if c == '\'' {
+                in_literal = !in_literal;
+            }
+            key.push(c);
+        }
+        key
+    }
+
+    /// Get the parsed form of a cached query.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized text of the query.
+    pub fn get(&self, key: &str) -> Option<&SelectNode> {
+        self.queries.get(key)
+    }
+
+    /// Cache the parsed form of a query, evicting the oldest query if the cache is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The normalized text of the query.
+    /// * `query` - The parsed query.
+    pub fn insert(&mut self, key: String, query: SelectNode) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.queries.insert(key.clone(), query).is_none() {
+            self.order.push_back(key);
+        }
+        while self.queries.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.queries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Remove every cached query that reads from a table or view, e.g. because its definition was
+    /// changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `table_name` - The name of the table or view.
+    pub fn invalidate(&mut self, table_name: &str) {
+        self.queries
+            .retain(|_, query| !query.tables.iter().any(|t| t == table_name));
+        let queries = &self.queries;
+        self.order.retain(|key| queries.contains_key(key));
+    }
+
+    /// Get the number of cached queries.
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parser::{parse, RootNode};
+
+    use super::*;
+
+    fn select(sql: &str) -> SelectNode {
+        match parse(sql).unwrap() {
+            RootNode::Select(node) => node,
+            _ => panic!("not a select statement"),
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            QueryCache::normalize("  SELECT sid\n\tFROM   student  "),
+            "SELECT sid FROM student"
+        );
+        assert_eq!(
+            QueryCache::normalize("SELECT sid FROM student WHERE sname = 'a  ''b  c'"),
+            "SELECT sid FROM student WHERE sname = 'a  ''b  c'"
+        );
+    }
+
+    #[test]
+    fn test_evict_and_invalidate() {
+        let mut cache = QueryCache::new(2);
+        cache.insert("a".to_string(), select("SELECT sid FROM student"));
+        cache.insert("b".to_string(), select("SELECT did FROM dept"));
+        cache.insert(
+            "c".to_string(),
+            select("SELECT sid, did FROM student, dept"),
+        );
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_none());
+        assert!(cache.get("b").is_some());
+
+        cache.invalidate("student");
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get("c").is_none());
+        assert!(cache.get("b").is_some());
+    }
+}
@@ -20,6 +20,24 @@ impl SelectPlan {
     pub fn new(plan: Box<dyn Plan>, predicate: Predicate) -> Self {
         Self { plan, predicate }
     }
+
+    /// Select the records of a plan that satisfy a predicate. If the plan is itself a select, the
+    /// predicate is conjoined with its own rather than stacking another select on top, so each
+    /// record is read and tested in a single pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - The plan whose records are filtered.
+    /// * `predicate` - The predicate that selected records must satisfy.
+    pub fn select(mut plan: Box<dyn Plan>, mut predicate: Predicate) -> Box<dyn Plan> {
+        match plan.as_select_mut() {
+            Some(select) => {
+                select.predicate.conjoin_with(&mut predicate);
+                plan
+            }
+            None => Box::new(Self::new(plan, predicate)),
+        }
+    }
 }
 
 impl Plan for SelectPlan {
@@ -45,10 +63,85 @@ impl Plan for SelectPlan {
     fn schema(&self) -> &Schema {
         self.plan.schema()
     }
+
+    fn as_select_mut(&mut self) -> Option<&mut SelectPlan> {
+        Some(self)
+    }
 }
 
 impl Display for SelectPlan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        write!(f, "Select({}, {})", self.predicate, self.plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        parser::{constant::Value, expression::Expression, predicate::Predicate, term::Term},
+        planning::{plan::Plan, table_plan::TablePlan},
+        scan::scan::{Scan, Scannable},
+        tests::test_utils::{create_default_tables, default_test_db},
+    };
+
+    use super::SelectPlan;
+
+    #[test]
+    fn test_stacked_selects_are_fused() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let major = Predicate::from_term(Term::new(
+            Expression::Field("major_id".to_string()),
+            Expression::Constant(Value::Int(20)),
+        ));
+        let year = Predicate::from_term(Term::new(
+            Expression::Field("grad_year".to_string()),
+            Expression::Constant(Value::Int(2020)),
+        ));
+        let mm = db.metadata_manager();
+        let table_plan = || {
+            Box::new(TablePlan::new(
+                tx.clone(),
+                "student",
+                &mut mm.write().unwrap(),
+            ))
+        };
+
+        let mut stacked = SelectPlan::new(
+            Box::new(SelectPlan::new(table_plan(), major.clone())),
+            year.clone(),
+        );
+        let mut fused = SelectPlan::select(SelectPlan::select(table_plan(), major), year);
+        assert_eq!(
+            fused.to_string(),
+            "Select(major_id = 20 AND grad_year = 2020, Table(student))"
+        );
+
+        let run = |plan: &mut dyn Plan| {
+            let Scan::Select(mut scan) = plan.open() else {
+                panic!("expected a select scan");
+            };
+            let mut sids = vec![];
+            while scan.next() {
+                sids.push(scan.get_int("sid").unwrap());
+            }
+            (sids, scan.rows_examined())
+        };
+        let (stacked_sids, stacked_rows) = run(&mut stacked);
+        let (fused_sids, fused_rows) = run(&mut *fused);
+        assert_eq!(stacked_sids, vec![2, 6]);
+        assert_eq!(fused_sids, stacked_sids);
+
+        // The fused select reads each record once, rather than passing the records that satisfy
+        // the inner predicate up to another select
+        assert_eq!(fused_rows, 9);
+        assert_eq!(stacked_rows, 9 + 4);
     }
 }
@@ -0,0 +1,83 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    parser::parser::SortField,
+    scan::{
+        scan::Scan,
+        sort_scan::{SortScan, DEFAULT_RUN_SIZE},
+    },
+    schema::Schema,
+    transaction::Tx,
+};
+
+use super::plan::Plan;
+
+/// A plan that orders the records of an underlying plan by a list of sort fields, each in
+/// ascending or descending order.
+pub struct SortPlan {
+    tx: Arc<Mutex<Tx>>,
+    plan: Box<dyn Plan>,
+    sort_fields: Vec<SortField>,
+    run_size: usize,
+}
+
+impl SortPlan {
+    pub fn new(tx: Arc<Mutex<Tx>>, plan: Box<dyn Plan>, sort_fields: Vec<SortField>) -> Self {
+        Self {
+            tx,
+            plan,
+            sort_fields,
+            run_size: DEFAULT_RUN_SIZE,
+        }
+    }
+
+    /// Sort at most `run_size` records in memory at once, merging the sorted runs through
+    /// temporary tables. By default `DEFAULT_RUN_SIZE` records are sorted at once.
+    pub fn with_run_size(mut self, run_size: usize) -> Self {
+        self.run_size = run_size;
+        self
+    }
+}
+
+impl Plan for SortPlan {
+    fn open(&mut self) -> Scan {
+        let scan = Box::new(self.plan.open());
+        let tx = self.tx.clone();
+        let schema = self.plan.schema();
+        Scan::Sort(SortScan::with_run_size(
+            tx,
+            scan,
+            schema,
+            &self.sort_fields,
+            self.run_size,
+        ))
+    }
+
+    // Only blocks of the underlying plan are counted; the temporary table holding the sorted
+    // records is not.
+    fn blocks_accessed(&self) -> u64 {
+        self.plan.blocks_accessed()
+    }
+
+    fn records_output(&self) -> u64 {
+        self.plan.records_output()
+    }
+
+    fn distinct_values(&self, field_name: &str) -> u64 {
+        self.plan.distinct_values(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.plan.schema()
+    }
+}
+
+impl Display for SortPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sort_fields: Vec<String> = self.sort_fields.iter().map(|f| f.to_string()).collect();
+        write!(f, "Sort([{}], {})", sort_fields.join(", "), self.plan)
+    }
+}
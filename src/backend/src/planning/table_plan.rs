@@ -6,6 +6,7 @@ use std::{
 use crate::{
     layout::Layout,
     metadata::{metadata_manager::MetadataManager, stats_manager::StatisticsInfo},
+    parser::predicate::Predicate,
     scan::scan::Scan,
     schema::Schema,
     table_scan::TableScan,
@@ -39,6 +40,26 @@ impl TablePlan {
             stat_info,
         }
     }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Whether the table's statistics show that none of its records can satisfy a predicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - The predicate applied to the table's records.
+    pub fn excludes(&self, predicate: &Predicate) -> bool {
+        let schema = self.layout.schema();
+        predicate.is_unsatisfiable(&|field_name| {
+            if schema.has_field(field_name) {
+                self.stat_info.field_range(field_name)
+            } else {
+                None
+            }
+        })
+    }
 }
 
 impl Plan for TablePlan {
@@ -69,6 +90,6 @@ impl Plan for TablePlan {
 
 impl Display for TablePlan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        write!(f, "Table({})", self.tbl_name)
     }
 }
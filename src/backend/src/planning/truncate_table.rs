@@ -0,0 +1,151 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    metadata::metadata_manager::MetadataManager, record_page::RecordPage, scan::scan::Scannable,
+    table_scan::TableScan, transaction::Tx,
+};
+
+use super::update_planner::RowCount;
+
+/// Delete every record of a table, returning the number of records deleted.
+///
+/// Rather than deleting each record, the table's file, its overflow file, and the files of its
+/// indexes are truncated, so none of the records are written or logged. Tables do not keep a
+/// count of their records, so counting them still reads every block of the table once, making
+/// the truncation O(n) in the table's size; only the writes and log records of a record by record
+/// delete are saved. Versioned tables must not be truncated, since snapshot readers may still
+/// need the versions of deleted records.
+///
+/// # Arguments
+///
+/// * `metadata_mgr` - The metadata manager holding the table's catalog entries.
+/// * `tblname` - The name of the table.
+/// * `tx` - The transaction that the change will run inside of.
+pub fn truncate_table(
+    metadata_mgr: &MetadataManager,
+    tblname: &str,
+    tx: &Arc<Mutex<Tx>>,
+) -> Result<RowCount, String> {
+    let layout = metadata_mgr
+        .get_table_layout(tblname, tx)
+        .ok_or_else(|| format!("table '{}' does not exist", tblname))?;
+
+    // Values that did not fit in a page's dictionary are also stored in overflow blocks
+    let has_overflow = layout.has_overflow_fields() || !layout.dictionary_fields().is_empty();
+    // The count is the only reason the table's blocks are read
+    let mut count = 0;
+    {
+        let mut scan = TableScan::new(tx.clone(), layout, tblname);
+        while scan.next() {
+            count += 1;
+        }
+        scan.close();
+    }
+
+    for ii in metadata_mgr.get_index_info(tblname, tx.clone()).values() {
+        ii.open().clear();
+    }
+    tx.lock()
+        .unwrap()
+        .truncate(tblname)
+        .map_err(|e| format!("{:?}", e))?;
+    if has_overflow {
+        tx.lock()
+            .unwrap()
+            .truncate(&RecordPage::overflow_file(tblname))
+            .map_err(|e| format!("{:?}", e))?;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{
+        file_manager::HEADER_SIZE,
+        index::index::Index,
+        parser::constant::Value,
+        planning::{plan::Plan, table_plan::TablePlan},
+        scan::scan::Scannable,
+        tests::test_utils::{create_default_tables, default_test_db},
+    };
+
+    use super::truncate_table;
+
+    #[test]
+    fn test_truncate_table() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(truncate_table(&mm.read().unwrap(), "student", &tx), Ok(9));
+        tx.lock().unwrap().commit().unwrap();
+        let path = td.path().join("data").join("student");
+        assert_eq!(fs::metadata(path).unwrap().len(), HEADER_SIZE);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mut plan = TablePlan::new(tx.clone(), "student", &mut mm.write().unwrap());
+        let mut scan = plan.open();
+        assert!(!scan.next());
+        scan.close();
+
+        // The index on sid is emptied along with the table
+        let ii = mm
+            .read()
+            .unwrap()
+            .get_index_info("student", tx.clone())
+            .remove("sid")
+            .unwrap();
+        let mut index = ii.open();
+        index.before_first(&Value::Int(4));
+        assert!(!index.next());
+        index.close();
+
+        assert_eq!(
+            truncate_table(&mm.read().unwrap(), "nothing", &tx),
+            Err("table 'nothing' does not exist".to_string())
+        );
+    }
+
+    #[test]
+    fn test_truncate_table_rollback() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let mm = db.metadata_manager();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(truncate_table(&mm.read().unwrap(), "student", &tx), Ok(9));
+        tx.lock().unwrap().rollback().unwrap();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let ii = mm
+            .read()
+            .unwrap()
+            .get_index_info("student", tx.clone())
+            .remove("sid")
+            .unwrap();
+        let mut index = ii.open();
+        index.before_first(&Value::Int(4));
+        assert!(index.next());
+        index.close();
+
+        let mut plan = TablePlan::new(tx.clone(), "student", &mut mm.write().unwrap());
+        let mut scan = plan.open();
+        let mut sids = vec![];
+        while scan.next() {
+            sids.push(scan.get_int("sid").unwrap());
+        }
+        scan.close();
+        assert_eq!(sids, (1..=9).collect::<Vec<_>>());
+    }
+}
@@ -1,10 +1,14 @@
 use std::sync::{Arc, Mutex};
 
 use crate::{
-    parser::parser::{CreateNode, DeleteNode, InsertNode, UpdateNode},
+    parser::parser::{
+        AlterNode, CreateNode, DeleteNode, DropNode, InsertNode, TableName, UpdateNode, ViewName,
+    },
     transaction::Tx,
 };
 
+use super::plan::Plan;
+
 pub type RowCount = u64;
 
 pub trait UpdatePlanner {
@@ -31,4 +35,22 @@ pub trait UpdatePlanner {
         create: &CreateNode,
         tx: Arc<Mutex<Tx>>,
     ) -> Result<RowCount, String>;
+
+    /// Create a table from the plan of the query in a `CREATE TABLE ... AS SELECT` statement.
+    fn execute_create_table_as(
+        &mut self,
+        tblname: &TableName,
+        plan: Box<dyn Plan>,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String>;
+
+    fn execute_alter(&mut self, alter: &AlterNode, tx: Arc<Mutex<Tx>>) -> Result<RowCount, String>;
+
+    fn execute_drop(&mut self, drop: &DropNode, tx: Arc<Mutex<Tx>>) -> Result<RowCount, String>;
+
+    fn execute_refresh(
+        &mut self,
+        view_name: &ViewName,
+        tx: Arc<Mutex<Tx>>,
+    ) -> Result<RowCount, String>;
 }
@@ -0,0 +1,357 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::{
+    metadata::{metadata_manager::MetadataManager, table_manager::check_name_length},
+    parser::{
+        constant::Value,
+        parser::{
+            parse, AlterNode, CreateNode, DeleteNode, DropNode, FieldDefinitions, InsertNode,
+            RootNode, SelectField, SelectNode, UpdateNode,
+        },
+        predicate::Predicate,
+    },
+    planning::group_by_plan::{check_grouped_fields, group_by_schema},
+    schema::Schema,
+    transaction::Tx,
+};
+
+/// Checks statements against the metadata catalogs without executing them.
+///
+/// Validation resolves the same tables, views, and fields that the planners would, but never
+/// opens a scan over (or collects statistics for) a user table, so no records are read or
+/// written.
+pub struct Validator {
+    metadata_mgr: Arc<RwLock<MetadataManager>>,
+}
+
+impl Validator {
+    pub fn new(metadata_mgr: Arc<RwLock<MetadataManager>>) -> Self {
+        Self { metadata_mgr }
+    }
+
+    /// Validate a parsed statement.
+    ///
+    /// # Arguments
+    ///
+    /// * `ast` - The statement produced by the parser.
+    /// * `tx` - The transaction used to read the metadata catalogs.
+    pub fn validate(&self, ast: &RootNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        match ast {
+            RootNode::Select(select) => self.select_schema(select, tx).map(|_| ()),
+            RootNode::Insert(insert) => self.validate_insert(insert, tx),
+            RootNode::Delete(delete) => self.validate_delete(delete, tx),
+            RootNode::Update(update) => self.validate_modify(update, tx),
+            RootNode::Create(create) => self.validate_create(create, tx),
+            RootNode::Alter(alter) => self.validate_alter(alter, tx),
+            RootNode::Drop(drop) => self.validate_drop(drop, tx),
+            RootNode::Refresh(view_name) => self.validate_refresh(view_name, tx),
+        }
+    }
+
+    /// Resolve the schema of the records produced by a query.
+    fn select_schema(&self, select: &SelectNode, tx: &Arc<Mutex<Tx>>) -> Result<Schema, String> {
+        let mut schema = Schema::new();
+        for tblname in &select.tables {
+            let tbl_schema = self.relation_schema(tblname, tx)?;
+            for field_name in tbl_schema.fields() {
+                if schema.has_field(&field_name) {
+                    return Err(format!("field '{}' is ambiguous", field_name));
+                }
+                schema.add_from(&field_name, &tbl_schema);
+            }
+        }
+
+        // Constants are named after their values, and can only be selected without a FROM clause
+        for field in &select.fields {
+            let SelectField::Constant(val) = field else {
+                continue;
+            };
+            if !select.tables.is_empty() {
+                return Err(format!(
+                    "constant {} can only be selected by a query without a FROM clause",
+                    val
+                ));
+            }
+            let name = val.to_string();
+            match val {
+                _ if schema.has_field(&name) => {}
+                Value::Int(_) => {
+                    schema.add_int_field(&name);
+                }
+                Value::Varchar(s) => schema.add_string_field(&name, s.len() as u64),
+            }
+        }
+
+        Self::check_predicate(&schema, &select.predicate)?;
+
+        // HAVING filters the groups of an aggregate query, whose fields replace those of its
+        // tables
+        let schema = if select.is_aggregate() {
+            check_grouped_fields(select)?;
+            group_by_schema(&schema, &select.group_by, &select.aggregates())?
+        } else if select.having.is_some() {
+            return Err("HAVING requires GROUP BY or an aggregate".to_string());
+        } else {
+            schema
+        };
+        Self::check_predicate(&schema, &select.having)?;
+
+        let mut output = Schema::new();
+        let field_names = select.fields.iter().flat_map(|f| match f {
+            SelectField::Star => schema.fields(),
+            SelectField::FieldName(name) => vec![name.to_string()],
+            SelectField::Constant(val) => vec![val.to_string()],
+            SelectField::Aggregate(agg) => vec![agg.to_string()],
+        });
+        for name in field_names {
+            if !schema.has_field(&name) {
+                return Err(format!("field '{}' does not exist", name));
+            }
+            if output.has_field(&name) {
+                return Err(format!("field '{}' is selected more than once", name));
+            }
+            output.add_from(&name, &schema);
+        }
+
+        if let Some(sort_field) = select.order_by.iter().find(|f| !schema.has_field(&f.0)) {
+            return Err(format!("field '{}' does not exist", sort_field.0));
+        }
+
+        Ok(output)
+    }
+
+    /// Resolve the schema of a table or view.
+    fn relation_schema(&self, name: &str, tx: &Arc<Mutex<Tx>>) -> Result<Schema, String> {
+        let view_def = self.metadata_mgr.read().unwrap().get_view_def(name, tx);
+        if let Some(def) = view_def {
+            return match parse(&def)? {
+                RootNode::Select(node) => self.select_schema(&node, tx),
+                _ => Err(format!(
+                    "did not find SELECT query statement in view defined with name '{}'",
+                    name
+                )),
+            };
+        }
+
+        self.table_schema(name, tx)
+    }
+
+    /// Resolve the schema of a table, which must not be a view.
+    fn table_schema(&self, tblname: &str, tx: &Arc<Mutex<Tx>>) -> Result<Schema, String> {
+        let mm = self.metadata_mgr.read().unwrap();
+        // The records of a materialized view are held in a table with the view's name
+        if mm.get_view_def(tblname, tx).is_some() {
+            return Err(format!("'{}' is a view, not a table", tblname));
+        }
+
+        mm.get_table_layout(tblname, tx)
+            .map(|layout| layout.schema().clone())
+            .ok_or_else(|| format!("table '{}' does not exist", tblname))
+    }
+
+    fn validate_insert(&self, insert: &InsertNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let schema = self.table_schema(&insert.0, tx)?;
+        if insert.1.len() != insert.2.len() {
+            return Err(format!(
+                "insert into '{}' specifies {} fields but {} values",
+                insert.0,
+                insert.1.len(),
+                insert.2.len()
+            ));
+        }
+
+        for (field_name, val) in insert.fields() {
+            Self::check_assignment(&schema, field_name, val)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_delete(&self, delete: &DeleteNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let schema = self.table_schema(&delete.0, tx)?;
+        Self::check_predicate(&schema, &delete.1)
+    }
+
+    fn validate_modify(&self, update: &UpdateNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let schema = self.table_schema(&update.id, tx)?;
+        let field_type = schema
+            .get_field_type(&update.field)
+            .ok_or_else(|| format!("field '{}' does not exist", update.field))?;
+        if update.expr.field_type(&schema)? != field_type {
+            return Err(format!(
+                "cannot assign '{}' to field '{}': mismatched types",
+                update.expr, update.field
+            ));
+        }
+
+        Self::check_predicate(&schema, &update.where_clause)
+    }
+
+    fn validate_create(&self, create: &CreateNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        match create {
+            CreateNode::Table(tblname, fields, if_not_exists) => {
+                check_name_length("table", tblname)?;
+                if *if_not_exists
+                    && self
+                        .metadata_mgr
+                        .read()
+                        .unwrap()
+                        .get_table_layout(tblname, tx)
+                        .is_some()
+                {
+                    return Ok(());
+                }
+                self.check_name_available(tblname, tx)?;
+                Self::check_field_definitions(fields)
+            }
+            CreateNode::TableAs(tblname, select) => {
+                check_name_length("table", tblname)?;
+                self.check_name_available(tblname, tx)?;
+                for field_name in self.select_schema(select, tx)?.fields() {
+                    check_name_length("field", &field_name)?;
+                }
+                Ok(())
+            }
+            CreateNode::View(viewname, select) => {
+                check_name_length("view", viewname)?;
+                self.check_name_available(viewname, tx)?;
+                self.select_schema(select, tx).map(|_| ())
+            }
+            CreateNode::MaterializedView(viewname, select) => {
+                check_name_length("view", viewname)?;
+                self.check_name_available(viewname, tx)?;
+                let [tblname] = select.tables.as_slice() else {
+                    return Err(format!(
+                        "materialized view '{}' must select from a single table",
+                        viewname
+                    ));
+                };
+                if !select.order_by.is_empty() {
+                    return Err(format!(
+                        "materialized view '{}' cannot have an ORDER BY clause",
+                        viewname
+                    ));
+                }
+                if select.is_aggregate() {
+                    return Err(format!(
+                        "materialized view '{}' cannot group its records",
+                        viewname
+                    ));
+                }
+                self.table_schema(tblname, tx)?;
+                self.select_schema(select, tx).map(|_| ())
+            }
+            CreateNode::Index(idxname, tblname, field_name) => {
+                check_name_length("index", idxname)?;
+                let schema = self.table_schema(tblname, tx)?;
+                if !schema.has_field(field_name) {
+                    return Err(format!("field '{}' does not exist", field_name));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_alter(&self, alter: &AlterNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        match alter {
+            AlterNode::AddColumn(tblname, def) => {
+                check_name_length("field", &def.0)?;
+                let schema = self.table_schema(tblname, tx)?;
+                if schema.has_field(&def.0) {
+                    return Err(format!(
+                        "field '{}' already exists in table '{}'",
+                        def.0, tblname
+                    ));
+                }
+                Ok(())
+            }
+            AlterNode::DropColumn(tblname, field_name) => {
+                let schema = self.table_schema(tblname, tx)?;
+                if !schema.has_field(field_name) {
+                    return Err(format!("field '{}' does not exist", field_name));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_drop(&self, drop: &DropNode, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let mm = self.metadata_mgr.read().unwrap();
+        if !drop.if_exists && mm.get_table_layout(&drop.table, tx).is_none() {
+            return Err(format!("table '{}' does not exist", drop.table));
+        }
+
+        Ok(())
+    }
+
+    fn validate_refresh(&self, view_name: &str, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let mm = self.metadata_mgr.read().unwrap();
+        if mm.get_view_def(view_name, tx).is_none() {
+            return Err(format!("view '{}' does not exist", view_name));
+        }
+        if !mm.is_materialized_view(view_name, tx) {
+            return Err(format!("view '{}' is not materialized", view_name));
+        }
+
+        Ok(())
+    }
+
+    /// Verify that no table or view is already defined with the given name.
+    fn check_name_available(&self, name: &str, tx: &Arc<Mutex<Tx>>) -> Result<(), String> {
+        let mm = self.metadata_mgr.read().unwrap();
+        if mm.get_table_layout(name, tx).is_some() || mm.get_view_def(name, tx).is_some() {
+            return Err(format!("a table or view named '{}' already exists", name));
+        }
+
+        Ok(())
+    }
+
+    fn check_field_definitions(fields: &FieldDefinitions) -> Result<(), String> {
+        let mut seen = vec![];
+        for def in fields {
+            check_name_length("field", &def.0)?;
+            if seen.contains(&&def.0) {
+                return Err(format!("field '{}' is defined more than once", def.0));
+            }
+            seen.push(&def.0);
+        }
+
+        Ok(())
+    }
+
+    fn check_predicate(schema: &Schema, pred: &Option<Predicate>) -> Result<(), String> {
+        match pred {
+            Some(pred) => {
+                let mut pred = pred.clone();
+                pred.coerce(schema)?;
+                pred.type_check(schema)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Verify that a value can be stored in a field.
+    fn check_assignment(schema: &Schema, field_name: &str, val: &Value) -> Result<(), String> {
+        let field_type = schema
+            .get_field_type(field_name)
+            .ok_or_else(|| format!("field '{}' does not exist", field_name))?;
+        if val.field_type() != field_type {
+            return Err(format!(
+                "cannot assign {} to field '{}': mismatched types",
+                val, field_name
+            ));
+        }
+
+        if let Value::Varchar(s) = val {
+            let max_len = schema.get_field_length(field_name).unwrap_or_default();
+            if s.len() as u64 > max_len {
+                return Err(format!(
+                    "value {} exceeds the maximum length ({}) of field '{}'",
+                    val, max_len, field_name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,27 +1,136 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    mem::size_of,
+    sync::{Arc, Mutex},
+};
 
-use crate::{block_id::BlockId, layout::Layout, transaction::Tx};
+use crate::{
+    block_id::BlockId,
+    layout::{
+        LargeValuePolicy, Layout, PageFormat, CREATED_TX_FIELD, DELETED_TX_FIELD,
+        DICTIONARY_ENTRIES,
+    },
+    parser::constant::Value,
+    transaction::{Tx, TxError},
+};
 
 // TODO: slot should be a type
 
-const EMPTY: i32 = 0;
-const USED: i32 = 1;
+/// The state of a record slot. In a page of fixed-size slots the state is held in the first byte
+/// of the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotState {
+    /// The slot has never held a record.
+    Empty = 0,
+    /// The slot holds a record.
+    Used = 1,
+    /// The slot held a record that was deleted. Like an empty slot, it can be reused.
+    Deleted = 2,
+}
+
+impl SlotState {
+    fn from_flag(flag: i32) -> Self {
+        match flag {
+            1 => Self::Used,
+            2 => Self::Deleted,
+            _ => Self::Empty,
+        }
+    }
+}
+
+// The bits of a fixed-size slot's first word that hold its state
+const STATE_MASK: i32 = 0xff;
+
+// The deleting transaction of a versioned record that has not been deleted
+const NOT_DELETED: i32 = -1;
+
+const WORD: usize = size_of::<i32>();
+
+// A slotted page begins with the number of slots in its directory, followed by the offset of the
+// first record body. An offset of 0 means that no body has been written, so the bodies end at the
+// end of the page.
+const NUM_SLOTS_POS: usize = 0;
+const BODIES_START_POS: usize = WORD;
+const DIRECTORY_START: usize = 2 * WORD;
+
+// Each directory entry holds the slot's state, followed by the offset and length of its body
+const ENTRY_SIZE: usize = 3 * WORD;
 
+// An overflow block begins with a pointer to the next block holding the value, followed by the
+// part of the value stored in this block. A pointer holds the block number plus one, so that 0
+// marks the end of the chain; a slot holds a pointer to the first block, or 0 for an empty string.
+const OVERFLOW_NEXT_POS: usize = 0;
+const OVERFLOW_DATA_POS: usize = WORD;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum RecordPageError {
+    /// The slot does not hold a record, e.g. because the record was deleted.
+    SlotNotUsed(BlockId, i16),
+    /// The record in the slot cannot grow, since its slotted page has no space left.
+    PageFull(BlockId, i16),
+    /// The layout of the records has no field with the given name.
+    UnknownField(String),
+    /// The page could not be read or written by its transaction, e.g. because a lock could not be
+    /// acquired in time.
+    Tx(TxError),
+}
+
+impl From<TxError> for RecordPageError {
+    fn from(e: TxError) -> Self {
+        Self::Tx(e)
+    }
+}
+
+/// The records stored in a block.
+///
+/// Records are stored in fixed-size slots, or in a slotted page if the layout says so. In a
+/// slotted page a record body holds its fields in the order of their layout offsets: an integer
+/// takes 4 bytes, and a string takes its length plus 4 bytes. A record is inserted only if the
+/// page has room for it to grow to its largest size, but a later update that lengthens a string
+/// fails with `PageFull` if other records have since taken that space.
+///
+/// A fixed-size slot holds only a pointer for a field whose values are stored in overflow blocks.
+/// Each time such a value is written, it is stored in new blocks appended to the table's overflow
+/// file; the blocks of the value it replaces are not reused.
+///
+/// A fixed-size slot holds only a position in a dictionary at the end of the page for a
+/// dictionary-encoded field. A record is inserted only if every dictionary has room for its
+/// value, and a later update to a value that does not fit is stored in overflow blocks.
 pub struct RecordPage {
     tx: Arc<Mutex<Tx>>,
     blk: BlockId,
     layout: Layout,
+    body_fields: Vec<String>,
 }
 
 impl RecordPage {
     pub fn new(tx: Arc<Mutex<Tx>>, blk: BlockId, layout: Layout) -> Self {
-        tx.lock().unwrap().pin(&blk);
+        // TODO: error handling
+        Self::try_new(tx, blk, layout).expect("failed to pin record page")
+    }
 
-        Self {
+    /// Create a new `RecordPage`, returning an error if its block could not be pinned.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction used to access the page.
+    /// * `blk` - The block holding the records.
+    /// * `layout` - The layout of the records stored in the block.
+    pub fn try_new(tx: Arc<Mutex<Tx>>, blk: BlockId, layout: Layout) -> Result<Self, TxError> {
+        assert!(
+            !(layout.is_versioned() && layout.format() == PageFormat::Slotted),
+            "slotted pages cannot hold versioned records"
+        );
+        tx.lock().unwrap().try_pin(&blk)?;
+
+        let mut body_fields = layout.schema().fields();
+        body_fields.sort_by_key(|f| layout.offset(f));
+
+        Ok(Self {
             tx: tx.clone(),
             blk,
             layout,
-        }
+            body_fields,
+        })
     }
 
     /// Get the block number of the underlying page.
@@ -35,16 +144,11 @@ impl RecordPage {
     ///
     /// * `slot` - The slot to take the value from.
     /// * `field_name` - The field to read the i32 from.
-    pub fn get_int(&self, slot: i16, field_name: &str) -> i32 {
-        assert!(
-            self.get_flag(slot) == USED,
-            "the specified slot {} in block {} is not marked USED",
-            slot,
-            self.blk,
-        );
+    pub fn get_int(&self, slot: i16, field_name: &str) -> Result<i32, RecordPageError> {
+        self.check_used(slot)?;
 
-        let pos = self.offset(slot) + self.layout.offset(field_name) as usize;
-        self.tx.lock().unwrap().get_int(&self.blk, pos)
+        let pos = self.field_offset(slot, field_name)?;
+        Ok(self.tx.lock().unwrap().get_int(&self.blk, pos)?)
     }
 
     /// Get a String value from a field.
@@ -53,15 +157,17 @@ impl RecordPage {
     ///
     /// * `slot` - The slot to take the value from.
     /// * `field_name` - The field to read the String from.
-    pub fn get_string(&self, slot: i16, field_name: &str) -> String {
-        assert!(
-            self.get_flag(slot) == USED,
-            "the specified slot {} is not marked USED",
-            slot
-        );
+    pub fn get_string(&self, slot: i16, field_name: &str) -> Result<String, RecordPageError> {
+        self.check_used(slot)?;
 
-        let pos = self.offset(slot) + self.layout.offset(field_name) as usize;
-        self.tx.lock().unwrap().get_string(&self.blk, pos)
+        let pos = self.field_offset(slot, field_name)?;
+        if self.layout.is_overflow_field(field_name) {
+            return Ok(self.read_overflow(pos)?);
+        }
+        if self.layout.is_dictionary_field(field_name) {
+            return Ok(self.read_dictionary(pos, field_name)?);
+        }
+        Ok(self.tx.lock().unwrap().get_string(&self.blk, pos)?)
     }
 
     /// Set an integer field in a slot.
@@ -71,15 +177,17 @@ impl RecordPage {
     /// * `slot` - The slot where the field will be set.
     /// * `field_name` - The name of the field to set.
     /// * `val` - The integer value.
-    pub fn set_int(&mut self, slot: i16, field_name: &str, val: i32) {
-        assert!(
-            self.get_flag(slot) == USED,
-            "the specified slot {} is not marked USED",
-            slot
-        );
+    pub fn set_int(
+        &mut self,
+        slot: i16,
+        field_name: &str,
+        val: i32,
+    ) -> Result<(), RecordPageError> {
+        self.check_used(slot)?;
 
-        let pos = self.offset(slot) + self.layout.offset(field_name) as usize;
-        self.tx.lock().unwrap().set_int(&self.blk, pos, val, true);
+        let pos = self.field_offset(slot, field_name)?;
+        self.tx.lock().unwrap().set_int(&self.blk, pos, val, true)?;
+        Ok(())
     }
 
     /// Set string field in a slot.
@@ -89,23 +197,68 @@ impl RecordPage {
     /// * `slot` - The slot where the field will be set.
     /// * `field_name` - The name of the field to set.
     /// * `val` - The string value.
-    pub fn set_string(&mut self, slot: i16, field_name: &str, val: &str) {
-        assert!(
-            self.get_flag(slot) == USED,
-            "the specified slot {} is not marked USED",
-            slot
-        );
+    pub fn set_string(
+        &mut self,
+        slot: i16,
+        field_name: &str,
+        val: &str,
+    ) -> Result<(), RecordPageError> {
+        self.check_used(slot)?;
+        let val = self.truncate_to_field(field_name, val);
+        if self.layout.format() == PageFormat::Slotted {
+            return self.set_body_string(slot, field_name, val);
+        }
 
-        let pos = self.offset(slot) + self.layout.offset(field_name) as usize;
+        let pos = self.field_offset(slot, field_name)?;
+        if self.layout.is_overflow_field(field_name) {
+            self.write_overflow(pos, val)?;
+            return Ok(());
+        }
+        if self.layout.is_dictionary_field(field_name) {
+            self.write_dictionary(pos, field_name, val)?;
+            return Ok(());
+        }
         self.tx
             .lock()
             .unwrap()
-            .set_string(&self.blk, pos, val, true);
+            .set_string(&self.blk, pos, val, true)?;
+        Ok(())
+    }
+
+    /// Mark the specified slot as deleted, so that it can be reused.
+    ///
+    /// A versioned record is instead marked as deleted by the transaction, and stays in its slot
+    /// for transactions reading a snapshot that includes it.
+    pub fn delete(&mut self, slot: i16) -> Result<(), TxError> {
+        if self.layout.is_versioned() {
+            let tx_num = self.tx.lock().unwrap().tx_num();
+            return self.set_version(slot, DELETED_TX_FIELD, tx_num as i32);
+        }
+
+        self.set_state(slot, SlotState::Deleted, true)
     }
 
-    /// Mark the specified slot as empty.
-    pub fn delete(&mut self, slot: i16) {
-        self.set_flag(slot, EMPTY);
+    /// Get the most space, in bytes, that a record with the given layout can take in a page: the
+    /// size of its slot along with the page's dictionaries, or in a slotted page its directory
+    /// entry and its longest body.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The layout of the record.
+    pub fn max_record_size(layout: &Layout) -> usize {
+        match layout.format() {
+            PageFormat::Fixed => layout.slot_size() as usize + layout.dictionary_size(),
+            PageFormat::Slotted => DIRECTORY_START + ENTRY_SIZE + Self::max_body_length(layout),
+        }
+    }
+
+    /// Get the name of the file holding the overflow blocks of a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The name of the file holding the table's records.
+    pub fn overflow_file(file_id: &str) -> String {
+        format!("{}.overflow", file_id)
     }
 
     /// Get the `RecordPage`'s underlying `BlockId`.
@@ -113,105 +266,623 @@ impl RecordPage {
         self.blk.clone()
     }
 
-    /// Format the RecordPage so that all slots are empty with default values.
-    pub fn format(&mut self) {
-        let mut slot = 0i16;
-        while self.is_valid_slot(slot) {
-            self.tx
-                .lock()
-                .unwrap()
-                .set_int(&self.blk, self.offset(slot), EMPTY, false);
-
-            let schema = self.layout.schema();
-            for field_name in schema.fields().iter() {
-                let field_pos = self.offset(slot) + self.layout.offset(field_name) as usize;
-                // TODO: terrible!
-                match schema.get_field_type(field_name) {
-                    Some(0) => self
-                        .tx
-                        .lock()
-                        .unwrap()
-                        .set_int(&self.blk, field_pos, 0, false),
-                    Some(1) => self
-                        .tx
-                        .lock()
-                        .unwrap()
-                        .set_string(&self.blk, field_pos, "", false),
-                    _ => panic!("Unsupported schema field type"),
-                }
-            }
+    /// Format the RecordPage so that all slots are empty with default values. The page is written
+    /// as a single image, and the write is not logged.
+    pub fn format(&mut self) -> Result<(), TxError> {
+        self.write_empty_image(false)
+    }
 
-            slot += 1;
-        }
+    /// Format a RecordPage that may already hold records, possibly written with a different
+    /// layout. Unlike `format`, the old contents of the page are restored if the transaction rolls
+    /// back.
+    pub fn reformat(&mut self) -> Result<(), TxError> {
+        // The whole old image is logged in a single record, whatever layout its records used
+        self.write_empty_image(true)
     }
 
-    /// Get the next slot available for insert after the specified slot. The chosen slot will be
-    /// updated with a `USED` flag.
+    // Write the image of a page with every slot empty. It is all zeroes in every format: an empty
+    // fixed-size slot has a zero flag and zeroed fields, an empty string has a zero length or
+    // dictionary code, and an empty slotted page or dictionary has a zero count.
+    fn write_empty_image(&mut self, ok_to_log: bool) -> Result<(), TxError> {
+        let mut tx = self.tx.lock().unwrap();
+        let image = vec![0; tx.block_size()];
+        tx.set_bytes(&self.blk, 0, &image, ok_to_log)
+    }
+
+    /// Get the next slot available for insert after the specified slot, which is either empty or
+    /// holds a deleted record. The chosen slot will be marked `Used`.
     ///
     /// # Arguments
     ///
-    /// * `slot` - The search for an EMPTY slot begins directly after this slot.
-    pub fn insert_after(&mut self, slot: i16) -> i16 {
-        let new_slot = self.search_after(slot, EMPTY);
+    /// * `slot` - The search for an available slot begins directly after this slot.
+    pub fn insert_after(&mut self, slot: i16) -> Result<i16, TxError> {
+        if self.layout.format() == PageFormat::Slotted {
+            return self.insert_body_after(slot);
+        }
+
+        // Each new record may add a value to every dictionary
+        if !self.has_dictionary_room()? {
+            return Ok(-1);
+        }
+        let new_slot = self.search_after(slot, |state| state != SlotState::Used)?;
         if new_slot != -1 {
-            self.set_flag(new_slot, USED);
+            self.set_state(new_slot, SlotState::Used, true)?;
+            if self.layout.is_versioned() {
+                let tx_num = self.tx.lock().unwrap().tx_num();
+                self.set_version(new_slot, CREATED_TX_FIELD, tx_num as i32)?;
+                self.set_version(new_slot, DELETED_TX_FIELD, NOT_DELETED)?;
+            }
         }
-        new_slot
+        Ok(new_slot)
     }
 
-    /// Get the next used slot in the page
+    /// Get the next used slot in the page, skipping versioned records that are not visible to the
+    /// transaction.
     ///
     /// # Arguments
     ///
-    /// * `slot` - The search for a USED slot starts directly after this slot.
-    pub fn next_after(&self, slot: i16) -> i16 {
-        self.search_after(slot, USED)
+    /// * `slot` - The search for a used slot starts directly after this slot.
+    pub fn next_after(&self, slot: i16) -> Result<i16, TxError> {
+        let mut slot = slot;
+        loop {
+            slot = self.search_after(slot, |state| state == SlotState::Used)?;
+            if slot == -1 || self.is_visible(slot)? {
+                return Ok(slot);
+            }
+        }
+    }
+
+    /// Whether the record in a used slot is visible to the transaction: it was created, and not
+    /// deleted, by a transaction whose changes are visible. Records of unversioned layouts are
+    /// always visible.
+    pub fn is_visible(&self, slot: i16) -> Result<bool, TxError> {
+        if !self.layout.is_versioned() {
+            return Ok(true);
+        }
+
+        let created_pos = self.version_offset(slot, CREATED_TX_FIELD);
+        let deleted_pos = self.version_offset(slot, DELETED_TX_FIELD);
+        let mut tx = self.tx.lock().unwrap();
+        let created = tx.get_int(&self.blk, created_pos)?;
+        let deleted = tx.get_int(&self.blk, deleted_pos)?;
+        Ok(tx.is_visible(created as i64)
+            && (deleted == NOT_DELETED || !tx.is_visible(deleted as i64)))
+    }
+
+    // Set a hidden version field of a record.
+    fn set_version(&mut self, slot: i16, field_name: &str, tx_num: i32) -> Result<(), TxError> {
+        let pos = self.version_offset(slot, field_name);
+        self.tx
+            .lock()
+            .unwrap()
+            .set_int(&self.blk, pos, tx_num, true)
+    }
+
+    // Get the offset in the page of a hidden version field of a record.
+    fn version_offset(&self, slot: i16, field_name: &str) -> usize {
+        self.layout
+            .field_offset(slot, field_name)
+            .expect("versioned layout has version fields")
     }
 
-    // Search for the next slot with the given flag.
-    fn search_after(&self, slot: i16, flag: i32) -> i16 {
+    // Search for the next slot whose state matches the predicate.
+    fn search_after(&self, slot: i16, matches: impl Fn(SlotState) -> bool) -> Result<i16, TxError> {
         let mut slot = slot + 1;
-        while self.is_valid_slot(slot) {
-            if self.get_flag(slot) == flag {
-                return slot;
+        while self.is_valid_slot(slot)? {
+            if matches(self.slot_state(slot)?) {
+                return Ok(slot);
             }
 
             slot += 1;
         }
 
-        -1
+        Ok(-1)
     }
 
-    // Returns a boolean indicating whether or not the slot fits in a record page.
-    fn is_valid_slot(&self, slot: i16) -> bool {
-        self.offset(slot + 1) as u64 <= self.tx.lock().unwrap().block_size() as u64
+    /// Whether the slot fits in the record page, regardless of whether it holds a record.
+    pub fn is_valid_slot(&self, slot: i16) -> Result<bool, TxError> {
+        Ok((slot as usize) < self.num_valid_slots()?)
+    }
+
+    // Get the number of slots in the page: every slot that fits in a page of fixed-size slots, or
+    // the entries of a slotted page's directory.
+    fn num_valid_slots(&self) -> Result<usize, TxError> {
+        match self.layout.format() {
+            PageFormat::Fixed => {
+                let block_size = self.tx.lock().unwrap().block_size();
+                Ok(self.layout.max_records(block_size))
+            }
+            PageFormat::Slotted => self.num_slots(),
+        }
+    }
+
+    /// Get the number of slots that a record can be inserted into without growing the page: those
+    /// that are empty, or that held a record that was deleted. A slotted page adds entries to its
+    /// directory while it has space, so only its unused entries are counted. No record can be
+    /// inserted once a dictionary is full.
+    pub fn free_slots(&self) -> Result<usize, TxError> {
+        if !self.has_dictionary_room()? {
+            return Ok(0);
+        }
+        let mut count = 0;
+        for slot in 0..self.num_valid_slots()? as i16 {
+            if self.slot_state(slot)? != SlotState::Used {
+                count += 1;
+            }
+        }
+        Ok(count)
     }
 
     // TODO: get_string
     //pub fn get_string(&self, slot: u16, field_name: &str) -> i32 {
-    //    let pos = self.offset(slot) + self.layout.offset(field_name) as usize;
+    //    let pos = self.layout.field_offset(slot, field_name);
     //    self.tx.lock().unwrap().get_string(&self.blk, pos)
     //}
 
-    // Set the flag of the specified slot.
-    fn set_flag(&mut self, slot: i16, flag: i32) {
+    // Set the state of the specified slot. The state of a fixed-size slot shares a word with
+    // the slot's first field, which is written back unchanged.
+    fn set_state(&mut self, slot: i16, state: SlotState, ok_to_log: bool) -> Result<(), TxError> {
+        let pos = self.offset(slot);
+        let mut tx = self.tx.lock().unwrap();
+        let flag = match self.layout.format() {
+            PageFormat::Fixed => (tx.get_int(&self.blk, pos)? & !STATE_MASK) | state as i32,
+            PageFormat::Slotted => state as i32,
+        };
+        tx.set_int(&self.blk, pos, flag, ok_to_log)
+    }
+
+    /// Whether the specified slot holds a record.
+    pub fn is_used(&self, slot: i16) -> Result<bool, TxError> {
+        Ok(slot >= 0 && self.is_valid_slot(slot)? && self.slot_state(slot)? == SlotState::Used)
+    }
+
+    // Return an error if the specified slot does not hold a record.
+    fn check_used(&self, slot: i16) -> Result<(), RecordPageError> {
+        if !self.is_used(slot)? {
+            return Err(RecordPageError::SlotNotUsed(self.blk.clone(), slot));
+        }
+
+        Ok(())
+    }
+
+    /// Get the state of the specified slot, which must fit in the page.
+    pub fn slot_state(&self, slot: i16) -> Result<SlotState, TxError> {
+        let flag = self
+            .tx
+            .lock()
+            .unwrap()
+            .get_int(&self.blk, self.offset(slot))?;
+        Ok(match self.layout.format() {
+            PageFormat::Fixed => SlotState::from_flag(flag & STATE_MASK),
+            PageFormat::Slotted => SlotState::from_flag(flag),
+        })
+    }
+
+    // get the offset in the page of the specified slot, or of its directory entry in a slotted
+    // page.
+    fn offset(&self, slot: i16) -> usize {
+        match self.layout.format() {
+            PageFormat::Fixed => self.layout.slot_offset(slot),
+            PageFormat::Slotted => DIRECTORY_START + slot as usize * ENTRY_SIZE,
+        }
+    }
+
+    // Get the offset in the page of a field of the record in the specified slot.
+    fn field_offset(&self, slot: i16, field_name: &str) -> Result<usize, RecordPageError> {
+        let unknown = || RecordPageError::UnknownField(field_name.to_string());
+        if self.layout.format() == PageFormat::Fixed {
+            return self
+                .layout
+                .field_offset(slot, field_name)
+                .ok_or_else(unknown);
+        }
+
+        let mut pos = self.get_entry_int(slot, 1)? as usize;
+        for name in &self.body_fields {
+            if name == field_name {
+                return Ok(pos);
+            }
+            pos += self.field_length(name, pos)?;
+        }
+
+        Err(unknown())
+    }
+
+    // Get the number of bytes taken by the value of a field stored at the given offset of a
+    // record body.
+    fn field_length(&self, field_name: &str, pos: usize) -> Result<usize, TxError> {
+        Ok(match self.layout.schema().get_field_type(field_name) {
+            Some(0) => WORD,
+            Some(1) => WORD + self.tx.lock().unwrap().get_string(&self.blk, pos)?.len(),
+            _ => panic!("Unsupported schema field type"),
+        })
+    }
+
+    // Get the number of slots in the directory of a slotted page.
+    fn num_slots(&self) -> Result<usize, TxError> {
+        Ok(self.tx.lock().unwrap().get_int(&self.blk, NUM_SLOTS_POS)? as usize)
+    }
+
+    // Get the offset in a slotted page where the record bodies begin.
+    fn bodies_start(&self) -> Result<usize, TxError> {
+        let mut tx = self.tx.lock().unwrap();
+        Ok(match tx.get_int(&self.blk, BODIES_START_POS)? {
+            0 => tx.block_size(),
+            start => start as usize,
+        })
+    }
+
+    // Get the number of bytes between the end of the slot directory and the first record body.
+    fn free_space(&self) -> Result<usize, TxError> {
+        Ok(self.bodies_start()? - (DIRECTORY_START + self.num_slots()? * ENTRY_SIZE))
+    }
+
+    // Get the offset (at index 1) or length (at index 2) of a slot's body from its directory
+    // entry.
+    fn get_entry_int(&self, slot: i16, index: usize) -> Result<i32, TxError> {
         self.tx
             .lock()
             .unwrap()
-            .set_int(&self.blk, self.offset(slot), flag, true);
+            .get_int(&self.blk, self.offset(slot) + index * WORD)
     }
 
-    // Get the flag of the specified slot.
-    fn get_flag(&self, slot: i16) -> i32 {
+    fn set_entry_int(&mut self, slot: i16, index: usize, val: i32) -> Result<(), TxError> {
         self.tx
             .lock()
             .unwrap()
-            .get_int(&self.blk, self.offset(slot))
+            .set_int(&self.blk, self.offset(slot) + index * WORD, val, true)
     }
 
-    // get the offset in the page of the specified slot.
-    fn offset(&self, slot: i16) -> usize {
-        self.layout.slot_size() as usize * slot as usize
+    // Get the length of a record body holding the given values.
+    fn body_length(vals: &[Value]) -> usize {
+        vals.iter()
+            .map(|val| match val {
+                Value::Int(_) => WORD,
+                Value::Varchar(s) => WORD + s.len(),
+            })
+            .sum()
+    }
+
+    // Get the length of a record body when each of its strings is as long as its field allows.
+    fn max_body_length(layout: &Layout) -> usize {
+        let schema = layout.schema();
+        schema
+            .fields()
+            .iter()
+            .map(|f| match schema.get_field_type(f) {
+                Some(1) => WORD + schema.get_field_length(f).unwrap_or_default() as usize,
+                _ => WORD,
+            })
+            .sum()
+    }
+
+    // Read the values of the record in a slot, in body order.
+    fn read_body(&self, slot: i16) -> Result<Vec<Value>, TxError> {
+        let mut pos = self.get_entry_int(slot, 1)? as usize;
+        let mut vals = vec![];
+        for name in &self.body_fields {
+            let mut tx = self.tx.lock().unwrap();
+            let val = match self.layout.schema().get_field_type(name) {
+                Some(0) => Value::Int(tx.get_int(&self.blk, pos)?),
+                Some(1) => Value::Varchar(tx.get_string(&self.blk, pos)?),
+                _ => panic!("Unsupported schema field type"),
+            };
+            drop(tx);
+
+            pos += Self::body_length(std::slice::from_ref(&val));
+            vals.push(val);
+        }
+        Ok(vals)
+    }
+
+    // Write record values at the given offset. Each value is written over whatever bytes a
+    // moved or deleted body left behind, so the first word of every string is zeroed before
+    // the string is logged, keeping the logged old value readable.
+    fn write_body(&mut self, pos: usize, vals: &[Value]) -> Result<(), TxError> {
+        let mut tx = self.tx.lock().unwrap();
+        let mut pos = pos;
+        for val in vals {
+            match val {
+                Value::Int(i) => tx.set_int(&self.blk, pos, *i, true)?,
+                Value::Varchar(s) => {
+                    tx.set_int(&self.blk, pos, 0, true)?;
+                    tx.set_string(&self.blk, pos, s, true)?;
+                }
+            }
+            pos += Self::body_length(std::slice::from_ref(val));
+        }
+        Ok(())
+    }
+
+    // Take space for a body of the given length from the free space of a slotted page, returning
+    // the offset of the body. The space must be available.
+    fn allocate_body(&mut self, len: usize) -> Result<usize, TxError> {
+        let pos = self.bodies_start()? - len;
+        self.tx
+            .lock()
+            .unwrap()
+            .set_int(&self.blk, BODIES_START_POS, pos as i32, true)?;
+        Ok(pos)
+    }
+
+    // Move the bodies of every used slot to the end of a slotted page, reclaiming the space left
+    // by deleted and relocated bodies.
+    fn compact(&mut self) -> Result<(), TxError> {
+        let mut records: Vec<(i16, Vec<Value>)> = vec![];
+        for slot in 0..self.num_slots()? as i16 {
+            if self.slot_state(slot)? == SlotState::Used {
+                records.push((slot, self.read_body(slot)?));
+            }
+        }
+
+        self.tx
+            .lock()
+            .unwrap()
+            .set_int(&self.blk, BODIES_START_POS, 0, true)?;
+        for (slot, vals) in records {
+            let len = Self::body_length(&vals);
+            let pos = self.allocate_body(len)?;
+            self.write_body(pos, &vals)?;
+            self.set_entry_int(slot, 1, pos as i32)?;
+            self.set_entry_int(slot, 2, len as i32)?;
+        }
+        Ok(())
+    }
+
+    // Insert into a slotted page, reusing the directory entry of a deleted record or adding an
+    // entry to the directory. The new record holds default values.
+    fn insert_body_after(&mut self, slot: i16) -> Result<i16, TxError> {
+        let num_slots = self.num_slots()? as i16;
+        let mut new_slot = num_slots.max(slot + 1);
+        for s in slot + 1..num_slots {
+            if self.slot_state(s)? != SlotState::Used {
+                new_slot = s;
+                break;
+            }
+        }
+        let new_entries = (new_slot + 1 - num_slots).max(0) as usize;
+
+        let needed = new_entries * ENTRY_SIZE + Self::max_body_length(&self.layout);
+        if self.free_space()? < needed {
+            self.compact()?;
+            if self.free_space()? < needed {
+                return Ok(-1);
+            }
+        }
+
+        if new_entries > 0 {
+            self.tx
+                .lock()
+                .unwrap()
+                .set_int(&self.blk, NUM_SLOTS_POS, new_slot as i32 + 1, true)?;
+        }
+
+        let schema = self.layout.schema();
+        let vals: Vec<Value> = self
+            .body_fields
+            .iter()
+            .map(|f| match schema.get_field_type(f) {
+                Some(0) => Value::Int(0),
+                _ => Value::Varchar(String::new()),
+            })
+            .collect();
+        let len = Self::body_length(&vals);
+        let pos = self.allocate_body(len)?;
+        self.write_body(pos, &vals)?;
+        self.set_entry_int(new_slot, 1, pos as i32)?;
+        self.set_entry_int(new_slot, 2, len as i32)?;
+        self.set_state(new_slot, SlotState::Used, true)?;
+
+        Ok(new_slot)
+    }
+
+    // Shorten a string to the length of its field, if the layout truncates large values.
+    fn truncate_to_field<'a>(&self, field_name: &str, val: &'a str) -> &'a str {
+        if self.layout.large_values() != LargeValuePolicy::Truncate {
+            return val;
+        }
+
+        let mut len = self
+            .layout
+            .schema()
+            .get_field_length(field_name)
+            .unwrap_or_default() as usize;
+        if val.len() <= len {
+            return val;
+        }
+        while !val.is_char_boundary(len) {
+            len -= 1;
+        }
+        &val[..len]
+    }
+
+    // Read a value from the chain of overflow blocks pointed to by the given offset.
+    fn read_overflow(&self, pos: usize) -> Result<String, TxError> {
+        let first = self.tx.lock().unwrap().get_int(&self.blk, pos)?;
+        self.read_overflow_chain(first)
+    }
+
+    // Read a value from the chain of overflow blocks starting at the given pointer.
+    fn read_overflow_chain(&self, first: i32) -> Result<String, TxError> {
+        let file_id = Self::overflow_file(self.blk.file_id());
+        let mut tx = self.tx.lock().unwrap();
+        let mut next = first;
+        let mut val = String::new();
+        while next > 0 {
+            let blk = BlockId::new(&file_id, next as u64 - 1);
+            tx.pin(&blk);
+            let part = tx
+                .get_int(&blk, OVERFLOW_NEXT_POS)
+                .and_then(|n| Ok((n, tx.get_string(&blk, OVERFLOW_DATA_POS)?)));
+            tx.unpin(&blk);
+            let (n, part) = part?;
+            next = n;
+            val.push_str(&part);
+        }
+        Ok(val)
+    }
+
+    // Write a value to new overflow blocks, storing a pointer to the first of them at the given
+    // offset.
+    fn write_overflow(&mut self, pos: usize, val: &str) -> Result<(), TxError> {
+        let first = self.write_overflow_chain(val)?;
+        self.tx.lock().unwrap().set_int(&self.blk, pos, first, true)
+    }
+
+    // Write a value to new overflow blocks, returning a pointer to the first of them.
+    fn write_overflow_chain(&mut self, val: &str) -> Result<i32, TxError> {
+        let file_id = Self::overflow_file(self.blk.file_id());
+        let mut tx = self.tx.lock().unwrap();
+
+        // Each block holds as much of the value as fits after its pointer and the length of the
+        // string stored in it
+        let capacity = tx.block_size() - OVERFLOW_DATA_POS - WORD;
+        let mut parts = vec![];
+        let mut rest = val;
+        while !rest.is_empty() {
+            let mut len = rest.len().min(capacity);
+            while !rest.is_char_boundary(len) {
+                len -= 1;
+            }
+            parts.push(&rest[..len]);
+            rest = &rest[len..];
+        }
+
+        let blks = parts
+            .iter()
+            .map(|_| tx.append(&file_id))
+            .collect::<Result<Vec<BlockId>, TxError>>()?;
+        for (i, (blk, part)) in blks.iter().zip(parts).enumerate() {
+            let next = blks.get(i + 1).map_or(0, |b| b.num() as i32 + 1);
+            tx.pin(blk);
+            let res = tx
+                .set_int(blk, OVERFLOW_NEXT_POS, next, true)
+                .and_then(|_| tx.set_string(blk, OVERFLOW_DATA_POS, part, true));
+            tx.unpin(blk);
+            res?;
+        }
+
+        Ok(blks.first().map_or(0, |b| b.num() as i32 + 1))
+    }
+
+    // Read the value of a dictionary-encoded field from the slot at the given offset, which holds
+    // the position of the value in the page's dictionary plus one, 0 for an empty string, or the
+    // negated pointer to the overflow blocks of a value that did not fit in the dictionary.
+    fn read_dictionary(&self, pos: usize, field_name: &str) -> Result<String, TxError> {
+        let code = self.tx.lock().unwrap().get_int(&self.blk, pos)?;
+        match code {
+            0 => Ok(String::new()),
+            code if code < 0 => self.read_overflow_chain(-code),
+            code => {
+                let entry = self.dictionary_entry(field_name, code as usize - 1);
+                self.tx.lock().unwrap().get_string(&self.blk, entry)
+            }
+        }
+    }
+
+    // Write the value of a dictionary-encoded field to the slot at the given offset, adding the
+    // value to the page's dictionary if it is not there yet. Values are never removed from a
+    // dictionary, so a value that finds it full is stored in overflow blocks instead.
+    fn write_dictionary(&mut self, pos: usize, field_name: &str, val: &str) -> Result<(), TxError> {
+        let code = if val.is_empty() {
+            0
+        } else {
+            match self.dictionary_position(field_name, val)? {
+                Some(index) => index as i32 + 1,
+                None => -self.write_overflow_chain(val)?,
+            }
+        };
+        self.tx.lock().unwrap().set_int(&self.blk, pos, code, true)
+    }
+
+    // Find the position of a value in the dictionary of a field, adding it if there is room.
+    fn dictionary_position(
+        &mut self,
+        field_name: &str,
+        val: &str,
+    ) -> Result<Option<usize>, TxError> {
+        let start = self.dictionary_start(field_name);
+        let entry_length = self.layout.dictionary_entry_length(field_name);
+        let entry = |index: usize| start + WORD + index * entry_length;
+
+        let mut tx = self.tx.lock().unwrap();
+        let count = tx.get_int(&self.blk, start)? as usize;
+        for index in 0..count {
+            if tx.get_string(&self.blk, entry(index))? == val {
+                return Ok(Some(index));
+            }
+        }
+        if count == DICTIONARY_ENTRIES || WORD + val.len() > entry_length {
+            return Ok(None);
+        }
+
+        tx.set_string(&self.blk, entry(count), val, true)?;
+        tx.set_int(&self.blk, start, count as i32 + 1, true)?;
+        Ok(Some(count))
+    }
+
+    // Whether the dictionary of every dictionary-encoded field has room for another value, so
+    // that a new record's values can be added to them.
+    fn has_dictionary_room(&self) -> Result<bool, TxError> {
+        for f in self.layout.dictionary_fields() {
+            let start = self.dictionary_start(&f);
+            if self.tx.lock().unwrap().get_int(&self.blk, start)? as usize >= DICTIONARY_ENTRIES {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Get the offset in the page of the dictionary of a field, which begins with the number of
+    // values it holds.
+    fn dictionary_start(&self, field_name: &str) -> usize {
+        let block_size = self.tx.lock().unwrap().block_size();
+        self.layout.dictionary_offset(field_name, block_size)
+    }
+
+    // Get the offset in the page of a value in the dictionary of a field.
+    fn dictionary_entry(&self, field_name: &str, index: usize) -> usize {
+        self.dictionary_start(field_name)
+            + WORD
+            + index * self.layout.dictionary_entry_length(field_name)
+    }
+
+    // Set a string in a slotted page. A body that no longer fits in its space is moved to the
+    // free space of the page, compacting the page first if necessary.
+    fn set_body_string(
+        &mut self,
+        slot: i16,
+        field_name: &str,
+        val: &str,
+    ) -> Result<(), RecordPageError> {
+        let mut vals = self.read_body(slot)?;
+        let index = self
+            .body_fields
+            .iter()
+            .position(|f| f == field_name)
+            .ok_or_else(|| RecordPageError::UnknownField(field_name.to_string()))?;
+        vals[index] = Value::Varchar(val.to_string());
+
+        let len = Self::body_length(&vals);
+        if len <= self.get_entry_int(slot, 2)? as usize {
+            let pos = self.get_entry_int(slot, 1)? as usize;
+            self.write_body(pos, &vals)?;
+            self.set_entry_int(slot, 2, len as i32)?;
+            return Ok(());
+        }
+
+        if self.free_space()? < len {
+            self.compact()?;
+            if self.free_space()? < len {
+                return Err(RecordPageError::PageFull(self.blk.clone(), slot));
+            }
+        }
+
+        let pos = self.allocate_body(len)?;
+        self.write_body(pos, &vals)?;
+        self.set_entry_int(slot, 1, pos as i32)?;
+        self.set_entry_int(slot, 2, len as i32)?;
+        Ok(())
     }
 }
 
@@ -228,12 +899,20 @@ mod tests {
         layout,
         lock_table::LockTable,
         log_manager::LogManager,
-        schema::Schema,
+        schema::{Encoding, Schema},
+        tests::test_utils::test_db,
     };
 
     use super::*;
 
     fn get_record_page() -> RecordPage {
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        schema.add_string_field("B", 10);
+        new_record_page(Layout::from_schema(schema))
+    }
+
+    fn new_record_page(layout: Layout) -> RecordPage {
         let td = tempdir().unwrap();
         let data_dir = td.path().join("data");
         fs::create_dir_all(&data_dir).unwrap();
@@ -251,13 +930,15 @@ mod tests {
         let lt = Arc::new(LockTable::new());
         let t = Arc::new(Mutex::new(Tx::new(fm.clone(), lm.clone(), bm.clone(), lt)));
 
-        let blk = t.lock().unwrap().append("T");
+        let blk = t.lock().unwrap().append("T").unwrap();
+        RecordPage::new(t.clone(), blk, layout)
+    }
 
+    fn wide_layout(format: PageFormat) -> Layout {
         let mut schema = Schema::new();
         schema.add_int_field("A");
-        schema.add_string_field("B", 10);
-        let layout = Layout::from_schema(schema);
-        RecordPage::new(t.clone(), blk, layout)
+        schema.add_string_field("B", 200);
+        Layout::from_schema(schema).with_format(format)
     }
 
     #[test]
@@ -266,24 +947,32 @@ mod tests {
         let mut slot = -1;
 
         while slot < 3 {
-            slot = rp.insert_after(slot);
+            slot = rp.insert_after(slot).unwrap();
 
-            rp.set_int(slot, "A", 10 + slot as i32);
-            assert_eq!(rp.get_int(slot, "A"), 10 + slot as i32);
+            rp.set_int(slot, "A", 10 + slot as i32).unwrap();
+            assert_eq!(rp.get_int(slot, "A"), Ok(10 + slot as i32));
 
-            rp.set_string(slot, "B", &format!("str {}", 20 + slot as i32));
+            rp.set_string(slot, "B", &format!("str {}", 20 + slot as i32))
+                .unwrap();
             assert_eq!(
                 rp.get_string(slot, "B"),
-                format!("str {}", 20 + slot as i32)
+                Ok(format!("str {}", 20 + slot as i32))
             );
         }
 
         for i in 0..4 {
-            rp.delete(i);
+            rp.delete(i).unwrap();
         }
 
         // assert that all slots have been deleted
-        assert_eq!(rp.next_after(-1), -1);
+        assert_eq!(rp.next_after(-1).unwrap(), -1);
+
+        // assert that deleted slots can no longer be accessed
+        let not_used = RecordPageError::SlotNotUsed(rp.block(), 2);
+        assert_eq!(rp.get_int(2, "A"), Err(not_used.clone()));
+        assert_eq!(rp.get_string(2, "B"), Err(not_used.clone()));
+        assert_eq!(rp.set_int(2, "A", 1), Err(not_used.clone()));
+        assert_eq!(rp.set_string(2, "B", "x"), Err(not_used));
     }
 
     #[test]
@@ -293,29 +982,30 @@ mod tests {
 
         // insert at 0, 1, 2
         while slot < 2 {
-            slot = rp.insert_after(slot);
+            slot = rp.insert_after(slot).unwrap();
 
-            rp.set_int(slot, "A", 10 + slot as i32);
-            assert_eq!(rp.get_int(slot, "A"), 10 + slot as i32);
+            rp.set_int(slot, "A", 10 + slot as i32).unwrap();
+            assert_eq!(rp.get_int(slot, "A"), Ok(10 + slot as i32));
 
-            rp.set_string(slot, "B", &format!("str {}", 20 + slot as i32));
+            rp.set_string(slot, "B", &format!("str {}", 20 + slot as i32))
+                .unwrap();
             assert_eq!(
                 rp.get_string(slot, "B"),
-                format!("str {}", 20 + slot as i32)
+                Ok(format!("str {}", 20 + slot as i32))
             );
         }
 
         // delete middle slot
-        rp.delete(1);
+        rp.delete(1).unwrap();
 
         // verify that slot 1 is identified as insertable
-        assert_eq!(rp.insert_after(-1), 1);
+        assert_eq!(rp.insert_after(-1).unwrap(), 1);
 
         // verify setting data at the slot
-        rp.set_int(1, "A", 42);
-        rp.set_string(1, "B", "new str");
-        assert_eq!(rp.get_int(1, "A"), 42);
-        assert_eq!(rp.get_string(1, "B"), "new str");
+        rp.set_int(1, "A", 42).unwrap();
+        rp.set_string(1, "B", "new str").unwrap();
+        assert_eq!(rp.get_int(1, "A"), Ok(42));
+        assert_eq!(rp.get_string(1, "B"), Ok("new str".to_string()));
     }
 
     #[test]
@@ -325,20 +1015,304 @@ mod tests {
 
         // insert at 0, 1, 2
         while slot < 2 {
-            slot = rp.insert_after(slot);
+            slot = rp.insert_after(slot).unwrap();
 
-            rp.set_int(slot, "A", 10 + slot as i32);
-            assert_eq!(rp.get_int(slot, "A"), 10 + slot as i32);
+            rp.set_int(slot, "A", 10 + slot as i32).unwrap();
+            assert_eq!(rp.get_int(slot, "A"), Ok(10 + slot as i32));
 
-            rp.set_string(slot, "B", &format!("str {}", 20 + slot as i32));
+            rp.set_string(slot, "B", &format!("str {}", 20 + slot as i32))
+                .unwrap();
             assert_eq!(
                 rp.get_string(slot, "B"),
-                format!("str {}", 20 + slot as i32)
+                Ok(format!("str {}", 20 + slot as i32))
             );
         }
 
-        rp.format();
+        rp.format().unwrap();
         // Nothing should be marked used now
-        assert_eq!(rp.next_after(-1), -1);
+        assert_eq!(rp.next_after(-1).unwrap(), -1);
+    }
+
+    #[test]
+    fn test_reformat_logs_whole_page_once() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let blk = tx.lock().unwrap().append("T").unwrap();
+        let layout = wide_layout(PageFormat::Fixed);
+        let mut rp = RecordPage::new(tx.clone(), blk.clone(), layout.clone());
+        let mut slot = -1;
+        for i in 0..3 {
+            slot = rp.insert_after(slot).unwrap();
+            rp.set_int(slot, "A", i).unwrap();
+            rp.set_string(slot, "B", &format!("str {}", i)).unwrap();
+        }
+        tx.lock().unwrap().commit().unwrap();
+
+        // The old image is logged in one record, rather than one per word of the page
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mut rp = RecordPage::new(tx.clone(), blk.clone(), layout.clone());
+        let before = tx.lock().unwrap().profile().log_records;
+        rp.reformat().unwrap();
+        assert_eq!(tx.lock().unwrap().profile().log_records, before + 1);
+        assert_eq!(rp.next_after(-1).unwrap(), -1);
+        assert_eq!(rp.free_slots().unwrap(), layout.max_records(4096));
+        for offset in (0..4096).step_by(WORD) {
+            assert_eq!(tx.lock().unwrap().get_int(&blk, offset), Ok(0));
+        }
+
+        // Rolling back restores the records
+        tx.lock().unwrap().rollback().unwrap();
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let rp = RecordPage::new(tx, blk, layout);
+        assert_eq!(rp.next_after(0).unwrap(), 1);
+        assert_eq!(rp.get_int(2, "A"), Ok(2));
+        assert_eq!(rp.get_string(2, "B"), Ok("str 2".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        for format in [PageFormat::Fixed, PageFormat::Slotted] {
+            let mut rp = new_record_page(wide_layout(format));
+            let slot = rp.insert_after(-1).unwrap();
+
+            let unknown = RecordPageError::UnknownField("C".to_string());
+            assert_eq!(rp.get_int(slot, "C"), Err(unknown.clone()));
+            assert_eq!(rp.get_string(slot, "C"), Err(unknown.clone()));
+            assert_eq!(rp.set_int(slot, "C", 1), Err(unknown.clone()));
+            assert_eq!(rp.set_string(slot, "C", "x"), Err(unknown));
+
+            // The record's fields are untouched
+            assert_eq!(rp.get_int(slot, "A"), Ok(0));
+            assert_eq!(rp.get_string(slot, "B"), Ok(String::new()));
+        }
+    }
+
+    #[test]
+    fn test_slotted_space_savings() {
+        let long = "x".repeat(200);
+
+        // Fill a page of each format with alternating short and long strings
+        let mut counts = vec![];
+        for format in [PageFormat::Fixed, PageFormat::Slotted] {
+            let mut rp = new_record_page(wide_layout(format));
+            let mut slot = rp.insert_after(-1).unwrap();
+            let mut count = 0;
+            while slot != -1 {
+                let val = if count % 2 == 0 { "short" } else { &long };
+                rp.set_int(slot, "A", count).unwrap();
+                rp.set_string(slot, "B", val).unwrap();
+                count += 1;
+                slot = rp.insert_after(slot).unwrap();
+            }
+
+            let mut slot = rp.next_after(-1).unwrap();
+            for i in 0..count {
+                let val = if i % 2 == 0 { "short" } else { &long };
+                assert_eq!(rp.get_int(slot, "A"), Ok(i));
+                assert_eq!(rp.get_string(slot, "B"), Ok(val.to_string()));
+                slot = rp.next_after(slot).unwrap();
+            }
+            assert_eq!(slot, -1);
+            counts.push(count);
+        }
+
+        assert_eq!(counts[0], 19);
+        assert!(counts[1] > 30, "only {} records fit", counts[1]);
+    }
+
+    #[test]
+    fn test_dictionary_encoding() {
+        let majors = ["compsci", "math", "drama"];
+
+        // Fill a page with and without a dictionary for a field with few distinct values
+        let mut counts = vec![];
+        for encoding in [Encoding::None, Encoding::Dictionary] {
+            let mut schema = Schema::new();
+            schema.add_int_field("A");
+            schema.add_string_field("B", 20);
+            schema.set_field_encoding("B", encoding);
+            let mut rp = new_record_page(Layout::from_schema(schema));
+            let mut slot = rp.insert_after(-1).unwrap();
+            let mut count = 0;
+            while slot != -1 {
+                rp.set_int(slot, "A", count).unwrap();
+                rp.set_string(slot, "B", majors[count as usize % 3])
+                    .unwrap();
+                count += 1;
+                slot = rp.insert_after(slot).unwrap();
+            }
+
+            let mut slot = rp.next_after(-1).unwrap();
+            for i in 0..count {
+                assert_eq!(rp.get_int(slot, "A"), Ok(i));
+                assert_eq!(
+                    rp.get_string(slot, "B"),
+                    Ok(majors[i as usize % 3].to_string())
+                );
+                slot = rp.next_after(slot).unwrap();
+            }
+            assert_eq!(slot, -1);
+            counts.push(count);
+        }
+
+        // A slot holds a 4-byte position in the dictionary instead of a 28-byte string, leaving
+        // room for the dictionary's 16 entries at the end of the page
+        assert_eq!(counts, vec![4096 / 33, (4096 - 388) / 9]);
+    }
+
+    #[test]
+    fn test_dictionary_full() {
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        schema.add_string_field("B", 20);
+        schema.set_field_encoding("B", Encoding::Dictionary);
+        // Values that do not fit in the dictionary are written to a new overflow file, so the
+        // data directory must outlive the page
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let blk = tx.lock().unwrap().append("T").unwrap();
+        let mut rp = RecordPage::new(tx, blk, Layout::from_schema(schema));
+
+        // Every record adds a value, so no more records are inserted once the dictionary is full
+        let mut slot = -1;
+        for i in 0..DICTIONARY_ENTRIES {
+            slot = rp.insert_after(slot).unwrap();
+            rp.set_string(slot, "B", &format!("value {}", i)).unwrap();
+        }
+        assert_eq!(rp.free_slots().unwrap(), 0);
+        assert_eq!(rp.insert_after(slot).unwrap(), -1);
+
+        // Values already in the dictionary are shared, and new values are stored elsewhere
+        rp.set_string(0, "B", "value 15").unwrap();
+        rp.set_string(1, "B", "new value").unwrap();
+        rp.set_string(2, "B", &"z".repeat(21)).unwrap();
+        rp.set_string(3, "B", "").unwrap();
+        assert_eq!(rp.get_string(0, "B"), Ok("value 15".to_string()));
+        assert_eq!(rp.get_string(1, "B"), Ok("new value".to_string()));
+        assert_eq!(rp.get_string(2, "B"), Ok("z".repeat(21)));
+        assert_eq!(rp.get_string(3, "B"), Ok(String::new()));
+        assert_eq!(rp.get_string(4, "B"), Ok("value 4".to_string()));
+
+        // Formatting empties the dictionary
+        rp.format().unwrap();
+        assert_eq!(rp.free_slots().unwrap(), rp.layout.max_records(4096));
+    }
+
+    #[test]
+    fn test_slotted_update_and_compact() {
+        let long = "y".repeat(200);
+        let mut rp = new_record_page(wide_layout(PageFormat::Slotted));
+
+        let mut slots = vec![];
+        let mut slot = rp.insert_after(-1).unwrap();
+        while slot != -1 {
+            rp.set_int(slot, "A", slot as i32).unwrap();
+            rp.set_string(slot, "B", "a").unwrap();
+            slots.push(slot);
+            slot = rp.insert_after(slot).unwrap();
+        }
+
+        // Growing a record moves it, shrinking it leaves it in place
+        rp.set_string(0, "B", "longer").unwrap();
+        assert_eq!(rp.get_string(0, "B"), Ok("longer".to_string()));
+        assert_eq!(rp.get_int(0, "A"), Ok(0));
+        rp.set_string(0, "B", "b").unwrap();
+        assert_eq!(rp.get_string(0, "B"), Ok("b".to_string()));
+
+        // The page is full, so lengthening every record eventually fails
+        let full = slots
+            .iter()
+            .find_map(|slot| rp.set_string(*slot, "B", &long).err());
+        assert!(matches!(full, Some(RecordPageError::PageFull(_, _))));
+
+        // Deleting records frees space that is reclaimed by compaction
+        for slot in &slots[..slots.len() / 2] {
+            rp.delete(*slot).unwrap();
+        }
+        for slot in &slots[slots.len() / 2..] {
+            rp.set_string(*slot, "B", "c").unwrap();
+        }
+        assert_eq!(rp.insert_after(-1).unwrap(), 0);
+        rp.set_string(0, "B", &long).unwrap();
+
+        assert_eq!(rp.get_string(0, "B"), Ok(long));
+        for slot in &slots[slots.len() / 2..] {
+            assert_eq!(rp.get_int(*slot, "A"), Ok(*slot as i32));
+            assert_eq!(rp.get_string(*slot, "B"), Ok("c".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_slot_states() {
+        let mut rp = get_record_page();
+
+        // The state takes a single byte, so 178 slots of 23 bytes fit where a 4-byte flag would
+        // leave room for 157
+        assert_eq!(rp.layout.slot_size(), 23);
+        let mut slot = -1;
+        let mut count = 0;
+        loop {
+            slot = rp.insert_after(slot).unwrap();
+            if slot == -1 {
+                break;
+            }
+            rp.set_int(slot, "A", -1).unwrap();
+            rp.set_string(slot, "B", "full width").unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 178);
+
+        rp.format().unwrap();
+        assert_eq!(rp.slot_state(0).unwrap(), SlotState::Empty);
+
+        // The state shares a word with the first field, which is left unchanged
+        let slot = rp.insert_after(-1).unwrap();
+        assert_eq!(rp.slot_state(slot).unwrap(), SlotState::Used);
+        rp.set_int(slot, "A", -1).unwrap();
+        rp.delete(slot).unwrap();
+        assert_eq!(rp.slot_state(slot).unwrap(), SlotState::Deleted);
+        assert_eq!(rp.next_after(-1).unwrap(), -1);
+
+        assert_eq!(rp.insert_after(-1).unwrap(), slot);
+        assert_eq!(rp.slot_state(slot).unwrap(), SlotState::Used);
+        assert_eq!(rp.get_int(slot, "A"), Ok(-1));
+        assert_eq!(rp.slot_state(slot + 1).unwrap(), SlotState::Empty);
+    }
+
+    #[test]
+    fn test_max_records() {
+        for layout in [
+            get_record_page().layout,
+            wide_layout(PageFormat::Fixed),
+            wide_layout(PageFormat::Fixed).with_versions(),
+        ] {
+            let mut rp = new_record_page(layout.clone());
+            let max = layout.max_records(rp.tx.lock().unwrap().block_size());
+            assert_eq!(rp.free_slots().unwrap(), max);
+
+            let mut slot = -1;
+            let mut count = 0;
+            loop {
+                slot = rp.insert_after(slot).unwrap();
+                if slot == -1 {
+                    break;
+                }
+                count += 1;
+                assert_eq!(rp.free_slots().unwrap(), max - count);
+            }
+            assert_eq!(count, max);
+
+            // Deleted slots can be reused, unless they hold versioned records
+            rp.delete(1).unwrap();
+            if layout.is_versioned() {
+                assert_eq!(rp.free_slots().unwrap(), 0);
+                continue;
+            }
+            assert_eq!(rp.free_slots().unwrap(), 1);
+            assert_eq!(rp.insert_after(-1).unwrap(), 1);
+            assert_eq!(rp.free_slots().unwrap(), 0);
+        }
     }
 }
@@ -1,7 +1,9 @@
 use std::fmt::Display;
 
 // TODO: the blocknumber should be a type definition shared by the `BlockId` class
-#[derive(Debug, PartialEq, Eq)]
+/// Identifies a record by its block and slot. RIDs order by block, then by slot within a block,
+/// which is the order a table scan visits records in.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RID {
     block_num: u64,
     slot: i16,
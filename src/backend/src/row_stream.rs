@@ -0,0 +1,167 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    db::DbError,
+    parser::constant::Value,
+    planning::plan::Plan,
+    scan::scan::{Scan, Scannable},
+    transaction::Tx,
+};
+
+/// The records of a query, read one at a time from the query's open scan as the stream is
+/// iterated, so that only the current record is held in memory.
+///
+/// The stream holds the transaction its scan reads in, so the transaction lives as long as the
+/// stream does. It must not commit or roll back before the stream is dropped, since that releases
+/// the blocks the scan has pinned. The scan is closed when the stream is dropped.
+pub struct RowStream {
+    scan: Scan,
+    fields: Vec<String>,
+    // Held so that the transaction outlives the scan
+    tx: Arc<Mutex<Tx>>,
+    done: bool,
+}
+
+impl RowStream {
+    /// Open a plan and stream the values of its fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - The plan of the query.
+    /// * `tx` - The transaction the plan was created in.
+    pub fn new(plan: &mut dyn Plan, tx: Arc<Mutex<Tx>>) -> Self {
+        Self {
+            fields: plan.schema().fields(),
+            scan: plan.open(),
+            tx,
+            done: false,
+        }
+    }
+
+    /// The fields of each row, in the order their values are returned.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+}
+
+impl Iterator for RowStream {
+    type Item = Result<Vec<Value>, DbError>;
+
+    /// Move to the next record and read its values. The stream ends after the last record, or
+    /// after the first record that cannot be read.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || !self.scan.next() {
+            self.done = true;
+            return None;
+        }
+
+        let row = self
+            .fields
+            .iter()
+            .map(|field_name| self.scan.get_val(field_name))
+            .collect::<Result<Vec<Value>, _>>()
+            .map_err(DbError::from);
+        self.done = row.is_err();
+        Some(row)
+    }
+}
+
+impl Drop for RowStream {
+    fn drop(&mut self) {
+        self.scan.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{
+        db::{DbError, SimpleDB},
+        parser::constant::Value,
+        scan::scan::{Scannable, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::BlockCounter,
+    };
+
+    #[test]
+    fn test_stream_reads_rows_on_demand() {
+        let td = tempdir().unwrap();
+        let (data_dir, log_dir) = (td.path().join("data"), td.path().join("log"));
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(&log_dir).unwrap();
+        let num_bufs = 10;
+        let db = SimpleDB::new(&data_dir, &log_dir, num_bufs);
+
+        // Fill a table with more blocks than there are buffers
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        db.execute("CREATE TABLE big (id int, pad varchar(200))", &tx)
+            .unwrap();
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("big", &tx)
+            .unwrap();
+        let num_recs = 2000;
+        let mut scan = TableScan::new(tx.clone(), layout, "big");
+        for i in 0..num_recs {
+            scan.insert();
+            scan.set_int("id", i);
+            scan.set_string("pad", &format!("record {}", i));
+        }
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+        db.buffer_manager().lock().unwrap().discard_file("big");
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let num_blocks = tx.lock().unwrap().size("big").unwrap();
+        assert!(num_blocks > num_bufs as u64);
+
+        // Planning a query over the table first reads all of it to collect statistics
+        drop(db.query("SELECT id FROM big", &tx).unwrap());
+        db.buffer_manager().lock().unwrap().discard_file("big");
+
+        // Pulling a few rows reads only the blocks holding them
+        let counter = BlockCounter::new(&db, &tx);
+        let mut stream = db.query("SELECT id FROM big", &tx).unwrap();
+        assert_eq!(stream.fields(), ["id"]);
+        let first: Vec<Vec<Value>> = stream.by_ref().take(5).map(Result::unwrap).collect();
+        assert_eq!(
+            first,
+            (0..5).map(|i| vec![Value::Int(i)]).collect::<Vec<_>>()
+        );
+        assert_eq!(counter.blocks_read(), 1);
+
+        // The rest of the table streams through a buffer pool smaller than the table
+        let mut count = first.len() as i32;
+        for row in stream.by_ref() {
+            assert_eq!(row, Ok(vec![Value::Int(count)]));
+            count += 1;
+        }
+        assert_eq!(count, num_recs);
+        assert_eq!(counter.blocks_read(), num_blocks);
+        assert!(stream.next().is_none());
+
+        // Dropping the stream unpins the block its scan was on
+        let buffer_mgr = db.buffer_manager();
+        assert_eq!(buffer_mgr.lock().unwrap().num_available(), num_bufs - 1);
+        drop(stream);
+        assert_eq!(buffer_mgr.lock().unwrap().num_available(), num_bufs);
+
+        assert!(matches!(
+            db.query("DELETE FROM big", &tx),
+            Err(DbError::Plan(_))
+        ));
+        assert!(matches!(
+            db.query("SELECT id FROM bigger", &tx),
+            Err(DbError::Plan(_))
+        ));
+        tx.lock().unwrap().commit().unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{parser::constant::Value, schema::Schema};
+
+use super::scan::{ScanError, ScanResult, Scannable};
+
+/// A scan over a single record of constant values, used by queries without a FROM clause.
+pub struct ConstantScan {
+    schema: Schema,
+    vals: Vec<Value>,
+    state: ConstantScanState,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ConstantScanState {
+    BeforeFirst,
+    OnRecord,
+    AfterLast,
+}
+
+impl ConstantScan {
+    /// Create a scan whose only record holds the given values, in the order of the schema's
+    /// fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The fields of the record.
+    /// * `vals` - The value of each field.
+    pub fn new(schema: Schema, vals: Vec<Value>) -> Self {
+        Self {
+            schema,
+            vals,
+            state: ConstantScanState::BeforeFirst,
+        }
+    }
+}
+
+impl Scannable for ConstantScan {
+    fn before_first(&mut self) {
+        self.state = ConstantScanState::BeforeFirst;
+    }
+
+    fn next(&mut self) -> bool {
+        self.state = match self.state {
+            ConstantScanState::BeforeFirst => ConstantScanState::OnRecord,
+            _ => ConstantScanState::AfterLast,
+        };
+        self.state == ConstantScanState::OnRecord
+    }
+
+    /// Get the value of an integer field. Fields holding strings are not integer fields.
+    fn get_int(&self, field_name: &str) -> ScanResult<i32> {
+        match self.get_val(field_name)? {
+            Value::Int(i) => Ok(i),
+            Value::Varchar(_) => Err(ScanError::NonExistentField(field_name.to_string())),
+        }
+    }
+
+    /// Get the value of a string field. Fields holding integers are not string fields.
+    fn get_string(&self, field_name: &str) -> ScanResult<String> {
+        match self.get_val(field_name)? {
+            Value::Varchar(s) => Ok(s),
+            Value::Int(_) => Err(ScanError::NonExistentField(field_name.to_string())),
+        }
+    }
+
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        let index = self
+            .schema
+            .fields()
+            .iter()
+            .position(|f| f == field_name)
+            .ok_or_else(|| ScanError::NonExistentField(field_name.to_string()))?;
+        if self.state != ConstantScanState::OnRecord {
+            return Err(ScanError::NoCurrentRecord);
+        }
+
+        Ok(self.vals[index].clone())
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.schema.has_field(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn close(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::constant::Value,
+        scan::scan::{ScanError, Scannable},
+        schema::Schema,
+        tests::test_utils::assert_rescannable,
+    };
+
+    use super::ConstantScan;
+
+    #[test]
+    fn test_constant_scan_has_one_record() {
+        let mut schema = Schema::new();
+        schema.add_int_field("a");
+        schema.add_string_field("b", 5);
+        let vals = vec![Value::Int(3), Value::Varchar("hello".to_string())];
+        let mut scan = ConstantScan::new(schema, vals.clone());
+
+        assert_eq!(scan.get_int("a"), Err(ScanError::NoCurrentRecord));
+        assert_eq!(assert_rescannable(&mut scan, &["a", "b"]), vec![vals]);
+
+        scan.before_first();
+        assert!(scan.next());
+        assert_eq!(scan.get_int("a"), Ok(3));
+        assert_eq!(scan.get_string("b"), Ok("hello".to_string()));
+        assert_eq!(
+            scan.get_int("b"),
+            Err(ScanError::NonExistentField("b".to_string()))
+        );
+        assert_eq!(
+            scan.get_val("c"),
+            Err(ScanError::NonExistentField("c".to_string()))
+        );
+        assert!(!scan.next());
+        assert_eq!(scan.get_int("a"), Err(ScanError::NoCurrentRecord));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{parser::constant::Value, schema::Schema};
+
+use super::scan::{ScanError, ScanResult, Scannable};
+
+/// A scan over no records, with the fields of a schema.
+pub struct EmptyScan {
+    schema: Schema,
+}
+
+impl EmptyScan {
+    pub fn new(schema: Schema) -> Self {
+        Self { schema }
+    }
+
+    // There is never a current record, so reading any field fails.
+    fn no_record<T>(&self, field_name: &str) -> ScanResult<T> {
+        if self.has_field(field_name) {
+            Err(ScanError::NoCurrentRecord)
+        } else {
+            Err(ScanError::NonExistentField(field_name.to_string()))
+        }
+    }
+}
+
+impl Scannable for EmptyScan {
+    fn before_first(&mut self) {}
+
+    fn next(&mut self) -> bool {
+        false
+    }
+
+    fn get_int(&self, field_name: &str) -> ScanResult<i32> {
+        self.no_record(field_name)
+    }
+
+    fn get_string(&self, field_name: &str) -> ScanResult<String> {
+        self.no_record(field_name)
+    }
+
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        self.no_record(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.schema.has_field(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn close(&mut self) {}
+}
@@ -0,0 +1,314 @@
+use crate::{
+    parser::{
+        constant::Value,
+        parser::{AggregateField, AggregateFn},
+    },
+    schema::Schema,
+};
+
+use super::scan::{Scan, ScanError, ScanResult, Scannable};
+
+/// A scan that groups the records of an underlying scan by a list of group fields, returning one
+/// record per group holding the values of the group fields and of each aggregate over the group.
+///
+/// The underlying scan must produce the records of each group next to each other, e.g. by being
+/// sorted on the group fields. Without group fields every record is in a single group, and a
+/// scan with no records returns no group.
+pub struct GroupByScan {
+    scan: Box<Scan>,
+    schema: Schema,
+    group_fields: Vec<String>,
+    aggregates: Vec<AggregateField>,
+    // Whether the underlying scan is on a record that has not been grouped yet
+    more: bool,
+    // The values of the group fields and aggregates of the current group, if there is one
+    group: Option<(Vec<Value>, Vec<Value>)>,
+}
+
+impl GroupByScan {
+    /// Create a new scan grouping the records of an underlying scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `scan` - The scan to read records from, with the records of each group together.
+    /// * `schema` - The fields of the output records: the group fields followed by a field for
+    ///   each aggregate, named after the aggregate.
+    /// * `group_fields` - The fields records are grouped by.
+    /// * `aggregates` - The aggregates computed over each group.
+    pub fn new(
+        scan: Box<Scan>,
+        schema: Schema,
+        group_fields: Vec<String>,
+        aggregates: Vec<AggregateField>,
+    ) -> Self {
+        let mut sel = Self {
+            scan,
+            schema,
+            group_fields,
+            aggregates,
+            more: false,
+            group: None,
+        };
+        sel.before_first();
+        sel
+    }
+
+    fn group_values(&self) -> ScanResult<Vec<Value>> {
+        self.group_fields
+            .iter()
+            .map(|field_name| self.scan.get_val(field_name))
+            .collect()
+    }
+
+    // Read the records of the group the underlying scan is on, leaving it on the first record of
+    // the next group.
+    fn read_group(&mut self) -> ScanResult<(Vec<Value>, Vec<Value>)> {
+        let group_vals = self.group_values()?;
+        let mut accumulators: Vec<Accumulator> = self
+            .aggregates
+            .iter()
+            .map(|agg| Accumulator::new(agg.0))
+            .collect();
+        loop {
+            for (acc, agg) in accumulators.iter_mut().zip(self.aggregates.iter()) {
+                acc.add(self.scan.get_val(&agg.1)?);
+            }
+
+            self.more = self.scan.next();
+            if !self.more || self.group_values()? != group_vals {
+                break;
+            }
+        }
+
+        let agg_vals = accumulators.into_iter().map(Accumulator::value).collect();
+        Ok((group_vals, agg_vals))
+    }
+}
+
+// The running value of an aggregate over the records of a group read so far.
+struct Accumulator {
+    agg_fn: AggregateFn,
+    count: i64,
+    sum: i64,
+    min: Option<Value>,
+    max: Option<Value>,
+}
+
+impl Accumulator {
+    fn new(agg_fn: AggregateFn) -> Self {
+        Self {
+            agg_fn,
+            count: 0,
+            sum: 0,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn add(&mut self, val: Value) {
+        self.count += 1;
+        if let Value::Int(i) = val {
+            self.sum += i as i64;
+        }
+        if self.min.as_ref().is_none_or(|min| val < *min) {
+            self.min = Some(val.clone());
+        }
+        if self.max.as_ref().is_none_or(|max| val > *max) {
+            self.max = Some(val);
+        }
+    }
+
+    // Sums that do not fit in an integer wrap around. Averages are rounded towards zero.
+    fn value(self) -> Value {
+        match self.agg_fn {
+            AggregateFn::Count => Value::Int(self.count as i32),
+            AggregateFn::Sum => Value::Int(self.sum as i32),
+            AggregateFn::Avg => Value::Int((self.sum / self.count) as i32),
+            AggregateFn::Min => self.min.expect("a group has at least one record"),
+            AggregateFn::Max => self.max.expect("a group has at least one record"),
+        }
+    }
+}
+
+impl Scannable for GroupByScan {
+    fn before_first(&mut self) {
+        self.scan.before_first();
+        self.more = self.scan.next();
+        self.group = None;
+    }
+
+    fn next(&mut self) -> bool {
+        if !self.more {
+            self.group = None;
+            return false;
+        }
+
+        // TODO: error handling
+        self.group = Some(
+            self.read_group()
+                .expect("failed to read record for grouping"),
+        );
+        true
+    }
+
+    fn get_int(&self, field_name: &str) -> ScanResult<i32> {
+        match self.get_val(field_name)? {
+            Value::Int(i) => Ok(i),
+            Value::Varchar(_) => Err(ScanError::NonExistentField(field_name.to_string())),
+        }
+    }
+
+    fn get_string(&self, field_name: &str) -> ScanResult<String> {
+        match self.get_val(field_name)? {
+            Value::Varchar(s) => Ok(s),
+            Value::Int(_) => Err(ScanError::NonExistentField(field_name.to_string())),
+        }
+    }
+
+    /// Get the value of a group field, or the value an aggregate computed over the current group.
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        let index = self
+            .schema
+            .fields()
+            .iter()
+            .position(|f| f == field_name)
+            .ok_or_else(|| ScanError::NonExistentField(field_name.to_string()))?;
+        let (group_vals, agg_vals) = self.group.as_ref().ok_or(ScanError::NoCurrentRecord)?;
+
+        Ok(group_vals
+            .iter()
+            .chain(agg_vals.iter())
+            .nth(index)
+            .expect("every output field has a value")
+            .clone())
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.schema.has_field(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn close(&mut self) {
+        self.scan.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        parser::{
+            constant::Value,
+            parser::{AggregateField, AggregateFn, SortField, SortOrder},
+        },
+        planning::group_by_plan::group_by_schema,
+        scan::{
+            scan::{Scan, ScanError, Scannable},
+            sort_scan::SortScan,
+        },
+        table_scan::TableScan,
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
+    };
+
+    use super::GroupByScan;
+
+    #[test]
+    fn test_group_by_scan() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let aggregates = vec![
+            AggregateField(AggregateFn::Count, "sid".to_string()),
+            AggregateField(AggregateFn::Sum, "sid".to_string()),
+            AggregateField(AggregateFn::Avg, "grad_year".to_string()),
+            AggregateField(AggregateFn::Min, "sname".to_string()),
+            AggregateField(AggregateFn::Max, "grad_year".to_string()),
+        ];
+        let group_fields = vec!["major_id".to_string()];
+        let schema = group_by_schema(layout.schema(), &group_fields, &aggregates).unwrap();
+        let table_scan = Box::new(Scan::Table(TableScan::new(
+            tx.clone(),
+            layout.clone(),
+            "student",
+        )));
+        let sorted = Box::new(Scan::Sort(SortScan::new(
+            tx.clone(),
+            table_scan,
+            layout.schema(),
+            &[SortField("major_id".to_string(), SortOrder::Ascending)],
+        )));
+        let mut scan = GroupByScan::new(sorted, schema, group_fields, aggregates);
+        assert_eq!(
+            scan.schema().fields(),
+            [
+                "major_id",
+                "count(sid)",
+                "sum(sid)",
+                "avg(grad_year)",
+                "min(sname)",
+                "max(grad_year)"
+            ]
+        );
+        assert_eq!(scan.get_val("major_id"), Err(ScanError::NoCurrentRecord));
+
+        let fields = scan.schema().fields();
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        let rows = assert_rescannable(&mut scan, &fields);
+        let row = |vals: (i32, i32, i32, i32, &str, i32)| {
+            vec![
+                Value::Int(vals.0),
+                Value::Int(vals.1),
+                Value::Int(vals.2),
+                Value::Int(vals.3),
+                Value::from(vals.4),
+                Value::Int(vals.5),
+            ]
+        };
+        assert_eq!(
+            rows,
+            vec![
+                row((10, 3, 13, 2021, "joe", 2022)),
+                row((20, 4, 20, 2020, "amy", 2022)),
+                row((30, 2, 12, 2020, "art", 2021)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_scan_without_group_fields() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let aggregates = vec![AggregateField(AggregateFn::Count, "sid".to_string())];
+        let schema = group_by_schema(layout.schema(), &[], &aggregates).unwrap();
+        let table_scan = Box::new(Scan::Table(TableScan::new(tx.clone(), layout, "student")));
+        let mut scan = GroupByScan::new(table_scan, schema, vec![], aggregates);
+
+        // Every record is in a single group
+        assert!(scan.next());
+        assert_eq!(scan.get_int("count(sid)"), Ok(9));
+        assert!(!scan.next());
+    }
+}
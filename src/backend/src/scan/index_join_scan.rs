@@ -1,12 +1,15 @@
-use crate::{index::index::Index, parser::constant::Value};
+use crate::{
+    index::index::Index, parser::constant::Value, record_page::RecordPageError, schema::Schema,
+};
 
-use super::scan::{Scan, ScanResult, Scannable, UpdateScannable};
+use super::scan::{Scan, ScanError, ScanResult, Scannable, UpdateScannable};
 
 pub struct IndexJoinScan {
     lhs: Box<Scan>,
     rhs: Box<Scan>,
     index: Box<dyn Index>,
     join_field: String,
+    schema: Schema,
 }
 
 impl IndexJoinScan {
@@ -15,11 +18,20 @@ impl IndexJoinScan {
     /// `lhs`; each record in `lhs` will incur an index lookup that will be used to set the position 
     /// in the `rhs` scan.
     pub fn new(lhs: Box<Scan>, rhs: Box<Scan>, index: Box<dyn Index>, join_field: &str) -> Self {
+        let mut schema = Schema::new();
+        schema.add_all(lhs.schema());
+        // A field of both scans, such as the join field, is only listed once
+        for field_name in rhs.schema().fields() {
+            if !schema.has_field(&field_name) {
+                schema.add_from(&field_name, rhs.schema());
+            }
+        }
         Self {
             lhs,
             rhs,
             index,
             join_field: join_field.to_string(),
+            schema,
         }
     }
 }
@@ -40,14 +52,20 @@ impl Scannable for IndexJoinScan {
         }
     }
 
+    /// Move to the next joined record. Index entries that point at a slot without a record in
+    /// `rhs`, e.g. one left behind for a deleted record, or outside of `rhs` altogether, are
+    /// skipped.
     fn next(&mut self) -> bool {
         loop {
-            if self.index.next() {
+            while self.index.next() {
                 // TODO: error handling
                 let rid = self.index.get_rid().unwrap();
-                println!("moved rhs to rid: {}", rid);
                 self.rhs.move_to_rid(rid);
-                return true;
+                match self.rhs.get_val(&self.join_field) {
+                    Err(ScanError::RecordPage(RecordPageError::SlotNotUsed(..)))
+                    | Err(ScanError::InvalidRid(..)) => continue,
+                    _ => return true,
+                }
             }
 
             if !self.lhs.next() {
@@ -93,6 +111,10 @@ impl Scannable for IndexJoinScan {
         self.rhs.has_field(field_name) || self.lhs.has_field(field_name)
     }
 
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn close(&mut self) {
         self.lhs.close();
         self.index.close();
@@ -110,9 +132,9 @@ mod tests {
         assert_table_scan_results,
         metadata::metadata_manager::MetadataManager,
         parser::{constant::Value, expression::Expression, predicate::Predicate, term::Term},
-        scan::scan::{Scan, Scannable},
+        scan::scan::{Scan, Scannable, UpdateScannable},
         table_scan::TableScan,
-        tests::test_utils::{create_default_tables, default_test_db},
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
     };
 
     use super::IndexJoinScan;
@@ -189,4 +211,68 @@ mod tests {
 
         assert!(!ijs.next());
     }
+
+    #[test]
+    fn test_index_join_scan_skips_dangling_rids() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let student_layout = metadata_manager.get_table_layout("student", &tx).unwrap();
+
+        // Delete student 4 without removing its entry from the index
+        let mut student_scan = TableScan::new(tx.clone(), student_layout.clone(), "student");
+        while student_scan.next() {
+            if student_scan.get_int("sid") == Ok(4) {
+                student_scan.delete();
+            }
+        }
+        student_scan.close();
+
+        let lhs_layout = metadata_manager.get_table_layout("enroll", &tx).unwrap();
+        let lhs = Box::new(Scan::Table(TableScan::new(tx.clone(), lhs_layout, "enroll")));
+        let rhs = Box::new(Scan::Table(TableScan::new(tx.clone(), student_layout, "student")));
+        let index_info = metadata_manager.get_index_info("student", tx.clone());
+        let index = Box::new(index_info.get("sid").unwrap().open());
+
+        let mut ijs = IndexJoinScan::new(lhs, rhs, index, "sid");
+        ijs.before_first();
+
+        let mut joined = vec![];
+        while ijs.next() {
+            joined.push((ijs.get_int("eid").unwrap(), ijs.get_int("sid").unwrap()));
+        }
+        assert_eq!(joined, vec![(14, 1), (24, 1), (34, 2), (64, 6)]);
+    }
+
+    #[test]
+    fn test_index_join_scan_before_first_resets() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let table_scan = |tblname: &str| {
+            Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                metadata_manager.get_table_layout(tblname, &tx).unwrap(),
+                tblname,
+            )))
+        };
+        let index_info = metadata_manager.get_index_info("student", tx.clone());
+        let index = Box::new(index_info.get("sid").unwrap().open());
+
+        let mut ijs = IndexJoinScan::new(table_scan("enroll"), table_scan("student"), index, "sid");
+
+        let records = assert_rescannable(&mut ijs, &["eid", "sname"]);
+        assert_eq!(
+            records,
+            [(14, "joe"), (24, "joe"), (34, "amy"), (44, "sue"), (54, "sue"), (64, "kim")]
+                .map(|(eid, sname)| vec![Value::from(eid), Value::from(sname)])
+                .to_vec()
+        );
+    }
 }
@@ -0,0 +1,191 @@
+use crate::{
+    index::{
+        btree::btree_index::{BTreeEntries, BTreeIndex},
+        index::Index,
+    },
+    parser::constant::Value,
+    schema::Schema,
+    table_scan::TableScan,
+};
+
+use super::scan::{ScanResult, Scannable, UpdateScannable};
+
+/// A scan that returns every record of a table in the order of a btree index on one of its
+/// fields.
+pub struct IndexOrderedScan {
+    inner_scan: Box<TableScan>,
+    idx: BTreeIndex,
+    entries: BTreeEntries,
+}
+
+impl IndexOrderedScan {
+    pub fn new(scan: Box<TableScan>, idx: BTreeIndex) -> Self {
+        let entries = idx.entries();
+        Self {
+            inner_scan: scan,
+            idx,
+            entries,
+        }
+    }
+}
+
+impl Scannable for IndexOrderedScan {
+    fn before_first(&mut self) {
+        self.entries = self.idx.entries();
+    }
+
+    /// Move to the record of the next index entry. Index entries that point at a slot without a
+    /// record, e.g. one left behind for a deleted record, or at a version of a record that the
+    /// transaction's snapshot does not include, are skipped.
+    fn next(&mut self) -> bool {
+        for (_, rid) in self.entries.by_ref() {
+            self.inner_scan.move_to_rid(rid);
+            // TODO: error handling
+            if self
+                .inner_scan
+                .is_at_record()
+                .expect("failed to read record slot")
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn get_int(&self, field_name: &str) -> ScanResult<i32> {
+        self.inner_scan.get_int(field_name)
+    }
+
+    fn get_string(&self, field_name: &str) -> ScanResult<String> {
+        self.inner_scan.get_string(field_name)
+    }
+
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        self.inner_scan.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.inner_scan.has_field(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.inner_scan.schema()
+    }
+
+    fn close(&mut self) {
+        self.idx.close();
+        self.inner_scan.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        index::{btree::btree_index::BTreeIndex, index::Index},
+        layout::Layout,
+        make_schema,
+        metadata::metadata_manager::MetadataManager,
+        parser::constant::Value,
+        scan::scan::{Scannable, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db, test_db},
+        transaction::Tx,
+    };
+
+    use super::IndexOrderedScan;
+
+    #[test]
+    fn test_index_ordered_scan() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+
+        let table_layout = metadata_manager.get_table_layout("student", &tx).unwrap();
+        let indexes = metadata_manager.get_index_info("student", tx.clone());
+        let index = indexes.get("sid").unwrap().open();
+
+        let table_scan = Box::new(TableScan::new(tx.clone(), table_layout, "student"));
+        let mut scan = IndexOrderedScan::new(table_scan, index);
+
+        let rows = assert_rescannable(&mut scan, &["sid"]);
+        let expected: Vec<Vec<Value>> = (1..=9).map(|sid| vec![Value::Int(sid)]).collect();
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn test_index_ordered_scan_reads_snapshot() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let layout = Layout::from_schema(make_schema! { "A" => i32 }).with_versions();
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => i32,
+            "block" => i32,
+            "id" => i32
+        });
+        let new_tx = || Arc::new(Mutex::new(db.new_tx()));
+        let open_index =
+            |tx: &Arc<Mutex<Tx>>| BTreeIndex::new(tx.clone(), "T-A", leaf_layout.clone());
+
+        // Insert records along with their index entries
+        let insert = |tx: &Arc<Mutex<Tx>>, vals: &[i32]| {
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "T");
+            let mut index = open_index(tx);
+            for val in vals {
+                scan.insert();
+                scan.set_int("A", *val);
+                index.insert(&Value::Int(*val), scan.get_rid());
+            }
+            index.close();
+            scan.close();
+        };
+        let read_ordered = |tx: &Arc<Mutex<Tx>>| {
+            let table_scan = Box::new(TableScan::new(tx.clone(), layout.clone(), "T"));
+            let mut scan = IndexOrderedScan::new(table_scan, open_index(tx));
+            let mut vals = vec![];
+            while scan.next() {
+                vals.push(scan.get_int("A").unwrap());
+            }
+            scan.close();
+            vals
+        };
+
+        let tx = new_tx();
+        insert(&tx, &[3, 1, 2]);
+        tx.lock().unwrap().commit().unwrap();
+
+        let reader = new_tx();
+        reader.lock().unwrap().read_snapshot();
+
+        // A later transaction deletes a record and inserts others, and another inserts without
+        // committing. The deleted record keeps its index entry for older snapshots.
+        let writer = new_tx();
+        insert(&writer, &[0, 4]);
+        let mut scan = TableScan::new(writer.clone(), layout.clone(), "T");
+        while scan.next() {
+            if scan.get_int("A") == Ok(2) {
+                scan.delete();
+            }
+        }
+        scan.close();
+        writer.lock().unwrap().commit().unwrap();
+        let uncommitted = new_tx();
+        insert(&uncommitted, &[5]);
+
+        // The index has entries for every version, but only the snapshot's records are read
+        assert_eq!(read_ordered(&reader), vec![1, 2, 3]);
+        uncommitted.lock().unwrap().rollback().unwrap();
+        reader.lock().unwrap().commit().unwrap();
+
+        let tx = new_tx();
+        assert_eq!(read_ordered(&tx), vec![0, 1, 3, 4]);
+        tx.lock().unwrap().commit().unwrap();
+    }
+}
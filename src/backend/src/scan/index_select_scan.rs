@@ -1,6 +1,6 @@
 use crate::{
     index::index::Index, metadata::index_manager::IndexInfo, parser::constant::Value,
-    planning::plan::Plan, table_scan::TableScan,
+    planning::plan::Plan, schema::Schema, table_scan::TableScan,
 };
 
 use super::scan::{Scan, ScanResult, Scannable, UpdateScannable};
@@ -26,17 +26,26 @@ impl Scannable for IndexSelectScan {
         self.idx.before_first(&self.val);
     }
 
+    /// Move to the next record matching the index value. Index entries that point at a slot
+    /// without a record, e.g. one left behind for a deleted record, are skipped.
     fn next(&mut self) -> bool {
-        if !self.idx.next() {
-            return false;
+        while self.idx.next() {
+            if let Some(rid) = self.idx.get_rid() {
+                self.inner_scan.move_to_rid(rid);
+                // TODO: error handling
+                if self
+                    .inner_scan
+                    .is_at_record()
+                    .expect("failed to read record slot")
+                {
+                    return true;
+                }
+            } else {
+                return false;
+            }
         }
 
-        if let Some(rid) = self.idx.get_rid() {
-            self.inner_scan.move_to_rid(rid);
-            true
-        } else {
-            false
-        }
+        false
     }
 
     fn get_int(&self, field_name: &str) -> ScanResult<i32> {
@@ -55,6 +64,10 @@ impl Scannable for IndexSelectScan {
         self.inner_scan.has_field(field_name)
     }
 
+    fn schema(&self) -> &Schema {
+        self.inner_scan.schema()
+    }
+
     fn close(&mut self) {
         self.idx.close();
         self.inner_scan.close();
@@ -71,7 +84,11 @@ mod tests {
     use tempfile::tempdir;
 
     use crate::{
-        index::btree::btree_index::BTreeIndex,
+        index::{
+            btree::btree_index::BTreeIndex, hash::static_hash_index::StaticHashIndex, index::Index,
+        },
+        layout::Layout,
+        make_schema,
         metadata::metadata_manager::MetadataManager,
         parser::{
             constant::Value,
@@ -80,9 +97,10 @@ mod tests {
             predicate::Predicate,
             term::Term,
         },
-        scan::scan::{Scan, Scannable},
+        scan::scan::{Scan, Scannable, UpdateScannable},
+        schema::Schema,
         table_scan::TableScan,
-        tests::test_utils::{create_default_tables, default_test_db},
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
     };
 
     use super::IndexSelectScan;
@@ -114,4 +132,100 @@ mod tests {
         assert_eq!(idx_select_scan.get_int("sid").unwrap(), 4);
         assert!(!idx_select_scan.next());
     }
+
+    #[test]
+    fn test_select_index_scan_skips_dangling_rids() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let table_layout = metadata_manager.get_table_layout("student", &tx).unwrap();
+        let indexes = metadata_manager.get_index_info("student", tx.clone());
+
+        // Delete student 4 without removing its entry from the index
+        let mut table_scan = Box::new(TableScan::new(tx.clone(), table_layout, "student"));
+        while table_scan.next() {
+            if table_scan.get_int("sid") == Ok(4) {
+                table_scan.delete();
+            }
+        }
+
+        let index = Box::new(indexes.get("sid").unwrap().open());
+        let mut idx_select_scan = IndexSelectScan::new(table_scan, index, Value::Int(4));
+        idx_select_scan.before_first();
+        assert!(!idx_select_scan.next());
+    }
+
+    #[test]
+    fn test_select_index_scan_before_first_resets() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let table_layout = metadata_manager.get_table_layout("student", &tx).unwrap();
+        let indexes = metadata_manager.get_index_info("student", tx.clone());
+        let index = Box::new(indexes.get("sid").unwrap().open());
+
+        let table_scan = Box::new(TableScan::new(tx.clone(), table_layout, "student"));
+        let mut idx_select_scan = IndexSelectScan::new(table_scan, index, Value::Int(7));
+
+        let records = assert_rescannable(&mut idx_select_scan, &["sid", "sname"]);
+        assert_eq!(records, vec![vec![Value::Int(7), Value::from("art")]]);
+    }
+
+    #[test]
+    fn test_select_index_scan_hash_and_btree() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let table_layout = metadata_manager.get_table_layout("student", &tx).unwrap();
+
+        let index_layout = Layout::from_schema(make_schema! {
+            "dataval" => i32,
+            "block" => i32,
+            "id" => i32
+        });
+        // Few buckets, so that buckets hold entries for more than one key
+        let mut indexes: Vec<Box<dyn Index>> = vec![
+            Box::new(StaticHashIndex::new(
+                2,
+                tx.clone(),
+                "major-hash",
+                index_layout.clone(),
+            )),
+            Box::new(BTreeIndex::new(tx.clone(), "major-btree", index_layout)),
+        ];
+
+        let mut table_scan = TableScan::new(tx.clone(), table_layout.clone(), "student");
+        while table_scan.next() {
+            let major_id = table_scan.get_val("major_id").unwrap();
+            for idx in indexes.iter_mut() {
+                idx.insert(&major_id, table_scan.get_rid());
+            }
+        }
+        table_scan.close();
+
+        let mut results = vec![];
+        for idx in indexes {
+            let table_scan = Box::new(TableScan::new(tx.clone(), table_layout.clone(), "student"));
+            let mut idx_select_scan = IndexSelectScan::new(table_scan, idx, Value::Int(20));
+            let mut records = assert_rescannable(&mut idx_select_scan, &["sid", "major_id"]);
+            records.sort();
+            results.push(records);
+        }
+
+        let expected: Vec<Vec<Value>> = [2, 4, 6, 8]
+            .into_iter()
+            .map(|sid| vec![Value::Int(sid), Value::Int(20)])
+            .collect();
+        assert_eq!(results[0], expected);
+        assert_eq!(results[1], expected);
+    }
 }
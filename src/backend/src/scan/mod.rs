@@ -1,6 +1,12 @@
+pub mod constant_scan;
+pub mod empty_scan;
+pub mod group_by_scan;
 pub mod index_join_scan;
+pub mod index_ordered_scan;
 pub mod index_select_scan;
 pub mod product_scan;
 pub mod project_scan;
+pub mod row;
 pub mod scan;
 pub mod select_scan;
+pub mod sort_scan;
@@ -1,56 +1,213 @@
-use crate::parser::constant::Value;
+use crate::{parser::constant::Value, schema::Schema};
 
-use super::scan::{Scan, ScanResult, Scannable};
+use super::scan::{Scan, ScanError, ScanResult, Scannable};
 
+/// A scan over every combination of a record of a left scan with a record of a right scan.
+///
+/// By default the right scan is read once for each record of the left scan. A chunked product
+/// instead holds a chunk of records of the left scan in memory and reads the right scan once per
+/// chunk, combining each of its records with every record of the chunk: a block nested loop. The
+/// same records are produced, though not in the same order.
 pub struct ProductScan {
     left: Box<Scan>,
     right: Box<Scan>,
+    // Whether the left scan is positioned at a record; false once it is exhausted. A chunked
+    // product reads past the records of its chunk, so it is only false once the scan is exhausted.
+    has_left: bool,
+    schema: Schema,
+    chunk: Option<Chunk>,
+    right_passes: u64,
+}
+
+// The records of the left scan held in memory by a chunked product.
+struct Chunk {
+    size: usize,
+    fields: Vec<String>,
+    rows: Vec<Vec<Value>>,
+    // The row combined with the current record of the right scan, if there is one
+    current: Option<usize>,
+}
+
+impl Chunk {
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        let row = self.current.ok_or(ScanError::NoCurrentRecord)?;
+        let index = self
+            .fields
+            .iter()
+            .position(|f| f == field_name)
+            .ok_or_else(|| ScanError::NonExistentField(field_name.to_string()))?;
+        Ok(self.rows[row][index].clone())
+    }
 }
 
 impl ProductScan {
     pub fn new(left: Box<Scan>, right: Box<Scan>) -> Self {
-        let mut s = Self { left, right };
+        Self::product(left, right, None)
+    }
+
+    /// Create a product that reads the right scan once for every `chunk_size` records of the
+    /// left scan, rather than once for every record.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The scan whose records are held in memory, a chunk at a time.
+    /// * `right` - The scan read once per chunk.
+    /// * `chunk_size` - The number of records of the left scan held in memory at once. Must be at
+    ///   least 1.
+    pub fn with_chunk_size(left: Box<Scan>, right: Box<Scan>, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "a chunk must hold at least one record");
+        let chunk = Chunk {
+            size: chunk_size,
+            fields: left.schema().fields(),
+            rows: Vec::with_capacity(chunk_size),
+            current: None,
+        };
+        Self::product(left, right, Some(chunk))
+    }
+
+    fn product(left: Box<Scan>, right: Box<Scan>, chunk: Option<Chunk>) -> Self {
+        let mut schema = Schema::new();
+        schema.add_all(left.schema());
+        // A field of both scans is read from the left one, so it is only listed once
+        for field_name in right.schema().fields() {
+            if !schema.has_field(&field_name) {
+                schema.add_from(&field_name, right.schema());
+            }
+        }
+        let mut s = Self {
+            left,
+            right,
+            has_left: false,
+            schema,
+            chunk,
+            right_passes: 0,
+        };
         s.before_first();
         s
     }
+
+    /// The number of times the right scan has been read from its start, counting the pass
+    /// under way.
+    pub fn right_passes(&self) -> u64 {
+        self.right_passes
+    }
+
+    fn restart_right(&mut self) {
+        self.right.before_first();
+        self.right_passes += 1;
+    }
+
+    // Replace the chunk with the next records of the left scan, returning false if there are none.
+    fn fill_chunk(&mut self) -> bool {
+        let chunk = self.chunk.as_mut().expect("product is chunked");
+        chunk.rows.clear();
+        chunk.current = None;
+        while self.has_left && chunk.rows.len() < chunk.size {
+            self.has_left = self.left.next();
+            if self.has_left {
+                let row = chunk
+                    .fields
+                    .iter()
+                    .map(|field_name| self.left.get_val(field_name))
+                    .collect::<ScanResult<Vec<Value>>>()
+                    // TODO: error handling
+                    .expect("failed to read record of the left scan");
+                chunk.rows.push(row);
+            }
+        }
+        !chunk.rows.is_empty()
+    }
+
+    fn next_chunked(&mut self) -> bool {
+        loop {
+            let chunk = self.chunk.as_mut().expect("product is chunked");
+            if chunk.rows.is_empty() {
+                return false;
+            }
+            // Combine the current record of the right scan with the next row of the chunk
+            if let Some(row) = chunk.current.filter(|row| row + 1 < chunk.rows.len()) {
+                chunk.current = Some(row + 1);
+                return true;
+            }
+            if self.right.next() {
+                chunk.current = Some(0);
+                return true;
+            }
+
+            if self.fill_chunk() {
+                self.restart_right();
+            }
+        }
+    }
+
+    // Get a field of the left scan, from the chunk if the product is chunked.
+    fn get_left_val(&self, field_name: &str) -> ScanResult<Value> {
+        match &self.chunk {
+            Some(chunk) => chunk.get_val(field_name),
+            None => self.left.get_val(field_name),
+        }
+    }
 }
 
 impl Scannable for ProductScan {
     fn before_first(&mut self) {
         self.left.before_first();
-        self.left.next();
-        self.right.before_first();
+        if self.chunk.is_some() {
+            self.has_left = true;
+            self.fill_chunk();
+        } else {
+            self.has_left = self.left.next();
+        }
+        self.restart_right();
     }
 
     fn next(&mut self) -> bool {
+        if self.chunk.is_some() {
+            return self.next_chunked();
+        }
+        if !self.has_left {
+            return false;
+        }
+
         let records_exist_in_right = self.right.next();
         if records_exist_in_right {
             true
         } else {
-            self.right.before_first();
-            self.right.next() && self.left.next()
+            self.restart_right();
+            self.has_left = self.right.next() && self.left.next();
+            self.has_left
         }
     }
 
     fn get_int(&self, field_name: &str) -> ScanResult<i32> {
-        if self.left.has_field(field_name) {
+        if !self.left.has_field(field_name) {
+            self.right.get_int(field_name)
+        } else if self.chunk.is_none() {
             self.left.get_int(field_name)
         } else {
-            self.right.get_int(field_name)
+            match self.get_left_val(field_name)? {
+                Value::Int(i) => Ok(i),
+                Value::Varchar(_) => Err(ScanError::NonExistentField(field_name.to_string())),
+            }
         }
     }
 
     fn get_string(&self, field_name: &str) -> ScanResult<String> {
-        if self.left.has_field(field_name) {
+        if !self.left.has_field(field_name) {
+            self.right.get_string(field_name)
+        } else if self.chunk.is_none() {
             self.left.get_string(field_name)
         } else {
-            self.right.get_string(field_name)
+            match self.get_left_val(field_name)? {
+                Value::Varchar(s) => Ok(s),
+                Value::Int(_) => Err(ScanError::NonExistentField(field_name.to_string())),
+            }
         }
     }
 
     fn get_val(&self, field_name: &str) -> ScanResult<Value> {
         if self.left.has_field(field_name) {
-            self.left.get_val(field_name)
+            self.get_left_val(field_name)
         } else {
             self.right.get_val(field_name)
         }
@@ -60,6 +217,10 @@ impl Scannable for ProductScan {
         self.left.has_field(field_name) || self.right.has_field(field_name)
     }
 
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn close(&mut self) {
         self.left.close();
         self.right.close();
@@ -74,9 +235,10 @@ mod tests {
 
     use crate::{
         metadata::metadata_manager::MetadataManager,
-        scan::scan::{Scan, Scannable},
+        parser::constant::Value,
+        scan::scan::{Scan, ScanError, Scannable},
         table_scan::TableScan,
-        tests::test_utils::{create_default_tables, default_test_db},
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
     };
 
     use super::ProductScan;
@@ -115,5 +277,122 @@ mod tests {
         assert!(!product_scan.next());
     }
 
-    // TODO: test join against empty table
+    #[test]
+    fn test_product_scan_before_first_resets() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let meta_mgr = MetadataManager::new(&tx);
+        let table_scan = |tblname: &str| {
+            Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                meta_mgr.get_table_layout(tblname, &tx).unwrap(),
+                tblname,
+            )))
+        };
+
+        let mut product_scan = ProductScan::new(table_scan("dept"), table_scan("course"));
+        let records = assert_rescannable(&mut product_scan, &["did", "cid"]);
+        assert_eq!(records.len(), 18);
+        assert_eq!(records[0], vec![Value::Int(10), Value::Int(12)]);
+        assert_eq!(records[17], vec![Value::Int(30), Value::Int(62)]);
+    }
+
+    #[test]
+    fn test_chunked_product_scan() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let meta_mgr = MetadataManager::new(&tx);
+        let table_scan = |tblname: &str| {
+            Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                meta_mgr.get_table_layout(tblname, &tx).unwrap(),
+                tblname,
+            )))
+        };
+        let fields = ["sid", "sname", "did", "dname"];
+
+        // The right scan is read once for each of the 9 students, and restarted once more when
+        // the students run out
+        let mut product_scan = ProductScan::new(table_scan("student"), table_scan("dept"));
+        let mut expected = vec![];
+        while product_scan.next() {
+            expected.push(fields.map(|f| product_scan.get_val(f).unwrap()).to_vec());
+        }
+        assert_eq!(expected.len(), 27);
+        assert_eq!(product_scan.right_passes(), 10);
+        expected.sort();
+
+        // Holding 4 students at a time, the right scan is read once for each chunk of them
+        for (chunk_size, passes) in [(1, 9), (4, 3), (9, 1), (100, 1)] {
+            let mut product_scan =
+                ProductScan::with_chunk_size(table_scan("student"), table_scan("dept"), chunk_size);
+            assert_eq!(product_scan.right_passes(), 1);
+            let mut records = vec![];
+            while product_scan.next() {
+                records.push(fields.map(|f| product_scan.get_val(f).unwrap()).to_vec());
+            }
+            assert_eq!(product_scan.right_passes(), passes);
+            assert_eq!(product_scan.get_int("sid"), Err(ScanError::NoCurrentRecord));
+
+            records.sort();
+            assert_eq!(records, expected);
+            let mut records = assert_rescannable(&mut product_scan, &fields);
+            records.sort();
+            assert_eq!(records, expected);
+        }
+
+        // Fields of the left scan are typed as in the scan
+        let mut product_scan =
+            ProductScan::with_chunk_size(table_scan("student"), table_scan("dept"), 4);
+        assert!(product_scan.next());
+        assert_eq!(product_scan.get_int("sid"), Ok(1));
+        assert_eq!(product_scan.get_string("sname"), Ok("joe".to_string()));
+        assert!(product_scan.get_string("sid").is_err());
+        assert_eq!(product_scan.get_int("did"), Ok(10));
+    }
+
+    #[test]
+    fn test_product_scan_with_empty_table() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let meta_mgr = MetadataManager::new(&tx);
+        let layout = meta_mgr.get_table_layout("dept", &tx).unwrap();
+        let empty_scan = || {
+            Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                layout.clone(),
+                "empty",
+            )))
+        };
+        let dept_scan = || {
+            Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                layout.clone(),
+                "dept",
+            )))
+        };
+
+        let mut product_scan = ProductScan::new(empty_scan(), dept_scan());
+        assert!(!product_scan.next());
+        product_scan.before_first();
+        assert!(!product_scan.next());
+
+        let mut product_scan = ProductScan::new(dept_scan(), empty_scan());
+        assert!(!product_scan.next());
+
+        let mut product_scan = ProductScan::with_chunk_size(empty_scan(), dept_scan(), 2);
+        assert!(!product_scan.next());
+        let mut product_scan = ProductScan::with_chunk_size(dept_scan(), empty_scan(), 2);
+        assert!(!product_scan.next());
+        assert!(!product_scan.next());
+    }
 }
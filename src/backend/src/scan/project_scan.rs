@@ -1,17 +1,26 @@
 use std::collections::HashSet;
 
-use crate::parser::constant::Value;
+use crate::{parser::constant::Value, schema::Schema};
 
 use super::scan::{Scan, ScanError, ScanResult, Scannable};
 
 pub struct ProjectScan {
     field_list: Vec<String>,
     scan: Box<Scan>,
+    schema: Schema,
 }
 
 impl ProjectScan {
     pub fn new(field_list: Vec<String>, scan: Box<Scan>) -> Self {
-        let mut s = Self { field_list, scan };
+        let mut schema = Schema::new();
+        for field_name in &field_list {
+            schema.add_from(field_name, scan.schema());
+        }
+        let mut s = Self {
+            field_list,
+            scan,
+            schema,
+        };
         s.before_first();
         s
     }
@@ -44,13 +53,21 @@ impl Scannable for ProjectScan {
     }
 
     fn get_val(&self, field_name: &str) -> ScanResult<Value> {
-        self.scan.get_val(field_name)
+        if !self.has_field(field_name) {
+            Err(ScanError::NonExistentField(field_name.to_string()))
+        } else {
+            self.scan.get_val(field_name)
+        }
     }
 
     fn has_field(&self, field_name: &str) -> bool {
         self.field_list.contains(&field_name.to_string())
     }
 
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
     fn close(&mut self) {
         self.scan.close();
     }
@@ -67,9 +84,10 @@ mod tests {
 
     use crate::{
         metadata::metadata_manager::MetadataManager,
-        scan::scan::{Scan, Scannable},
+        parser::constant::Value,
+        scan::scan::{Scan, ScanError, Scannable},
         table_scan::TableScan,
-        tests::test_utils::{create_default_tables, default_test_db},
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
     };
 
     use super::ProjectScan;
@@ -98,9 +116,38 @@ mod tests {
             assert!(project_scan.get_int("grad_year").is_ok());
             assert!(project_scan.get_int("sname").is_err());
             assert!(project_scan.get_int("major_id").is_err());
+            assert!(project_scan.get_val("sid").is_ok());
+            assert_eq!(
+                project_scan.get_val("sname"),
+                Err(ScanError::NonExistentField("sname".to_string()))
+            );
             num_students += 1;
         }
 
         assert_eq!(num_students, 9);
     }
+
+    #[test]
+    fn test_project_scan_before_first_resets() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let meta_mgr = MetadataManager::new(&tx);
+        let scan = Box::new(Scan::Table(TableScan::new(
+            tx.clone(),
+            meta_mgr.get_table_layout("dept", &tx).unwrap(),
+            "dept",
+        )));
+        let mut project_scan = ProjectScan::new(vec!["dname".to_string()], scan);
+
+        let records = assert_rescannable(&mut project_scan, &["dname"]);
+        assert_eq!(
+            records,
+            ["compsci", "math", "drama"]
+                .map(|d| vec![Value::from(d)])
+                .to_vec()
+        );
+    }
 }
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::{parser::constant::Value, schema::Schema};
+
+use super::scan::{ScanError, ScanResult, Scannable, UpdateScannable};
+
+/// A record keyed by field name.
+pub type Row = HashMap<String, Value>;
+
+/// Read every field of the schema from the current record of a scan.
+///
+/// # Arguments
+///
+/// * `scan` - The scan, positioned at the record to read.
+/// * `schema` - The schema describing the fields to read.
+pub fn row_to_map<S: Scannable>(scan: &S, schema: &Schema) -> ScanResult<Row> {
+    schema
+        .fields()
+        .into_iter()
+        .map(|field_name| {
+            let val = scan.get_val(&field_name)?;
+            Ok((field_name, val))
+        })
+        .collect()
+}
+
+/// Write the values of a row into the current record of a scan.
+///
+/// To insert a new record, call `insert()` on the scan first. Returns an error, before any value
+/// is written, if the row names a field that the scan does not have.
+///
+/// # Arguments
+///
+/// * `scan` - The scan, positioned at the record to write.
+/// * `row` - The values to write, keyed by field name.
+pub fn map_to_row<S: UpdateScannable>(scan: &mut S, row: &Row) -> ScanResult<()> {
+    if let Some(field_name) = row.keys().find(|f| !scan.has_field(f)) {
+        return Err(ScanError::NonExistentField(field_name.to_string()));
+    }
+
+    for (field_name, val) in row {
+        scan.set_val(field_name, val);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        table_scan::TableScan,
+        tests::test_utils::{create_default_tables, default_test_db},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_row_round_trip() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout.clone(), "student");
+
+        let row = Row::from([
+            ("sid".to_string(), Value::from(10)),
+            ("sname".to_string(), Value::from("ann")),
+            ("grad_year".to_string(), Value::from(2023)),
+            ("major_id".to_string(), Value::from(30)),
+        ]);
+        scan.insert();
+        map_to_row(&mut scan, &row).unwrap();
+
+        scan.before_first();
+        let mut found = vec![];
+        while scan.next() {
+            if scan.get_int("sid") == Ok(10) {
+                found.push(row_to_map(&scan, layout.schema()).unwrap());
+            }
+        }
+        assert_eq!(found, vec![row]);
+
+        // Rows naming unknown fields are rejected without writing anything
+        let bad_row = Row::from([
+            ("sid".to_string(), Value::from(11)),
+            ("dname".to_string(), Value::from("math")),
+        ]);
+        scan.insert();
+        assert_eq!(
+            map_to_row(&mut scan, &bad_row),
+            Err(ScanError::NonExistentField("dname".to_string()))
+        );
+        assert_eq!(scan.get_int("sid"), Ok(0));
+
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+    }
+}
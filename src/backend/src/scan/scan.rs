@@ -1,15 +1,43 @@
 use crate::{index::index::Index, parser::constant::Value, rid::RID, table_scan::TableScan};
+use crate::record_page::RecordPageError;
+use crate::transaction::TxError;
 use crate::scan::index_join_scan::IndexJoinScan;
+use crate::schema::Schema;
 
 use super::{
-    index_select_scan::IndexSelectScan, product_scan::ProductScan, project_scan::ProjectScan,
-    select_scan::SelectScan,
+    constant_scan::ConstantScan, empty_scan::EmptyScan, group_by_scan::GroupByScan,
+    index_ordered_scan::IndexOrderedScan, index_select_scan::IndexSelectScan,
+    product_scan::ProductScan, project_scan::ProjectScan, select_scan::SelectScan,
+    sort_scan::SortScan,
 };
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum ScanError {
     NonExistentField(String),
     UpdateNotSupported,
+    Closed,
+    /// The scan is not positioned on a record.
+    NoCurrentRecord,
+    /// The scan was moved to a RID outside of its table: past the last block of the file, or
+    /// past the last slot of a block.
+    InvalidRid(RID),
+    RecordPage(RecordPageError),
+    Tx(TxError),
+}
+
+impl From<RecordPageError> for ScanError {
+    fn from(e: RecordPageError) -> Self {
+        match e {
+            RecordPageError::Tx(e) => Self::Tx(e),
+            e => Self::RecordPage(e),
+        }
+    }
+}
+
+impl From<TxError> for ScanError {
+    fn from(e: TxError) -> Self {
+        Self::Tx(e)
+    }
 }
 
 pub type ScanResult<T> = Result<T, ScanError>;
@@ -19,9 +47,26 @@ pub enum Scan {
     Select(SelectScan),
     Project(ProjectScan),
     Product(ProductScan),
+    Sort(SortScan),
+    GroupBy(GroupByScan),
 
     IndexSelect(IndexSelectScan),
     IndexJoin(IndexJoinScan),
+    IndexOrdered(IndexOrderedScan),
+
+    Empty(EmptyScan),
+    Constant(ConstantScan),
+}
+
+impl Scan {
+    /// Get the RID of the current record, if the scan reads its records directly from a table.
+    pub fn current_rid(&self) -> Option<RID> {
+        match self {
+            Scan::Table(scan) => Some(scan.get_rid()),
+            Scan::Select(scan) => scan.current_rid(),
+            _ => None,
+        }
+    }
 }
 
 impl Scannable for Scan {
@@ -31,9 +76,15 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.before_first(),
             Scan::Project(scan) => scan.before_first(),
             Scan::Product(scan) => scan.before_first(),
+            Scan::Sort(scan) => scan.before_first(),
+            Scan::GroupBy(scan) => scan.before_first(),
 
             Scan::IndexSelect(scan) => scan.before_first(),
             Scan::IndexJoin(scan) => scan.before_first(),
+            Scan::IndexOrdered(scan) => scan.before_first(),
+
+            Scan::Empty(scan) => scan.before_first(),
+            Scan::Constant(scan) => scan.before_first(),
         }
     }
 
@@ -43,9 +94,15 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.next(),
             Scan::Project(scan) => scan.next(),
             Scan::Product(scan) => scan.next(),
+            Scan::Sort(scan) => scan.next(),
+            Scan::GroupBy(scan) => scan.next(),
 
             Scan::IndexSelect(scan) => scan.next(),
             Scan::IndexJoin(scan) => scan.next(),
+            Scan::IndexOrdered(scan) => scan.next(),
+
+            Scan::Empty(scan) => scan.next(),
+            Scan::Constant(scan) => scan.next(),
         }
     }
 
@@ -55,9 +112,15 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.get_int(field_name),
             Scan::Project(scan) => scan.get_int(field_name),
             Scan::Product(scan) => scan.get_int(field_name),
+            Scan::Sort(scan) => scan.get_int(field_name),
+            Scan::GroupBy(scan) => scan.get_int(field_name),
 
             Scan::IndexSelect(scan) => scan.get_int(field_name),
             Scan::IndexJoin(scan) => scan.get_int(field_name),
+            Scan::IndexOrdered(scan) => scan.get_int(field_name),
+
+            Scan::Empty(scan) => scan.get_int(field_name),
+            Scan::Constant(scan) => scan.get_int(field_name),
         }
     }
 
@@ -67,9 +130,15 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.get_string(field_name),
             Scan::Project(scan) => scan.get_string(field_name),
             Scan::Product(scan) => scan.get_string(field_name),
+            Scan::Sort(scan) => scan.get_string(field_name),
+            Scan::GroupBy(scan) => scan.get_string(field_name),
 
             Scan::IndexSelect(scan) => scan.get_string(field_name),
             Scan::IndexJoin(scan) => scan.get_string(field_name),
+            Scan::IndexOrdered(scan) => scan.get_string(field_name),
+
+            Scan::Empty(scan) => scan.get_string(field_name),
+            Scan::Constant(scan) => scan.get_string(field_name),
         }
     }
 
@@ -79,9 +148,15 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.get_val(field_name),
             Scan::Project(scan) => scan.get_val(field_name),
             Scan::Product(scan) => scan.get_val(field_name),
+            Scan::Sort(scan) => scan.get_val(field_name),
+            Scan::GroupBy(scan) => scan.get_val(field_name),
 
             Scan::IndexSelect(scan) => scan.get_val(field_name),
             Scan::IndexJoin(scan) => scan.get_val(field_name),
+            Scan::IndexOrdered(scan) => scan.get_val(field_name),
+
+            Scan::Empty(scan) => scan.get_val(field_name),
+            Scan::Constant(scan) => scan.get_val(field_name),
         }
     }
 
@@ -91,9 +166,33 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.has_field(field_name),
             Scan::Project(scan) => scan.has_field(field_name),
             Scan::Product(scan) => scan.has_field(field_name),
+            Scan::Sort(scan) => scan.has_field(field_name),
+            Scan::GroupBy(scan) => scan.has_field(field_name),
 
             Scan::IndexSelect(scan) => scan.has_field(field_name),
             Scan::IndexJoin(scan) => scan.has_field(field_name),
+            Scan::IndexOrdered(scan) => scan.has_field(field_name),
+
+            Scan::Empty(scan) => scan.has_field(field_name),
+            Scan::Constant(scan) => scan.has_field(field_name),
+        }
+    }
+
+    fn schema(&self) -> &Schema {
+        match self {
+            Scan::Table(scan) => scan.schema(),
+            Scan::Select(scan) => scan.schema(),
+            Scan::Project(scan) => scan.schema(),
+            Scan::Product(scan) => scan.schema(),
+            Scan::Sort(scan) => scan.schema(),
+            Scan::GroupBy(scan) => scan.schema(),
+
+            Scan::IndexSelect(scan) => scan.schema(),
+            Scan::IndexJoin(scan) => scan.schema(),
+            Scan::IndexOrdered(scan) => scan.schema(),
+
+            Scan::Empty(scan) => scan.schema(),
+            Scan::Constant(scan) => scan.schema(),
         }
     }
 
@@ -103,9 +202,15 @@ impl Scannable for Scan {
             Scan::Select(scan) => scan.close(),
             Scan::Project(scan) => scan.close(),
             Scan::Product(scan) => scan.close(),
+            Scan::Sort(scan) => scan.close(),
+            Scan::GroupBy(scan) => scan.close(),
 
             Scan::IndexSelect(scan) => scan.close(),
             Scan::IndexJoin(scan) => scan.close(),
+            Scan::IndexOrdered(scan) => scan.close(),
+
+            Scan::Empty(scan) => scan.close(),
+            Scan::Constant(scan) => scan.close(),
         }
     }
 }
@@ -171,6 +276,9 @@ impl UpdateScannable for Scan {
 pub trait Scannable {
     // Move before the first record in the scan. Immediately calling `next()` after this should
     // move to the first available record.
+    //
+    // This fully resets the scan, even part way through a pass, so a scan can be reused for any
+    // number of passes (e.g. as the inner side of a product) without being reconstructed.
     fn before_first(&mut self);
 
     fn next(&mut self) -> bool;
@@ -179,7 +287,13 @@ pub trait Scannable {
     fn get_val(&self, field_name: &str) -> ScanResult<Value>;
     fn has_field(&self, field_name: &str) -> bool;
 
-    /// Close the scan and clean up as necessary.
+    /// The schema of the records produced by the scan.
+    fn schema(&self) -> &Schema;
+
+    /// Close the scan and clean up as necessary. Closing a scan closes every scan and index it
+    /// reads from, releasing all of their pins. Dropping a scan closes it too, so a tree of scans
+    /// dropped part way through a pass leaves no blocks pinned. Closing a scan that is already
+    /// closed, or whose transaction has ended, does nothing.
     fn close(&mut self);
 }
 
@@ -192,3 +306,202 @@ pub trait UpdateScannable: Scannable {
     fn get_rid(&self) -> RID;
     fn move_to_rid(&mut self, rid: RID);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::{
+        make_schema,
+        metadata::metadata_manager::MetadataManager,
+        parser::{
+            expression::Expression,
+            parser::{SortField, SortOrder},
+            predicate::Predicate,
+            term::Term,
+        },
+        tests::test_utils::{create_default_tables, default_test_db},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_scan_schemas() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_mgr = MetadataManager::new(&tx);
+        let student_layout = metadata_mgr.get_table_layout("student", &tx).unwrap();
+        let table = |name: &str| {
+            let layout = metadata_mgr.get_table_layout(name, &tx).unwrap();
+            Box::new(Scan::Table(TableScan::new(tx.clone(), layout, name)))
+        };
+        let fields =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|f| f.to_string()).collect() };
+        let student_fields = fields(&["sid", "sname", "grad_year", "major_id"]);
+
+        assert_eq!(table("student").schema(), student_layout.schema());
+
+        let predicate = Predicate::from_term(Term::new(
+            Expression::Field("sid".to_string()),
+            Expression::Constant(Value::Int(1)),
+        ));
+        let scan = Scan::Select(SelectScan::new(predicate, table("student")));
+        assert_eq!(scan.schema().fields(), student_fields);
+
+        // A projection reports only the projected fields, in the order they were listed
+        let scan = Scan::Project(ProjectScan::new(
+            fields(&["sname", "sid"]),
+            table("student"),
+        ));
+        assert_eq!(scan.schema().fields(), fields(&["sname", "sid"]));
+        assert_eq!(scan.schema().get_field_length("sname"), Some(20));
+
+        let scan = Scan::Product(ProductScan::new(table("student"), table("dept")));
+        assert_eq!(
+            scan.schema().fields(),
+            fields(&["sid", "sname", "grad_year", "major_id", "did", "dname"])
+        );
+
+        let sort_schema = make_schema! {
+            "grad_year" => i32,
+            "sname" => varchar(20)
+        };
+        let scan = Scan::Sort(SortScan::new(
+            tx.clone(),
+            table("student"),
+            &sort_schema,
+            &[SortField("grad_year".to_string(), SortOrder::Ascending)],
+        ));
+        assert_eq!(scan.schema(), &sort_schema);
+
+        let ii = metadata_mgr
+            .get_index_info("student", tx.clone())
+            .remove("sid")
+            .unwrap();
+        let student_table = || {
+            Box::new(TableScan::new(
+                tx.clone(),
+                student_layout.clone(),
+                "student",
+            ))
+        };
+        let scan = Scan::IndexSelect(IndexSelectScan::new(
+            student_table(),
+            Box::new(ii.open()),
+            Value::Int(1),
+        ));
+        assert_eq!(scan.schema().fields(), student_fields);
+        let scan = Scan::IndexOrdered(IndexOrderedScan::new(student_table(), ii.open()));
+        assert_eq!(scan.schema().fields(), student_fields);
+
+        // The join field is in both scans, but is only reported once
+        let scan = Scan::IndexJoin(IndexJoinScan::new(
+            table("enroll"),
+            table("student"),
+            Box::new(ii.open()),
+            "sid",
+        ));
+        assert_eq!(
+            scan.schema().fields(),
+            fields(&[
+                "eid",
+                "sid",
+                "section_id",
+                "grade",
+                "sname",
+                "grad_year",
+                "major_id"
+            ])
+        );
+
+        let scan = Scan::Empty(EmptyScan::new(student_layout.schema().clone()));
+        assert_eq!(scan.schema().fields(), student_fields);
+    }
+
+    #[test]
+    fn test_drop_nested_scans_releases_pins() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_mgr = MetadataManager::new(&tx);
+        let num_available = || db.buffer_manager().lock().unwrap().num_available();
+        let table = |name: &str| {
+            let layout = metadata_mgr.get_table_layout(name, &tx).unwrap();
+            TableScan::new(tx.clone(), layout, name)
+        };
+        let index = || {
+            metadata_mgr
+                .get_index_info("student", tx.clone())
+                .remove("sid")
+                .unwrap()
+                .open()
+        };
+        let available = num_available();
+
+        // Students enrolled in a section, paired with every department name in order
+        let predicate = Predicate::from_term(Term::new(
+            Expression::Field("grade".to_string()),
+            Expression::Constant(Value::from("A")),
+        ));
+        let join = Scan::IndexJoin(IndexJoinScan::new(
+            Box::new(Scan::Table(table("enroll"))),
+            Box::new(Scan::Table(table("student"))),
+            Box::new(index()),
+            "sid",
+        ));
+        let depts = Scan::Sort(SortScan::new(
+            tx.clone(),
+            Box::new(Scan::Table(table("dept"))),
+            &make_schema! { "dname" => varchar(20) },
+            &[SortField("dname".to_string(), SortOrder::Ascending)],
+        ));
+        let mut scan = Scan::Project(ProjectScan::new(
+            vec!["sname".to_string(), "dname".to_string()],
+            Box::new(Scan::Product(ProductScan::new(
+                Box::new(Scan::Select(SelectScan::new(predicate, Box::new(join)))),
+                Box::new(depts),
+            ))),
+        ));
+
+        // Stop part way through the scan, while blocks of every table are pinned
+        assert!(scan.next());
+        assert!(scan.next());
+        assert_eq!(scan.get_string("sname"), Ok("joe".to_string()));
+        assert_eq!(scan.get_string("dname"), Ok("drama".to_string()));
+        assert!(num_available() < available);
+        drop(scan);
+        assert_eq!(num_available(), available);
+
+        // Closing first and then dropping releases each pin once
+        let mut scan = Scan::IndexSelect(IndexSelectScan::new(
+            Box::new(table("student")),
+            Box::new(index()),
+            Value::Int(4),
+        ));
+        scan.before_first();
+        assert!(scan.next());
+        scan.close();
+        assert_eq!(num_available(), available);
+        drop(scan);
+        assert_eq!(num_available(), available);
+
+        // Scans dropped after their transaction has ended have nothing left to unpin
+        let mut scan = Scan::IndexSelect(IndexSelectScan::new(
+            Box::new(table("student")),
+            Box::new(index()),
+            Value::Int(4),
+        ));
+        scan.before_first();
+        assert!(scan.next());
+        tx.lock().unwrap().commit().unwrap();
+        drop(scan);
+        assert_eq!(num_available(), available);
+    }
+}
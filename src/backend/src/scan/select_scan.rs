@@ -1,6 +1,7 @@
 use crate::{
     parser::{constant::Value, predicate::Predicate},
     rid::RID,
+    schema::Schema,
 };
 
 use super::scan::{Scan, ScanResult, Scannable, UpdateScannable};
@@ -8,6 +9,7 @@ use super::scan::{Scan, ScanResult, Scannable, UpdateScannable};
 pub struct SelectScan {
     predicate: Predicate,
     scan: Box<Scan>,
+    rows_examined: u64,
 }
 
 impl SelectScan {
@@ -19,7 +21,33 @@ impl SelectScan {
     ///     Only records that satisfy the predicate will be returned by this scan.
     /// * `scan` - The `Scan` underlying this `SelectScan`.
     pub fn new(predicate: Predicate, scan: Box<Scan>) -> Self {
-        Self { predicate, scan }
+        Self {
+            predicate,
+            scan,
+            rows_examined: 0,
+        }
+    }
+
+    /// Get the number of records that were read from the underlying scan and tested against the
+    /// predicate, including those read by any select scans nested beneath this one.
+    pub fn rows_examined(&self) -> u64 {
+        let nested = match &*self.scan {
+            Scan::Select(scan) => scan.rows_examined(),
+            _ => 0,
+        };
+        self.rows_examined + nested
+    }
+
+    /// Get the RID of the current record, if the underlying scan reads its records directly from
+    /// a table.
+    pub fn current_rid(&self) -> Option<RID> {
+        self.scan.current_rid()
+    }
+
+    /// Whether the current record of the underlying scan satisfies the predicate. This is
+    /// always true after `next` returns true, but may be false after `move_to_rid`.
+    pub fn is_satisfied(&self) -> bool {
+        self.predicate.is_satisfied(&*self.scan)
     }
 }
 
@@ -30,6 +58,7 @@ impl Scannable for SelectScan {
 
     fn next(&mut self) -> bool {
         while self.scan.next() {
+            self.rows_examined += 1;
             //if self.predicate.is_satisfied(self.scan.as_super_mut()) {
             if self.predicate.is_satisfied(&*self.scan) {
                 // This is a record that satisfies the predicate
@@ -56,6 +85,10 @@ impl Scannable for SelectScan {
         self.scan.has_field(field_name)
     }
 
+    fn schema(&self) -> &Schema {
+        self.scan.schema()
+    }
+
     fn close(&mut self) {
         self.scan.close()
     }
@@ -86,6 +119,9 @@ impl UpdateScannable for SelectScan {
         self.scan.get_rid()
     }
 
+    /// Move the underlying scan to the specified record, even if it does not satisfy the
+    /// predicate. Fields of that record can be read and modified, and a following call to
+    /// `next` moves to the next record after it that satisfies the predicate.
     fn move_to_rid(&mut self, rid: RID) {
         self.scan.move_to_rid(rid);
     }
@@ -100,9 +136,9 @@ mod tests {
     use crate::{
         metadata::metadata_manager::MetadataManager,
         parser::{constant::Value, expression::Expression, predicate::Predicate, term::Term},
-        scan::scan::{Scan, Scannable},
+        scan::scan::{Scan, Scannable, UpdateScannable},
         table_scan::TableScan,
-        tests::test_utils::{create_default_tables, default_test_db},
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
     };
 
     use super::SelectScan;
@@ -139,4 +175,69 @@ mod tests {
         assert_eq!(select_scan.get_int("sid").unwrap(), 9);
         assert!(!select_scan.next());
     }
+
+    #[test]
+    fn test_select_scan_before_first_resets() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let table_scan = Box::new(Scan::Table(TableScan::new(
+            tx.clone(),
+            metadata_manager.get_table_layout("student", &tx).unwrap(),
+            "student",
+        )));
+        let predicate = Predicate::from_term(Term::new(
+            Expression::Field("major_id".to_string()),
+            Expression::Constant(Value::Int(20)),
+        ));
+        let mut select_scan = SelectScan::new(predicate, table_scan);
+
+        let records = assert_rescannable(&mut select_scan, &["sid"]);
+        assert_eq!(
+            records,
+            [2, 4, 6, 8].map(|sid| vec![Value::Int(sid)]).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_select_scan_move_to_filtered_rid() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let metadata_manager = MetadataManager::new(&tx);
+        let layout = metadata_manager.get_table_layout("student", &tx).unwrap();
+
+        // Find the record for student 2, who did not graduate in 2021
+        let mut student_scan = TableScan::new(tx.clone(), layout.clone(), "student");
+        while student_scan.next() && student_scan.get_int("sid").unwrap() != 2 {}
+
+        let predicate = Predicate::from_term(Term::new(
+            Expression::Field("grad_year".to_string()),
+            Expression::Constant(Value::Int(2021)),
+        ));
+        let table_scan = Box::new(Scan::Table(TableScan::new(tx.clone(), layout, "student")));
+        let mut select_scan = SelectScan::new(predicate, table_scan);
+
+        assert!(select_scan.next());
+        assert_eq!(select_scan.get_int("sid").unwrap(), 1);
+        assert!(select_scan.is_satisfied());
+
+        select_scan.move_to_rid(student_scan.get_rid());
+        assert_eq!(select_scan.get_rid(), student_scan.get_rid());
+        assert_eq!(select_scan.get_val("sid").unwrap(), Value::Int(2));
+        assert!(!select_scan.is_satisfied());
+
+        // Moving forward applies the predicate again
+        assert!(select_scan.next());
+        assert_eq!(select_scan.get_int("sid").unwrap(), 7);
+        assert!(select_scan.is_satisfied());
+        assert!(select_scan.next());
+        assert_eq!(select_scan.get_int("sid").unwrap(), 9);
+        assert!(!select_scan.next());
+    }
 }
@@ -0,0 +1,508 @@
+use std::{
+    cmp::Ordering,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    parser::{
+        constant::Value,
+        parser::{SortField, SortOrder},
+    },
+    rid::RID,
+    schema::Schema,
+    table_scan::TableScan,
+    temp_table::TempTable,
+    transaction::Tx,
+};
+
+use super::scan::{Scan, ScanResult, Scannable, UpdateScannable};
+
+// Fields added to every record of a run, holding the RID the record was read from, so that ties
+// are broken the same way when runs are merged as when a run is sorted. Records that were not read
+// from a table have a block of -1.
+const RID_BLOCK_FIELD: &str = "$rid_block";
+const RID_SLOT_FIELD: &str = "$rid_slot";
+
+/// The number of records a sort scan holds in memory at once, unless it is given a run size.
+pub const DEFAULT_RUN_SIZE: usize = 1024;
+
+// A record being sorted: the RID it was read from, and its values in schema order.
+type Row = (Option<RID>, Vec<Value>);
+
+// The position of a sort field in a row, and the direction it is ordered in.
+type SortKey = (usize, SortOrder);
+
+/// A scan that returns the records of an underlying scan ordered by a list of sort fields, each
+/// in ascending or descending order.
+///
+/// The underlying scan is read in full, and closed, when the sort scan is created. Records are
+/// sorted in memory in runs, which are written to temporary tables and then merged two at a time
+/// until a single, sorted run remains. The temporary tables are deleted when they are merged, or
+/// when the sort scan is dropped. Records that compare equal on every sort field are ordered by
+/// RID when the underlying scan reads them from a table, so the order of ties does not depend on
+/// how they were read, and otherwise keep the order in which the underlying scan produced them.
+pub struct SortScan {
+    // Declared before `temp` so the scan is closed before the table is deleted
+    scan: TableScan,
+    temp: TempTable,
+    schema: Schema,
+    runs: usize,
+}
+
+impl SortScan {
+    /// Create a new sort scan that holds at most `DEFAULT_RUN_SIZE` records in memory at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction that owns the temporary tables holding the sorted records.
+    /// * `scan` - The scan to read records from.
+    /// * `schema` - The fields of the underlying scan to keep for each record.
+    /// * `sort_fields` - The fields to order records by, most significant first, with the
+    ///   direction of each. Each must be in `schema`.
+    pub fn new(
+        tx: Arc<Mutex<Tx>>,
+        scan: Box<Scan>,
+        schema: &Schema,
+        sort_fields: &[SortField],
+    ) -> Self {
+        Self::with_run_size(tx, scan, schema, sort_fields, DEFAULT_RUN_SIZE)
+    }
+
+    /// Create a new sort scan that holds at most `run_size` records in memory at once. Merging
+    /// the runs pins only three blocks at a time, so inputs larger than the buffer pool can be
+    /// sorted.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction that owns the temporary tables holding the sorted records.
+    /// * `scan` - The scan to read records from.
+    /// * `schema` - The fields of the underlying scan to keep for each record.
+    /// * `sort_fields` - The fields to order records by, most significant first, with the
+    ///   direction of each. Each must be in `schema`.
+    /// * `run_size` - The number of records sorted in memory at once. Must be at least 1.
+    pub fn with_run_size(
+        tx: Arc<Mutex<Tx>>,
+        scan: Box<Scan>,
+        schema: &Schema,
+        sort_fields: &[SortField],
+        run_size: usize,
+    ) -> Self {
+        assert!(run_size > 0, "a sort run must hold at least one record");
+        Self::sort(tx, scan, schema, sort_fields, run_size)
+    }
+
+    /// The number of runs the records of the underlying scan were sorted in before being merged.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    fn sort(
+        tx: Arc<Mutex<Tx>>,
+        mut scan: Box<Scan>,
+        schema: &Schema,
+        sort_fields: &[SortField],
+        run_size: usize,
+    ) -> Self {
+        let fields = schema.fields();
+        let keys: Vec<SortKey> = sort_fields
+            .iter()
+            .map(|SortField(sort_field, order)| {
+                let position = fields
+                    .iter()
+                    .position(|f| f == sort_field)
+                    .expect("sort field is not one of the scan fields");
+                (position, *order)
+            })
+            .collect();
+        let mut run_schema = schema.clone();
+        run_schema.add_int_field(RID_BLOCK_FIELD);
+        run_schema.add_int_field(RID_SLOT_FIELD);
+
+        let mut runs = vec![];
+        let mut rows = vec![];
+        scan.before_first();
+        loop {
+            let more = scan.next();
+            if more {
+                let row = fields
+                    .iter()
+                    .map(|field_name| scan.get_val(field_name))
+                    .collect::<ScanResult<Vec<Value>>>()
+                    // TODO: error handling
+                    .expect("failed to read record for sorting");
+                rows.push((scan.current_rid(), row));
+            }
+
+            if rows.len() >= run_size || (!more && (runs.is_empty() || !rows.is_empty())) {
+                rows.sort_by(|a, b| compare(&keys, a, b));
+                let run = TempTable::new(tx.clone(), run_schema.clone());
+                let mut dest = run.open();
+                for row in rows.drain(..) {
+                    write_row(&mut dest, &fields, &row);
+                }
+                dest.close();
+                runs.push(run);
+            }
+            if !more {
+                break;
+            }
+        }
+        scan.close();
+
+        // Merge neighbouring runs, so that ties between records that were not read from a table
+        // keep the order of the runs
+        let num_runs = runs.len();
+        while runs.len() > 1 {
+            let mut merged = vec![];
+            let mut runs_iter = runs.into_iter();
+            while let Some(left) = runs_iter.next() {
+                merged.push(match runs_iter.next() {
+                    Some(right) => merge(&tx, &run_schema, &fields, &keys, left, right),
+                    None => left,
+                });
+            }
+            runs = merged;
+        }
+
+        let temp = runs.pop().expect("a sort always writes at least one run");
+        let mut scan = temp.open();
+        scan.before_first();
+
+        Self {
+            scan,
+            temp,
+            schema: schema.clone(),
+            runs: num_runs,
+        }
+    }
+}
+
+// Compare two records by the sort fields, then by RID. Ties are broken by RID in ascending order
+// whatever the direction of the sort fields.
+fn compare(keys: &[SortKey], (a_rid, a): &Row, (b_rid, b): &Row) -> Ordering {
+    keys.iter()
+        .map(|&(k, order)| match order {
+            SortOrder::Ascending => a[k].cmp(&b[k]),
+            SortOrder::Descending => b[k].cmp(&a[k]),
+        })
+        .find(|ord| *ord != Ordering::Equal)
+        .unwrap_or_else(|| a_rid.cmp(b_rid))
+}
+
+fn read_row(scan: &TableScan, fields: &[String]) -> Row {
+    let row = fields
+        .iter()
+        .map(|field_name| scan.get_val(field_name))
+        .collect::<ScanResult<Vec<Value>>>()
+        .expect("failed to read record of a sort run");
+    let block = scan.get_int(RID_BLOCK_FIELD).unwrap();
+    let slot = scan.get_int(RID_SLOT_FIELD).unwrap();
+    let rid = (block >= 0).then(|| RID::new(block as u64, slot as i16));
+    (rid, row)
+}
+
+fn write_row(dest: &mut TableScan, fields: &[String], (rid, row): &Row) {
+    dest.insert();
+    for (field_name, val) in fields.iter().zip(row.iter()) {
+        dest.set_val(field_name, val);
+    }
+    let (block, slot) = rid
+        .as_ref()
+        .map_or((-1, -1), |rid| (rid.block_num() as i32, rid.slot() as i32));
+    dest.set_int(RID_BLOCK_FIELD, block);
+    dest.set_int(RID_SLOT_FIELD, slot);
+}
+
+// Merge two sorted runs into a new one. Ties go to the left run, which holds earlier records.
+fn merge(
+    tx: &Arc<Mutex<Tx>>,
+    run_schema: &Schema,
+    fields: &[String],
+    keys: &[SortKey],
+    left: TempTable,
+    right: TempTable,
+) -> TempTable {
+    let run = TempTable::new(tx.clone(), run_schema.clone());
+    let mut dest = run.open();
+    let mut left_scan = left.open();
+    let mut right_scan = right.open();
+    let mut left_row = left_scan.next().then(|| read_row(&left_scan, fields));
+    let mut right_row = right_scan.next().then(|| read_row(&right_scan, fields));
+    loop {
+        let take_left = match (&left_row, &right_row) {
+            (Some(l), Some(r)) => compare(keys, l, r) != Ordering::Greater,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_left {
+            write_row(&mut dest, fields, left_row.as_ref().unwrap());
+            left_row = left_scan.next().then(|| read_row(&left_scan, fields));
+        } else {
+            write_row(&mut dest, fields, right_row.as_ref().unwrap());
+            right_row = right_scan.next().then(|| read_row(&right_scan, fields));
+        }
+    }
+
+    left_scan.close();
+    right_scan.close();
+    dest.close();
+    run
+}
+
+impl Scannable for SortScan {
+    fn before_first(&mut self) {
+        self.scan.before_first();
+    }
+
+    fn next(&mut self) -> bool {
+        self.scan.next()
+    }
+
+    fn get_int(&self, field_name: &str) -> ScanResult<i32> {
+        self.scan.get_int(field_name)
+    }
+
+    fn get_string(&self, field_name: &str) -> ScanResult<String> {
+        self.scan.get_string(field_name)
+    }
+
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        self.scan.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.schema.has_field(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn close(&mut self) {
+        self.scan.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{
+        db::SimpleDB,
+        layout::Layout,
+        make_schema,
+        parser::{
+            constant::Value,
+            parser::{SortField, SortOrder},
+        },
+        scan::scan::{Scan, Scannable, UpdateScannable},
+        schema::Schema,
+        table_scan::TableScan,
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db},
+    };
+
+    use super::SortScan;
+
+    #[test]
+    fn test_sort_scan() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let temp_dir = db.file_manager().temp_directory();
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let mut schema = Schema::new();
+        schema.add_from("sid", layout.schema());
+        schema.add_from("grad_year", layout.schema());
+        let table_scan = Box::new(Scan::Table(TableScan::new(tx.clone(), layout, "student")));
+
+        let sort_fields = [asc("grad_year"), asc("sid")];
+        let mut scan = SortScan::new(tx.clone(), table_scan, &schema, &sort_fields);
+        assert_eq!(scan.runs(), 1);
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 1);
+
+        let rows = assert_rescannable(&mut scan, &["grad_year", "sid"]);
+        assert_eq!(rows.len(), 9);
+        assert!(rows.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(rows[0], vec![Value::Int(2019), Value::Int(8)]);
+
+        // The temp table holding the sorted records is deleted with the scan
+        drop(scan);
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_sort_scan_descending() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let mut schema = Schema::new();
+        schema.add_from("grad_year", layout.schema());
+        schema.add_from("sname", layout.schema());
+
+        // Sorting in one run and merging runs of two records give the same order
+        let sort_fields = [
+            SortField("grad_year".to_string(), SortOrder::Descending),
+            asc("sname"),
+        ];
+        for run_size in [9, 2] {
+            let table_scan = Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                layout.clone(),
+                "student",
+            )));
+            let mut scan =
+                SortScan::with_run_size(tx.clone(), table_scan, &schema, &sort_fields, run_size);
+            let rows = assert_rescannable(&mut scan, &["grad_year", "sname"]);
+            let expected = [
+                (2022, "max"),
+                (2022, "sue"),
+                (2021, "art"),
+                (2021, "joe"),
+                (2021, "lee"),
+                (2020, "amy"),
+                (2020, "bob"),
+                (2020, "kim"),
+                (2019, "pat"),
+            ]
+            .map(|(year, name)| vec![Value::Int(year), Value::from(name)]);
+            assert_eq!(rows, expected);
+        }
+    }
+
+    #[test]
+    fn test_sort_scan_breaks_ties_by_rid() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+
+        // Free the slot of the first student, so that a student inserted later takes it and
+        // comes first in RID order
+        let mut table_scan = TableScan::new(tx.clone(), layout.clone(), "student");
+        assert!(table_scan.next());
+        table_scan.delete();
+        table_scan.before_first();
+        table_scan.insert();
+        table_scan.set_int("sid", 10);
+        table_scan.set_int("major_id", 20);
+
+        let mut rids = HashMap::new();
+        table_scan.before_first();
+        while table_scan.next() {
+            rids.insert(table_scan.get_int("sid").unwrap(), table_scan.get_rid());
+        }
+        table_scan.close();
+
+        let mut schema = Schema::new();
+        schema.add_from("sid", layout.schema());
+        schema.add_from("major_id", layout.schema());
+        let sort_fields = [asc("major_id")];
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let table_scan = Box::new(Scan::Table(TableScan::new(
+                tx.clone(),
+                layout.clone(),
+                "student",
+            )));
+            let mut scan = SortScan::new(tx.clone(), table_scan, &schema, &sort_fields);
+            runs.push(assert_rescannable(&mut scan, &["major_id", "sid"]));
+        }
+        assert_eq!(runs[0], runs[1]);
+
+        let rows = &runs[0];
+        assert_eq!(rows.len(), 9);
+        assert!(rows
+            .windows(2)
+            .all(|w| w[0][0] < w[1][0]
+                || (w[0][0] == w[1][0] && rids[&sid(&w[0])] < rids[&sid(&w[1])])));
+        let sids: Vec<i32> = rows.iter().map(|row| sid(row)).collect();
+        assert_eq!(sids, [3, 9, 10, 2, 4, 6, 8, 5, 7]);
+    }
+
+    #[test]
+    fn test_sort_scan_in_runs() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(&log_dir).unwrap();
+        let num_bufs = 10;
+        let db = SimpleDB::new(&data_dir, &log_dir, num_bufs);
+        let temp_dir = db.file_manager().temp_directory();
+
+        // Fill a table with more blocks than there are buffers
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let schema = make_schema! {
+            "key" => i32,
+            "pad" => varchar(200)
+        };
+        let layout = Layout::from_schema(schema.clone());
+        let num_recs = 500;
+        let mut table_scan = TableScan::new(tx.clone(), layout.clone(), "big");
+        for i in 0..num_recs {
+            table_scan.insert();
+            table_scan.set_int("key", (i * 37) % num_recs);
+            table_scan.set_string("pad", &format!("record {}", i));
+        }
+        table_scan.close();
+        assert!(tx.lock().unwrap().size("big").unwrap() > num_bufs as u64);
+
+        let sort_fields = [asc("key")];
+        let table_scan = Box::new(Scan::Table(TableScan::new(tx.clone(), layout, "big")));
+        let mut scan = SortScan::with_run_size(tx.clone(), table_scan, &schema, &sort_fields, 64);
+        assert_eq!(scan.runs(), 8);
+        assert!(!scan.has_field("$rid_block"));
+
+        // The merged runs have been deleted, and only the block the scan is on stays pinned
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 1);
+        assert_eq!(
+            db.buffer_manager().lock().unwrap().num_available(),
+            num_bufs - 1
+        );
+
+        let rows = assert_rescannable(&mut scan, &["key", "pad"]);
+        let keys: Vec<Value> = rows.iter().map(|row| row[0].clone()).collect();
+        assert_eq!(keys, (0..num_recs).map(Value::Int).collect::<Vec<_>>());
+        assert_eq!(rows[37], vec![Value::Int(37), Value::from("record 1")]);
+    }
+
+    fn asc(field_name: &str) -> SortField {
+        SortField(field_name.to_string(), SortOrder::Ascending)
+    }
+
+    fn sid(row: &[Value]) -> i32 {
+        match row[1] {
+            Value::Int(sid) => sid,
+            _ => panic!("sid is not an integer"),
+        }
+    }
+}
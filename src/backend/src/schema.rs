@@ -2,15 +2,36 @@ use std::collections::HashMap;
 
 use crate::parser::parser::{FieldDefinition, FieldDefinitions, FieldType};
 
+/// A hint for how the values of a field are encoded within a block. Readers see the same values
+/// whatever the encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Every value is stored in full in its record.
+    #[default]
+    None,
+    /// Each block holds a dictionary of the distinct values of the field, and records hold the
+    /// position of their value in it. Only applies to varchar fields in fixed-size slots, and
+    /// suits fields with few distinct values.
+    Dictionary,
+    /// Values are stored as the difference from a value held by the block. Not yet applied by
+    /// record pages, which store every value in full.
+    Delta,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct FieldInfo {
     typ: i32,
     length: u64,
+    encoding: Encoding,
 }
 
 impl FieldInfo {
     pub fn new(typ: i32, length: u64) -> Self {
-        Self { typ, length }
+        Self {
+            typ,
+            length,
+            encoding: Encoding::None,
+        }
     }
 
     /// Map a `FieldType` provided by the parser to the FieldInfo required by a `Schema`.
@@ -20,11 +41,8 @@ impl FieldInfo {
     /// * `typ` - A FieldType generated by the Parser.
     pub fn from_field_type(typ: &FieldType) -> Self {
         match typ {
-            FieldType::Int => Self { typ: 0, length: 0 },
-            FieldType::Varchar(len) => Self {
-                typ: 1,
-                length: *len as u64,
-            },
+            FieldType::Int => Self::new(0, 0),
+            FieldType::Varchar(len) => Self::new(1, *len as u64),
         }
     }
 }
@@ -101,6 +119,24 @@ impl Schema {
         }
     }
 
+    /// Change the length of a field. Nothing is changed if the schema has no such field.
+    pub fn set_field_length(&mut self, name: &str, length: u64) {
+        if let Some(info) = self.info.get_mut(name) {
+            info.length = length;
+        }
+    }
+
+    /// Set the encoding of a field's values. Nothing is changed if the schema has no such field.
+    pub fn set_field_encoding(&mut self, name: &str, encoding: Encoding) {
+        if let Some(info) = self.info.get_mut(name) {
+            info.encoding = encoding;
+        }
+    }
+
+    pub fn get_field_encoding(&self, name: &str) -> Option<Encoding> {
+        self.info.get(name).map(|f| f.encoding)
+    }
+
     pub fn get_field_type(&self, name: &str) -> Option<i32> {
         self.info.get(name).map(|f| f.typ)
     }
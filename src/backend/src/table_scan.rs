@@ -1,4 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
 
 use log::Record;
 
@@ -9,6 +12,7 @@ use crate::{
     record_page::RecordPage,
     rid::RID,
     scan::scan::{ScanError, ScanResult, Scannable, UpdateScannable},
+    schema::Schema,
     transaction::Tx,
 };
 
@@ -19,45 +23,51 @@ pub struct TableScan {
     file_name: String,
     current_slot: i16,
     is_closed: bool,
+    invalid_rid: Option<RID>,
+    prefetch_depth: u64,
+    prefetch: Option<JoinHandle<()>>,
 }
 
 impl Scannable for TableScan {
     /// Move to the next record.
     ///
     /// Iterate through all records in a table. Each call to `next` will find the next slot with a
-    /// valid record in it. Iteration will continue until there are no remaining Record pages.
+    /// valid record in it. Iteration will continue until there are no remaining Record pages, or
+    /// the scan has been closed.
     fn next(&mut self) -> bool {
-        self.current_slot = self.record_page.next_after(self.current_slot);
-
-        while self.current_slot == -1 {
-            if self.at_last_block() {
-                return false;
-            }
-
-            self.move_to_block(self.record_page.block_number() + 1);
-            self.current_slot = self.record_page.next_after(self.current_slot);
-        }
-
-        true
+        // TODO: error handling
+        self.try_next().expect("failed to move to the next record")
     }
 
+    /// Move before the first record. If the table has no blocks, e.g. because it was truncated,
+    /// the scan is left closed so that `next` finds no records.
     fn before_first(&mut self) {
-        self.move_to_block(0);
+        // TODO: error handling
+        self.try_before_first()
+            .expect("failed to move before the first record");
     }
 
     fn get_int(&self, field_name: &str) -> ScanResult<i32> {
-        if !self.has_field(field_name) {
+        if let Some(rid) = &self.invalid_rid {
+            Err(ScanError::InvalidRid(rid.clone()))
+        } else if self.is_closed {
+            Err(ScanError::Closed)
+        } else if !self.has_field(field_name) {
             Err(ScanError::NonExistentField(field_name.to_string()))
         } else {
-            Ok(self.record_page.get_int(self.current_slot, field_name))
+            Ok(self.record_page.get_int(self.current_slot, field_name)?)
         }
     }
 
     fn get_string(&self, field_name: &str) -> ScanResult<String> {
-        if !self.has_field(field_name) {
+        if let Some(rid) = &self.invalid_rid {
+            Err(ScanError::InvalidRid(rid.clone()))
+        } else if self.is_closed {
+            Err(ScanError::Closed)
+        } else if !self.has_field(field_name) {
             Err(ScanError::NonExistentField(field_name.to_string()))
         } else {
-            Ok(self.record_page.get_string(self.current_slot, field_name))
+            Ok(self.record_page.get_string(self.current_slot, field_name)?)
         }
     }
 
@@ -73,9 +83,25 @@ impl Scannable for TableScan {
         self.layout.schema().has_field(field_name)
     }
 
+    fn schema(&self) -> &Schema {
+        self.layout.schema()
+    }
+
+    /// Close the scan, unpinning its current block. Closing an already closed scan does nothing.
+    ///
+    /// Records cannot be read from a closed scan until it is repositioned with `before_first` or
+    /// `move_to_rid`.
     fn close(&mut self) {
+        self.join_prefetch();
+
         if !self.is_closed {
-            self.tx.lock().unwrap().unpin(&self.record_page.block());
+            // The transaction releases every pin when it commits or rolls back, in which case a
+            // scan closed (or dropped) afterwards has nothing left to unpin.
+            let blk = self.record_page.block();
+            let mut tx = self.tx.lock().unwrap();
+            if tx.is_pinned(&blk) {
+                tx.unpin(&blk);
+            }
             self.is_closed = true;
         }
     }
@@ -83,51 +109,46 @@ impl Scannable for TableScan {
 
 impl UpdateScannable for TableScan {
     fn set_int(&mut self, field_name: &str, val: i32) {
-        self.record_page.set_int(self.current_slot, field_name, val);
+        // TODO: error handling
+        self.record_page
+            .set_int(self.current_slot, field_name, val)
+            .expect("failed to set int field");
     }
 
     fn set_string(&mut self, field_name: &str, val: &str) {
+        // TODO: error handling
         self.record_page
-            .set_string(self.current_slot, field_name, val);
+            .set_string(self.current_slot, field_name, val)
+            .expect("failed to set string field");
     }
 
     fn set_val(&mut self, field_name: &str, val: &Value) {
-        //self.record_page.set
         match val {
-            Value::Int(i) => self.record_page.set_int(self.current_slot, field_name, *i),
-            Value::Varchar(s) => self
-                .record_page
-                .set_string(self.current_slot, field_name, s),
+            Value::Int(i) => self.set_int(field_name, *i),
+            Value::Varchar(s) => self.set_string(field_name, s),
         }
     }
 
     /// Move to the next slot available for insertion and mark it USED.
     ///
     /// If there is no slot available in the current `RecordPage`, creates a new `RecordPage` and
-    /// uses the first slot there.
+    /// uses the first slot there. This is also the case for a table with no blocks.
     fn insert(&mut self) {
-        self.current_slot = self.record_page.insert_after(self.current_slot);
-
-        while self.current_slot == -1 {
-            if self.at_last_block() {
-                self.move_to_new_block();
-            } else {
-                self.move_to_block(self.record_page.block_number() + 1);
-            }
-
-            self.current_slot = self.record_page.insert_after(self.current_slot);
-        }
+        // TODO: error handling
+        self.try_insert().expect("failed to insert record");
     }
 
     fn delete(&mut self) {
-        self.record_page.delete(self.current_slot);
+        // TODO: error handling
+        self.try_delete().expect("failed to delete record");
     }
 
+    /// Move to the specified record. If the RID lies outside of the table, e.g. because it was
+    /// read from a corrupt index entry, the scan is left closed and reading any field returns
+    /// `ScanError::InvalidRid`.
     fn move_to_rid(&mut self, rid: RID) {
-        self.close();
-        let blk = BlockId::new(&self.file_name, rid.block_num());
-        self.record_page = RecordPage::new(self.tx.clone(), blk, self.layout.clone());
-        self.current_slot = rid.slot();
+        // TODO: error handling
+        self.try_move_to_rid(rid).expect("failed to move to record");
     }
 
     fn get_rid(&self) -> RID {
@@ -137,46 +158,200 @@ impl UpdateScannable for TableScan {
 
 impl TableScan {
     pub fn new(tx: Arc<Mutex<Tx>>, layout: Layout, file_name: &str) -> Self {
+        // TODO: error handling
+        Self::try_new(tx, layout, file_name).expect("failed to open table scan")
+    }
+
+    /// Create a new `TableScan` positioned before the first record of a table. Returns an error if
+    /// the first block of the table could not be pinned, e.g. because the buffer pool is exhausted.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction used to access the table.
+    /// * `layout` - The layout of the table's records.
+    /// * `file_name` - The name of the file holding the table.
+    pub fn try_new(tx: Arc<Mutex<Tx>>, layout: Layout, file_name: &str) -> ScanResult<Self> {
         let blk = {
             let mut ltx = tx.lock().unwrap();
-            if ltx.size(file_name) == 0 {
-                ltx.append(file_name)
+            if ltx.size(file_name)? == 0 {
+                ltx.append(file_name)?
             } else {
                 BlockId::new(file_name, 0)
             }
         };
 
-        Self {
-            record_page: RecordPage::new(tx.clone(), blk, layout.clone()),
+        Ok(Self {
+            record_page: RecordPage::try_new(tx.clone(), blk, layout.clone())?,
             tx,
             layout,
             file_name: file_name.to_string(),
             current_slot: -1,
             is_closed: false,
-        }
+            invalid_rid: None,
+            prefetch_depth: 0,
+            prefetch: None,
+        })
     }
 
     pub fn get_layout(&self) -> &Layout {
         &self.layout
     }
 
-    fn at_last_block(&self) -> bool {
-        let num_blocks = self.tx.lock().unwrap().size(&self.file_name);
-        self.record_page.block_number() == num_blocks - 1
+    /// Whether the scan is positioned at a record, rather than at an empty slot or before the
+    /// first record of a block. A versioned record that the transaction's snapshot does not
+    /// include is not counted, so that scans positioned by RID skip it as `next` does.
+    pub fn is_at_record(&self) -> ScanResult<bool> {
+        Ok(!self.is_closed
+            && self.record_page.is_used(self.current_slot)?
+            && self.record_page.is_visible(self.current_slot)?)
     }
 
-    fn move_to_new_block(&mut self) {
+    /// Move to the next record, like `next`. Returns an error if a block could not be read, e.g.
+    /// because its lock could not be acquired in time.
+    pub fn try_next(&mut self) -> ScanResult<bool> {
+        if self.is_closed {
+            return Ok(false);
+        }
+
+        self.current_slot = self.record_page.next_after(self.current_slot)?;
+
+        while self.current_slot == -1 {
+            if self.at_last_block()? {
+                return Ok(false);
+            }
+
+            self.move_to_block(self.record_page.block_number() + 1)?;
+            self.current_slot = self.record_page.next_after(self.current_slot)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Move before the first record, like `before_first`. Returns an error if the size of the
+    /// table could not be read or its first block could not be pinned.
+    pub fn try_before_first(&mut self) -> ScanResult<()> {
+        if self.tx.lock().unwrap().size(&self.file_name)? == 0 {
+            self.close();
+            self.invalid_rid = None;
+            self.current_slot = -1;
+            return Ok(());
+        }
+
+        self.move_to_block(0)
+    }
+
+    /// Insert a new record, like `insert`. Returns an error if a block could not be read or
+    /// appended.
+    pub fn try_insert(&mut self) -> ScanResult<()> {
+        if self.is_closed && self.tx.lock().unwrap().size(&self.file_name)? == 0 {
+            self.move_to_new_block()?;
+        }
+
+        self.current_slot = self.record_page.insert_after(self.current_slot)?;
+
+        while self.current_slot == -1 {
+            if self.at_last_block()? {
+                self.move_to_new_block()?;
+            } else {
+                self.move_to_block(self.record_page.block_number() + 1)?;
+            }
+
+            self.current_slot = self.record_page.insert_after(self.current_slot)?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete the current record, like `delete`. Returns an error if its block could not be
+    /// written.
+    pub fn try_delete(&mut self) -> ScanResult<()> {
+        Ok(self.record_page.delete(self.current_slot)?)
+    }
+
+    /// Move to the specified record, like `move_to_rid`. Returns an error if the size of the
+    /// table or the record's block could not be read.
+    pub fn try_move_to_rid(&mut self, rid: RID) -> ScanResult<()> {
         self.close();
-        let blk = self.tx.lock().unwrap().append(&self.file_name);
-        self.record_page = RecordPage::new(self.tx.clone(), blk, self.layout.clone());
+        self.invalid_rid = None;
+        if rid.block_num() >= self.tx.lock().unwrap().size(&self.file_name)? {
+            self.invalid_rid = Some(rid);
+            return Ok(());
+        }
+
+        let blk = BlockId::new(&self.file_name, rid.block_num());
+        self.record_page = RecordPage::try_new(self.tx.clone(), blk, self.layout.clone())?;
+        self.is_closed = false;
+        self.current_slot = rid.slot();
+        if rid.slot() < 0 || !self.record_page.is_valid_slot(rid.slot())? {
+            self.close();
+            self.invalid_rid = Some(rid);
+        }
+        Ok(())
+    }
+
+    /// Read up to `depth` blocks ahead into the buffer pool each time the scan moves to a new
+    /// block, so that sequential scans are served from the pool. A depth of 0 disables
+    /// prefetching.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of blocks to read ahead of the current block.
+    pub fn set_prefetch(&mut self, depth: u64) {
+        self.prefetch_depth = depth;
+    }
+
+    fn at_last_block(&self) -> ScanResult<bool> {
+        let num_blocks = self.tx.lock().unwrap().size(&self.file_name)?;
+        Ok(self.record_page.block_number() + 1 >= num_blocks)
+    }
+
+    fn move_to_new_block(&mut self) -> ScanResult<()> {
+        self.close();
+        self.invalid_rid = None;
+        let blk = self.tx.lock().unwrap().append(&self.file_name)?;
+        self.record_page = RecordPage::try_new(self.tx.clone(), blk, self.layout.clone())?;
+        self.is_closed = false;
         self.current_slot = -1;
+        Ok(())
     }
 
-    fn move_to_block(&mut self, block_num: u64) {
+    fn move_to_block(&mut self, block_num: u64) -> ScanResult<()> {
         self.close();
+        self.invalid_rid = None;
         let blk = BlockId::new(&self.file_name, block_num);
-        self.record_page = RecordPage::new(self.tx.clone(), blk, self.layout.clone());
+        self.record_page = RecordPage::try_new(self.tx.clone(), blk, self.layout.clone())?;
+        self.is_closed = false;
         self.current_slot = -1;
+        self.prefetch_after(block_num)
+    }
+
+    // Start reading the blocks following `block_num` into the buffer pool, without reading past
+    // the end of the file. Any earlier prefetch is finished first, so at most one prefetch thread
+    // runs for the scan at a time.
+    fn prefetch_after(&mut self, block_num: u64) -> ScanResult<()> {
+        self.join_prefetch();
+        if self.prefetch_depth == 0 {
+            return Ok(());
+        }
+
+        let mut tx = self.tx.lock().unwrap();
+        let end = tx
+            .size(&self.file_name)?
+            .min(block_num + 1 + self.prefetch_depth);
+        let blks: Vec<BlockId> = (block_num + 1..end)
+            .map(|n| BlockId::new(&self.file_name, n))
+            .collect();
+        if !blks.is_empty() {
+            self.prefetch = Some(tx.prefetch(blks));
+        }
+        Ok(())
+    }
+
+    // Wait for the running prefetch, if any, to finish.
+    fn join_prefetch(&mut self) {
+        if let Some(prefetch) = self.prefetch.take() {
+            prefetch.join().expect("prefetch thread panicked");
+        }
     }
 }
 
@@ -190,7 +365,7 @@ impl Drop for TableScan {
 macro_rules! insert {
     ($scan:expr, $( ($($val:expr),*) ),*) => {{
         use crate::scan::scan::{Scan, Scannable, UpdateScannable};
-        use crate::parser::constant::{FromDynamic};
+        use crate::parser::constant::Value;
 
         let fields = $scan.get_layout().schema().fields();
         $scan.before_first();
@@ -198,8 +373,7 @@ macro_rules! insert {
             $scan.insert();
             let mut index = 0;
             $(
-                let val: &dyn FromDynamic = &$val;
-                $scan.set_val(&fields[index], &val.as_val());
+                $scan.set_val(&fields[index], &Value::from($val));
                 index += 1;
             )*
         )*
@@ -223,7 +397,7 @@ macro_rules! insert {
 macro_rules! assert_table_scan_results {
         ($scan:expr, $( ($($val:expr),*) ),*) => {{
             use crate::scan::scan::{Scan, Scannable};
-        use crate::parser::constant::{FromDynamic};
+        use crate::parser::constant::Value;
 
             let fields = $scan.get_layout().schema().fields();
             $scan.before_first();
@@ -231,8 +405,7 @@ macro_rules! assert_table_scan_results {
                 assert!($scan.next(), "Failed to navigate to the first record in the scan");
                 let mut index = 0;
                 $(
-                    let expected_val: &dyn FromDynamic = &$val;
-                    let expected_val = expected_val.as_val();
+                    let expected_val = Value::from($val);
 
                     if let Ok(actual_val) = $scan.get_val(&fields[index]) {
                         assert_eq!(actual_val, expected_val, "field '{}' not equal", &fields[index]);
@@ -249,18 +422,25 @@ macro_rules! assert_table_scan_results {
 #[cfg(test)]
 mod tests {
 
-    use std::{fs, sync::mpsc, thread};
+    use std::{fs, sync::mpsc, thread, time::Duration};
 
     use tempfile::tempdir;
 
     use crate::{
-        buffer_manager::{self, BufferManager},
+        buffer_manager::{self, BufferError, BufferManager},
         eviction_policy::SimpleEvictionPolicy,
         file_manager::FileManager,
+        index::{btree::btree_index::BTreeIndex, index::Index},
         layout,
-        lock_table::LockTable,
+        lock_table::{LockAbortError, LockKey, LockTable},
         log_manager::LogManager,
+        make_schema,
+        parser::parser::{parse, RootNode},
+        record_page::RecordPageError,
+        scan::scan::Scan,
         schema::Schema,
+        tests::test_utils::{assert_rescannable, create_default_tables, default_test_db, test_db},
+        transaction::TxError,
     };
 
     use super::*;
@@ -333,6 +513,616 @@ mod tests {
         assert_eq!(count, 40);
 
         scan.close();
-        t.lock().unwrap().commit();
+        t.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_before_first_resets() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let mut scan = Scan::Table(TableScan::new(tx.clone(), layout, "student"));
+
+        let records = assert_rescannable(&mut scan, &["sid"]);
+        assert_eq!(
+            records,
+            (1..10).map(|sid| vec![Value::Int(sid)]).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_writes_visible_to_other_scans_in_tx() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+
+        // Both scans have the first block of the table pinned before either writes to it
+        let mut writer = TableScan::new(tx.clone(), layout.clone(), "student");
+        let mut reader = TableScan::new(tx.clone(), layout.clone(), "student");
+        assert!(writer.next());
+        assert!(reader.next());
+        assert_eq!(writer.get_rid(), reader.get_rid());
+
+        writer.set_string("sname", "jon");
+        assert_eq!(reader.get_string("sname").unwrap(), "jon");
+        reader.set_int("grad_year", 2025);
+        assert_eq!(writer.get_int("grad_year").unwrap(), 2025);
+
+        // A record inserted by one scan is found by the other
+        writer.insert();
+        writer.set_int("sid", 10);
+        writer.set_string("sname", "ann");
+        let rid = writer.get_rid();
+        writer.close();
+
+        let mut found = None;
+        while reader.next() {
+            if reader.get_rid() == rid {
+                found = Some((
+                    reader.get_int("sid").unwrap(),
+                    reader.get_string("sname").unwrap(),
+                ));
+            }
+        }
+        assert_eq!(found, Some((10, "ann".to_string())));
+        reader.close();
+
+        // A scan opened after the others were closed also sees the writes
+        let mut scan = TableScan::new(tx.clone(), layout, "student");
+        assert!(scan.next());
+        assert_eq!(scan.get_string("sname").unwrap(), "jon");
+        assert_eq!(scan.get_int("grad_year").unwrap(), 2025);
+        scan.move_to_rid(rid);
+        assert_eq!(scan.get_int("sid").unwrap(), 10);
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_scan_table_without_blocks() {
+        let td = tempdir().unwrap();
+        let db = default_test_db(&td);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = Layout::from_schema(make_schema! {
+            "A" => i32
+        });
+
+        let mut scan = TableScan::new(tx.clone(), layout, "T");
+        for i in 0..3 {
+            scan.insert();
+            scan.set_int("A", i);
+        }
+
+        tx.lock().unwrap().truncate("T").unwrap();
+        assert_eq!(tx.lock().unwrap().size("T").unwrap(), 0);
+
+        scan.before_first();
+        assert!(!scan.next());
+        assert!(!scan.next());
+
+        // Inserting into the empty table appends its first block
+        scan.insert();
+        scan.set_int("A", 7);
+        assert_eq!(tx.lock().unwrap().size("T").unwrap(), 1);
+        assert_eq!(
+            assert_rescannable(&mut scan, &["A"]),
+            vec![vec![Value::Int(7)]]
+        );
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_read_deleted_record() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout, "student");
+
+        // Delete the record for student 4, keeping hold of its RID
+        scan.before_first();
+        while scan.next() {
+            if scan.get_int("sid") == Ok(4) {
+                break;
+            }
+        }
+        let rid = scan.get_rid();
+        let slot = rid.slot();
+        scan.delete();
+
+        // A stale RID can't be used to read the deleted record
+        scan.move_to_rid(rid);
+        let not_used = ScanError::RecordPage(RecordPageError::SlotNotUsed(
+            BlockId::new("student", 0),
+            slot,
+        ));
+        assert_eq!(scan.get_int("sid"), Err(not_used.clone()));
+        assert_eq!(scan.get_string("sname"), Err(not_used.clone()));
+        assert_eq!(scan.get_val("grad_year"), Err(not_used));
+
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_move_to_invalid_rid() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let num_blocks = tx.lock().unwrap().size("student").unwrap();
+        let mut scan = TableScan::new(tx.clone(), layout.clone(), "student");
+
+        // A block past the end of the file is neither read nor created
+        let past_end = RID::new(num_blocks, 0);
+        scan.move_to_rid(past_end.clone());
+        assert_eq!(
+            scan.get_int("sid"),
+            Err(ScanError::InvalidRid(past_end.clone()))
+        );
+        assert_eq!(scan.get_val("sname"), Err(ScanError::InvalidRid(past_end)));
+        assert!(!scan.next());
+        assert_eq!(tx.lock().unwrap().size("student").unwrap(), num_blocks);
+
+        let past_slots = RID::new(
+            0,
+            layout.max_records(tx.lock().unwrap().block_size()) as i16,
+        );
+        scan.move_to_rid(past_slots.clone());
+        assert_eq!(
+            scan.get_string("sname"),
+            Err(ScanError::InvalidRid(past_slots))
+        );
+
+        // The scan can still be moved to a valid record
+        scan.move_to_rid(RID::new(0, 0));
+        assert_eq!(scan.get_int("sid"), Ok(1));
+
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_nonexistent_field() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            10,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        let lt = Arc::new(LockTable::new());
+        let t = Arc::new(Mutex::new(Tx::new(fm.clone(), lm.clone(), bm.clone(), lt)));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        schema.add_string_field("B", 10);
+        let layout = Layout::from_schema(schema);
+
+        let mut scan = TableScan::new(t.clone(), layout, "T");
+        scan.insert();
+        scan.set_int("A", 1);
+        scan.set_string("B", "one");
+
+        scan.before_first();
+        assert!(scan.next());
+        assert!(!scan.has_field("C"));
+        assert_eq!(
+            scan.get_int("C"),
+            Err(ScanError::NonExistentField("C".to_string()))
+        );
+        assert_eq!(
+            scan.get_string("C"),
+            Err(ScanError::NonExistentField("C".to_string()))
+        );
+        assert_eq!(
+            scan.get_val("C"),
+            Err(ScanError::NonExistentField("C".to_string()))
+        );
+        assert_eq!(scan.get_val("A"), Ok(Value::Int(1)));
+
+        scan.close();
+        t.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_use_after_close() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            10,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        let lt = Arc::new(LockTable::new());
+        let t = Arc::new(Mutex::new(Tx::new(fm.clone(), lm.clone(), bm.clone(), lt)));
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        let layout = Layout::from_schema(schema);
+
+        let mut scan = TableScan::new(t.clone(), layout, "T");
+        scan.insert();
+        scan.set_int("A", 1);
+        scan.insert();
+        scan.set_int("A", 2);
+
+        scan.before_first();
+        assert!(scan.next());
+        let blk = BlockId::new("T", 0);
+        assert!(t.lock().unwrap().is_pinned(&blk));
+
+        // Closing releases the block, and closing again is a no-op
+        scan.close();
+        scan.close();
+        assert!(!t.lock().unwrap().is_pinned(&blk));
+
+        assert!(!scan.next());
+        assert_eq!(scan.get_int("A"), Err(ScanError::Closed));
+        assert_eq!(scan.get_string("A"), Err(ScanError::Closed));
+        assert_eq!(scan.get_val("A"), Err(ScanError::Closed));
+
+        // Repositioning the scan reopens it
+        scan.before_first();
+        assert!(scan.next());
+        assert_eq!(scan.get_int("A"), Ok(1));
+        assert!(scan.next());
+        assert_eq!(scan.get_int("A"), Ok(2));
+        assert!(!scan.next());
+
+        // Dropping the scan after the transaction released its pins is safe
+        t.lock().unwrap().commit().unwrap();
+        drop(scan);
+    }
+
+    #[test]
+    fn test_scan_with_exhausted_buffer_pool() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            2,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        // Give up on a pin quickly rather than waiting for the other transaction
+        bm.lock().unwrap().set_max_wait(Duration::from_millis(100));
+        let lt = Arc::new(LockTable::new());
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        let layout = Layout::from_schema(schema);
+
+        // Hold every buffer in the pool with another transaction
+        let mut other = Tx::new(fm.clone(), lm.clone(), bm.clone(), lt.clone());
+        let blk0 = other.append("other").unwrap();
+        let blk1 = other.append("other").unwrap();
+        other.pin(&blk0);
+        other.pin(&blk1);
+
+        let t = Arc::new(Mutex::new(Tx::new(fm.clone(), lm.clone(), bm.clone(), lt)));
+        let res = TableScan::try_new(t.clone(), layout.clone(), "T");
+        assert!(matches!(
+            res,
+            Err(ScanError::Tx(TxError::Buffer(
+                BufferError::NoAvailableBuffers(_)
+            )))
+        ));
+
+        // Once a buffer is released, the scan can be retried
+        other.commit().unwrap();
+        let mut scan = TableScan::try_new(t.clone(), layout, "T").unwrap();
+        scan.insert();
+        scan.set_int("A", 1);
+        scan.before_first();
+        assert!(scan.next());
+        assert_eq!(scan.get_int("A"), Ok(1));
+
+        scan.close();
+        t.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_scan_lock_timeout() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let locks = Arc::new(LockTable::with_max_wait(Duration::from_millis(100)));
+        let new_tx = || {
+            Arc::new(Mutex::new(Tx::new(
+                db.file_manager(),
+                db.log_manager(),
+                db.buffer_manager(),
+                locks.clone(),
+            )))
+        };
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        let layout = Layout::from_schema(schema);
+        let tx = new_tx();
+        TableScan::new(tx.clone(), layout.clone(), "T").close();
+        tx.lock().unwrap().commit().unwrap();
+
+        // A writer holds an xlock on the table's only block
+        let writer = new_tx();
+        let mut scan = TableScan::new(writer.clone(), layout.clone(), "T");
+        scan.insert();
+        scan.set_int("A", 1);
+
+        // A reader's scan returns the lock timeout rather than panicking
+        let reader = new_tx();
+        let mut other = TableScan::try_new(reader.clone(), layout.clone(), "T").unwrap();
+        let blk = BlockId::new("T", 0);
+        assert_eq!(
+            other.try_next(),
+            Err(ScanError::Tx(TxError::LockAbort(LockAbortError(
+                LockKey::Block(blk)
+            ))))
+        );
+        other.close();
+        reader.lock().unwrap().rollback().unwrap();
+
+        scan.close();
+        writer.lock().unwrap().commit().unwrap();
+        let reader = new_tx();
+        let mut other = TableScan::try_new(reader.clone(), layout, "T").unwrap();
+        assert_eq!(other.try_next(), Ok(true));
+        assert_eq!(other.get_int("A"), Ok(1));
+        other.close();
+        reader.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_scan_with_prefetch() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let lt = Arc::new(LockTable::new());
+        let new_pool = || {
+            Arc::new(Mutex::new(BufferManager::new(
+                64,
+                fm.clone(),
+                lm.clone(),
+                SimpleEvictionPolicy::new(),
+            )))
+        };
+
+        let mut schema = Schema::new();
+        schema.add_int_field("A");
+        schema.add_string_field("B", 100);
+        let layout = Layout::from_schema(schema);
+
+        let bm = new_pool();
+        let t = Arc::new(Mutex::new(Tx::new(fm.clone(), lm.clone(), bm, lt.clone())));
+        let mut scan = TableScan::new(t.clone(), layout.clone(), "T");
+        for i in 0..500 {
+            scan.insert();
+            scan.set_int("A", i);
+            scan.set_string("B", &format!("string {}", i));
+        }
+        scan.close();
+        t.lock().unwrap().commit().unwrap();
+        let num_blocks = fm.length("T").unwrap();
+        assert!(num_blocks > 10);
+
+        // Scan the table with a cold buffer pool, with and without prefetching
+        let mut scan_all = |depth: u64| {
+            let bm = new_pool();
+            let t = Arc::new(Mutex::new(Tx::new(
+                fm.clone(),
+                lm.clone(),
+                bm.clone(),
+                lt.clone(),
+            )));
+            let mut scan = TableScan::new(t.clone(), layout.clone(), "T");
+            scan.set_prefetch(depth);
+            scan.before_first();
+            let mut count = 0;
+            while scan.next() {
+                assert_eq!(scan.get_int("A"), Ok(count));
+                count += 1;
+            }
+            assert_eq!(count, 500);
+            scan.close();
+            t.lock().unwrap().commit().unwrap();
+
+            bm.lock().unwrap().stats()
+        };
+
+        let stats = scan_all(0);
+        assert_eq!(stats.misses, num_blocks);
+        assert_eq!(stats.prefetched, 0);
+
+        let stats = scan_all(4);
+        assert!(stats.hits > 3 * stats.misses);
+        assert_eq!(stats.prefetched, num_blocks - 1);
+    }
+
+    #[test]
+    fn test_snapshot_reads_versioned_records() {
+        let td = tempdir().unwrap();
+        let db = default_test_db(&td);
+        let layout = Layout::from_schema(make_schema! { "A" => i32 }).with_versions();
+
+        let read_all = |tx: &Arc<Mutex<Tx>>| {
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "T");
+            let mut vals = vec![];
+            while scan.next() {
+                vals.push(scan.get_int("A").unwrap());
+            }
+            vals.sort();
+            vals
+        };
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        {
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "T");
+            for i in 0..10 {
+                scan.insert();
+                scan.set_int("A", i);
+            }
+        }
+        tx.lock().unwrap().commit().unwrap();
+
+        let reader = Arc::new(Mutex::new(db.new_tx()));
+        reader.lock().unwrap().read_snapshot();
+        let snapshot: Vec<i32> = (0..10).collect();
+        assert_eq!(read_all(&reader), snapshot);
+
+        // The writer inserts and deletes while the reader keeps scanning
+        let (to_reader, from_writer) = mpsc::channel();
+        let (to_writer, from_reader) = mpsc::channel::<()>();
+        let writer = Arc::new(Mutex::new(db.new_tx()));
+        let handle = {
+            let writer = writer.clone();
+            let layout = layout.clone();
+            thread::spawn(move || {
+                let mut scan = TableScan::new(writer.clone(), layout, "T");
+                for i in 10..15 {
+                    scan.insert();
+                    scan.set_int("A", i);
+                }
+                to_reader.send(()).unwrap();
+                from_reader.recv().unwrap();
+
+                scan.before_first();
+                while scan.next() {
+                    if scan.get_int("A").unwrap() < 3 {
+                        scan.delete();
+                    }
+                }
+                scan.close();
+                writer.lock().unwrap().commit().unwrap();
+                to_reader.send(()).unwrap();
+            })
+        };
+
+        from_writer.recv().unwrap();
+        assert_eq!(read_all(&reader), snapshot);
+        to_writer.send(()).unwrap();
+        from_writer.recv().unwrap();
+        handle.join().unwrap();
+        assert_eq!(read_all(&reader), snapshot);
+
+        // An aborted insert is never visible
+        let aborted = Arc::new(Mutex::new(db.new_tx()));
+        {
+            let mut scan = TableScan::new(aborted.clone(), layout.clone(), "T");
+            scan.insert();
+            scan.set_int("A", 100);
+        }
+        assert_eq!(read_all(&reader), snapshot);
+        aborted.lock().unwrap().rollback().unwrap();
+        assert_eq!(read_all(&reader), snapshot);
+        reader.lock().unwrap().commit().unwrap();
+
+        // A later snapshot, or a transaction without one, sees the writer's changes
+        let expected: Vec<i32> = (3..15).collect();
+        let reader = Arc::new(Mutex::new(db.new_tx()));
+        reader.lock().unwrap().read_snapshot();
+        assert_eq!(read_all(&reader), expected);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(read_all(&tx), expected);
+        assert_eq!(reader.lock().unwrap().profile().slocks, 0);
+    }
+
+    #[test]
+    fn test_parsed_values_stored_and_indexed() {
+        let td = tempdir().unwrap();
+        let db = default_test_db(&td);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let RootNode::Insert(node) = parse("insert into t (a, b) values (-7, 'it''s')").unwrap()
+        else {
+            panic!("expected an insert");
+        };
+        assert_eq!(node.2, vec![Value::Int(-7), Value::from("it's")]);
+
+        let layout = Layout::from_schema(make_schema! {
+            "a" => i32,
+            "b" => varchar(10)
+        });
+        let mut scan = TableScan::new(tx.clone(), layout, "t");
+        scan.insert();
+        for (field_name, val) in node.fields() {
+            scan.set_val(field_name, val);
+        }
+
+        // The values read back are the parsed values, and can be used directly as index keys
+        scan.before_first();
+        assert!(scan.next());
+        let leaf_layout = Layout::from_schema(make_schema! {
+            "dataval" => varchar(10),
+            "block" => i32,
+            "id" => i32
+        });
+        let mut index = BTreeIndex::new(tx.clone(), "t-b-idx", leaf_layout);
+        for (field_name, val) in node.fields() {
+            let stored = scan.get_val(field_name).unwrap();
+            assert_eq!(&stored, val);
+            if field_name == "b" {
+                index.insert(&stored, scan.get_rid());
+            }
+        }
+
+        index.before_first(&node.2[1]);
+        assert!(index.next());
+        assert_eq!(index.get_rid(), Some(scan.get_rid()));
     }
 }
@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{layout::Layout, schema::Schema, table_scan::TableScan, transaction::Tx};
+
+/// A table stored in a temporary file, for holding the intermediate results of a query, e.g.
+/// the records being sorted by a sort scan.
+///
+/// Temporary tables are not recorded in the metadata catalogs. The backing file is deleted when
+/// the table is dropped, or when its transaction ends if that happens first.
+pub struct TempTable {
+    tx: Arc<Mutex<Tx>>,
+    file_id: String,
+    layout: Layout,
+}
+
+impl TempTable {
+    /// Create a new, empty temporary table.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The transaction that owns the table.
+    /// * `schema` - The schema of the table's records.
+    pub fn new(tx: Arc<Mutex<Tx>>, schema: Schema) -> Self {
+        let file_id = tx.lock().unwrap().create_temp_file();
+        Self {
+            tx,
+            file_id,
+            layout: Layout::from_schema(schema),
+        }
+    }
+
+    /// Get the id of the file backing the table.
+    pub fn file_id(&self) -> &str {
+        &self.file_id
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Open a scan over the table. Scans must not outlive the table.
+    pub fn open(&self) -> TableScan {
+        TableScan::new(self.tx.clone(), self.layout.clone(), &self.file_id)
+    }
+}
+
+impl Drop for TempTable {
+    fn drop(&mut self) {
+        // The transaction may be poisoned if a query panicked, in which case the file is left
+        // for the next FileManager to clear
+        if let Ok(mut tx) = self.tx.lock() {
+            tx.delete_temp_file(&self.file_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use tempfile::tempdir;
+
+    use crate::{
+        make_schema,
+        scan::scan::{Scannable, UpdateScannable},
+        tests::test_utils::test_db,
+    };
+
+    use super::TempTable;
+
+    #[test]
+    fn test_temp_table_deleted_on_drop() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let temp_dir = db.file_manager().temp_directory();
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+
+        let schema = make_schema! {
+            "a" => i32
+        };
+        let table = TempTable::new(tx.clone(), schema);
+        let mut scan = table.open();
+        for i in 0..500 {
+            scan.insert();
+            scan.set_int("a", i);
+        }
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 1);
+
+        // Dropping the table while a scan is still open unpins the scan's block
+        drop(table);
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 0);
+        drop(scan);
+
+        // Temp tables still alive when the transaction ends are deleted with it
+        let schema = make_schema! {
+            "a" => i32
+        };
+        let table = TempTable::new(tx.clone(), schema);
+        let mut scan = table.open();
+        scan.insert();
+        scan.set_int("a", 1);
+        scan.close();
+
+        // Changes to temp files are not logged
+        assert_eq!(tx.lock().unwrap().profile().log_bytes, 0);
+
+        tx.lock().unwrap().commit().unwrap();
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 0);
+        drop(table);
+    }
+}
@@ -0,0 +1,265 @@
+//! End-to-end tests of the durability guarantee: after a crash, reopening the database keeps the
+//! changes of committed transactions and undoes everything else.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tempfile::tempdir;
+
+use crate::{
+    db::SimpleDB,
+    make_schema,
+    scan::scan::{Scannable, UpdateScannable},
+    schema::Schema,
+    table_scan::TableScan,
+    transaction::Tx,
+};
+
+const NUM_BUFFERS: usize = 64;
+
+/// A database that can be crashed and reopened.
+struct CrashHarness {
+    data_dir: PathBuf,
+    log_dir: PathBuf,
+    db: SimpleDB,
+}
+
+impl CrashHarness {
+    fn new(root: &Path) -> Self {
+        let data_dir = root.join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = root.join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let db = SimpleDB::new(&data_dir, &log_dir, NUM_BUFFERS);
+        Self {
+            data_dir,
+            log_dir,
+            db,
+        }
+    }
+
+    fn new_tx(&self) -> Arc<Mutex<Tx>> {
+        Arc::new(Mutex::new(self.db.new_tx()))
+    }
+
+    fn create_table(&self, tblname: &str, schema: &Schema, tx: &Arc<Mutex<Tx>>) {
+        self.db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .create_table(tblname, schema, tx)
+            .unwrap();
+    }
+
+    fn scan(&self, tblname: &str, tx: &Arc<Mutex<Tx>>) -> TableScan {
+        let layout = self
+            .db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout(tblname, tx)
+            .unwrap_or_else(|| panic!("table '{}' does not exist", tblname));
+        TableScan::new(tx.clone(), layout, tblname)
+    }
+
+    /// Insert (id, balance) records into an account table.
+    fn insert(&self, tblname: &str, records: &[(i32, i32)], tx: &Arc<Mutex<Tx>>) {
+        let mut scan = self.scan(tblname, tx);
+        for (id, balance) in records {
+            scan.insert();
+            scan.set_int("id", *id);
+            scan.set_int("balance", *balance);
+        }
+        scan.close();
+    }
+
+    fn set_balance(&self, tblname: &str, id: i32, balance: i32, tx: &Arc<Mutex<Tx>>) {
+        let mut scan = self.scan(tblname, tx);
+        while scan.next() {
+            if scan.get_int("id") == Ok(id) {
+                scan.set_int("balance", balance);
+            }
+        }
+        scan.close();
+    }
+
+    fn delete(&self, tblname: &str, id: i32, tx: &Arc<Mutex<Tx>>) {
+        let mut scan = self.scan(tblname, tx);
+        while scan.next() {
+            if scan.get_int("id") == Ok(id) {
+                scan.delete();
+            }
+        }
+        scan.close();
+    }
+
+    /// Read the (id, balance) records of an account table, ordered by id.
+    fn records(&self, tblname: &str) -> Vec<(i32, i32)> {
+        let tx = self.new_tx();
+        let mut scan = self.scan(tblname, &tx);
+        let mut records = vec![];
+        while scan.next() {
+            records.push((
+                scan.get_int("id").unwrap(),
+                scan.get_int("balance").unwrap(),
+            ));
+        }
+        scan.close();
+        tx.lock().unwrap().commit().unwrap();
+
+        records.sort();
+        records
+    }
+
+    /// Simulate a crash while the given transactions are still running, then reopen the
+    /// database. The transactions' modified buffers are written out first, as they could have
+    /// been by buffer replacement, so recovery has on-disk changes to undo.
+    fn crash_and_reopen(self, running: Vec<Arc<Mutex<Tx>>>) -> Self {
+        for tx in &running {
+            let tx_num = tx.lock().unwrap().tx_num();
+            self.db
+                .buffer_manager()
+                .lock()
+                .unwrap()
+                .flush_all(tx_num)
+                .unwrap();
+        }
+
+        // Nothing is committed, rolled back, or flushed on the way down. The running transactions
+        // are forgotten rather than dropped, since a dropped transaction rolls back.
+        let Self {
+            data_dir,
+            log_dir,
+            db,
+        } = self;
+        std::mem::forget(running);
+        drop(db);
+
+        let db = SimpleDB::open(&data_dir, &log_dir, NUM_BUFFERS);
+        Self {
+            data_dir,
+            log_dir,
+            db,
+        }
+    }
+}
+
+fn account_schema() -> Schema {
+    make_schema! {
+        "id" => i32,
+        "balance" => i32
+    }
+}
+
+#[test]
+fn test_recovery_keeps_only_committed_changes() {
+    let td = tempdir().unwrap();
+    let harness = CrashHarness::new(td.path());
+
+    // Committed: create two tables and fill them
+    let tx = harness.new_tx();
+    harness.create_table("acct", &account_schema(), &tx);
+    harness.create_table("ledger", &account_schema(), &tx);
+    harness.insert(
+        "acct",
+        &[(1, 100), (2, 100), (3, 100), (4, 100), (5, 100)],
+        &tx,
+    );
+    tx.lock().unwrap().commit().unwrap();
+
+    // Rolled back before the crash
+    let tx = harness.new_tx();
+    harness.set_balance("acct", 1, 0, &tx);
+    tx.lock().unwrap().rollback().unwrap();
+
+    // Committed updates and inserts
+    let tx = harness.new_tx();
+    harness.set_balance("acct", 2, 200, &tx);
+    harness.insert("acct", &[(6, 100)], &tx);
+    tx.lock().unwrap().commit().unwrap();
+
+    // Still running at the crash: an update, a delete, an insert, and later a new table
+    let running = harness.new_tx();
+    harness.set_balance("acct", 3, 999, &running);
+    harness.delete("acct", 4, &running);
+    harness.insert("acct", &[(7, 700)], &running);
+
+    // Committed while the other transaction is still running
+    let tx = harness.new_tx();
+    harness.insert("ledger", &[(1, 50), (2, 75)], &tx);
+    tx.lock().unwrap().commit().unwrap();
+
+    harness.create_table("scratch", &account_schema(), &running);
+
+    let harness = harness.crash_and_reopen(vec![running]);
+
+    assert_eq!(
+        harness.records("acct"),
+        vec![(1, 100), (2, 200), (3, 100), (4, 100), (5, 100), (6, 100)]
+    );
+    assert_eq!(harness.records("ledger"), vec![(1, 50), (2, 75)]);
+
+    let tx = harness.new_tx();
+    assert!(harness
+        .db
+        .metadata_manager()
+        .read()
+        .unwrap()
+        .get_table_layout("scratch", &tx)
+        .is_none());
+    tx.lock().unwrap().commit().unwrap();
+
+    // A second crash with no running transactions changes nothing
+    let harness = harness.crash_and_reopen(vec![]);
+    assert_eq!(harness.records("acct").len(), 6);
+    assert_eq!(harness.records("ledger"), vec![(1, 50), (2, 75)]);
+}
+
+#[test]
+fn test_restore_snapshot_backup() {
+    let td = tempdir().unwrap();
+    let harness = CrashHarness::new(&td.path().join("db"));
+
+    let tx = harness.new_tx();
+    harness.create_table("acct", &account_schema(), &tx);
+    harness.insert("acct", &[(1, 100), (2, 100)], &tx);
+    tx.lock().unwrap().commit().unwrap();
+
+    let backup_dir = td.path().join("backup");
+    harness.db.snapshot_backup(&backup_dir).unwrap();
+
+    // Changes committed after the backup are not part of it
+    let tx = harness.new_tx();
+    harness.set_balance("acct", 1, 0, &tx);
+    harness.insert("acct", &[(3, 100)], &tx);
+    harness.create_table("ledger", &account_schema(), &tx);
+    tx.lock().unwrap().commit().unwrap();
+    assert_eq!(harness.records("acct"), vec![(1, 0), (2, 100), (3, 100)]);
+
+    let data_dir = backup_dir.join("data");
+    let log_dir = backup_dir.join("log");
+    let backup = CrashHarness {
+        db: SimpleDB::open(&data_dir, &log_dir, NUM_BUFFERS),
+        data_dir,
+        log_dir,
+    };
+    assert_eq!(backup.records("acct"), vec![(1, 100), (2, 100)]);
+
+    let tx = backup.new_tx();
+    assert!(backup
+        .db
+        .metadata_manager()
+        .read()
+        .unwrap()
+        .get_table_layout("ledger", &tx)
+        .is_none());
+    tx.lock().unwrap().commit().unwrap();
+
+    // The original database is unaffected by the backup
+    drop(backup);
+    assert_eq!(harness.records("acct"), vec![(1, 0), (2, 100), (3, 100)]);
+}
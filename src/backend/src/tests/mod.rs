@@ -1 +1,2 @@
+mod crash_recovery;
 pub mod test_utils;
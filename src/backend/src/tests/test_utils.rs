@@ -1,4 +1,5 @@
 use std::{
+    cell::Cell,
     fs,
     sync::{Arc, Mutex},
 };
@@ -7,14 +8,16 @@ use log::warn;
 use tempfile::{tempdir, TempDir};
 
 use crate::{
+    buffer_manager::BufferManager,
     db::SimpleDB,
     index::{btree::btree_index::BTreeIndex, index::Index},
     insert,
     layout::Layout,
     make_schema,
     metadata::metadata_manager::{self, MetadataManager},
-    parser::constant::FromDynamic,
-    scan::scan::{Scannable, UpdateScannable},
+    parser::constant::Value,
+    scan::scan::{ScanResult, Scannable, UpdateScannable},
+    schema::Schema,
     table_scan::TableScan,
     transaction::Tx,
 };
@@ -71,11 +74,19 @@ pub fn create_default_tables(db: &mut SimpleDB) {
     };
 
     let meta_mgr = MetadataManager::new(&tx);
-    meta_mgr.create_table("student", &student_schema, &tx);
-    meta_mgr.create_table("dept", &dept_schema, &tx);
-    meta_mgr.create_table("enroll", &enroll_schema, &tx);
-    meta_mgr.create_table("course", &course_schema, &tx);
-    meta_mgr.create_table("section", &section_schema, &tx);
+    meta_mgr
+        .create_table("student", &student_schema, &tx)
+        .unwrap();
+    meta_mgr.create_table("dept", &dept_schema, &tx).unwrap();
+    meta_mgr
+        .create_table("enroll", &enroll_schema, &tx)
+        .unwrap();
+    meta_mgr
+        .create_table("course", &course_schema, &tx)
+        .unwrap();
+    meta_mgr
+        .create_table("section", &section_schema, &tx)
+        .unwrap();
 
     let mut scan = TableScan::new(
         tx.clone(),
@@ -158,7 +169,153 @@ pub fn create_default_tables(db: &mut SimpleDB) {
         (53, 62, "brando", 2017)
     ];
 
-    tx.lock().unwrap().commit();
+    tx.lock().unwrap().commit().unwrap();
+}
+
+/// Read the given fields from every record of a scan, then assert that repositioning the scan
+/// with `before_first` after a partial pass produces the same records again. Returns the records.
+pub fn assert_rescannable<S: Scannable>(scan: &mut S, fields: &[&str]) -> Vec<Vec<Value>> {
+    let read_all = |scan: &mut S| {
+        scan.before_first();
+        let mut records = vec![];
+        while scan.next() {
+            records.push(fields.iter().map(|f| scan.get_val(f).unwrap()).collect());
+        }
+        records
+    };
+
+    let records = read_all(scan);
+
+    scan.before_first();
+    for _ in 0..records.len() / 2 {
+        assert!(scan.next());
+    }
+    assert_eq!(
+        read_all(scan),
+        records,
+        "second pass differs from the first"
+    );
+
+    records
+}
+
+/// The calls made to the scan wrapped by a `CountingScan`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScanCalls {
+    pub before_first: u64,
+    pub next: u64,
+    /// Calls to `get_int`, `get_string` and `get_val`.
+    pub gets: u64,
+}
+
+/// A scan that counts the calls made to the scan it wraps, so that a test can assert how much
+/// work is asked of a scan.
+pub struct CountingScan<S: Scannable> {
+    scan: S,
+    before_first: u64,
+    next: u64,
+    gets: Cell<u64>,
+}
+
+impl<S: Scannable> CountingScan<S> {
+    pub fn new(scan: S) -> Self {
+        Self {
+            scan,
+            before_first: 0,
+            next: 0,
+            gets: Cell::new(0),
+        }
+    }
+
+    /// The calls made since the scan was wrapped.
+    pub fn calls(&self) -> ScanCalls {
+        ScanCalls {
+            before_first: self.before_first,
+            next: self.next,
+            gets: self.gets.get(),
+        }
+    }
+
+    fn count_get(&self) {
+        self.gets.set(self.gets.get() + 1);
+    }
+}
+
+impl<S: Scannable> Scannable for CountingScan<S> {
+    fn before_first(&mut self) {
+        self.before_first += 1;
+        self.scan.before_first();
+    }
+
+    fn next(&mut self) -> bool {
+        self.next += 1;
+        self.scan.next()
+    }
+
+    fn get_int(&self, field_name: &str) -> ScanResult<i32> {
+        self.count_get();
+        self.scan.get_int(field_name)
+    }
+
+    fn get_string(&self, field_name: &str) -> ScanResult<String> {
+        self.count_get();
+        self.scan.get_string(field_name)
+    }
+
+    fn get_val(&self, field_name: &str) -> ScanResult<Value> {
+        self.count_get();
+        self.scan.get_val(field_name)
+    }
+
+    fn has_field(&self, field_name: &str) -> bool {
+        self.scan.has_field(field_name)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.scan.schema()
+    }
+
+    fn close(&mut self) {
+        self.scan.close()
+    }
+}
+
+/// Counts the blocks pinned by a transaction, and the blocks read from storage into the buffer
+/// pool by any transaction, since the counter was created.
+pub struct BlockCounter {
+    tx: Arc<Mutex<Tx>>,
+    buffer_mgr: Arc<Mutex<BufferManager>>,
+    pinned: u64,
+    read: u64,
+}
+
+impl BlockCounter {
+    pub fn new(db: &SimpleDB, tx: &Arc<Mutex<Tx>>) -> Self {
+        let buffer_mgr = db.buffer_manager();
+        let pinned = tx.lock().unwrap().profile().blocks_pinned;
+        let read = Self::reads(&buffer_mgr);
+        Self {
+            tx: tx.clone(),
+            buffer_mgr,
+            pinned,
+            read,
+        }
+    }
+
+    /// The pins made by the transaction, including repeated pins of the same block.
+    pub fn blocks_pinned(&self) -> u64 {
+        self.tx.lock().unwrap().profile().blocks_pinned - self.pinned
+    }
+
+    /// The blocks read from storage, whether to serve a pin or ahead of one.
+    pub fn blocks_read(&self) -> u64 {
+        Self::reads(&self.buffer_mgr) - self.read
+    }
+
+    fn reads(buffer_mgr: &Arc<Mutex<BufferManager>>) -> u64 {
+        let stats = buffer_mgr.lock().unwrap().stats();
+        stats.misses + stats.prefetched
+    }
 }
 
 fn populate_i32_index(
@@ -186,3 +343,53 @@ fn populate_i32_index(
         index.insert(&val, rid);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use crate::table_scan::TableScan;
+
+    use super::*;
+
+    #[test]
+    fn test_counters_match_scan_calls() {
+        let td = tempdir().unwrap();
+        let mut db = test_db(&td);
+        create_default_tables(&mut db);
+
+        // Leave none of the student table's blocks in the buffer pool
+        let buffer_mgr = db.buffer_manager();
+        buffer_mgr.lock().unwrap().flush_file("student");
+        buffer_mgr.lock().unwrap().discard_file("student");
+
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let layout = db
+            .metadata_manager()
+            .read()
+            .unwrap()
+            .get_table_layout("student", &tx)
+            .unwrap();
+        let counter = BlockCounter::new(&db, &tx);
+        let mut scan = CountingScan::new(TableScan::new(tx.clone(), layout, "student"));
+
+        // Two full passes over the 9 students, with half of a pass between them
+        let records = assert_rescannable(&mut scan, &["sid"]);
+        assert_eq!(records.len(), 9);
+        assert_eq!(
+            scan.calls(),
+            ScanCalls {
+                before_first: 3,
+                next: 10 + 4 + 10,
+                gets: 9 + 9,
+            }
+        );
+
+        // The table's only block is read once, but pinned on opening the scan and again by each
+        // call to before_first
+        assert_eq!(counter.blocks_read(), 1);
+        assert_eq!(counter.blocks_pinned(), 4);
+    }
+}
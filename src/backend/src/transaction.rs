@@ -1,35 +1,160 @@
 use std::{
-    fmt::{Debug, Write},
+    collections::BTreeSet,
+    fmt::{Debug, Display, Write},
     sync::{
         atomic::{AtomicI64, AtomicU64},
-        Arc, Mutex,
+        Arc, Mutex, MutexGuard, PoisonError,
     },
+    thread::{self, JoinHandle},
 };
 
 use tracing::{
     span,
     span::{Entered, EnteredSpan},
-    trace, Level, Span,
+    trace, warn, Level, Span,
 };
 
 use crate::{
+    admission::AdmissionGate,
     block_id::BlockId,
     buffer::Buffer,
     buffer_list::BufferList,
-    buffer_manager::BufferManager,
-    concurrency_manager::ConcurrencyManager,
+    buffer_manager::{BufferError, BufferManager},
+    concurrency_manager::{ConcurrencyManager, LockGranularity},
     eviction_policy::SimpleEvictionPolicy,
     file_manager::FileManager,
-    lock_table::LockTable,
-    log_manager::LogManager,
+    layout::Layout,
+    lock_table::{LockAbortError, LockTable},
+    log_manager::{LogError, LogManager},
     log_record::LogRecord,
-    page::{Page, PAGE_SIZE},
+    page::{Page, PageError, PAGE_SIZE},
+    parser::constant::Value,
 };
 
 static NEXT_TRANSACTION_NUM: AtomicI64 = AtomicI64::new(0);
-static END_OF_FILE: u64 = std::u64::MAX;
 
-// TODO: commit/rollback on drop
+// The transactions that have neither committed nor finished rolling back
+static ACTIVE_TRANSACTIONS: Mutex<BTreeSet<i64>> = Mutex::new(BTreeSet::new());
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum TxError {
+    /// A change could not be written to the log.
+    Log(LogError),
+    /// A block could not be pinned into a buffer.
+    Buffer(BufferError),
+    /// A value does not fit in the block where it was to be written.
+    Page(PageError),
+    /// A lock could not be acquired in time, likely because of a deadlock. The transaction should
+    /// be rolled back and retried.
+    LockAbort(LockAbortError),
+    /// The transaction has already committed or rolled back, and can no longer be used.
+    NotActive(TxState),
+    /// A thread panicked while holding the named lock, so the state it guards may be
+    /// inconsistent.
+    Poisoned(String),
+}
+
+impl Display for TxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Log(e) => write!(f, "failed to log change: {}", e),
+            Self::Buffer(e) => write!(f, "failed to pin block: {}", e),
+            Self::Page(e) => write!(f, "failed to write value: {}", e),
+            Self::LockAbort(e) => write!(f, "failed to lock block: {}", e),
+            Self::NotActive(state) => write!(f, "transaction not active: {}", state),
+            Self::Poisoned(name) => write!(f, "lock on the {} is poisoned", name),
+        }
+    }
+}
+
+impl From<LogError> for TxError {
+    fn from(e: LogError) -> Self {
+        Self::Log(e)
+    }
+}
+
+impl From<BufferError> for TxError {
+    fn from(e: BufferError) -> Self {
+        Self::Buffer(e)
+    }
+}
+
+impl From<PageError> for TxError {
+    fn from(e: PageError) -> Self {
+        Self::Page(e)
+    }
+}
+
+impl From<LockAbortError> for TxError {
+    fn from(e: LockAbortError) -> Self {
+        Self::LockAbort(e)
+    }
+}
+
+// Lock state shared with other transactions, failing if a thread panicked while holding the lock.
+fn lock<'a, T>(mutex: &'a Mutex<T>, name: &str) -> Result<MutexGuard<'a, T>, TxError> {
+    mutex
+        .lock()
+        .map_err(|_| TxError::Poisoned(name.to_string()))
+}
+
+fn encode(record: &LogRecord) -> Result<Vec<u8>, TxError> {
+    bincode::serialize(record).map_err(|e| LogError::Encode(e.to_string()).into())
+}
+
+/// The stage of a transaction's life. A transaction is active from when it starts until it
+/// commits or rolls back; after that its locks are released and its buffers unpinned, so it can
+/// no longer read or write.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum TxState {
+    Active,
+    Committed,
+    /// Rolled back, either explicitly or by an abort.
+    RolledBack,
+}
+
+impl Display for TxState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Active => write!(f, "active"),
+            Self::Committed => write!(f, "committed"),
+            Self::RolledBack => write!(f, "rolled back"),
+        }
+    }
+}
+
+/// Counters describing the work done by a transaction, for profiling statements.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TxProfile {
+    /// Pins of blocks, including repeated pins of the same block.
+    pub blocks_pinned: u64,
+    /// Shared locks acquired.
+    pub slocks: u64,
+    /// Exclusive locks acquired.
+    pub xlocks: u64,
+    /// Bytes of log records written.
+    pub log_bytes: u64,
+    /// Log records written.
+    pub log_records: u64,
+}
+
+/// A point in a transaction that it can be rolled back to, without undoing the changes it made
+/// before that point. Created by `Tx::savepoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavepointId {
+    tx_num: i64,
+    // The lsn of the last record appended to the log before the savepoint was taken
+    lsn: i64,
+}
+
+/// The transactions whose changes are visible to a transaction reading a snapshot: those that
+/// had finished when the snapshot was taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    // Every transaction numbered below the horizon had started when the snapshot was taken
+    horizon: i64,
+    active: BTreeSet<i64>,
+}
 
 pub struct Tx {
     concurrency_mgr: ConcurrencyManager,
@@ -37,7 +162,15 @@ pub struct Tx {
     log_mgr: Arc<Mutex<LogManager>>,
     file_mgr: Arc<FileManager>,
     tx_num: i64,
+    state: TxState,
     buffer_list: Arc<Mutex<BufferList>>,
+    blocks_pinned: u64,
+    log_bytes: u64,
+    log_records: u64,
+    temp_files: Vec<String>,
+    truncated_backups: Vec<String>,
+    snapshot: Option<Snapshot>,
+    admission: Option<Arc<AdmissionGate>>,
 }
 
 impl Tx {
@@ -48,15 +181,49 @@ impl Tx {
         lock_tbl: Arc<LockTable>,
     ) -> Self {
         // TODO: verify the atomic ordering
-        let tx_num = NEXT_TRANSACTION_NUM.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let tx_num = {
+            let mut active = ACTIVE_TRANSACTIONS.lock().unwrap();
+            let tx_num = NEXT_TRANSACTION_NUM.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            active.insert(tx_num);
+            tx_num
+        };
 
         Self {
             file_mgr,
             log_mgr,
             tx_num,
+            state: TxState::Active,
             buffer_mgr: buffer_mgr.clone(),
             buffer_list: Arc::new(Mutex::new(BufferList::new(buffer_mgr))),
             concurrency_mgr: ConcurrencyManager::new(lock_tbl),
+            blocks_pinned: 0,
+            log_bytes: 0,
+            log_records: 0,
+            temp_files: vec![],
+            truncated_backups: vec![],
+            snapshot: None,
+            admission: None,
+        }
+    }
+
+    /// Record that the transaction was admitted by the gate, which is told when the transaction
+    /// commits, rolls back, or is dropped.
+    pub fn with_admission(mut self, gate: Arc<AdmissionGate>) -> Self {
+        self.admission = Some(gate);
+        self
+    }
+
+    /// Set the unit that the transaction's locks are taken on. Under table-level locking, a single
+    /// lock is taken on each file the transaction reads or writes.
+    pub fn with_lock_granularity(mut self, granularity: LockGranularity) -> Self {
+        self.concurrency_mgr.set_granularity(granularity);
+        self
+    }
+
+    // Leave the admission gate, if the transaction was admitted by one.
+    fn leave_admission(&mut self) {
+        if let Some(gate) = self.admission.take() {
+            gate.leave();
         }
     }
 
@@ -64,25 +231,196 @@ impl Tx {
         self.tx_num
     }
 
-    pub fn commit(&mut self) {
-        self.buffer_mgr.lock().unwrap().flush_all(self.tx_num);
+    /// Get the stage of the transaction's life.
+    pub fn state(&self) -> TxState {
+        self.state
+    }
+
+    /// Return an error if the transaction has committed or rolled back.
+    pub fn ensure_active(&self) -> Result<(), TxError> {
+        match self.state {
+            TxState::Active => Ok(()),
+            state => Err(TxError::NotActive(state)),
+        }
+    }
+
+    // Panic if the transaction has committed or rolled back. Used by methods that cannot return
+    // an error, since a finished transaction no longer holds the locks and pins they rely on.
+    fn assert_active(&self) {
+        if let Err(e) = self.ensure_active() {
+            panic!("transaction {}: {}", self.tx_num, e);
+        }
+    }
+
+    /// Get the counters accumulated over the lifetime of this transaction.
+    pub fn profile(&self) -> TxProfile {
+        TxProfile {
+            blocks_pinned: self.blocks_pinned,
+            slocks: self.concurrency_mgr.slocks_acquired(),
+            xlocks: self.concurrency_mgr.xlocks_acquired(),
+            log_bytes: self.log_bytes,
+            log_records: self.log_records,
+        }
+    }
+
+    /// Commit the transaction: write its changes and a commit record to storage, then release its
+    /// locks and buffers. Returns an error if the changes or the commit record could not be
+    /// written, or a lock shared with other transactions was poisoned by a panicking thread. The
+    /// transaction has then not committed and is still active, so it can be rolled back.
+    pub fn commit(&mut self) -> Result<(), TxError> {
+        self.ensure_active()?;
+        lock(&self.buffer_mgr, "buffer manager")?.flush_all(self.tx_num)?;
         let log_record = LogRecord::Commit {
             tx_num: self.tx_num,
         };
 
-        // TODO: error handling
-        let encoded = bincode::serialize(&log_record).unwrap();
-        self.log_mgr.lock().unwrap().append(&encoded);
+        let encoded = encode(&log_record)?;
+        {
+            let mut log_mgr = lock(&self.log_mgr, "log manager")?;
+            let lsn = log_mgr.append(&encoded)?;
+            // Once a backup is deleted, the truncation can no longer be undone by recovery
+            if !self.truncated_backups.is_empty() {
+                log_mgr.flush(lsn)?;
+            }
+        }
+        self.log_bytes += encoded.len() as u64;
+        self.log_records += 1;
+
+        // The commit record is logged, so the transaction has committed and its resources are
+        // released regardless of errors. A backup that cannot be deleted is only wasted space.
+        self.state = TxState::Committed;
+        self.release();
+        for backup in std::mem::take(&mut self.truncated_backups) {
+            if let Err(e) = self.file_mgr.delete_file(&backup) {
+                warn!("failed to delete truncated file {}: {}", backup, e);
+            }
+        }
+        trace!("Transaction {} committed", self.tx_num);
+        Ok(())
+    }
+
+    /// Write the buffers modified by this transaction and the log to disk, without committing.
+    /// The transaction stays active and keeps its locks, but the changes made so far are on disk
+    /// along with the log records that recovery needs to redo or undo them. Returns an error if
+    /// either could not be written.
+    pub fn flush(&mut self) -> Result<(), TxError> {
+        self.ensure_active()?;
+        lock(&self.buffer_mgr, "buffer manager")?.flush_all(self.tx_num)?;
+        let mut log_mgr = lock(&self.log_mgr, "log manager")?;
+        let lsn = log_mgr.latest_lsn();
+        log_mgr.flush(lsn)?;
+        trace!("Transaction {} flushed", self.tx_num);
+        Ok(())
+    }
+
+    /// Rollback the transaction associated with this RecoveryManager. Returns an error if the
+    /// undone changes or the rollback record could not be written, or a lock shared with other
+    /// transactions was poisoned, in which case the transaction is still active.
+    pub fn rollback(&mut self) -> Result<(), TxError> {
+        self.undo_and_end(LogRecord::Rollback {
+            tx_num: self.tx_num,
+        })?;
+        trace!("Rolled back transaction with id {}", self.tx_num);
+        Ok(())
+    }
+
+    /// Roll back the transaction because of an error. Unlike `rollback`, the transaction ends with
+    /// an `Abort` log record holding the reason, so that the cause can be found in the log.
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - A description of the error that caused the abort.
+    pub fn abort(&mut self, reason: &str) -> Result<(), TxError> {
+        self.undo_and_end(LogRecord::Abort {
+            tx_num: self.tx_num,
+            reason: reason.to_string(),
+        })?;
+        trace!("Aborted transaction with id {}: {}", self.tx_num, reason);
+        Ok(())
+    }
 
+    /// Mark the current point in the transaction, so that the changes made after it can later be
+    /// undone by `rollback_to`.
+    pub fn savepoint(&self) -> SavepointId {
+        self.assert_active();
+        SavepointId {
+            tx_num: self.tx_num,
+            lsn: self.log_mgr.lock().unwrap().latest_lsn(),
+        }
+    }
+
+    /// Undo the changes this transaction made after a savepoint, keeping those it made before.
+    /// The transaction stays active, and its locks are kept. Returns an error if the changes could
+    /// not be undone or the rollback could not be logged, as `rollback` does. Panics if the
+    /// savepoint was taken by another transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `savepoint` - A savepoint taken earlier by this transaction.
+    pub fn rollback_to(&mut self, savepoint: SavepointId) -> Result<(), TxError> {
+        self.ensure_active()?;
+        assert_eq!(
+            savepoint.tx_num, self.tx_num,
+            "savepoint was taken by another transaction"
+        );
+        self.undo(Some(savepoint.lsn))?;
+
+        // If the transaction commits, recovery must not redo the changes that were undone
+        let records = lock(&self.log_mgr, "log manager")?.latest_lsn() - savepoint.lsn;
+        if records > 0 {
+            self.append_to_log_and_flush(&LogRecord::RollbackTo {
+                tx_num: self.tx_num,
+                records: records as u64,
+            })?;
+        }
+        trace!(
+            "Rolled back transaction {} to lsn {}",
+            self.tx_num,
+            savepoint.lsn
+        );
+        Ok(())
+    }
+
+    // Undo every change made by this transaction, then log the record that ends it and release
+    // its resources.
+    fn undo_and_end(&mut self, end_record: LogRecord) -> Result<(), TxError> {
+        self.ensure_active()?;
+        self.undo(None)?;
+
+        lock(&self.buffer_mgr, "buffer manager")?.flush_all(self.tx_num)?;
+        self.append_to_log_and_flush(&end_record)?;
+
+        self.state = TxState::RolledBack;
+        self.release();
+        Ok(())
+    }
+
+    // Release the locks, buffers and temporary files of a transaction that has ended. A poisoned
+    // lock is taken anyway, since the transaction is leaving the state it guards.
+    fn release(&mut self) {
         self.concurrency_mgr.release();
-        self.buffer_list.lock().unwrap().unpin_all();
-        trace!("Transaction {} committed", self.tx_num);
+        self.buffer_list
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .unpin_all();
+        self.delete_temp_files();
+        ACTIVE_TRANSACTIONS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.tx_num);
+        self.leave_admission();
     }
 
-    /// Rollback the transaction associated with this RecoveryManager
-    pub fn rollback(&mut self) {
-        let snapshot = self.log_mgr.lock().unwrap().snapshot();
-        for record in snapshot {
+    // Undo the changes made by this transaction, newest first, back to the record following the
+    // given lsn, or back to the start of the transaction.
+    fn undo(&mut self, until_lsn: Option<i64>) -> Result<(), TxError> {
+        let (snapshot, latest_lsn) = {
+            let mut log_mgr = lock(&self.log_mgr, "log manager")?;
+            (log_mgr.snapshot(), log_mgr.latest_lsn())
+        };
+        // Every append adds one record to the log, so the records after the lsn are the newest
+        let num_records = until_lsn.map_or(usize::MAX, |lsn| (latest_lsn - lsn) as usize);
+        for record in snapshot.take(num_records) {
             // TODO: error handling
             let decoded = bincode::deserialize::<LogRecord>(&record)
                 .expect("failed to deserialize log record");
@@ -92,7 +430,10 @@ impl Tx {
                     // Reached the start of the transaction, nothing left to do
                     break;
                 }
-                LogRecord::SetInt { tx_num, .. } | LogRecord::SetString { tx_num, .. }
+                LogRecord::SetInt { tx_num, .. }
+                | LogRecord::SetString { tx_num, .. }
+                | LogRecord::SetBytes { tx_num, .. }
+                | LogRecord::Truncate { tx_num, .. }
                     if tx_num == self.tx_num =>
                 {
                     decoded.undo(self)
@@ -100,71 +441,331 @@ impl Tx {
                 _ => continue,
             }
         }
+        Ok(())
+    }
 
-        self.buffer_mgr.lock().unwrap().flush_all(self.tx_num);
-        let log_record = LogRecord::Rollback {
-            tx_num: self.tx_num,
-        };
-        self.append_to_log_and_flush(&log_record);
+    /// Read from a snapshot of the database taken now. Versioned records are visible only if they
+    /// were created, and not deleted, by transactions that had committed when the snapshot was
+    /// taken, or by this transaction. No shared locks are taken, so reads never wait for writers;
+    /// the transaction is meant to be read-only.
+    ///
+    /// Updates are made in place, so only inserts and deletes of versioned records are isolated
+    /// from the snapshot.
+    pub fn read_snapshot(&mut self) {
+        self.assert_active();
+        let active = ACTIVE_TRANSACTIONS.lock().unwrap();
+        self.snapshot = Some(Snapshot {
+            horizon: NEXT_TRANSACTION_NUM.load(std::sync::atomic::Ordering::SeqCst),
+            active: active.clone(),
+        });
+    }
 
-        self.concurrency_mgr.release();
-        self.buffer_list.lock().unwrap().unpin_all();
-        trace!("Rolled back transaction with id {}", self.tx_num);
+    /// Whether the changes made by a transaction are visible to this one. Without a snapshot, the
+    /// changes of every transaction that has committed are visible.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_num` - The number of the transaction that made the changes.
+    pub fn is_visible(&self, tx_num: i64) -> bool {
+        if tx_num == self.tx_num {
+            return true;
+        }
+
+        match &self.snapshot {
+            Some(s) => tx_num < s.horizon && !s.active.contains(&tx_num),
+            None => !ACTIVE_TRANSACTIONS.lock().unwrap().contains(&tx_num),
+        }
+    }
+
+    // Take a shared lock on a block, unless reading from a snapshot.
+    fn slock(&mut self, blk: &BlockId) -> Result<(), TxError> {
+        if self.snapshot.is_none() {
+            self.concurrency_mgr.slock(blk)?;
+        }
+        Ok(())
     }
 
     /// Pin the specified block
-    #[tracing::instrument(name = "tx-pin", skip(self, blk), fields(txid = self.tx_num(), blk = %blk))]
     pub fn pin(&mut self, blk: &BlockId) {
-        trace!("Pinning block {}", blk);
         // TODO: error handling
-        self.buffer_list.lock().unwrap().pin(blk);
+        self.try_pin(blk).expect("failed to pin block");
+    }
+
+    /// Pin the specified block, waiting for a buffer if every buffer is pinned by other
+    /// transactions. Returns an error if no buffer becomes available to hold it in time.
+    #[tracing::instrument(name = "tx-pin", skip(self, blk), fields(txid = self.tx_num(), blk = %blk))]
+    pub fn try_pin(&mut self, blk: &BlockId) -> Result<(), TxError> {
+        self.ensure_active()?;
+        trace!("Pinning block {}", blk);
+        self.buffer_list.lock().unwrap().pin(blk)?;
+        self.blocks_pinned += 1;
+        Ok(())
     }
 
-    /// Unpin the specified block
+    /// Unpin the specified block. Does nothing once the transaction has committed or rolled back,
+    /// since every block it pinned was unpinned then; a scan may still be closed afterwards.
     #[tracing::instrument(name = "tx-unpin", skip(self, blk), fields(txid = self.tx_num(), blk = %blk))]
     pub fn unpin(&mut self, blk: &BlockId) {
+        if self.state != TxState::Active {
+            return;
+        }
         trace!("Unpinning block {}", blk);
         // TODO: error handling
         self.buffer_list.lock().unwrap().unpin(blk);
     }
 
+    /// Whether the specified block is pinned by this transaction.
+    pub fn is_pinned(&self, blk: &BlockId) -> bool {
+        self.buffer_list.lock().unwrap().is_pinned(blk)
+    }
+
+    /// Read blocks into the buffer pool on a background thread, ahead of them being pinned. No
+    /// locks are taken on the blocks; they are only pinned (and locked) when accessed.
+    ///
+    /// # Arguments
+    ///
+    /// * `blks` - The blocks to read, in the order they are expected to be accessed.
+    pub fn prefetch(&self, blks: Vec<BlockId>) -> JoinHandle<()> {
+        let buffer_mgr = self.buffer_mgr.clone();
+        thread::spawn(move || {
+            buffer_mgr.lock().unwrap().prefetch(&blks);
+        })
+    }
+
     pub fn block_size(&self) -> usize {
         self.file_mgr.page_size()
     }
 
-    fn recover(&mut self) {
-        self.buffer_mgr.lock().unwrap().flush_all(self.tx_num);
+    /// Allocate a temporary file owned by this transaction. Changes to temporary files are not
+    /// logged, and the file is deleted when it is passed to `delete_temp_file` or, at the latest,
+    /// when the transaction commits or rolls back.
+    pub fn create_temp_file(&mut self) -> String {
+        self.assert_active();
+        let file_id = self.file_mgr.new_temp_file_id();
+        self.temp_files.push(file_id.clone());
+        file_id
+    }
+
+    /// Delete a temporary file created by this transaction, unpinning any of its blocks. Does
+    /// nothing if the file was already deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the temporary file.
+    pub fn delete_temp_file(&mut self, file_id: &str) {
+        let Some(pos) = self.temp_files.iter().position(|f| f == file_id) else {
+            return;
+        };
+        self.temp_files.swap_remove(pos);
+
+        self.buffer_list.lock().unwrap().unpin_file(file_id);
+        self.buffer_mgr.lock().unwrap().discard_file(file_id);
+        // TODO: error handling
+        self.file_mgr
+            .delete_file(file_id)
+            .expect("failed to delete temp file");
+    }
+
+    fn delete_temp_files(&mut self) {
+        for file_id in self.temp_files.clone() {
+            self.delete_temp_file(&file_id);
+        }
+    }
+
+    /// Remove every block of a file. This is much cheaper than deleting each record, since the
+    /// file is moved aside rather than logged block by block; it is moved back if the transaction
+    /// rolls back, and deleted once it commits. Every block of the file is locked exclusively,
+    /// and none of them may be pinned by another transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the file to truncate.
+    ///
+    /// Returns an error, leaving the file in place, if its blocks could not be locked in time.
+    pub fn truncate(&mut self, file_id: &str) -> Result<(), TxError> {
+        self.ensure_active()?;
+        self.move_aside(file_id)?;
+        // TODO: error handling
+        self.file_mgr
+            .truncate(file_id)
+            .expect("failed to truncate file");
+        Ok(())
+    }
+
+    /// Remove a file entirely, e.g. when its table is dropped. Like `truncate`, the file is moved
+    /// aside, to be moved back if the transaction rolls back and deleted once it commits. Until
+    /// then the file has no blocks, and is created again if a block is appended to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the file to remove.
+    ///
+    /// Returns an error, leaving the file in place, if its blocks could not be locked in time.
+    pub fn drop_file(&mut self, file_id: &str) -> Result<(), TxError> {
+        self.ensure_active()?;
+        self.move_aside(file_id)
+    }
+
+    // Lock every block of a file exclusively, then move the file to a backup that recovery can
+    // restore, logging the move. The file no longer exists once this returns, unless a lock could
+    // not be acquired in time.
+    fn move_aside(&mut self, file_id: &str) -> Result<(), TxError> {
+        let size = self.size(file_id)?;
+        for num in 0..size {
+            self.concurrency_mgr.xlock(&BlockId::new(file_id, num))?;
+        }
+        self.concurrency_mgr.xlock_end_of_file(file_id)?;
+
+        // The moved file must hold every change made to it so far, which may be undone later
+        self.buffer_list.lock().unwrap().unpin_file(file_id);
+        {
+            let mut buffer_mgr = self.buffer_mgr.lock().unwrap();
+            buffer_mgr.flush_file(file_id);
+            buffer_mgr.discard_file(file_id);
+        }
+
+        let backup = format!(
+            "{}.truncated{}-{}",
+            file_id,
+            self.tx_num,
+            self.truncated_backups.len()
+        );
+        // TODO: error handling
+        self.append_to_log_and_flush(&LogRecord::Truncate {
+            tx_num: self.tx_num,
+            file_id: file_id.to_string(),
+            backup: backup.clone(),
+        })
+        .expect("failed to log truncation");
+        // TODO: error handling
+        self.file_mgr
+            .rename_file(file_id, &backup)
+            .expect("failed to move truncated file");
+        self.truncated_backups.push(backup);
+        Ok(())
+    }
+
+    /// Undo the truncation of a file by moving its backup back in place. Does nothing if the
+    /// backup does not exist, e.g. because of a crash before the file was moved aside.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_id` - The id of the truncated file.
+    /// * `backup` - The id that the file was moved to.
+    pub fn restore_truncated(&mut self, file_id: &str, backup: &str) {
+        self.buffer_list.lock().unwrap().unpin_file(file_id);
+        self.buffer_mgr.lock().unwrap().discard_file(file_id);
+        // TODO: error handling
+        self.file_mgr
+            .rename_file(backup, file_id)
+            .expect("failed to restore truncated file");
+        self.truncated_backups.retain(|b| b != backup);
+    }
 
+    /// Get a boolean indicating whether this transaction holds any lock on the specified block.
+    #[cfg(test)]
+    pub fn is_locked(&self, blk: &BlockId) -> bool {
+        self.concurrency_mgr.is_locked(blk)
+    }
+
+    /// Recover the database from the log after a shutdown, then write a checkpoint. Must run
+    /// before any other transaction starts.
+    ///
+    /// The log is read back to the last checkpoint in two passes. The first, reading backwards,
+    /// undoes the changes of every transaction that neither committed nor rolled back. The
+    /// second, reading forwards, redoes the changes of every committed transaction, except those
+    /// undone by rolling back to a savepoint and those to files that were later truncated or
+    /// dropped.
+    pub fn recover(&mut self) {
+        self.assert_active();
+        // TODO: error handling
+        self.buffer_mgr
+            .lock()
+            .unwrap()
+            .flush_all(self.tx_num)
+            .expect("failed to write buffers");
+
+        let mut committed_txs: Vec<i64> = vec![];
         let mut completed_txs: Vec<i64> = vec![];
+        // The log position of the oldest record undone by each rollback to a savepoint
+        let mut undone_until: Vec<(i64, usize)> = vec![];
+        let mut truncated_files: Vec<String> = vec![];
+        let mut redo_records = vec![];
         let log_snapshot = self.log_mgr.lock().unwrap().snapshot();
-        for record in log_snapshot.map(|b| {
-            bincode::deserialize::<LogRecord>(&b).expect("Failed to deserialize log record")
-        }) {
+        for (pos, record) in log_snapshot
+            .map(|b| {
+                bincode::deserialize::<LogRecord>(&b).expect("Failed to deserialize log record")
+            })
+            .enumerate()
+        {
             match record {
                 LogRecord::Checkpoint => break,
-                LogRecord::Commit { tx_num } | LogRecord::Rollback { tx_num } => {
+                LogRecord::Commit { tx_num } => {
+                    committed_txs.push(tx_num);
+                    completed_txs.push(tx_num);
+                }
+                LogRecord::Rollback { tx_num } | LogRecord::Abort { tx_num, .. } => {
                     completed_txs.push(tx_num)
                 }
-                LogRecord::SetInt { tx_num, .. } | LogRecord::SetString { tx_num, .. }
+                LogRecord::RollbackTo { tx_num, records } => {
+                    undone_until.push((tx_num, pos + records as usize))
+                }
+                LogRecord::SetInt { tx_num, .. }
+                | LogRecord::SetString { tx_num, .. }
+                | LogRecord::SetBytes { tx_num, .. }
+                | LogRecord::Truncate { tx_num, .. }
                     if !completed_txs.contains(&tx_num) =>
                 {
                     record.undo(self)
                 }
+                LogRecord::Truncate {
+                    tx_num,
+                    ref file_id,
+                    ..
+                } if committed_txs.contains(&tx_num) => truncated_files.push(file_id.clone()),
+                LogRecord::SetInt {
+                    tx_num, ref block, ..
+                }
+                | LogRecord::SetString {
+                    tx_num, ref block, ..
+                }
+                | LogRecord::SetBytes {
+                    tx_num, ref block, ..
+                } if committed_txs.contains(&tx_num)
+                    && !truncated_files.iter().any(|f| f == block.file_id())
+                    && !undone_until
+                        .iter()
+                        .any(|&(n, until)| n == tx_num && pos <= until) =>
+                {
+                    redo_records.push(record)
+                }
                 _ => continue,
             }
         }
-        // TODO: recovery
 
-        self.buffer_mgr.lock().unwrap().flush_all(self.tx_num);
-        self.append_to_log_and_flush(&LogRecord::Checkpoint);
+        for record in redo_records.iter().rev() {
+            record.redo(self);
+        }
+
+        self.buffer_mgr
+            .lock()
+            .unwrap()
+            .flush_all(self.tx_num)
+            .expect("failed to write buffers");
+        self.append_to_log_and_flush(&LogRecord::Checkpoint)
+            .expect("failed to log checkpoint");
     }
 
-    fn append_to_log_and_flush(&mut self, record: &LogRecord) {
-        // TODO: error handling
-        let encoded = bincode::serialize(record).unwrap();
-        let mut log_mgr_locked = self.log_mgr.lock().unwrap();
-        let lsn = log_mgr_locked.append(&encoded);
-        log_mgr_locked.flush(lsn);
+    fn append_to_log_and_flush(&mut self, record: &LogRecord) -> Result<(), TxError> {
+        let encoded = encode(record)?;
+        {
+            let mut log_mgr = lock(&self.log_mgr, "log manager")?;
+            let lsn = log_mgr.append(&encoded)?;
+            log_mgr.flush(lsn)?;
+        }
+        self.log_bytes += encoded.len() as u64;
+        self.log_records += 1;
+        Ok(())
     }
 
     /// Sets an integer in a block. The block will be locked exclusively for the remaining duration
@@ -176,19 +777,37 @@ impl Tx {
     /// * `offset` - The offset in the block's page to write the integer.
     /// * `val` - The integer value to write.
     /// * `ok_to_log` - A boolean indicating whether the change should be logged.
-    pub fn set_int(&mut self, blk: &BlockId, offset: usize, val: i32, ok_to_log: bool) {
-        self.concurrency_mgr.xlock(blk);
+    ///
+    /// A logged write of the value already stored at the offset is skipped, so it appends no log
+    /// record. The block is still locked exclusively.
+    ///
+    /// Returns an error, leaving the block unchanged, if the block could not be locked in time or
+    /// the change could not be logged.
+    pub fn set_int(
+        &mut self,
+        blk: &BlockId,
+        offset: usize,
+        val: i32,
+        ok_to_log: bool,
+    ) -> Result<(), TxError> {
+        self.ensure_active()?;
+        self.concurrency_mgr.xlock(blk)?;
         let buf = self.buffer_list.lock().unwrap().get_buffer(blk);
         let mut buf = buf.write().unwrap();
+        if ok_to_log && buf.page.read::<i32>(offset) == val {
+            return Ok(());
+        }
 
-        let lsn = if ok_to_log {
-            self.log_set_int(&mut buf, offset, val)
+        // Temporary files are deleted when the transaction ends, so they never need recovery
+        let lsn = if ok_to_log && !self.file_mgr.is_temp_file(blk.file_id()) {
+            self.log_set_int(&mut buf, offset, val)?
         } else {
             -1
         };
 
         buf.page.write(val, offset);
         buf.set_modified(self.tx_num, lsn);
+        Ok(())
     }
 
     /// Sets a string in a block. The block will be locked exclusively for the remaining duration
@@ -200,30 +819,95 @@ impl Tx {
     /// * `offset` - The offset in the block's page to write the integer.
     /// * `val` - The &str value to write.
     /// * `ok_to_log` - A boolean indicating whether the change should be logged.
-    pub fn set_string(&mut self, blk: &BlockId, offset: usize, val: &str, ok_to_log: bool) {
-        self.concurrency_mgr.xlock(blk);
+    ///
+    /// A logged write of the value already stored at the offset is skipped, so it appends no log
+    /// record. The block is still locked exclusively.
+    ///
+    /// Returns an error, leaving the block unchanged, if the string does not fit in the block at
+    /// the offset, the block could not be locked in time, or the change could not be logged.
+    pub fn set_string(
+        &mut self,
+        blk: &BlockId,
+        offset: usize,
+        val: &str,
+        ok_to_log: bool,
+    ) -> Result<(), TxError> {
+        self.ensure_active()?;
+        Page::check_fits(&val, offset)?;
+        self.concurrency_mgr.xlock(blk)?;
+
+        let buf = self.buffer_list.lock().unwrap().get_buffer(blk);
+        let mut buf = buf.write().unwrap();
+        if ok_to_log && buf.page.read::<String>(offset) == val {
+            return Ok(());
+        }
+
+        let lsn = if ok_to_log && !self.file_mgr.is_temp_file(blk.file_id()) {
+            self.log_set_string(&mut buf, offset, val)?
+        } else {
+            -1
+        };
+
+        buf.page.try_write(val, offset)?;
+        buf.set_modified(self.tx_num, lsn);
+        Ok(())
+    }
+
+    /// Sets a run of bytes in a block, e.g. a whole page image, with a single log record. The
+    /// block will be locked exclusively for the remaining duration of the Transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `blk` - The block where the bytes will be written.
+    /// * `offset` - The offset in the block's page to write the bytes.
+    /// * `val` - The bytes to write.
+    /// * `ok_to_log` - A boolean indicating whether the change should be logged.
+    ///
+    /// A logged write of the bytes already stored at the offset is skipped, so it appends no log
+    /// record. The block is still locked exclusively.
+    ///
+    /// Returns an error, leaving the block unchanged, if the bytes do not fit in the block at the
+    /// offset, the block could not be locked in time, or the change could not be logged.
+    pub fn set_bytes(
+        &mut self,
+        blk: &BlockId,
+        offset: usize,
+        val: &[u8],
+        ok_to_log: bool,
+    ) -> Result<(), TxError> {
+        self.ensure_active()?;
+        Page::check_fits(&val, offset)?;
+        self.concurrency_mgr.xlock(blk)?;
 
         let buf = self.buffer_list.lock().unwrap().get_buffer(blk);
         let mut buf = buf.write().unwrap();
+        if ok_to_log && buf.page.read_bytes(offset, val.len()) == val {
+            return Ok(());
+        }
 
-        let lsn = if ok_to_log {
-            self.log_set_string(&mut buf, offset, val)
+        let lsn = if ok_to_log && !self.file_mgr.is_temp_file(blk.file_id()) {
+            self.log_set_bytes(&mut buf, offset, val)?
         } else {
             -1
         };
 
-        buf.page.write(val, offset);
+        buf.page.try_write(val, offset)?;
         buf.set_modified(self.tx_num, lsn);
+        Ok(())
     }
 
     /// Get the number of blocks in a file. A shared lock will be acquired on the file.
-    pub fn size(&mut self, file_id: &str) -> u64 {
-        // Take a shared lock on the dummy block
-        self.concurrency_mgr
-            .slock(&BlockId::new(file_id, END_OF_FILE));
+    ///
+    /// Returns an error if the end of the file could not be locked in time.
+    pub fn size(&mut self, file_id: &str) -> Result<u64, TxError> {
+        self.ensure_active()?;
+        // Take a shared lock on the end of the file, unless reading from a snapshot
+        if self.snapshot.is_none() {
+            self.concurrency_mgr.slock_end_of_file(file_id)?;
+        }
 
         // TODO: error handling
-        self.file_mgr.length(file_id).unwrap()
+        Ok(self.file_mgr.length(file_id).unwrap())
     }
 
     /// Append a new block to a file.
@@ -231,15 +915,17 @@ impl Tx {
     /// # Arguments
     ///
     /// * `file_id` - The id of the file that will have a block appended.
-    pub fn append(&mut self, file_id: &str) -> BlockId {
-        trace!("xlocking the dummy block for file '{}'", file_id);
+    ///
+    /// Returns an error if the end of the file could not be locked in time.
+    pub fn append(&mut self, file_id: &str) -> Result<BlockId, TxError> {
+        self.ensure_active()?;
+        trace!("xlocking the end of file '{}'", file_id);
 
-        // Take an exclusive lock on the dummy block
-        self.concurrency_mgr
-            .xlock(&BlockId::new(file_id, END_OF_FILE));
+        // Take an exclusive lock on the end of the file
+        self.concurrency_mgr.xlock_end_of_file(file_id)?;
 
         // TODO: error handling
-        self.file_mgr.append_block(file_id, &Page::new()).unwrap()
+        Ok(self.file_mgr.append_block(file_id, &Page::new()).unwrap())
     }
 
     /// Get an integer from the specified block.
@@ -248,20 +934,29 @@ impl Tx {
     ///
     /// * `blk` - The `BlockId` where the integer will be read from.
     /// * `offset` - The offset in the block that the integer will be read from.
-    pub fn get_int(&mut self, blk: &BlockId, offset: usize) -> i32 {
-        self.concurrency_mgr.slock(blk);
+    ///
+    /// Returns an error if the block could not be locked in time.
+    pub fn get_int(&mut self, blk: &BlockId, offset: usize) -> Result<i32, TxError> {
+        self.ensure_active()?;
+        self.slock(blk)?;
         let buff = self.buffer_list.lock().unwrap().get_buffer(blk);
         let val = buff.read().unwrap().page.read(offset);
-        val
+        Ok(val)
     }
 
-    pub fn action_on_raw_page(&mut self, blk: &BlockId, action: fn(&Page) -> ()) {
-        self.concurrency_mgr.slock(blk);
+    pub fn action_on_raw_page(
+        &mut self,
+        blk: &BlockId,
+        action: fn(&Page) -> (),
+    ) -> Result<(), TxError> {
+        self.ensure_active()?;
+        self.slock(blk)?;
         let buff = self.buffer_list.lock().unwrap().get_buffer(blk);
         {
             let buff = buff.read().unwrap();
             action(&buff.page);
         }
+        Ok(())
     }
 
     /// Get a string from the specified block.
@@ -270,13 +965,111 @@ impl Tx {
     ///
     /// * `blk` - The `BlockId` where the string will be read from.
     /// * `offset` - The offset in the block that the string will be read from.
-    pub fn get_string(&mut self, blk: &BlockId, offset: usize) -> String {
-        self.concurrency_mgr.slock(blk);
+    ///
+    /// Returns an error if the block could not be locked in time.
+    pub fn get_string(&mut self, blk: &BlockId, offset: usize) -> Result<String, TxError> {
+        self.ensure_active()?;
+        self.slock(blk)?;
         let buff = self.buffer_list.lock().unwrap().get_buffer(blk);
         let val = buff.read().unwrap().page.read(offset);
+        Ok(val)
+    }
+
+    /// Get an integer from the specified block without taking a shared lock on it. This is a
+    /// READ UNCOMMITTED read: it never waits for other transactions, but may see changes that
+    /// they have not committed and may yet roll back. It is only suited to readers that can
+    /// tolerate approximate values, such as progress counters. The block must be pinned.
+    ///
+    /// # Arguments
+    ///
+    /// * `blk` - The `BlockId` where the integer will be read from.
+    /// * `offset` - The offset in the block that the integer will be read from.
+    pub fn get_int_dirty(&self, blk: &BlockId, offset: usize) -> i32 {
+        self.assert_active();
+        let buff = self.buffer_list.lock().unwrap().get_buffer(blk);
+        let buff = buff.read().unwrap();
+        buff.page.read(offset)
+    }
+
+    /// Get a string from the specified block without taking a shared lock on it. Like
+    /// `get_int_dirty`, this may see uncommitted changes of other transactions. The block must be
+    /// pinned.
+    ///
+    /// # Arguments
+    ///
+    /// * `blk` - The `BlockId` where the string will be read from.
+    /// * `offset` - The offset in the block that the string will be read from.
+    pub fn get_string_dirty(&self, blk: &BlockId, offset: usize) -> String {
+        self.assert_active();
+        let buff = self.buffer_list.lock().unwrap().get_buffer(blk);
+        let buff = buff.read().unwrap();
+        buff.page.read(offset)
+    }
+
+    /// Get the value of a field in a record slot. The block is pinned for the duration of the
+    /// read, so callers do not need to pin it beforehand.
+    ///
+    /// # Arguments
+    ///
+    /// * `blk` - The `BlockId` containing the record.
+    /// * `layout` - The `Layout` of the records stored in the block.
+    /// * `slot` - The slot of the record within the block.
+    /// * `field_name` - The name of the field to read.
+    ///
+    /// Returns an error if the block could not be locked in time.
+    pub fn get_field(
+        &mut self,
+        blk: &BlockId,
+        layout: &Layout,
+        slot: i16,
+        field_name: &str,
+    ) -> Result<Value, TxError> {
+        let offset = layout
+            .field_offset(slot, field_name)
+            .unwrap_or_else(|| panic!("field '{}' does not exist in the layout", field_name));
+        self.pin(blk);
+        let val = match layout.schema().get_field_type(field_name) {
+            Some(0) => self.get_int(blk, offset).map(Value::Int),
+            Some(1) => self.get_string(blk, offset).map(Value::Varchar),
+            _ => panic!("unsupported type for field '{}'", field_name),
+        };
+        self.unpin(blk);
         val
     }
 
+    /// Set the value of a field in a record slot. The change is logged and the block will be
+    /// locked exclusively for the remaining duration of the Transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `blk` - The `BlockId` containing the record.
+    /// * `layout` - The `Layout` of the records stored in the block.
+    /// * `slot` - The slot of the record within the block.
+    /// * `field_name` - The name of the field to write.
+    /// * `val` - The value to write; its type must match the type of the field.
+    pub fn set_field(
+        &mut self,
+        blk: &BlockId,
+        layout: &Layout,
+        slot: i16,
+        field_name: &str,
+        val: &Value,
+    ) -> Result<(), TxError> {
+        self.ensure_active()?;
+        let offset = layout
+            .field_offset(slot, field_name)
+            .unwrap_or_else(|| panic!("field '{}' does not exist in the layout", field_name));
+        self.pin(blk);
+        // TODO: error handling
+        let res = match (layout.schema().get_field_type(field_name), val) {
+            (Some(0), Value::Int(i)) => self.set_int(blk, offset, *i, true),
+            (Some(1), Value::Varchar(s)) => self.set_string(blk, offset, s, true),
+            _ => panic!("value {} cannot be written to field '{}'", val, field_name),
+        };
+        self.unpin(blk);
+        res
+    }
+
     /// Logs (for recovery) the setting of an integer value in a buffer.
     ///
     /// # Arguments
@@ -284,8 +1077,12 @@ impl Tx {
     /// * `buf` - The buffer where the integer is being written.
     /// * `offset` - The offset in the buffer's page.
     /// * `new_val` - The new value to be written.
-    // TODO: why is `new_val` provided to this method?
-    fn log_set_int(&mut self, buf: &mut Buffer, offset: usize, new_val: i32) -> i64 {
+    fn log_set_int(
+        &mut self,
+        buf: &mut Buffer,
+        offset: usize,
+        new_val: i32,
+    ) -> Result<i64, TxError> {
         let old_val: i32 = buf.page.read(offset);
 
         // TODO: error handling
@@ -297,10 +1094,14 @@ impl Tx {
                 .expect("buffer does not have a block loaded")
                 .clone(),
             offset: offset as u16,
-            val: old_val,
+            old_val,
+            new_val,
         };
-        let encoded = bincode::serialize(&log_record).unwrap();
-        self.log_mgr.lock().unwrap().append(&encoded)
+        let encoded = encode(&log_record)?;
+        let lsn = lock(&self.log_mgr, "log manager")?.append(&encoded)?;
+        self.log_bytes += encoded.len() as u64;
+        self.log_records += 1;
+        Ok(lsn)
     }
 
     /// Logs (for recovery) the setting of a string in a buffer.
@@ -310,8 +1111,12 @@ impl Tx {
     /// * `buf` - The buffer where the string is being written.
     /// * `offset` - The offset in the buffer's page.
     /// * `new_val` - The new value being written.
-    // TODO: why is `new_val` provided to this method?
-    fn log_set_string(&mut self, buf: &mut Buffer, offset: usize, new_val: &str) -> i64 {
+    fn log_set_string(
+        &mut self,
+        buf: &mut Buffer,
+        offset: usize,
+        new_val: &str,
+    ) -> Result<i64, TxError> {
         let old_val: String = buf.page.read(offset);
 
         // TODO: error handling
@@ -323,11 +1128,67 @@ impl Tx {
                 .expect("buffer does not have a block loaded")
                 .clone(),
             offset: offset as u16,
-            // TODO: best way to handle the string stuff?
-            val: old_val,
+            old_val,
+            new_val: new_val.to_string(),
         };
-        let encoded = bincode::serialize(&log_record).unwrap();
-        self.log_mgr.lock().unwrap().append(&encoded)
+        let encoded = encode(&log_record)?;
+        let lsn = lock(&self.log_mgr, "log manager")?.append(&encoded)?;
+        self.log_bytes += encoded.len() as u64;
+        self.log_records += 1;
+        Ok(lsn)
+    }
+
+    /// Logs (for recovery) the setting of a run of bytes in a buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer where the bytes are being written.
+    /// * `offset` - The offset in the buffer's page.
+    /// * `new_val` - The bytes being written.
+    fn log_set_bytes(
+        &mut self,
+        buf: &mut Buffer,
+        offset: usize,
+        new_val: &[u8],
+    ) -> Result<i64, TxError> {
+        let old_val = buf.page.read_bytes(offset, new_val.len()).to_vec();
+
+        // TODO: error handling
+        let log_record = LogRecord::SetBytes {
+            tx_num: self.tx_num,
+            block: buf
+                .blk
+                .as_ref()
+                .expect("buffer does not have a block loaded")
+                .clone(),
+            offset: offset as u16,
+            old_val,
+            new_val: new_val.to_vec(),
+        };
+        let encoded = encode(&log_record)?;
+        let lsn = lock(&self.log_mgr, "log manager")?.append(&encoded)?;
+        self.log_bytes += encoded.len() as u64;
+        self.log_records += 1;
+        Ok(lsn)
+    }
+}
+
+impl Drop for Tx {
+    /// A transaction dropped without committing or rolling back is rolled back, undoing its
+    /// changes and releasing its locks and buffers. While the thread is panicking it is left for
+    /// recovery to undo instead, since rolling back could panic again; it still gives up its place
+    /// in the admission gate.
+    fn drop(&mut self) {
+        if self.state == TxState::Active
+            && !thread::panicking()
+            && let Err(e) = self.rollback()
+        {
+            warn!(
+                "failed to roll back dropped transaction {}: {}",
+                self.tx_num, e
+            );
+        }
+        self.leave_admission();
     }
 }
 
@@ -339,11 +1200,29 @@ impl Debug for Tx {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, sync::mpsc, thread};
+    use std::{
+        fs,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc,
+        },
+        thread,
+        time::Duration,
+    };
 
     use tempfile::tempdir;
 
-    use crate::{buffer_manager, eviction_policy::SimpleEvictionPolicy, page::Page};
+    use crate::{
+        buffer_manager::{self, BufferError},
+        eviction_policy::SimpleEvictionPolicy,
+        lock_table::{LockKey, LockTable},
+        make_schema,
+        metadata::metadata_manager::MetadataManager,
+        page::Page,
+        scan::scan::{Scannable, UpdateScannable},
+        table_scan::TableScan,
+        tests::test_utils::{create_default_tables, default_test_db, test_db},
+    };
 
     use super::*;
 
@@ -370,65 +1249,66 @@ mod tests {
         // Verify that committed sets are read from a separate transaction
         let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
         tx.pin(&blk);
-        tx.set_int(&blk, 0, 10, true);
-        tx.set_string(&blk, 100, "test string", true);
-        assert_eq!(tx.get_int(&blk, 0), 10);
-        assert_eq!(tx.get_string(&blk, 100), "test string");
-        tx.commit();
+        tx.set_int(&blk, 0, 10, true).unwrap();
+        tx.set_string(&blk, 100, "test string", true).unwrap();
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 10);
+        assert_eq!(tx.get_string(&blk, 100).unwrap(), "test string");
+        tx.commit().unwrap();
         let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
         tx.pin(&blk);
-        let int_val: i32 = tx.get_int(&blk, 0);
-        let str_val: String = tx.get_string(&blk, 100);
+        let int_val: i32 = tx.get_int(&blk, 0).unwrap();
+        let str_val: String = tx.get_string(&blk, 100).unwrap();
         assert_eq!(int_val, 10);
         assert_eq!(str_val, "test string");
-        tx.commit();
+        tx.commit().unwrap();
 
         // Verify that sets are read in the same transaction
         let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
         tx.pin(&blk);
-        tx.set_int(&blk, 0, 20, true);
-        tx.set_string(&blk, 100, "another test string", true);
-        let int_val: i32 = tx.get_int(&blk, 0);
-        let str_val: String = tx.get_string(&blk, 100);
+        tx.set_int(&blk, 0, 20, true).unwrap();
+        tx.set_string(&blk, 100, "another test string", true)
+            .unwrap();
+        let int_val: i32 = tx.get_int(&blk, 0).unwrap();
+        let str_val: String = tx.get_string(&blk, 100).unwrap();
         assert_eq!(int_val, 20);
         assert_eq!(str_val, "another test string");
-        tx.rollback();
+        tx.rollback().unwrap();
 
         // Verify that the above data is not read after rollback
         let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
         tx.pin(&blk);
-        let int_val: i32 = tx.get_int(&blk, 0);
-        let str_val: String = tx.get_string(&blk, 100);
+        let int_val: i32 = tx.get_int(&blk, 0).unwrap();
+        let str_val: String = tx.get_string(&blk, 100).unwrap();
         assert_eq!(int_val, 10);
         assert_eq!(str_val, "test string");
-        tx.commit();
+        tx.commit().unwrap();
 
         // Verify that multiple integers and string get rolled back
         let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
         tx.pin(&blk);
-        tx.set_int(&blk, 20, 3, true);
-        tx.set_int(&blk, 40, 6, true);
-        tx.set_int(&blk, 60, 9, true);
-        tx.set_string(&blk, 200, "test1", true);
-        tx.set_string(&blk, 300, "test2", true);
-        tx.set_string(&blk, 400, "test3", true);
-        assert_eq!(tx.get_int(&blk, 20), 3);
-        assert_eq!(tx.get_int(&blk, 40), 6);
-        assert_eq!(tx.get_int(&blk, 60), 9);
-        assert_eq!(tx.get_string(&blk, 200), "test1");
-        assert_eq!(tx.get_string(&blk, 300), "test2");
-        assert_eq!(tx.get_string(&blk, 400), "test3");
-        tx.rollback();
+        tx.set_int(&blk, 20, 3, true).unwrap();
+        tx.set_int(&blk, 40, 6, true).unwrap();
+        tx.set_int(&blk, 60, 9, true).unwrap();
+        tx.set_string(&blk, 200, "test1", true).unwrap();
+        tx.set_string(&blk, 300, "test2", true).unwrap();
+        tx.set_string(&blk, 400, "test3", true).unwrap();
+        assert_eq!(tx.get_int(&blk, 20).unwrap(), 3);
+        assert_eq!(tx.get_int(&blk, 40).unwrap(), 6);
+        assert_eq!(tx.get_int(&blk, 60).unwrap(), 9);
+        assert_eq!(tx.get_string(&blk, 200).unwrap(), "test1");
+        assert_eq!(tx.get_string(&blk, 300).unwrap(), "test2");
+        assert_eq!(tx.get_string(&blk, 400).unwrap(), "test3");
+        tx.rollback().unwrap();
 
         let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
         tx.pin(&blk);
-        assert_eq!(tx.get_int(&blk, 20), 0);
-        assert_eq!(tx.get_int(&blk, 40), 0);
-        assert_eq!(tx.get_int(&blk, 60), 0);
-        assert_eq!(tx.get_string(&blk, 200), "");
-        assert_eq!(tx.get_string(&blk, 300), "");
-        assert_eq!(tx.get_string(&blk, 400), "");
-        tx.rollback();
+        assert_eq!(tx.get_int(&blk, 20).unwrap(), 0);
+        assert_eq!(tx.get_int(&blk, 40).unwrap(), 0);
+        assert_eq!(tx.get_int(&blk, 60).unwrap(), 0);
+        assert_eq!(tx.get_string(&blk, 200).unwrap(), "");
+        assert_eq!(tx.get_string(&blk, 300).unwrap(), "");
+        assert_eq!(tx.get_string(&blk, 400).unwrap(), "");
+        tx.rollback().unwrap();
     }
 
     #[test]
@@ -467,7 +1347,7 @@ mod tests {
                 tx_a.pin(&blk1);
                 tx_a.pin(&blk2);
 
-                let val = tx_a.get_int(&blk1, 0);
+                let val = tx_a.get_int(&blk1, 0).unwrap();
                 assert_eq!(val, 0);
 
                 send_c.send(true).unwrap();
@@ -477,10 +1357,10 @@ mod tests {
 
                 // The slock required here should not be granted until B commits and releases its
                 // xlock on blk2
-                let val = tx_a.get_int(&blk2, 0);
+                let val = tx_a.get_int(&blk2, 0).unwrap();
                 assert_eq!(val, 2);
 
-                tx_a.commit();
+                tx_a.commit().unwrap();
             }
         });
 
@@ -492,15 +1372,15 @@ mod tests {
                 tx_b.pin(&blk1);
                 tx_b.pin(&blk2);
 
-                tx_b.set_int(&blk2, 0, 2, false);
+                tx_b.set_int(&blk2, 0, 2, false).unwrap();
 
                 send_a.send(true).unwrap();
 
-                let val = tx_b.get_int(&blk1, 0);
+                let val = tx_b.get_int(&blk1, 0).unwrap();
                 assert_eq!(val, 0);
 
                 // The commit will release a shared lock on blk1 and the xlock on blk2
-                tx_b.commit();
+                tx_b.commit().unwrap();
             }
         });
 
@@ -516,11 +1396,11 @@ mod tests {
                 recv_c.recv().unwrap();
 
                 // This should block until A commits and releases its slock on blk1
-                tx_c.set_int(&blk1, 0, 3, false);
+                tx_c.set_int(&blk1, 0, 3, false).unwrap();
 
-                let val = tx_c.get_int(&blk2, 0);
+                let val = tx_c.get_int(&blk2, 0).unwrap();
                 assert_eq!(val, 2);
-                tx_c.commit();
+                tx_c.commit().unwrap();
             }
         });
 
@@ -532,7 +1412,914 @@ mod tests {
         tx.pin(&blk1);
         tx.pin(&blk2);
 
-        assert_eq!(tx.get_int(&blk1, 0), 3);
-        assert_eq!(tx.get_int(&blk2, 0), 2);
+        assert_eq!(tx.get_int(&blk1, 0).unwrap(), 3);
+        assert_eq!(tx.get_int(&blk2, 0).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_deadlock_times_out() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let fm = db.file_manager();
+        let locks = Arc::new(LockTable::with_max_wait(Duration::from_millis(1000)));
+        let new_tx = || {
+            Tx::new(
+                fm.clone(),
+                db.log_manager(),
+                db.buffer_manager(),
+                locks.clone(),
+            )
+        };
+
+        let blk1 = fm.append_block("test", &Page::new()).unwrap();
+        let blk2 = fm.append_block("test", &Page::new()).unwrap();
+
+        let mut tx_a = new_tx();
+        let mut tx_b = new_tx();
+        let (send_a, recv_a) = mpsc::channel();
+        let (send_b, recv_b) = mpsc::channel();
+        let (send_result, recv_result) = mpsc::channel();
+
+        // A holds an xlock on blk1 and waits for blk2, while B holds an xlock on blk2 and waits
+        // for blk1. B starts waiting halfway through A's wait, so A times out first and B is
+        // still waiting when A rolls back.
+        thread::spawn({
+            let (blk1, blk2) = (blk1.clone(), blk2.clone());
+            let send_result = send_result.clone();
+            move || {
+                tx_a.pin(&blk1);
+                tx_a.pin(&blk2);
+                tx_a.set_int(&blk1, 0, 1, true).unwrap();
+                send_b.send(()).unwrap();
+                recv_a.recv().unwrap();
+
+                let res = tx_a.set_int(&blk2, 0, 1, true);
+                // Rolling back releases the xlock on blk1 that B is waiting for
+                tx_a.rollback().unwrap();
+                send_result.send(('a', res)).unwrap();
+            }
+        });
+        thread::spawn({
+            let (blk1, blk2) = (blk1.clone(), blk2.clone());
+            move || {
+                tx_b.pin(&blk1);
+                tx_b.pin(&blk2);
+                tx_b.set_int(&blk2, 0, 2, true).unwrap();
+                send_a.send(()).unwrap();
+                recv_b.recv().unwrap();
+                thread::sleep(Duration::from_millis(500));
+
+                let res = tx_b.set_int(&blk1, 0, 2, true);
+                tx_b.commit().unwrap();
+                send_result.send(('b', res)).unwrap();
+            }
+        });
+
+        let timeout = Duration::from_secs(10);
+        assert_eq!(
+            recv_result.recv_timeout(timeout),
+            Ok((
+                'a',
+                Err(TxError::LockAbort(LockAbortError(LockKey::Block(
+                    blk2.clone()
+                ))))
+            ))
+        );
+        assert_eq!(recv_result.recv_timeout(timeout), Ok(('b', Ok(()))));
+
+        let mut tx = new_tx();
+        tx.pin(&blk1);
+        tx.pin(&blk2);
+        assert_eq!(tx.get_int(&blk1, 0), Ok(2));
+        assert_eq!(tx.get_int(&blk2, 0), Ok(2));
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_lock_timeouts_are_returned() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let fm = db.file_manager();
+        let locks = Arc::new(LockTable::with_max_wait(Duration::from_millis(100)));
+        let new_tx = || {
+            Tx::new(
+                fm.clone(),
+                db.log_manager(),
+                db.buffer_manager(),
+                locks.clone(),
+            )
+        };
+
+        // A writer holds an xlock on a block and on the end of its file
+        let mut writer = new_tx();
+        let blk = writer.append("test").unwrap();
+        writer.pin(&blk);
+        writer.set_int(&blk, 0, 1, true).unwrap();
+
+        // A reader gives up waiting for either lock with an error, and can still roll back
+        let mut reader = new_tx();
+        reader.pin(&blk);
+        let block_lock = TxError::LockAbort(LockAbortError(LockKey::Block(blk.clone())));
+        assert_eq!(reader.get_int(&blk, 0), Err(block_lock.clone()));
+        assert_eq!(reader.get_string(&blk, 0), Err(block_lock));
+        let end_of_file = TxError::LockAbort(LockAbortError(LockKey::EndOfFile("test".into())));
+        assert_eq!(reader.size("test"), Err(end_of_file.clone()));
+        assert_eq!(reader.append("test"), Err(end_of_file));
+        reader.rollback().unwrap();
+
+        writer.commit().unwrap();
+        let mut reader = new_tx();
+        reader.pin(&blk);
+        assert_eq!(reader.get_int(&blk, 0), Ok(1));
+        assert_eq!(reader.size("test"), Ok(1));
+        reader.commit().unwrap();
+    }
+
+    #[test]
+    fn test_dirty_read() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            10,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        let locks = Arc::new(LockTable::new());
+
+        let blk = fm.append_block("test", &Page::new()).unwrap();
+
+        let mut writer = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
+        writer.pin(&blk);
+        writer.set_int(&blk, 0, 7, true).unwrap();
+        writer.set_string(&blk, 100, "uncommitted", true).unwrap();
+
+        // The dirty reads see the writer's changes without waiting for its xlock
+        let mut reader = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
+        reader.pin(&blk);
+        assert_eq!(reader.get_int_dirty(&blk, 0), 7);
+        assert_eq!(reader.get_string_dirty(&blk, 100), "uncommitted");
+
+        let read_done = AtomicBool::new(false);
+        thread::scope(|s| {
+            let handle = s.spawn(|| {
+                let val = reader.get_int(&blk, 0).unwrap();
+                read_done.store(true, Ordering::SeqCst);
+                val
+            });
+
+            // A locking read waits for the writer to finish, and never sees the rolled back value
+            thread::sleep(Duration::from_millis(100));
+            assert!(!read_done.load(Ordering::SeqCst));
+            writer.rollback().unwrap();
+            assert_eq!(handle.join().unwrap(), 0);
+        });
+
+        assert_eq!(reader.get_string_dirty(&blk, 100), "");
+        reader.commit().unwrap();
+    }
+
+    #[test]
+    fn test_set_unchanged_value_is_not_logged() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 10, true).unwrap();
+        tx.set_string(&blk, 100, "test string", true).unwrap();
+        let log_records = tx.profile().log_records;
+        assert_eq!(log_records, 2);
+
+        tx.set_int(&blk, 0, 10, true).unwrap();
+        tx.set_string(&blk, 100, "test string", true).unwrap();
+        assert_eq!(tx.profile().log_records, log_records);
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 10);
+        assert_eq!(tx.get_string(&blk, 100).unwrap(), "test string");
+
+        // Changed values are still logged, so they can be undone
+        tx.set_int(&blk, 0, 11, true).unwrap();
+        assert_eq!(tx.profile().log_records, log_records + 1);
+        tx.rollback().unwrap();
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 0);
+        assert_eq!(tx.get_string(&blk, 100).unwrap(), "");
+    }
+
+    #[test]
+    fn test_set_string_past_end_of_page() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            10,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        let locks = Arc::new(LockTable::new());
+
+        let blk = fm.append_block("test", &Page::new()).unwrap();
+
+        let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
+        tx.pin(&blk);
+        let offset = PAGE_SIZE - 100;
+        tx.set_string(&blk, offset, "fits", true).unwrap();
+        let log_records = tx.profile().log_records;
+
+        let res = tx.set_string(&blk, offset, &"x".repeat(100), true);
+        assert_eq!(
+            res,
+            Err(TxError::Page(PageError::Overrun { offset, len: 104 }))
+        );
+        assert_eq!(tx.profile().log_records, log_records);
+        assert_eq!(tx.get_string(&blk, offset).unwrap(), "fits");
+
+        // The transaction can carry on after the error
+        tx.set_string(&blk, offset, &"x".repeat(96), true).unwrap();
+        assert_eq!(tx.get_string(&blk, offset).unwrap(), "x".repeat(96));
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_size_after_reopen() {
+        let td = tempdir().unwrap();
+        let layout = Layout::from_schema(make_schema! {
+            "A" => i32,
+            "B" => varchar(100)
+        });
+
+        let num_blocks = {
+            let db = test_db(&td);
+            let tx = Arc::new(Mutex::new(db.new_tx()));
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "T");
+            for i in 0..200 {
+                scan.insert();
+                scan.set_int("A", i);
+                scan.set_string("B", &format!("rec{}", i));
+            }
+            scan.close();
+            let num_blocks = tx.lock().unwrap().size("T").unwrap();
+            tx.lock().unwrap().commit().unwrap();
+
+            assert!(num_blocks > 1);
+            num_blocks
+        };
+
+        // The file is on disk but has not been opened since the database was reopened
+        let db = test_db(&td);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(tx.lock().unwrap().size("T").unwrap(), num_blocks);
+
+        let mut scan = TableScan::new(tx.clone(), layout, "T");
+        let mut count = 0;
+        while scan.next() {
+            count += 1;
+        }
+        assert_eq!(count, 200);
+        assert_eq!(tx.lock().unwrap().size("T").unwrap(), num_blocks);
+    }
+
+    #[test]
+    fn test_set_field_visible_to_table_scan() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            10,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        let locks = Arc::new(LockTable::new());
+        let tx = Arc::new(Mutex::new(Tx::new(fm, lm, bm, locks)));
+
+        let layout = Layout::from_schema(make_schema! {
+            "A" => i32,
+            "B" => varchar(10)
+        });
+
+        let rids = {
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "T");
+            let mut rids = vec![];
+            for i in 0..3 {
+                scan.insert();
+                scan.set_int("A", i);
+                scan.set_string("B", &format!("rec{}", i));
+                rids.push(scan.get_rid());
+            }
+            rids
+        };
+
+        let blk = BlockId::new("T", rids[1].block_num());
+        {
+            let mut tx = tx.lock().unwrap();
+            tx.set_field(&blk, &layout, rids[1].slot(), "A", &Value::Int(42))
+                .unwrap();
+            tx.set_field(
+                &blk,
+                &layout,
+                rids[1].slot(),
+                "B",
+                &Value::Varchar("new".into()),
+            )
+            .unwrap();
+            assert_eq!(
+                tx.get_field(&blk, &layout, rids[1].slot(), "A"),
+                Ok(Value::Int(42))
+            );
+        }
+
+        let mut scan = TableScan::new(tx.clone(), layout, "T");
+        let mut results = vec![];
+        while scan.next() {
+            results.push((scan.get_int("A").unwrap(), scan.get_string("B").unwrap()));
+        }
+        assert_eq!(
+            results,
+            vec![
+                (0, "rec0".to_string()),
+                (42, "new".to_string()),
+                (2, "rec2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rollback_with_oversized_undo_record() {
+        let td = tempdir().unwrap();
+        let data_dir = td.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let log_dir = td.path().join("log");
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let lm = Arc::new(Mutex::new(LogManager::new(&log_dir)));
+        let fm = Arc::new(FileManager::new(&data_dir));
+        let bm = Arc::new(Mutex::new(BufferManager::new(
+            10,
+            fm.clone(),
+            lm.clone(),
+            SimpleEvictionPolicy::new(),
+        )));
+        let locks = Arc::new(LockTable::new());
+
+        let blk = fm.append_block("test", &Page::new()).unwrap();
+
+        // Write a string that nearly fills the page without logging it
+        let long_str = "x".repeat(PAGE_SIZE - 16);
+        let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
+        tx.pin(&blk);
+        tx.set_string(&blk, 0, &long_str, false).unwrap();
+        tx.commit().unwrap();
+
+        // Overwriting it requires an undo record containing the old value, which does not fit in
+        // a single log page
+        let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
+        tx.pin(&blk);
+        tx.set_string(&blk, 0, "short", true).unwrap();
+        assert_eq!(tx.get_string(&blk, 0).unwrap(), "short");
+        tx.rollback().unwrap();
+
+        let mut tx = Tx::new(fm.clone(), lm.clone(), bm.clone(), locks.clone());
+        tx.pin(&blk);
+        assert_eq!(tx.get_string(&blk, 0).unwrap(), long_str);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_use_after_end() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+
+        let mut tx = db.new_tx();
+        assert_eq!(tx.state(), TxState::Active);
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 7, true).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(tx.state(), TxState::Committed);
+
+        let err = tx.set_int(&blk, 0, 8, true).unwrap_err();
+        assert_eq!(err, TxError::NotActive(TxState::Committed));
+        assert_eq!(err.to_string(), "transaction not active: committed");
+        assert!(tx.try_pin(&blk).is_err());
+        assert!(!tx.is_locked(&blk));
+        // Unpinning after the end is harmless, so scans can still be closed
+        tx.unpin(&blk);
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.rollback().unwrap();
+        assert_eq!(
+            tx.set_string(&blk, 0, "x", true),
+            Err(TxError::NotActive(TxState::RolledBack))
+        );
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 7);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_commit_twice() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let mut tx = db.new_tx();
+        let savepoint = tx.savepoint();
+        tx.commit().unwrap();
+        assert_eq!(tx.commit(), Err(TxError::NotActive(TxState::Committed)));
+        assert_eq!(tx.rollback(), Err(TxError::NotActive(TxState::Committed)));
+        assert_eq!(
+            tx.rollback_to(savepoint),
+            Err(TxError::NotActive(TxState::Committed))
+        );
+    }
+
+    #[test]
+    fn test_end_fails_on_write_error() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 7, true).unwrap();
+
+        // The modified block cannot be written, so the transaction stays active with its locks
+        db.file_manager().set_fail_writes(true);
+        let err = tx.commit().unwrap_err();
+        assert!(
+            matches!(err, TxError::Buffer(BufferError::Flush(..))),
+            "{}",
+            err
+        );
+        assert_eq!(tx.state(), TxState::Active);
+        assert!(tx.is_locked(&blk));
+
+        db.file_manager().set_fail_writes(false);
+        tx.commit().unwrap();
+        assert_eq!(tx.state(), TxState::Committed);
+
+        // Nor can a transaction roll back while its undone changes cannot be written
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 8, true).unwrap();
+        db.file_manager().set_fail_writes(true);
+        assert!(tx.rollback().is_err());
+        assert_eq!(tx.state(), TxState::Active);
+
+        db.file_manager().set_fail_writes(false);
+        tx.rollback().unwrap();
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 7);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_drop_rolls_back() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+        let num_available = db.buffer_manager().lock().unwrap().num_available();
+
+        let layout = {
+            let tx = Arc::new(Mutex::new(db.new_tx()));
+            let layout = MetadataManager::new(&tx)
+                .get_table_layout("student", &tx)
+                .unwrap();
+            tx.lock().unwrap().commit().unwrap();
+            layout
+        };
+        let count_students = |tx: &Arc<Mutex<Tx>>| {
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), "student");
+            let mut count = 0;
+            while scan.next() {
+                count += 1;
+            }
+            scan.close();
+            count
+        };
+
+        // Drop a transaction partway through its writes, with a block still pinned
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        let mut scan = TableScan::new(tx.clone(), layout.clone(), "student");
+        while scan.next() {
+            scan.set_int("grad_year", 1999);
+        }
+        scan.insert();
+        scan.set_int("sid", 10);
+        assert_eq!(count_students(&tx), 10);
+        drop(scan);
+        drop(tx);
+
+        // Its locks and buffers are released, and none of its changes are seen
+        assert_eq!(
+            db.buffer_manager().lock().unwrap().num_available(),
+            num_available
+        );
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(count_students(&tx), 9);
+        let mut scan = TableScan::new(tx.clone(), layout.clone(), "student");
+        while scan.next() {
+            assert_ne!(scan.get_int("grad_year"), Ok(1999));
+        }
+        scan.close();
+
+        // Dropping a transaction that committed leaves its changes in place
+        let mut scan = TableScan::new(tx.clone(), layout.clone(), "student");
+        scan.insert();
+        scan.set_int("sid", 10);
+        drop(scan);
+        tx.lock().unwrap().commit().unwrap();
+        drop(tx);
+        let tx = Arc::new(Mutex::new(db.new_tx()));
+        assert_eq!(count_students(&tx), 10);
+        tx.lock().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn test_commit_fails_on_poisoned_lock() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let mut tx = db.new_tx();
+        let savepoint = tx.savepoint();
+
+        let log_mgr = db.log_manager();
+        thread::spawn(move || {
+            let _log_mgr = log_mgr.lock().unwrap();
+            panic!("panicked while holding the log manager");
+        })
+        .join()
+        .unwrap_err();
+
+        let err = tx.commit().unwrap_err();
+        assert_eq!(err, TxError::Poisoned("log manager".to_string()));
+        assert_eq!(err.to_string(), "lock on the log manager is poisoned");
+        assert_eq!(tx.state(), TxState::Active);
+        assert_eq!(tx.rollback_to(savepoint), Err(err));
+    }
+
+    #[test]
+    fn test_abort_logs_reason() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 7, true).unwrap();
+        tx.abort("duplicate key 7").unwrap();
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 0);
+        tx.commit().unwrap();
+
+        let dump: Vec<String> = db
+            .log_manager()
+            .lock()
+            .unwrap()
+            .snapshot()
+            .map(|r| format!("{:?}", bincode::deserialize::<LogRecord>(&r).unwrap()))
+            .collect();
+        assert!(
+            dump.iter()
+                .any(|r| r.contains("Abort") && r.contains("duplicate key 7")),
+            "{:?}",
+            dump
+        );
+    }
+
+    #[test]
+    fn test_flush_writes_log_before_commit() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 7, true).unwrap();
+
+        // Read the records of this transaction from the log on disk
+        let logged = |tx_num: i64| {
+            LogManager::new(&td.path().join("log"))
+                .snapshot()
+                .filter(|r| {
+                    matches!(
+                        bincode::deserialize::<LogRecord>(r).unwrap(),
+                        LogRecord::SetInt { tx_num: n, .. } if n == tx_num
+                    )
+                })
+                .count()
+        };
+        assert_eq!(logged(tx.tx_num()), 0);
+
+        tx.flush().unwrap();
+        assert_eq!(logged(tx.tx_num()), 1);
+        let mut page = Page::new();
+        db.file_manager().get_block(&blk, &mut page).unwrap();
+        assert_eq!(page.read::<i32>(0), 7);
+
+        // The transaction is still active, and can keep writing
+        tx.set_int(&blk, 0, 8, true).unwrap();
+        assert!(tx.is_locked(&blk));
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_rollback_to_savepoint() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.set_int(&blk, 0, 1, true).unwrap();
+        let savepoint = tx.savepoint();
+
+        // Another transaction's changes after the savepoint are left alone
+        let other_blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+        let mut other_tx = db.new_tx();
+        other_tx.pin(&other_blk);
+        other_tx.set_int(&other_blk, 0, 3, true).unwrap();
+        other_tx.commit().unwrap();
+
+        tx.set_int(&blk, 0, 2, true).unwrap();
+        tx.set_string(&blk, 8, "undone", true).unwrap();
+        tx.rollback_to(savepoint).unwrap();
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 1);
+        assert_eq!(tx.get_string(&blk, 8).unwrap(), "");
+
+        // The transaction carries on after rolling back to the savepoint
+        tx.set_int(&blk, 4, 4, true).unwrap();
+        tx.commit().unwrap();
+
+        let mut tx = db.new_tx();
+        tx.pin(&blk);
+        tx.pin(&other_blk);
+        assert_eq!(tx.get_int(&blk, 0).unwrap(), 1);
+        assert_eq!(tx.get_int(&blk, 4).unwrap(), 4);
+        assert_eq!(tx.get_string(&blk, 8).unwrap(), "");
+        assert_eq!(tx.get_int(&other_blk, 0).unwrap(), 3);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_recover_redoes_committed_and_undoes_uncommitted_changes() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+
+        // Simulate a crash: the block on disk holds a change of a transaction that never
+        // committed, but none of the changes of the transactions that did
+        let blk = db
+            .file_manager()
+            .append_block("test", &Page::new())
+            .unwrap();
+        let mut page = Page::new();
+        page.write(99, 8);
+        db.file_manager().write_block(&blk, &page).unwrap();
+        let dropped_blk = db
+            .file_manager()
+            .append_block("dropped", &Page::new())
+            .unwrap();
+        db.file_manager().truncate("dropped").unwrap();
+
+        let records = [
+            LogRecord::SetInt {
+                tx_num: 1001,
+                block: blk.clone(),
+                offset: 0,
+                old_val: 0,
+                new_val: 42,
+            },
+            LogRecord::SetInt {
+                tx_num: 1001,
+                block: blk.clone(),
+                offset: 4,
+                old_val: 0,
+                new_val: 7,
+            },
+            LogRecord::RollbackTo {
+                tx_num: 1001,
+                records: 1,
+            },
+            LogRecord::SetString {
+                tx_num: 1001,
+                block: blk.clone(),
+                offset: 16,
+                old_val: "".to_string(),
+                new_val: "committed".to_string(),
+            },
+            LogRecord::SetInt {
+                tx_num: 1002,
+                block: blk.clone(),
+                offset: 8,
+                old_val: 0,
+                new_val: 99,
+            },
+            LogRecord::Commit { tx_num: 1001 },
+            LogRecord::SetInt {
+                tx_num: 1003,
+                block: dropped_blk.clone(),
+                offset: 0,
+                old_val: 0,
+                new_val: 5,
+            },
+            LogRecord::Commit { tx_num: 1003 },
+            LogRecord::Truncate {
+                tx_num: 1004,
+                file_id: "dropped".to_string(),
+                backup: "dropped.backup".to_string(),
+            },
+            LogRecord::Commit { tx_num: 1004 },
+        ];
+        let log_mgr = db.log_manager();
+        for record in &records {
+            log_mgr
+                .lock()
+                .unwrap()
+                .append(&encode(record).unwrap())
+                .unwrap();
+        }
+
+        let mut tx = db.new_tx();
+        tx.recover();
+        tx.commit().unwrap();
+
+        // Only the committed changes that were not rolled back to a savepoint are on disk, and
+        // the truncated file is not written again
+        let mut page = Page::new();
+        db.file_manager().get_block(&blk, &mut page).unwrap();
+        assert_eq!(page.read::<i32>(0), 42);
+        assert_eq!(page.read::<i32>(4), 0);
+        assert_eq!(page.read::<i32>(8), 0);
+        assert_eq!(page.read::<String>(16), "committed");
+        assert_eq!(db.file_manager().length("dropped").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_table_level_locking() {
+        let td = tempdir().unwrap();
+        let mut db = default_test_db(&td);
+        create_default_tables(&mut db);
+
+        let (student_layout, dept_layout) = {
+            let tx = Arc::new(Mutex::new(db.new_tx()));
+            let metadata_mgr = MetadataManager::new(&tx);
+            let layouts = (
+                metadata_mgr.get_table_layout("student", &tx).unwrap(),
+                metadata_mgr.get_table_layout("dept", &tx).unwrap(),
+            );
+            tx.lock().unwrap().commit().unwrap();
+            layouts
+        };
+
+        // Update every student, holding a single lock on the whole table
+        let writer = Arc::new(Mutex::new(
+            db.new_tx().with_lock_granularity(LockGranularity::Table),
+        ));
+        let mut scan = TableScan::new(writer.clone(), student_layout.clone(), "student");
+        while scan.next() {
+            let grad_year = scan.get_int("grad_year").unwrap();
+            scan.set_int("grad_year", grad_year + 1);
+        }
+        scan.close();
+        assert_eq!(writer.lock().unwrap().profile().xlocks, 1);
+
+        let read_table = |tbl_name: &str, layout: &Layout| {
+            let tx = Arc::new(Mutex::new(
+                db.new_tx().with_lock_granularity(LockGranularity::Table),
+            ));
+            let mut scan = TableScan::new(tx.clone(), layout.clone(), tbl_name);
+            let mut count = 0;
+            while scan.next() {
+                count += 1;
+            }
+            scan.close();
+            tx.lock().unwrap().commit().unwrap();
+            count
+        };
+
+        let student_read = AtomicBool::new(false);
+        thread::scope(|s| {
+            let students = s.spawn(|| {
+                let count = read_table("student", &student_layout);
+                student_read.store(true, Ordering::SeqCst);
+                count
+            });
+
+            // A reader of another table is not blocked by the writer
+            assert_eq!(
+                s.spawn(|| read_table("dept", &dept_layout)).join().unwrap(),
+                3
+            );
+
+            thread::sleep(Duration::from_millis(100));
+            assert!(!student_read.load(Ordering::SeqCst));
+
+            writer.lock().unwrap().commit().unwrap();
+            assert_eq!(students.join().unwrap(), 9);
+        });
+        assert!(student_read.load(Ordering::SeqCst));
+    }
+    #[test]
+    fn test_table_locks_conflict_with_block_locks() {
+        let td = tempdir().unwrap();
+        let db = test_db(&td);
+        let fm = db.file_manager();
+        let blk0 = fm.append_block("test", &Page::new()).unwrap();
+        let blk1 = fm.append_block("test", &Page::new()).unwrap();
+        let locks = Arc::new(LockTable::with_max_wait(Duration::from_millis(100)));
+        let new_tx = |granularity| {
+            Tx::new(
+                fm.clone(),
+                db.log_manager(),
+                db.buffer_manager(),
+                locks.clone(),
+            )
+            .with_lock_granularity(granularity)
+        };
+        let file_lock = || {
+            Err(TxError::LockAbort(LockAbortError(LockKey::File(
+                "test".to_string(),
+            ))))
+        };
+
+        // A block-level writer holds an intention lock on the file, so a table-level writer
+        // cannot lock it, even to change a different block
+        let mut block_tx = new_tx(LockGranularity::Block);
+        block_tx.pin(&blk0);
+        block_tx.set_int(&blk0, 0, 1, true).unwrap();
+        let mut table_tx = new_tx(LockGranularity::Table);
+        table_tx.pin(&blk1);
+        assert_eq!(table_tx.set_int(&blk1, 0, 2, true), file_lock());
+        table_tx.rollback().unwrap();
+
+        // Block-level writers of different blocks do not conflict
+        let mut other_block_tx = new_tx(LockGranularity::Block);
+        other_block_tx.pin(&blk1);
+        other_block_tx.set_int(&blk1, 0, 3, true).unwrap();
+        other_block_tx.commit().unwrap();
+        block_tx.commit().unwrap();
+
+        // Once the table-level writer holds the file, a block-level writer cannot lock any of its
+        // blocks
+        let mut table_tx = new_tx(LockGranularity::Table);
+        table_tx.pin(&blk1);
+        table_tx.set_int(&blk1, 0, 4, true).unwrap();
+        let mut block_tx = new_tx(LockGranularity::Block);
+        block_tx.pin(&blk0);
+        assert_eq!(block_tx.set_int(&blk0, 0, 5, true), file_lock());
+        block_tx.rollback().unwrap();
+        table_tx.commit().unwrap();
+
+        let mut tx = new_tx(LockGranularity::Block);
+        tx.pin(&blk0);
+        tx.pin(&blk1);
+        assert_eq!(tx.get_int(&blk0, 0).unwrap(), 1);
+        assert_eq!(tx.get_int(&blk1, 0).unwrap(), 4);
+        tx.commit().unwrap();
     }
 }